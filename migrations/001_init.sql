@@ -1,3 +1,7 @@
+-- Free pages can be reclaimed with PRAGMA incremental_vacuum (applies to new databases;
+-- existing ones switch over on their next full VACUUM)
+PRAGMA auto_vacuum = INCREMENTAL;
+
 -- Credentials table
 CREATE TABLE IF NOT EXISTS credentials (
     id TEXT PRIMARY KEY,
@@ -15,6 +19,33 @@ CREATE TABLE IF NOT EXISTS credentials (
     webhook_headers TEXT, -- JSON string for custom headers
     is_active BOOLEAN NOT NULL DEFAULT 1,
     is_suspended BOOLEAN NOT NULL DEFAULT 0, -- Suspended workers won't auto-start
+    max_messages INTEGER, -- Per-credential message log cap (NULL = global default)
+    capture_webhook_response BOOLEAN NOT NULL DEFAULT 1, -- Store webhook response bodies
+    ack_token TEXT, -- Token downstream receivers use to acknowledge messages
+    schedule_start TEXT, -- Cron expression for starting the listener
+    schedule_stop TEXT, -- Cron expression for stopping the listener
+    schedule_timezone TEXT, -- IANA timezone for the schedule (NULL = UTC)
+    topic_webhooks TEXT, -- JSON map of topic -> webhook URL overrides
+    sink_config TEXT, -- JSON delivery sink (NULL = HTTP webhook)
+    webhook_secret TEXT, -- HMAC secret for signing webhook bodies (NULL = unsigned)
+    webhook_secret_previous TEXT, -- Previous secret, still signed with until rotation is finalized
+    extract_fields TEXT, -- JSON map of field name -> payload path extracted at ingest
+    webhook_enabled BOOLEAN NOT NULL DEFAULT 1, -- Live delivery (0 = log only, delivery skipped)
+    max_backoff_secs INTEGER, -- Reconnect delay ceiling (NULL = global default)
+    webhook_proxy TEXT, -- Outbound delivery proxy URL (NULL = WEBHOOK_PROXY / HTTPS_PROXY)
+    delivery_alerts TEXT, -- JSON delivery outcome alert policy (NULL = failure)
+    external_id TEXT, -- Caller-supplied key the credential ID is derived from (unique)
+    retry_on TEXT, -- JSON webhook retry policy per failure kind (NULL = retry all)
+    signing_key TEXT, -- Base64 Ed25519 seed for signing webhook bodies (NULL = WEBHOOK_SIGNING_KEY)
+    payload_encoding TEXT, -- How received payloads are stored: utf8 or base64 (NULL = utf8)
+    dedup_ignore_fields TEXT, -- JSON array of dotted payload paths left out of the dedup hash
+    json_format TEXT, -- Delivered JSON body format: raw, minify or canonical (NULL = raw)
+    webhook_verified BOOLEAN NOT NULL DEFAULT 0, -- Receiver echoed the verification challenge for webhook_url
+    webhook_verified_at TIMESTAMP, -- When webhook_url was last verified
+    store_payload BOOLEAN NOT NULL DEFAULT 1, -- Keep message bodies (0 = metadata and a payload hash only)
+    dedup_key_path TEXT, -- Dotted payload path of the sender's message id used for dedup (NULL = fcmMessageId)
+    allowed_senders TEXT, -- JSON array of accepted "from" values (NULL/empty = accept all)
+    webhook_auth TEXT, -- JSON structured Authorization for webhook requests (scheme, credentials, precedence)
     created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
     updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
 );
@@ -25,17 +56,31 @@ CREATE TABLE IF NOT EXISTS message_logs (
     credential_id TEXT NOT NULL,
     fcm_message_id TEXT, -- FCM message ID for deduplication
     payload TEXT NOT NULL,
+    payload_encoding TEXT NOT NULL DEFAULT 'utf8', -- utf8 text, or base64 of the raw bytes
+    payload_compressed BOOLEAN NOT NULL DEFAULT 0, -- payload holds base64 of its zstd-compressed form
+    payload_stored BOOLEAN NOT NULL DEFAULT 1, -- 0 = payload holds only a SHA-256 hash of the body
+    dedup_key TEXT, -- Value at the credential's dedup_key_path, for persistent dedup
     webhook_status INTEGER, -- HTTP status code
+    webhook_url TEXT, -- URL of the latest webhook delivery attempt
     webhook_response TEXT,
+    webhook_response_json TEXT, -- Parsed JSON body of a successful webhook response
+    extracted_fields TEXT, -- JSON object of values extracted per the credential's extract_fields
+    acknowledged BOOLEAN NOT NULL DEFAULT 0, -- Downstream confirmed processing
+    acknowledged_at TIMESTAMP,
     received_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
     FOREIGN KEY (credential_id) REFERENCES credentials(id) ON DELETE CASCADE
 );
 
 -- Indexes for performance
 CREATE INDEX IF NOT EXISTS idx_credentials_active ON credentials(is_active);
-CREATE INDEX IF NOT EXISTS idx_message_logs_credential ON message_logs(credential_id);
+-- Per-credential listing, retention and cleanup read one credential's range in received order
+CREATE INDEX IF NOT EXISTS idx_message_logs_credential_received ON message_logs(credential_id, received_at);
+-- Superseded by idx_message_logs_credential_received
+DROP INDEX IF EXISTS idx_message_logs_credential;
 CREATE INDEX IF NOT EXISTS idx_message_logs_received ON message_logs(received_at);
 CREATE INDEX IF NOT EXISTS idx_message_logs_fcm_id ON message_logs(credential_id, fcm_message_id);
+CREATE INDEX IF NOT EXISTS idx_message_logs_dedup_key ON message_logs(credential_id, dedup_key);
+CREATE UNIQUE INDEX IF NOT EXISTS idx_credentials_external_id ON credentials(external_id);
 
 -- Credential topics table
 CREATE TABLE IF NOT EXISTS credential_topics (
@@ -45,3 +90,9 @@ CREATE TABLE IF NOT EXISTS credential_topics (
     FOREIGN KEY (credential_id) REFERENCES credentials(id) ON DELETE CASCADE
 );
 
+-- Runtime settings changed via PATCH /api/admin/config (override environment defaults)
+CREATE TABLE IF NOT EXISTS settings (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
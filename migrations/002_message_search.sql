@@ -0,0 +1,26 @@
+-- Full-text search over message payloads (SQLite FTS5)
+-- External-content table: payload text lives only in message_logs, the index is keyed by its rowid.
+-- Rowids of message_logs must stay stable, so don't VACUUM without rebuilding the index afterwards:
+--   INSERT INTO message_logs_fts(message_logs_fts) VALUES('rebuild');
+CREATE VIRTUAL TABLE IF NOT EXISTS message_logs_fts USING fts5(
+    payload,
+    content='message_logs',
+    content_rowid='rowid'
+);
+
+-- Keep the index in sync with message_logs
+CREATE TRIGGER IF NOT EXISTS message_logs_fts_insert AFTER INSERT ON message_logs BEGIN
+    INSERT INTO message_logs_fts(rowid, payload) VALUES (new.rowid, new.payload);
+END;
+
+CREATE TRIGGER IF NOT EXISTS message_logs_fts_delete AFTER DELETE ON message_logs BEGIN
+    INSERT INTO message_logs_fts(message_logs_fts, rowid, payload) VALUES ('delete', old.rowid, old.payload);
+END;
+
+CREATE TRIGGER IF NOT EXISTS message_logs_fts_update AFTER UPDATE OF payload ON message_logs BEGIN
+    INSERT INTO message_logs_fts(message_logs_fts, rowid, payload) VALUES ('delete', old.rowid, old.payload);
+    INSERT INTO message_logs_fts(rowid, payload) VALUES (new.rowid, new.payload);
+END;
+
+-- Index messages logged before search existed
+INSERT INTO message_logs_fts(message_logs_fts) VALUES ('rebuild');
@@ -0,0 +1,24 @@
+-- Per-credential message counts, kept in step with message_logs by triggers so counting one
+-- credential's messages is a single-row lookup instead of a scan of its index range.
+CREATE TABLE IF NOT EXISTS message_counts (
+    credential_id TEXT PRIMARY KEY,
+    message_count INTEGER NOT NULL DEFAULT 0
+);
+
+CREATE TRIGGER IF NOT EXISTS message_counts_insert AFTER INSERT ON message_logs BEGIN
+    INSERT INTO message_counts(credential_id, message_count) VALUES (new.credential_id, 1)
+    ON CONFLICT(credential_id) DO UPDATE SET message_count = message_count + 1;
+END;
+
+CREATE TRIGGER IF NOT EXISTS message_counts_delete AFTER DELETE ON message_logs BEGIN
+    UPDATE message_counts SET message_count = message_count - 1
+    WHERE credential_id = old.credential_id;
+END;
+
+CREATE TRIGGER IF NOT EXISTS message_counts_credential_delete AFTER DELETE ON credentials BEGIN
+    DELETE FROM message_counts WHERE credential_id = old.id;
+END;
+
+-- Count messages logged before the counters existed
+INSERT OR REPLACE INTO message_counts(credential_id, message_count)
+SELECT credential_id, COUNT(*) FROM message_logs GROUP BY credential_id;
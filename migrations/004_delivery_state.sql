@@ -0,0 +1,13 @@
+-- Where each message is in the delivery lifecycle:
+-- pending, delivering, delivered, failed or dead_lettered
+ALTER TABLE message_logs ADD COLUMN delivery_state TEXT NOT NULL DEFAULT 'pending';
+
+-- Existing messages, judged by their last webhook status. A non-2xx status other than 0
+-- means retries were interrupted, so nothing will deliver those any more either.
+UPDATE message_logs SET delivery_state = CASE
+    WHEN webhook_status BETWEEN 200 AND 299 THEN 'delivered'
+    WHEN webhook_status IS NOT NULL THEN 'failed'
+    ELSE 'pending'
+END;
+
+CREATE INDEX IF NOT EXISTS idx_message_logs_delivery_state ON message_logs(delivery_state, received_at);
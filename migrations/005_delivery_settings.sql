@@ -0,0 +1,3 @@
+-- Per-credential delivery concurrency and queue settings (JSON), changed at runtime
+-- through PATCH /api/credentials/{id}/delivery. NULL uses the environment defaults.
+ALTER TABLE credentials ADD COLUMN delivery_settings TEXT;
@@ -0,0 +1,2 @@
+-- Send time read from the payload (google.sent_time, sent_time or sentTime), NULL when absent
+ALTER TABLE message_logs ADD COLUMN sent_at TIMESTAMP;
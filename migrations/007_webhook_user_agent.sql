@@ -0,0 +1,3 @@
+-- Per-credential User-Agent of webhook requests (may contain header placeholders).
+-- NULL uses WEBHOOK_USER_AGENT.
+ALTER TABLE credentials ADD COLUMN webhook_user_agent TEXT;
@@ -0,0 +1,3 @@
+-- Credentials with the same dedup_group share one in-memory dedup cache, so a message
+-- received by several of them is only logged and delivered once. NULL dedups per credential.
+ALTER TABLE credentials ADD COLUMN dedup_group TEXT;
@@ -0,0 +1,4 @@
+-- Per-credential webhook timeouts in seconds. NULL uses WEBHOOK_TIMEOUT_SECS and
+-- WEBHOOK_CONNECT_TIMEOUT_SECS.
+ALTER TABLE credentials ADD COLUMN webhook_timeout_secs INTEGER;
+ALTER TABLE credentials ADD COLUMN webhook_connect_timeout_secs INTEGER;
@@ -0,0 +1,3 @@
+-- Condition on the body of 2xx webhook responses ({"path", "equals"} JSON); deliveries
+-- whose response fails it are retried like non-2xx ones. NULL checks the status only.
+ALTER TABLE credentials ADD COLUMN webhook_response_match TEXT;
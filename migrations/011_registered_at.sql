@@ -0,0 +1,3 @@
+-- When the credential last completed FCM registration. NULL for credentials registered
+-- before this was tracked (and unregistered ones).
+ALTER TABLE credentials ADD COLUMN registered_at TIMESTAMP;
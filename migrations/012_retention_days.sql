@@ -0,0 +1,2 @@
+-- Days this credential's messages are kept. NULL uses MESSAGE_RETENTION_DAYS.
+ALTER TABLE credentials ADD COLUMN retention_days INTEGER;
@@ -0,0 +1,4 @@
+-- FCM delivery priority ("high" or "normal") and time to live in seconds, read from the
+-- payload. NULL when the payload doesn't carry them (e.g. notification-only messages).
+ALTER TABLE message_logs ADD COLUMN priority TEXT;
+ALTER TABLE message_logs ADD COLUMN ttl_secs INTEGER;
@@ -0,0 +1,2 @@
+-- Where this credential's lifecycle events go. NULL uses LIFECYCLE_WEBHOOK_URL.
+ALTER TABLE credentials ADD COLUMN lifecycle_webhook_url TEXT;
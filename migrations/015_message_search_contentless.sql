@@ -0,0 +1,34 @@
+-- Index payloads as received, also when PAYLOAD_COMPRESSION stores them compressed.
+-- The external-content index read payloads back from message_logs, which for compressed rows is
+-- base64 zstd; a contentless index keeps only its tokens, so the repository indexes compressed
+-- rows itself with the original text. Deleting an entry only needs its rowid (contentless_delete).
+DROP TRIGGER IF EXISTS message_logs_fts_insert;
+DROP TRIGGER IF EXISTS message_logs_fts_delete;
+DROP TRIGGER IF EXISTS message_logs_fts_update;
+DROP TABLE IF EXISTS message_logs_fts;
+
+CREATE VIRTUAL TABLE message_logs_fts USING fts5(
+    payload,
+    content='',
+    contentless_delete=1
+);
+
+-- Uncompressed payloads are indexed here, compressed ones by Repository::insert_message_log
+CREATE TRIGGER message_logs_fts_insert AFTER INSERT ON message_logs
+WHEN NOT new.payload_compressed BEGIN
+    INSERT INTO message_logs_fts(rowid, payload) VALUES (new.rowid, new.payload);
+END;
+
+CREATE TRIGGER message_logs_fts_delete AFTER DELETE ON message_logs BEGIN
+    DELETE FROM message_logs_fts WHERE rowid = old.rowid;
+END;
+
+CREATE TRIGGER message_logs_fts_update AFTER UPDATE OF payload ON message_logs BEGIN
+    DELETE FROM message_logs_fts WHERE rowid = old.rowid;
+    INSERT INTO message_logs_fts(rowid, payload)
+    SELECT new.rowid, new.payload WHERE NOT new.payload_compressed;
+END;
+
+-- Index the existing uncompressed messages; compressed ones are indexed on the next start
+INSERT INTO message_logs_fts(rowid, payload)
+SELECT rowid, payload FROM message_logs WHERE NOT payload_compressed;
@@ -0,0 +1,5 @@
+-- Messages never delivered because webhook delivery was disabled get their own terminal
+-- state instead of 'failed'. They keep webhook_status 0, so retry-failed still backfills them.
+UPDATE message_logs SET delivery_state = 'skipped'
+WHERE delivery_state = 'failed'
+  AND webhook_response = 'Delivery skipped: webhook delivery disabled for this credential';
@@ -0,0 +1,299 @@
+use crate::api::{
+    get_cors_allowed_origins, get_max_bulk_request_body_bytes, get_max_request_body_bytes,
+    get_request_timeout, get_slow_request_timeout, AppState,
+};
+use crate::db::{get_payload_compression, DbPoolConfig, LogBatchConfig};
+use crate::error::{AppError, AppResult};
+use crate::settings::{self, RuntimeSettings, SettingsUpdate};
+use crate::workers::{
+    get_alert_debounce_secs, get_alert_reconnect_threshold, get_alert_webhook_url,
+    get_bulk_retry_jitter, get_bulk_retry_rate, get_delivery_concurrency,
+    get_delivery_queue_capacity, get_delivery_queue_policy, get_endpoint_backoff_max,
+    get_event_stream_capacity, get_lifecycle_debounce_secs, get_lifecycle_webhook_url,
+    get_max_reconnect_backoff, get_registration_cooldown, get_registration_max_per_hour,
+    get_message_retention_days, get_vacuum_interval, get_webhook_proxy, get_webhook_signing_key,
+    get_watchdog_interval, get_worker_reap_interval, get_worker_start_stagger_ms,
+    get_header_templates_strict, get_log_payloads, get_shutdown_grace, get_webhook_debug_ttl,
+    get_webhook_connect_timeout, get_webhook_global_concurrency, get_webhook_timeout,
+    get_webhook_user_agent, get_webhook_verification,
+    get_worker_start_timeout,
+    get_topic_retry_interval, OverflowPolicy,
+    WebhookPublicKey, WebhookVerification,
+};
+use axum::{
+    extract::{Query, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+use utoipa::{IntoParams, ToSchema};
+
+/// Settings read from the environment at startup (changing them requires a restart)
+#[derive(Debug, Serialize, ToSchema)]
+pub struct StartupConfig {
+    pub delivery_queue_capacity: usize,
+    pub delivery_queue_policy: OverflowPolicy,
+    pub delivery_concurrency: usize,
+    pub webhook_global_concurrency: usize,
+    pub event_stream_capacity: usize,
+    pub bulk_retry_rate: f64,
+    pub bulk_retry_jitter: f64,
+    pub max_reconnect_backoff_secs: u64,
+    pub registration_cooldown_secs: u64,
+    pub registration_max_per_hour: usize,
+    pub endpoint_backoff_max_secs: u64,
+    pub request_timeout_secs: u64,
+    pub slow_request_timeout_secs: u64,
+    pub max_request_body_bytes: usize,
+    pub max_bulk_request_body_bytes: usize,
+    pub worker_reap_interval_secs: u64,
+    pub worker_start_stagger_ms: u64,
+    pub worker_start_timeout_secs: u64,
+    pub shutdown_grace_secs: u64,
+    pub webhook_header_templates_strict: bool,
+    pub webhook_verification: WebhookVerification,
+    pub webhook_debug_ttl_secs: u64,
+    pub log_payloads: bool,
+    /// Null when failed topic subscriptions aren't retried
+    pub topic_retry_interval_secs: Option<u64>,
+    /// Null when the worker watchdog is disabled
+    pub watchdog_interval_secs: Option<u64>,
+    /// Null when periodic maintenance is disabled
+    pub vacuum_interval_secs: Option<u64>,
+    /// Null when messages are kept until the max_messages cap
+    pub message_retention_days: Option<i64>,
+    pub payload_compression: bool,
+    /// Message logs written per transaction; null when batching is off
+    pub message_log_batch_size: Option<usize>,
+    pub db_max_connections: u32,
+    pub db_connect_timeout_secs: u64,
+    pub db_statement_timeout_secs: u64,
+    pub cors_allowed_origins: Vec<String>,
+    /// Whether WEBHOOK_PROXY is set (the URL may contain credentials)
+    pub webhook_proxy_configured: bool,
+    pub webhook_user_agent: String,
+    pub webhook_timeout_secs: u64,
+    pub webhook_connect_timeout_secs: u64,
+    /// Whether ALERT_WEBHOOK_URL is set
+    pub alerts_enabled: bool,
+    pub alert_debounce_secs: u64,
+    pub alert_reconnect_threshold: u32,
+    /// Whether LIFECYCLE_WEBHOOK_URL is set (credentials may still have their own)
+    pub lifecycle_webhook_enabled: bool,
+    pub lifecycle_debounce_secs: u64,
+    /// Key id of WEBHOOK_SIGNING_KEY (null when unset)
+    pub webhook_signing_key_id: Option<String>,
+}
+
+impl StartupConfig {
+    fn from_env() -> Self {
+        // Startup already refused invalid values, so this is what the pools were built with
+        let db_pool = DbPoolConfig::from_env().unwrap_or_default();
+        Self {
+            delivery_queue_capacity: get_delivery_queue_capacity(),
+            delivery_queue_policy: get_delivery_queue_policy(),
+            delivery_concurrency: get_delivery_concurrency(),
+            webhook_global_concurrency: get_webhook_global_concurrency(),
+            event_stream_capacity: get_event_stream_capacity(),
+            bulk_retry_rate: get_bulk_retry_rate(),
+            bulk_retry_jitter: get_bulk_retry_jitter(),
+            max_reconnect_backoff_secs: get_max_reconnect_backoff().as_secs(),
+            registration_cooldown_secs: get_registration_cooldown().as_secs(),
+            registration_max_per_hour: get_registration_max_per_hour(),
+            endpoint_backoff_max_secs: get_endpoint_backoff_max().as_secs(),
+            request_timeout_secs: get_request_timeout().as_secs(),
+            slow_request_timeout_secs: get_slow_request_timeout().as_secs(),
+            max_request_body_bytes: get_max_request_body_bytes(),
+            max_bulk_request_body_bytes: get_max_bulk_request_body_bytes(),
+            worker_reap_interval_secs: get_worker_reap_interval(),
+            worker_start_stagger_ms: get_worker_start_stagger_ms(),
+            worker_start_timeout_secs: get_worker_start_timeout().as_secs(),
+            shutdown_grace_secs: get_shutdown_grace().as_secs(),
+            webhook_header_templates_strict: get_header_templates_strict(),
+            webhook_verification: get_webhook_verification(),
+            webhook_debug_ttl_secs: get_webhook_debug_ttl().as_secs(),
+            log_payloads: get_log_payloads(),
+            topic_retry_interval_secs: get_topic_retry_interval().map(|d| d.as_secs()),
+            watchdog_interval_secs: get_watchdog_interval().map(|d| d.as_secs()),
+            vacuum_interval_secs: get_vacuum_interval().map(|d| d.as_secs()),
+            message_retention_days: get_message_retention_days(),
+            payload_compression: get_payload_compression(),
+            message_log_batch_size: LogBatchConfig::from_env().map(|c| c.batch_size),
+            db_max_connections: db_pool.max_connections,
+            db_connect_timeout_secs: db_pool.connect_timeout.as_secs(),
+            db_statement_timeout_secs: db_pool.statement_timeout.as_secs(),
+            cors_allowed_origins: get_cors_allowed_origins(),
+            webhook_proxy_configured: get_webhook_proxy().is_some(),
+            webhook_user_agent: get_webhook_user_agent(),
+            webhook_timeout_secs: get_webhook_timeout().as_secs(),
+            webhook_connect_timeout_secs: get_webhook_connect_timeout().as_secs(),
+            alerts_enabled: get_alert_webhook_url().is_some(),
+            alert_debounce_secs: get_alert_debounce_secs(),
+            alert_reconnect_threshold: get_alert_reconnect_threshold(),
+            lifecycle_webhook_enabled: get_lifecycle_webhook_url().is_some(),
+            lifecycle_debounce_secs: get_lifecycle_debounce_secs(),
+            webhook_signing_key_id: get_webhook_signing_key().map(|k| k.key_id().to_string()),
+        }
+    }
+}
+
+/// Effective runtime configuration (secrets are never included)
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ConfigResponse {
+    /// Settings adjustable via PATCH /api/admin/config
+    pub settings: RuntimeSettings,
+    /// Settings fixed at startup
+    pub startup: StartupConfig,
+}
+
+/// Get the effective runtime configuration
+#[utoipa::path(
+    get,
+    path = "/api/admin/config",
+    tag = "admin",
+    security(
+        ("api_key" = []),
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Effective configuration", body = ConfigResponse),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+pub async fn get_config() -> Json<ConfigResponse> {
+    Json(ConfigResponse {
+        settings: settings::current(),
+        startup: StartupConfig::from_env(),
+    })
+}
+
+/// Change runtime settings. Values are stored and survive restarts. Retry settings apply
+/// to the next delivery; dedup TTL and message cap to listeners started afterwards.
+#[utoipa::path(
+    patch,
+    path = "/api/admin/config",
+    tag = "admin",
+    request_body = SettingsUpdate,
+    security(
+        ("api_key" = []),
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Updated configuration", body = ConfigResponse),
+        (status = 400, description = "Invalid setting"),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+pub async fn update_config(
+    State(state): State<AppState>,
+    Json(update): Json<SettingsUpdate>,
+) -> AppResult<Json<ConfigResponse>> {
+    let updated = settings::current()
+        .apply(&update)
+        .map_err(AppError::BadRequest)?;
+    settings::save(&state.repo, &update, updated).await?;
+
+    info!("Runtime settings updated: {:?}", updated);
+
+    Ok(Json(ConfigResponse {
+        settings: updated,
+        startup: StartupConfig::from_env(),
+    }))
+}
+
+/// Query parameters for a manual vacuum
+#[derive(Debug, Deserialize, IntoParams, ToSchema)]
+pub struct VacuumQuery {
+    /// Run a full VACUUM instead of an incremental one. Rewrites the whole database
+    /// and blocks writes while it runs; also enables incremental vacuum on older databases.
+    #[serde(default)]
+    pub full: bool,
+}
+
+/// Result of a vacuum run
+#[derive(Debug, Serialize, ToSchema)]
+pub struct VacuumResponse {
+    /// "incremental" or "full"
+    pub mode: String,
+    /// Database size before, in bytes
+    pub size_before: i64,
+    /// Database size after, in bytes
+    pub size_after: i64,
+    /// Bytes returned to the filesystem
+    pub reclaimed: i64,
+}
+
+/// Reclaim free space in the database and refresh query statistics
+#[utoipa::path(
+    post,
+    path = "/api/admin/vacuum",
+    tag = "admin",
+    params(VacuumQuery),
+    security(
+        ("api_key" = []),
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Vacuum completed", body = VacuumResponse),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+pub async fn vacuum(
+    State(state): State<AppState>,
+    Query(query): Query<VacuumQuery>,
+) -> AppResult<Json<VacuumResponse>> {
+    let report = state.repo.vacuum(query.full).await?;
+    let mode = if report.full { "full" } else { "incremental" };
+
+    info!(
+        "Manual {} vacuum reclaimed {} bytes ({} -> {})",
+        mode,
+        report.reclaimed(),
+        report.size_before,
+        report.size_after
+    );
+
+    Ok(Json(VacuumResponse {
+        mode: mode.to_string(),
+        size_before: report.size_before,
+        size_after: report.size_after,
+        reclaimed: report.reclaimed(),
+    }))
+}
+
+/// Query parameters for the webhook public key
+#[derive(Debug, Deserialize, IntoParams, ToSchema)]
+pub struct PublicKeyQuery {
+    /// Credential whose key to return (default: the server-wide WEBHOOK_SIGNING_KEY)
+    pub credential_id: Option<String>,
+}
+
+/// Get the Ed25519 public key that verifies X-Webhook-Signature-Ed25519. Requires no API key,
+/// so webhook receivers can fetch it without holding admin credentials.
+#[utoipa::path(
+    get,
+    path = "/api/admin/webhook-public-key",
+    tag = "admin",
+    params(PublicKeyQuery),
+    responses(
+        (status = 200, description = "Public key", body = WebhookPublicKey),
+        (status = 404, description = "No signing key configured or credential not found")
+    )
+)]
+pub async fn get_webhook_public_key(
+    State(state): State<AppState>,
+    Query(query): Query<PublicKeyQuery>,
+) -> AppResult<Json<WebhookPublicKey>> {
+    let key = match &query.credential_id {
+        Some(id) => state
+            .repo
+            .get_credential(id)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("Credential {} not found", id)))?
+            .signing_key(),
+        None => get_webhook_signing_key(),
+    };
+
+    key.map(|k| Json(k.public_key()))
+        .ok_or_else(|| AppError::NotFound("No webhook signing key configured".to_string()))
+}
@@ -1,12 +1,26 @@
 use crate::api::AppState;
+use crate::db::TopicChanges;
 use crate::error::{AppError, AppResult};
-use crate::models::{CreateCredentialRequest, Credential, CredentialResponse, UpdateCredentialRequest};
+use crate::models::{
+    CreateCredentialRequest, Credential, CredentialExport, CredentialResponse,
+    UpdateCredentialRequest, EXPORT_SCHEMA_VERSION,
+};
+use crate::workers::{
+    get_header_templates_strict, get_webhook_debug_ttl, get_webhook_verification,
+    get_worker_start_timeout, register_device, unknown_placeholders, validate_proxy,
+    AuthPrecedence, AuthScheme, CredentialSchedule, DeliverySettings, DeliverySettingsUpdate,
+    Ed25519Key, LifecycleEvent, LifecycleEventKind, NeedsAttention, QueueStats, ResponseMatch, ScheduleConfig, ScheduledTransition, SinkConfig, TopicState, TopicSubscription, WebhookAuth,
+    WebhookPublicKey, WebhookVerification, WorkerError,
+};
 use axum::{
     extract::{Path, Query, State},
+    http::{HeaderName, HeaderValue},
     Json,
 };
 use serde::{Deserialize, Serialize};
-use tracing::info;
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+use tracing::{info, warn};
 use utoipa::{IntoParams, ToSchema};
 
 /// Query parameters for listing credentials
@@ -45,14 +59,25 @@ pub async fn list_credentials(
     State(state): State<AppState>,
     Query(query): Query<ListQuery>,
 ) -> AppResult<Json<ListCredentialsResponse>> {
+    // Two queries regardless of credential count, joined in memory
     let credentials = state.repo.list_credentials(query.active_only).await?;
-    let pool = state.listener_pool.read().await;
-    
+    let mut message_stats: HashMap<String, _> = state
+        .repo
+        .message_stats_by_credential()
+        .await?
+        .into_iter()
+        .map(|s| (s.credential_id.clone(), s))
+        .collect();
+    let running = state.listener_pool.read().await.running_ids().await;
+
     let responses: Vec<CredentialResponse> = credentials
         .iter()
         .map(|c| {
-            let is_listening = futures::executor::block_on(pool.is_running(&c.id));
-            c.to_response(is_listening)
+            let stats = message_stats.remove(&c.id);
+            let mut response = c.to_response(running.contains(&c.id));
+            response.message_count = Some(stats.as_ref().map_or(0, |s| s.message_count));
+            response.last_message_at = stats.and_then(|s| s.last_received_at);
+            response
         })
         .collect();
 
@@ -64,13 +89,22 @@ pub async fn list_credentials(
     }))
 }
 
+/// Query parameters for getting a credential
+#[derive(Debug, Deserialize, IntoParams, ToSchema)]
+pub struct GetCredentialQuery {
+    /// Show the full gcm_token instead of a masked one
+    #[serde(default)]
+    pub reveal: bool,
+}
+
 /// Get a single credential
 #[utoipa::path(
     get,
     path = "/api/credentials/{id}",
     tag = "credentials",
     params(
-        ("id" = String, Path, description = "Credential ID")
+        ("id" = String, Path, description = "Credential ID"),
+        GetCredentialQuery
     ),
     security(
         ("api_key" = []),
@@ -85,6 +119,7 @@ pub async fn list_credentials(
 pub async fn get_credential(
     State(state): State<AppState>,
     Path(id): Path<String>,
+    Query(query): Query<GetCredentialQuery>,
 ) -> AppResult<Json<CredentialResponse>> {
     let credential = state
         .repo
@@ -95,7 +130,190 @@ pub async fn get_credential(
     let pool = state.listener_pool.read().await;
     let is_listening = pool.is_running(&id).await;
 
-    Ok(Json(credential.to_response(is_listening)))
+    let mut response = credential.to_response(is_listening);
+    if query.reveal {
+        info!("Revealed gcm_token of credential {}", id);
+        response.gcm_token = credential.gcm_token.clone();
+    }
+
+    Ok(Json(response))
+}
+
+/// Worker status response
+#[derive(Debug, Serialize, ToSchema)]
+pub struct WorkerStatusResponse {
+    /// Credential ID
+    pub id: String,
+    /// Whether FCM listener is currently running
+    pub is_listening: bool,
+    /// Whether received messages are delivered live (false = logged only)
+    pub webhook_enabled: bool,
+    /// Number of reconnect attempts since the worker started
+    pub retry_count: u32,
+    /// Most recent listener error, classified by cause
+    pub last_error: Option<WorkerError>,
+    /// Webhook delivery queue (null when no worker has been started)
+    pub queue: Option<QueueStats>,
+    /// Next scheduled start/stop (null when the credential has no schedule)
+    pub next_transition: Option<ScheduledTransition>,
+    /// Start of the current FCM connection (resets on every reconnect, null while disconnected)
+    pub connected_since: Option<chrono::DateTime<chrono::Utc>>,
+    /// Messages received in the last minute
+    pub messages_last_minute: u64,
+    /// Average messages per second over the last minute
+    pub messages_per_second: f64,
+    /// Subscription outcome per topic from the latest connection
+    pub topic_subscriptions: BTreeMap<String, TopicSubscription>,
+    /// Set when the worker stopped after too many failed FCM registrations; cleared by
+    /// starting it again
+    pub needs_attention: Option<NeedsAttention>,
+}
+
+/// Get listener status for a credential
+#[utoipa::path(
+    get,
+    path = "/api/credentials/{id}/status",
+    tag = "credentials",
+    params(
+        ("id" = String, Path, description = "Credential ID")
+    ),
+    security(
+        ("api_key" = []),
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Worker status", body = WorkerStatusResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Credential not found")
+    )
+)]
+pub async fn get_credential_status(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> AppResult<Json<WorkerStatusResponse>> {
+    let credential = state
+        .repo
+        .get_credential(&id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Credential {} not found", id)))?;
+
+    let pool = state.listener_pool.read().await;
+    let is_listening = pool.is_running(&id).await;
+    let status = pool.worker_status(&id).await.unwrap_or_default();
+    let needs_attention = pool.needs_attention(&id);
+
+    let next_transition = credential
+        .schedule()
+        .and_then(|config| CredentialSchedule::parse(&config).ok())
+        .and_then(|schedule| schedule.next_transition(chrono::Utc::now()));
+
+    let now = std::time::Instant::now();
+
+    Ok(Json(WorkerStatusResponse {
+        id,
+        is_listening,
+        webhook_enabled: credential.webhook_enabled,
+        retry_count: status.retry_count,
+        last_error: status.last_error,
+        queue: status.queue,
+        next_transition,
+        connected_since: status.connected_since,
+        messages_last_minute: status.message_rate.count(now),
+        messages_per_second: status.message_rate.per_second(now),
+        topic_subscriptions: status.topics,
+        needs_attention,
+    }))
+}
+
+/// Subscription state of one of a credential's topics
+#[derive(Debug, Serialize, ToSchema)]
+pub struct TopicStatus {
+    /// Topic name
+    pub topic: String,
+    /// Current subscription state
+    pub state: TopicState,
+    /// Error of the last failed attempt
+    pub error: Option<String>,
+    /// Attempts since the worker started
+    pub attempts: u32,
+    /// When the state last changed (null if never attempted)
+    pub updated_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Topics of a credential with their subscription state
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ListTopicsResponse {
+    /// Credential ID
+    pub id: String,
+    /// Whether FCM listener is currently running
+    pub is_listening: bool,
+    pub topics: Vec<TopicStatus>,
+}
+
+/// Stored topics joined with the worker's subscription outcomes. Without a running
+/// worker every topic is inactive; topics the worker hasn't tried yet are pending.
+fn topic_statuses(
+    topics: Vec<String>,
+    is_listening: bool,
+    subscriptions: &BTreeMap<String, TopicSubscription>,
+) -> Vec<TopicStatus> {
+    topics
+        .into_iter()
+        .map(|topic| {
+            let subscription = subscriptions.get(&topic);
+            let state = match subscription {
+                _ if !is_listening => TopicState::Inactive,
+                Some(s) => s.state,
+                None => TopicState::Pending,
+            };
+            TopicStatus {
+                state,
+                error: subscription.and_then(|s| s.error.clone()),
+                attempts: subscription.map(|s| s.attempts).unwrap_or(0),
+                updated_at: subscription.map(|s| s.updated_at),
+                topic,
+            }
+        })
+        .collect()
+}
+
+/// List a credential's topics with their current subscription state
+#[utoipa::path(
+    get,
+    path = "/api/credentials/{id}/topics",
+    tag = "credentials",
+    params(
+        ("id" = String, Path, description = "Credential ID")
+    ),
+    security(
+        ("api_key" = []),
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Topic subscription states", body = ListTopicsResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Credential not found")
+    )
+)]
+pub async fn list_topics(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> AppResult<Json<ListTopicsResponse>> {
+    if state.repo.get_credential(&id).await?.is_none() {
+        return Err(AppError::NotFound(format!("Credential {} not found", id)));
+    }
+    let mut topics = state.repo.get_credential_topics(&id).await?;
+    topics.sort();
+
+    let pool = state.listener_pool.read().await;
+    let is_listening = pool.is_running(&id).await;
+    let status = pool.worker_status(&id).await.unwrap_or_default();
+
+    Ok(Json(ListTopicsResponse {
+        topics: topic_statuses(topics, is_listening, &status.topics),
+        id,
+        is_listening,
+    }))
 }
 
 /// Response for credential creation
@@ -107,135 +325,1455 @@ pub struct CreateCredentialResponse {
     pub message: String,
 }
 
-/// Create a new credential (does NOT auto-start, use /start endpoint)
+/// Query parameters for credential creation
+#[derive(Debug, Default, Deserialize, IntoParams, ToSchema)]
+pub struct CreateQuery {
+    /// Register the FCM device before responding, so the response carries the fcm_token
+    #[serde(default)]
+    pub register: bool,
+    /// Start the listener right away
+    #[serde(default)]
+    pub start: bool,
+}
+
+/// Create a new credential (does NOT auto-start by default, use /start endpoint).
+/// With `register=true` the FCM device is registered first and nothing is saved if that
+/// fails; with `start=true` the listener is started as well.
+/// With an external_id, creation is idempotent: the existing credential is returned unchanged.
 #[utoipa::path(
     post,
     path = "/api/credentials",
     tag = "credentials",
+    params(CreateQuery),
     request_body = CreateCredentialRequest,
     security(
         ("api_key" = []),
         ("bearer_auth" = [])
     ),
     responses(
-        (status = 200, description = "Credential created (not started)", body = CreateCredentialResponse),
+        (status = 200, description = "Credential created, or existing credential for external_id", body = CreateCredentialResponse),
         (status = 400, description = "Invalid request"),
-        (status = 401, description = "Unauthorized")
+        (status = 401, description = "Unauthorized"),
+        (status = 500, description = "FCM registration failed (credential not saved)")
     )
 )]
 pub async fn create_credential(
     State(state): State<AppState>,
-    Json(req): Json<CreateCredentialRequest>,
+    Query(query): Query<CreateQuery>,
+    Json(mut req): Json<CreateCredentialRequest>,
+) -> AppResult<Json<CreateCredentialResponse>> {
+    // Without a webhook URL the credential is pull-only
+    req.webhook_url = req.webhook_url.filter(|url| !url.is_empty());
+    if let Some(headers) = &req.webhook_headers {
+        req.webhook_headers = Some(normalize_webhook_headers(headers)?);
+    }
+
+    validate_credential_settings(&CredentialSettings {
+        webhook_url: req.webhook_url.as_deref(),
+        max_messages: req.max_messages,
+        retention_days: req.retention_days,
+        lifecycle_webhook_url: req.lifecycle_webhook_url.as_deref(),
+        schedule: req.schedule.as_ref(),
+        topic_webhooks: req.topic_webhooks.as_ref(),
+        sink: req.sink.as_ref(),
+        extract_fields: req.extract_fields.as_ref(),
+        dedup_ignore_fields: req.dedup_ignore_fields.as_deref(),
+        dedup_key_path: req.dedup_key_path.as_deref(),
+        allowed_senders: req.allowed_senders.as_deref(),
+        webhook_auth: req.webhook_auth.as_ref(),
+        webhook_user_agent: req.webhook_user_agent.as_deref(),
+        max_backoff_secs: req.max_backoff_secs,
+        webhook_timeout_secs: req.webhook_timeout_secs,
+        webhook_connect_timeout_secs: req.webhook_connect_timeout_secs,
+        webhook_response_match: req.webhook_response_match.as_ref(),
+        webhook_proxy: req.webhook_proxy.as_deref(),
+    })?;
+
+    if let Some(auth) = &req.webhook_auth {
+        warn_auth_header_conflict(&req.name, req.webhook_headers.as_ref(), auth);
+    }
+
+    req.dedup_group = req.dedup_group.map(|g| g.trim().to_string());
+
+    if let Some(external_id) = &req.external_id {
+        let external_id = external_id.trim();
+        if external_id.is_empty() {
+            return Err(AppError::BadRequest("external_id must not be empty".to_string()));
+        }
+
+        if let Some(existing) = state.repo.get_credential_by_external_id(external_id).await? {
+            info!("Credential for external_id {} already exists: {}", external_id, existing.id);
+            let is_listening = state.listener_pool.read().await.is_running(&existing.id).await;
+            return Ok(Json(CreateCredentialResponse {
+                credential: existing.to_response(is_listening),
+                message: "Credential already exists for this external_id.".to_string(),
+            }));
+        }
+
+        req.external_id = Some(external_id.to_string());
+    }
+
+    let topics = req.topics.clone();
+    let mut credential = Credential::new(req);
+
+    // Register before saving so a failed registration leaves nothing behind
+    if query.register {
+        register_device(&credential).await?.apply_to(&mut credential);
+        info!("Registered FCM device for new credential: {}", credential.name);
+    }
+    
+    // Save to database
+    state.repo.create_credential(&credential).await?;
+    notify_lifecycle(&state, &credential, LifecycleEventKind::Created);
+    if query.register {
+        notify_lifecycle(&state, &credential, LifecycleEventKind::RegistrationCompleted);
+    }
+
+    // Save topics if provided
+    if !topics.is_empty() {
+        state.repo.set_credential_topics(&credential.id, &topics).await?;
+    }
+
+    if get_webhook_verification() != WebhookVerification::Off && credential.has_webhook() {
+        verify_credential_webhook(&state, &mut credential).await?;
+    }
+
+    if !query.start {
+        // NOTE: Do NOT auto-start - user must call /start endpoint
+        info!("Created credential: {} ({}) - use /start to begin listening", credential.name, credential.id);
+
+        return Ok(Json(CreateCredentialResponse {
+            credential: credential.to_response(false),
+            message: "Credential created. Use POST /api/credentials/{id}/start to begin listening.".to_string(),
+        }));
+    }
+
+    state.listener_pool.read().await.start_worker(&credential).await?;
+    info!("Created and started credential: {} ({})", credential.name, credential.id);
+
+    Ok(Json(CreateCredentialResponse {
+        credential: credential.to_response(true),
+        message: "Credential created and listener started.".to_string(),
+    }))
+}
+
+/// Query parameters for credential export
+#[derive(Debug, Deserialize, IntoParams, ToSchema)]
+pub struct ExportQuery {
+    /// Include FCM registration tokens and keys (needed to keep the same device)
+    #[serde(default)]
+    pub include_secrets: bool,
+}
+
+/// Export a credential's full configuration
+#[utoipa::path(
+    get,
+    path = "/api/credentials/{id}/export",
+    tag = "credentials",
+    params(
+        ("id" = String, Path, description = "Credential ID"),
+        ExportQuery
+    ),
+    security(
+        ("api_key" = []),
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Credential export", body = CredentialExport),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Credential not found")
+    )
+)]
+pub async fn export_credential(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(query): Query<ExportQuery>,
+) -> AppResult<Json<CredentialExport>> {
+    let credential = state
+        .repo
+        .get_credential(&id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Credential {} not found", id)))?;
+
+    let topics = state.repo.get_credential_topics(&id).await?;
+
+    info!("Exported credential: {} (include_secrets={})", id, query.include_secrets);
+
+    Ok(Json(CredentialExport::new(&credential, topics, query.include_secrets)))
+}
+
+/// Import a credential from an export (does NOT auto-start, use /start endpoint)
+#[utoipa::path(
+    post,
+    path = "/api/credentials/import",
+    tag = "credentials",
+    request_body = CredentialExport,
+    security(
+        ("api_key" = []),
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Credential imported (not started)", body = CreateCredentialResponse),
+        (status = 400, description = "Invalid or unsupported export"),
+        (status = 401, description = "Unauthorized"),
+        (status = 409, description = "A credential with the same external_id exists")
+    )
+)]
+pub async fn import_credential(
+    State(state): State<AppState>,
+    Json(mut export): Json<CredentialExport>,
+) -> AppResult<Json<CreateCredentialResponse>> {
+    if export.schema_version != EXPORT_SCHEMA_VERSION {
+        return Err(AppError::BadRequest(format!(
+            "Unsupported export schema version {} (expected {})",
+            export.schema_version, EXPORT_SCHEMA_VERSION
+        )));
+    }
+
+    let imported = &mut export.credential;
+    imported.webhook_url = imported.webhook_url.take().filter(|url| !url.is_empty());
+    if let Some(headers) = &imported.webhook_headers {
+        imported.webhook_headers = Some(normalize_webhook_headers(headers)?);
+    }
+
+    validate_credential_settings(&CredentialSettings {
+        webhook_url: imported.webhook_url.as_deref(),
+        max_messages: imported.max_messages,
+        retention_days: imported.retention_days,
+        lifecycle_webhook_url: imported.lifecycle_webhook_url.as_deref(),
+        schedule: imported.schedule.as_ref(),
+        topic_webhooks: imported.topic_webhooks.as_ref(),
+        sink: Some(&imported.sink),
+        extract_fields: imported.extract_fields.as_ref(),
+        dedup_ignore_fields: Some(&imported.dedup_ignore_fields),
+        dedup_key_path: imported.dedup_key_path.as_deref(),
+        allowed_senders: Some(&imported.allowed_senders),
+        webhook_auth: imported.webhook_auth.as_ref(),
+        webhook_user_agent: imported.webhook_user_agent.as_deref(),
+        max_backoff_secs: imported.max_backoff_secs,
+        webhook_timeout_secs: imported.webhook_timeout_secs,
+        webhook_connect_timeout_secs: imported.webhook_connect_timeout_secs,
+        webhook_response_match: imported.webhook_response_match.as_ref(),
+        webhook_proxy: imported.webhook_proxy.as_deref(),
+    })?;
+
+    imported.dedup_group = imported.dedup_group.take().map(|g| g.trim().to_string());
+
+    if let Some(signing_key) = &export.credential.signing_key {
+        Ed25519Key::from_base64(signing_key).map_err(AppError::BadRequest)?;
+    }
+
+    if let Some(external_id) = &export.credential.external_id {
+        if state.repo.get_credential_by_external_id(external_id).await?.is_some() {
+            return Err(AppError::Conflict(format!(
+                "Credential with external_id {} already exists",
+                external_id
+            )));
+        }
+    }
+
+    let credential = export.to_credential();
+    state.repo.create_credential(&credential).await?;
+    notify_lifecycle(&state, &credential, LifecycleEventKind::Created);
+
+    if !export.credential.topics.is_empty() {
+        state
+            .repo
+            .set_credential_topics(&credential.id, &export.credential.topics)
+            .await?;
+    }
+
+    let registered = if export.registration.is_some() { "with" } else { "without" };
+    info!(
+        "Imported credential: {} ({}) {} existing registration",
+        credential.name, credential.id, registered
+    );
+
+    Ok(Json(CreateCredentialResponse {
+        credential: credential.to_response(false),
+        message: format!(
+            "Credential imported {} existing registration. Use POST /api/credentials/{{id}}/start to begin listening.",
+            registered
+        ),
+    }))
+}
+
+/// Settings replaced in a clone; everything else is copied from the source credential
+#[derive(Debug, Default, Deserialize, ToSchema)]
+pub struct CloneCredentialRequest {
+    /// Name of the clone (default: the source's name followed by "copy")
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub api_key: Option<String>,
+    #[serde(default)]
+    pub app_id: Option<String>,
+    #[serde(default)]
+    pub project_id: Option<String>,
+    #[serde(default)]
+    pub webhook_url: Option<String>,
+    /// external_id of the clone (the source's is never copied, since it is unique)
+    #[serde(default)]
+    pub external_id: Option<String>,
+}
+
+/// Create a new credential with another one's configuration and topics, but its own
+/// FCM registration (does NOT auto-start, use /start endpoint)
+#[utoipa::path(
+    post,
+    path = "/api/credentials/{id}/clone",
+    tag = "credentials",
+    params(
+        ("id" = String, Path, description = "Credential ID to copy")
+    ),
+    request_body = CloneCredentialRequest,
+    security(
+        ("api_key" = []),
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Credential cloned (not started)", body = CreateCredentialResponse),
+        (status = 400, description = "Invalid override"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Credential not found"),
+        (status = 409, description = "A credential with the same external_id exists")
+    )
+)]
+pub async fn clone_credential(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    body: Option<Json<CloneCredentialRequest>>,
 ) -> AppResult<Json<CreateCredentialResponse>> {
-    // Validate webhook URL
-    if !req.webhook_url.starts_with("http://") && !req.webhook_url.starts_with("https://") {
+    let req = body.map(|Json(req)| req).unwrap_or_default();
+    let source = state
+        .repo
+        .get_credential(&id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Credential {} not found", id)))?;
+    let topics = state.repo.get_credential_topics(&id).await?;
+
+    // The export carries the full configuration, including the webhook secrets and auth the
+    // receiver expects; the registration is dropped so the clone registers its own device
+    let mut export = CredentialExport::new(&source, topics, true);
+    export.registration = None;
+    let cloned = &mut export.credential;
+
+    cloned.name = req.name.unwrap_or_else(|| format!("{} copy", source.name));
+    if let Some(api_key) = req.api_key {
+        cloned.api_key = api_key;
+    }
+    if let Some(app_id) = req.app_id {
+        cloned.app_id = app_id;
+    }
+    if let Some(project_id) = req.project_id {
+        cloned.project_id = project_id;
+    }
+    if let Some(url) = req.webhook_url {
+        if !url.is_empty() {
+            validate_webhook_url(&url)?;
+        }
+        cloned.webhook_url = Some(url).filter(|url| !url.is_empty());
+    }
+
+    cloned.external_id = match req.external_id.as_deref().map(str::trim) {
+        Some("") => {
+            return Err(AppError::BadRequest("external_id must not be empty".to_string()));
+        }
+        Some(external_id) => {
+            if state.repo.get_credential_by_external_id(external_id).await?.is_some() {
+                return Err(AppError::Conflict(format!(
+                    "Credential with external_id {} already exists",
+                    external_id
+                )));
+            }
+            Some(external_id.to_string())
+        }
+        None => None,
+    };
+
+    let mut credential = export.to_credential();
+    state.repo.create_credential(&credential).await?;
+    notify_lifecycle(&state, &credential, LifecycleEventKind::Created);
+
+    if !export.credential.topics.is_empty() {
+        state
+            .repo
+            .set_credential_topics(&credential.id, &export.credential.topics)
+            .await?;
+    }
+
+    if get_webhook_verification() != WebhookVerification::Off && credential.has_webhook() {
+        verify_credential_webhook(&state, &mut credential).await?;
+    }
+
+    info!(
+        "Cloned credential {} into: {} ({})",
+        source.id, credential.name, credential.id
+    );
+
+    Ok(Json(CreateCredentialResponse {
+        credential: credential.to_response(false),
+        message: "Credential cloned. Use POST /api/credentials/{id}/start to begin listening.".to_string(),
+    }))
+}
+
+/// Update a credential (restarts worker if running to apply changes)
+#[utoipa::path(
+    put,
+    path = "/api/credentials/{id}",
+    tag = "credentials",
+    params(
+        ("id" = String, Path, description = "Credential ID")
+    ),
+    request_body = UpdateCredentialRequest,
+    security(
+        ("api_key" = []),
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Credential updated (worker restarted if running)", body = CredentialResponse),
+        (status = 400, description = "Invalid request"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Credential not found")
+    )
+)]
+pub async fn update_credential(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(req): Json<UpdateCredentialRequest>,
+) -> AppResult<Json<CredentialResponse>> {
+    // Check if exists
+    let old_credential = state
+        .repo
+        .get_credential(&id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Credential {} not found", id)))?;
+
+    // Only the settings being changed are checked; an empty webhook URL switches to
+    // pull-only mode
+    validate_credential_settings(&CredentialSettings {
+        webhook_url: req.webhook_url.as_deref(),
+        max_messages: req.max_messages,
+        retention_days: req.retention_days,
+        lifecycle_webhook_url: req.lifecycle_webhook_url.as_deref(),
+        topic_webhooks: req.topic_webhooks.as_ref(),
+        sink: req.sink.as_ref(),
+        extract_fields: req.extract_fields.as_ref(),
+        dedup_ignore_fields: req.dedup_ignore_fields.as_deref(),
+        dedup_key_path: req.dedup_key_path.as_deref(),
+        allowed_senders: req.allowed_senders.as_deref(),
+        webhook_auth: req.webhook_auth.as_ref(),
+        webhook_user_agent: req.webhook_user_agent.as_deref(),
+        max_backoff_secs: req.max_backoff_secs,
+        webhook_timeout_secs: req.webhook_timeout_secs,
+        webhook_connect_timeout_secs: req.webhook_connect_timeout_secs,
+        webhook_response_match: req.webhook_response_match.as_ref(),
+        webhook_proxy: req.webhook_proxy.as_deref(),
+        ..Default::default()
+    })?;
+
+    let webhook_headers = req
+        .webhook_headers
+        .as_ref()
+        .map(normalize_webhook_headers)
+        .transpose()?;
+
+    // Check the settings the credential ends up with, not just the ones being changed
+    let effective_auth = match &req.webhook_auth {
+        Some(auth) => Some(auth.clone()).filter(|a| a.scheme != AuthScheme::None),
+        None => old_credential.webhook_auth(),
+    };
+    if let Some(auth) = &effective_auth {
+        let headers = webhook_headers.clone().or_else(|| old_credential.get_webhook_headers());
+        let name = req.name.as_deref().unwrap_or(&old_credential.name);
+        warn_auth_header_conflict(name, headers.as_ref(), auth);
+    }
+
+    // Update in database
+    let mut update = req.to_update();
+    update.webhook_headers = webhook_headers
+        .as_ref()
+        .map(|h| serde_json::to_string(h).unwrap_or_default());
+    state.repo.update_credential(&id, &update).await?;
+
+    // A new receiver has to pass the handshake again
+    if req
+        .webhook_url
+        .as_ref()
+        .is_some_and(|url| *url != old_credential.webhook_url)
+    {
+        state.repo.set_webhook_verified(&id, false).await?;
+        if get_webhook_verification() != WebhookVerification::Off {
+            if let Some(mut credential) = state.repo.get_credential(&id).await? {
+                if credential.has_webhook() {
+                    verify_credential_webhook(&state, &mut credential).await?;
+                }
+            }
+        }
+    }
+    state.credential_cache.invalidate(&id);
+
+    // Update topics if provided, touching only those that changed
+    let topic_changes = match &req.topics {
+        Some(topics) => state.repo.set_credential_topics(&id, topics).await?,
+        None => TopicChanges::default(),
+    };
+    if !topic_changes.is_empty() {
+        info!(
+            "Topics changed for credential {}: added {:?}, removed {:?}",
+            id, topic_changes.added, topic_changes.removed
+        );
+    }
+
+    // Get updated credential
+    let updated_credential = state.repo.get_credential(&id).await?.unwrap();
+    let pool = state.listener_pool.read().await;
+
+    // Check if worker was running - if so, restart to apply changes. The FCM client can't
+    // subscribe or unsubscribe at runtime, so topic changes take effect through the restart;
+    // an update that leaves everything as it was doesn't restart the worker.
+    let was_running = pool.is_running(&id).await;
+    if was_running && (req.changes_settings() || !topic_changes.is_empty()) {
+        info!("Restarting worker to apply credential changes: {}", id);
+        let _ = pool.restart_worker(&updated_credential).await;
+    }
+
+    // If is_active was set to false, stop the worker
+    if req.is_active == Some(false) && was_running {
+        let _ = pool.stop_worker(&id).await;
+    }
+
+    let is_listening = pool.is_running(&id).await;
+    info!("Updated credential: {} (was_running={}, is_listening={})", id, was_running, is_listening);
+
+    Ok(Json(updated_credential.to_response(is_listening)))
+}
+
+/// Delivery settings of a credential and, while it listens, its live queue
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DeliverySettingsResponse {
+    /// Credential ID
+    pub id: String,
+    pub settings: DeliverySettings,
+    /// Whether FCM listener is currently running
+    pub is_listening: bool,
+    /// Live queue depth and deliveries in flight (null without a running worker)
+    pub queue: Option<QueueStats>,
+}
+
+async fn delivery_settings_response(
+    state: &AppState,
+    credential: &Credential,
+) -> DeliverySettingsResponse {
+    let pool = state.listener_pool.read().await;
+    let is_listening = pool.is_running(&credential.id).await;
+    let queue = match is_listening {
+        true => pool.worker_status(&credential.id).await.and_then(|s| s.queue),
+        false => None,
+    };
+
+    DeliverySettingsResponse {
+        id: credential.id.clone(),
+        settings: credential.delivery_settings(),
+        is_listening,
+        queue,
+    }
+}
+
+/// Get a credential's delivery concurrency and queue settings
+#[utoipa::path(
+    get,
+    path = "/api/credentials/{id}/delivery",
+    tag = "credentials",
+    params(
+        ("id" = String, Path, description = "Credential ID")
+    ),
+    security(
+        ("api_key" = []),
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Delivery settings", body = DeliverySettingsResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Credential not found")
+    )
+)]
+pub async fn get_delivery_settings(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> AppResult<Json<DeliverySettingsResponse>> {
+    let credential = state
+        .repo
+        .get_credential(&id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Credential {} not found", id)))?;
+
+    Ok(Json(delivery_settings_response(&state, &credential).await))
+}
+
+/// Change a credential's delivery concurrency and queue settings. A running worker
+/// picks them up immediately, without a restart.
+#[utoipa::path(
+    patch,
+    path = "/api/credentials/{id}/delivery",
+    tag = "credentials",
+    params(
+        ("id" = String, Path, description = "Credential ID")
+    ),
+    request_body = DeliverySettingsUpdate,
+    security(
+        ("api_key" = []),
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Delivery settings updated", body = DeliverySettingsResponse),
+        (status = 400, description = "Invalid settings"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Credential not found")
+    )
+)]
+pub async fn update_delivery_settings(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(update): Json<DeliverySettingsUpdate>,
+) -> AppResult<Json<DeliverySettingsResponse>> {
+    let mut credential = state
+        .repo
+        .get_credential(&id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Credential {} not found", id)))?;
+
+    let settings = credential
+        .delivery_settings()
+        .apply(&update)
+        .map_err(AppError::BadRequest)?;
+    state.repo.set_delivery_settings(&id, &settings).await?;
+    state.credential_cache.invalidate(&id);
+    credential.delivery_settings = Some(serde_json::to_string(&settings).unwrap_or_default());
+
+    let applied = state
+        .listener_pool
+        .read()
+        .await
+        .apply_delivery_settings(&id, settings)
+        .await;
+    info!(
+        "Updated delivery settings for {}: concurrency={}, queue_capacity={}, queue_policy={:?} (applied_live={})",
+        id, settings.concurrency, settings.queue_capacity, settings.queue_policy, applied
+    );
+
+    Ok(Json(delivery_settings_response(&state, &credential).await))
+}
+
+/// Set a credential's listening schedule (start/stop cron expressions)
+#[utoipa::path(
+    put,
+    path = "/api/credentials/{id}/schedule",
+    tag = "credentials",
+    params(
+        ("id" = String, Path, description = "Credential ID")
+    ),
+    request_body = ScheduleConfig,
+    security(
+        ("api_key" = []),
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Schedule updated", body = CredentialResponse),
+        (status = 400, description = "Invalid cron expression or timezone"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Credential not found")
+    )
+)]
+pub async fn set_schedule(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Json(schedule): Json<ScheduleConfig>,
+) -> AppResult<Json<CredentialResponse>> {
+    CredentialSchedule::parse(&schedule).map_err(AppError::BadRequest)?;
+
+    if !state.repo.set_credential_schedule(&id, Some(&schedule)).await? {
+        return Err(AppError::NotFound(format!("Credential {} not found", id)));
+    }
+    state.credential_cache.invalidate(&id);
+
+    info!("Updated schedule for credential: {}", id);
+    credential_response(&state, &id).await
+}
+
+/// Remove a credential's listening schedule (the listener keeps its current state)
+#[utoipa::path(
+    delete,
+    path = "/api/credentials/{id}/schedule",
+    tag = "credentials",
+    params(
+        ("id" = String, Path, description = "Credential ID")
+    ),
+    security(
+        ("api_key" = []),
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Schedule removed", body = CredentialResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Credential not found")
+    )
+)]
+pub async fn delete_schedule(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> AppResult<Json<CredentialResponse>> {
+    if !state.repo.set_credential_schedule(&id, None).await? {
+        return Err(AppError::NotFound(format!("Credential {} not found", id)));
+    }
+    state.credential_cache.invalidate(&id);
+
+    info!("Removed schedule for credential: {}", id);
+    credential_response(&state, &id).await
+}
+
+/// Request to install a new webhook signing secret
+#[derive(Debug, Default, Deserialize, ToSchema)]
+pub struct RotateWebhookSecretRequest {
+    /// New secret (a random one is generated when omitted)
+    #[serde(default)]
+    pub secret: Option<String>,
+}
+
+/// Result of a webhook secret rotation
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RotateWebhookSecretResponse {
+    /// The new current secret (only returned here)
+    pub secret: String,
+    /// Whether the previous secret is still signed with until the rotation is finalized
+    pub rotating: bool,
+    /// Updated credential
+    pub credential: CredentialResponse,
+}
+
+/// Install a new webhook signing secret; the current one stays active as previous
+/// until the rotation is finalized
+#[utoipa::path(
+    post,
+    path = "/api/credentials/{id}/webhook-secret",
+    tag = "credentials",
+    params(
+        ("id" = String, Path, description = "Credential ID")
+    ),
+    request_body = RotateWebhookSecretRequest,
+    security(
+        ("api_key" = []),
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Secret rotated", body = RotateWebhookSecretResponse),
+        (status = 400, description = "Empty secret"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Credential not found")
+    )
+)]
+pub async fn rotate_webhook_secret(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    body: Option<Json<RotateWebhookSecretRequest>>,
+) -> AppResult<Json<RotateWebhookSecretResponse>> {
+    let req = body.map(|Json(req)| req).unwrap_or_default();
+    let secret = match req.secret {
+        Some(secret) if secret.trim().is_empty() => {
+            return Err(AppError::BadRequest("Webhook secret cannot be empty".to_string()));
+        }
+        Some(secret) => secret,
+        None => crate::middleware::generate_api_key(),
+    };
+
+    if !state.repo.rotate_webhook_secret(&id, &secret).await? {
+        return Err(AppError::NotFound(format!("Credential {} not found", id)));
+    }
+
+    let Json(credential) = apply_signing_change(&state, &id).await?;
+    info!("Rotated webhook secret for credential: {}", id);
+
+    Ok(Json(RotateWebhookSecretResponse {
+        secret,
+        rotating: credential.webhook_secret_rotating,
+        credential,
+    }))
+}
+
+/// Finalize a webhook secret rotation, dropping the previous secret
+#[utoipa::path(
+    post,
+    path = "/api/credentials/{id}/webhook-secret/finalize",
+    tag = "credentials",
+    params(
+        ("id" = String, Path, description = "Credential ID")
+    ),
+    security(
+        ("api_key" = []),
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Rotation finalized", body = CredentialResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Credential not found")
+    )
+)]
+pub async fn finalize_webhook_secret(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> AppResult<Json<CredentialResponse>> {
+    if !state.repo.clear_previous_webhook_secret(&id).await? {
+        return Err(AppError::NotFound(format!("Credential {} not found", id)));
+    }
+
+    info!("Finalized webhook secret rotation for credential: {}", id);
+    apply_signing_change(&state, &id).await
+}
+
+/// Result of generating a credential signing key
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SigningKeyResponse {
+    /// Public key receivers verify X-Webhook-Signature-Ed25519 with
+    pub public_key: WebhookPublicKey,
+    /// Updated credential
+    pub credential: CredentialResponse,
+}
+
+/// Generate an Ed25519 key for signing this credential's webhook bodies, replacing
+/// its current one. The private key never leaves the server (except in exports with secrets).
+#[utoipa::path(
+    post,
+    path = "/api/credentials/{id}/signing-key",
+    tag = "credentials",
+    params(
+        ("id" = String, Path, description = "Credential ID")
+    ),
+    security(
+        ("api_key" = []),
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Key generated", body = SigningKeyResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Credential not found")
+    )
+)]
+pub async fn generate_signing_key(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> AppResult<Json<SigningKeyResponse>> {
+    let key = Ed25519Key::generate();
+    if !state.repo.set_signing_key(&id, Some(&key.to_base64())).await? {
+        return Err(AppError::NotFound(format!("Credential {} not found", id)));
+    }
+
+    let Json(credential) = apply_signing_change(&state, &id).await?;
+    info!("Generated signing key {} for credential: {}", key.key_id(), id);
+
+    Ok(Json(SigningKeyResponse {
+        public_key: key.public_key(),
+        credential,
+    }))
+}
+
+/// Remove the credential's own signing key; WEBHOOK_SIGNING_KEY is used instead when set
+#[utoipa::path(
+    delete,
+    path = "/api/credentials/{id}/signing-key",
+    tag = "credentials",
+    params(
+        ("id" = String, Path, description = "Credential ID")
+    ),
+    security(
+        ("api_key" = []),
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Key removed", body = CredentialResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Credential not found")
+    )
+)]
+pub async fn delete_signing_key(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> AppResult<Json<CredentialResponse>> {
+    if !state.repo.set_signing_key(&id, None).await? {
+        return Err(AppError::NotFound(format!("Credential {} not found", id)));
+    }
+
+    info!("Removed signing key for credential: {}", id);
+    apply_signing_change(&state, &id).await
+}
+
+/// Run the verification handshake against a credential's webhook_url and store the outcome.
+/// Returns why verification failed, if it did.
+async fn verify_credential_webhook(
+    state: &AppState,
+    credential: &mut Credential,
+) -> AppResult<Option<String>> {
+    let client = match credential.proxy_override() {
+        Some(proxy) => state.webhook_client.via_proxy(proxy),
+        None => state.webhook_client.clone(),
+    }
+    .with_auth(credential.webhook_auth())
+    .with_user_agent(credential.webhook_user_agent.clone())
+    .with_timeouts(credential.webhook_timeouts());
+    let headers = credential.get_webhook_headers();
+    let outcome = client
+        .verify_endpoint(&credential.webhook_url, headers.as_ref())
+        .await;
+
+    let verified = outcome.is_ok();
+    state.repo.set_webhook_verified(&credential.id, verified).await?;
+    state.credential_cache.invalidate(&credential.id);
+    credential.webhook_verified = verified;
+    credential.webhook_verified_at = verified.then(chrono::Utc::now);
+
+    match outcome {
+        Ok(()) => {
+            info!("Verified webhook for credential: {}", credential.id);
+            Ok(None)
+        }
+        Err(e) => {
+            warn!("Webhook verification failed for credential {}: {}", credential.id, e);
+            Ok(Some(e))
+        }
+    }
+}
+
+/// Result of a webhook verification handshake
+#[derive(Debug, Serialize, ToSchema)]
+pub struct VerifyWebhookResponse {
+    /// Whether the receiver echoed the challenge
+    pub verified: bool,
+    /// Why verification failed
+    pub error: Option<String>,
+    /// Updated credential
+    pub credential: CredentialResponse,
+}
+
+/// Send a verification challenge to the credential's webhook_url and record whether the
+/// receiver echoed it. Works in every WEBHOOK_VERIFICATION mode.
+#[utoipa::path(
+    post,
+    path = "/api/credentials/{id}/verify-webhook",
+    tag = "credentials",
+    params(
+        ("id" = String, Path, description = "Credential ID")
+    ),
+    security(
+        ("api_key" = []),
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Handshake outcome", body = VerifyWebhookResponse),
+        (status = 400, description = "Credential has no webhook_url"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Credential not found")
+    )
+)]
+pub async fn verify_webhook(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> AppResult<Json<VerifyWebhookResponse>> {
+    let mut credential = state
+        .repo
+        .get_credential(&id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Credential {} not found", id)))?;
+
+    if !credential.has_webhook() {
+        return Err(AppError::BadRequest(format!(
+            "Credential {} has no webhook_url to verify",
+            id
+        )));
+    }
+
+    let was_verified = credential.webhook_verified;
+    let error = verify_credential_webhook(&state, &mut credential).await?;
+
+    // Running workers decide whether to deliver when they start
+    let pool = state.listener_pool.read().await;
+    if was_verified != credential.webhook_verified
+        && get_webhook_verification() == WebhookVerification::Require
+        && pool.is_running(&id).await
+    {
+        info!("Restarting worker to apply webhook verification: {}", id);
+        let _ = pool.restart_worker(&credential).await;
+    }
+    let is_listening = pool.is_running(&id).await;
+
+    Ok(Json(VerifyWebhookResponse {
+        verified: error.is_none(),
+        error,
+        credential: credential.to_response(is_listening),
+    }))
+}
+
+/// Query parameters for webhook debug logging
+#[derive(Debug, Deserialize, IntoParams, ToSchema)]
+pub struct DebugWebhookQuery {
+    /// Switch debug logging on (default) or off
+    #[serde(default = "default_debug_enabled")]
+    pub enabled: bool,
+    /// How long it stays on (default: WEBHOOK_DEBUG_TTL_SECS, at most one day)
+    pub ttl_secs: Option<u64>,
+}
+
+fn default_debug_enabled() -> bool {
+    true
+}
+
+/// Webhook debug logging state of a credential
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DebugWebhookResponse {
+    /// Credential ID
+    pub id: String,
+    /// Whether deliveries are being logged in full
+    pub enabled: bool,
+    /// When debug logging switches itself off
+    pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Log this credential's webhook deliveries in full (request and response headers, bodies,
+/// timing) for a limited time. Headers in WEBHOOK_DEBUG_REDACT_HEADERS are masked.
+#[utoipa::path(
+    post,
+    path = "/api/credentials/{id}/debug-webhook",
+    tag = "credentials",
+    params(
+        ("id" = String, Path, description = "Credential ID"),
+        DebugWebhookQuery
+    ),
+    security(
+        ("api_key" = []),
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Debug logging updated", body = DebugWebhookResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Credential not found")
+    )
+)]
+pub async fn debug_webhook(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(query): Query<DebugWebhookQuery>,
+) -> AppResult<Json<DebugWebhookResponse>> {
+    if state.repo.get_credential(&id).await?.is_none() {
+        return Err(AppError::NotFound(format!("Credential {} not found", id)));
+    }
+
+    let debug = state.webhook_client.debug();
+    let expires_at = if query.enabled {
+        let ttl = query
+            .ttl_secs
+            .map(Duration::from_secs)
+            .unwrap_or_else(get_webhook_debug_ttl);
+        let expires_at = debug.enable(&id, ttl);
+        info!("Webhook debug logging enabled for credential {} until {}", id, expires_at);
+        Some(expires_at)
+    } else {
+        debug.disable(&id);
+        info!("Webhook debug logging disabled for credential {}", id);
+        None
+    };
+
+    Ok(Json(DebugWebhookResponse {
+        id,
+        enabled: expires_at.is_some(),
+        expires_at,
+    }))
+}
+
+/// Query parameters for resetting dedup state
+#[derive(Debug, Deserialize, IntoParams, ToSchema)]
+pub struct DedupResetQuery {
+    /// Also forget the dedup keys of logged messages (default: in-memory cache only)
+    #[serde(default)]
+    pub persistent: bool,
+}
+
+/// Dedup entries cleared for a credential
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DedupResetResponse {
+    /// Credential ID
+    pub id: String,
+    /// Entries cleared from the running worker's in-memory cache (its dedup group's, when
+    /// it shares one)
+    pub in_memory_cleared: usize,
+    /// Logged messages whose dedup key was forgotten
+    pub persistent_cleared: u64,
+}
+
+/// Clear a credential's dedup state after changing its dedup settings (dedup_key_path,
+/// dedup_ignore_fields, dedup_group), so the new scheme takes effect without a restart
+#[utoipa::path(
+    post,
+    path = "/api/credentials/{id}/dedup/reset",
+    tag = "credentials",
+    params(
+        ("id" = String, Path, description = "Credential ID"),
+        DedupResetQuery
+    ),
+    security(
+        ("api_key" = []),
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Dedup state cleared", body = DedupResetResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Credential not found")
+    )
+)]
+pub async fn reset_dedup(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(query): Query<DedupResetQuery>,
+) -> AppResult<Json<DedupResetResponse>> {
+    if state.repo.get_credential(&id).await?.is_none() {
+        return Err(AppError::NotFound(format!("Credential {} not found", id)));
+    }
+
+    let in_memory_cleared = state.listener_pool.read().await.reset_dedup(&id).await;
+    let persistent_cleared = if query.persistent {
+        state.repo.clear_dedup_keys(&id).await?
+    } else {
+        0
+    };
+
+    info!(
+        "Dedup state reset for credential {}: {} in-memory, {} persistent entries cleared",
+        id, in_memory_cleared, persistent_cleared
+    );
+
+    Ok(Json(DedupResetResponse {
+        id,
+        in_memory_cleared,
+        persistent_cleared,
+    }))
+}
+
+/// Restart a running listener so it signs with the updated secrets
+async fn apply_signing_change(state: &AppState, id: &str) -> AppResult<Json<CredentialResponse>> {
+    state.credential_cache.invalidate(id);
+    let credential = state
+        .repo
+        .get_credential(id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Credential {} not found", id)))?;
+
+    let pool = state.listener_pool.read().await;
+    if pool.is_running(id).await {
+        info!("Restarting worker to apply webhook secret change: {}", id);
+        let _ = pool.restart_worker(&credential).await;
+    }
+    let is_listening = pool.is_running(id).await;
+
+    Ok(Json(credential.to_response(is_listening)))
+}
+
+/// Credential settings checked the same way on create, update and import. `None` is a
+/// setting left unset (or unchanged), and empty strings that clear a setting pass.
+#[derive(Default)]
+struct CredentialSettings<'a> {
+    webhook_url: Option<&'a str>,
+    max_messages: Option<i64>,
+    retention_days: Option<i64>,
+    lifecycle_webhook_url: Option<&'a str>,
+    schedule: Option<&'a ScheduleConfig>,
+    topic_webhooks: Option<&'a HashMap<String, String>>,
+    sink: Option<&'a SinkConfig>,
+    extract_fields: Option<&'a HashMap<String, String>>,
+    dedup_ignore_fields: Option<&'a [String]>,
+    dedup_key_path: Option<&'a str>,
+    allowed_senders: Option<&'a [String]>,
+    webhook_auth: Option<&'a WebhookAuth>,
+    webhook_user_agent: Option<&'a str>,
+    max_backoff_secs: Option<i64>,
+    webhook_timeout_secs: Option<i64>,
+    webhook_connect_timeout_secs: Option<i64>,
+    webhook_response_match: Option<&'a ResponseMatch>,
+    webhook_proxy: Option<&'a str>,
+}
+
+fn validate_credential_settings(settings: &CredentialSettings) -> AppResult<()> {
+    if let Some(url) = settings.webhook_url.filter(|v| !v.is_empty()) {
+        validate_webhook_url(url)?;
+    }
+    if matches!(settings.max_messages, Some(m) if m < 1) {
+        return Err(AppError::BadRequest("max_messages must be at least 1".to_string()));
+    }
+    if matches!(settings.retention_days, Some(d) if d < 1) {
+        return Err(AppError::BadRequest("retention_days must be at least 1".to_string()));
+    }
+    if let Some(url) = settings.lifecycle_webhook_url.filter(|v| !v.is_empty()) {
+        validate_lifecycle_webhook_url(url)?;
+    }
+    if let Some(schedule) = settings.schedule {
+        CredentialSchedule::parse(schedule).map_err(AppError::BadRequest)?;
+    }
+    if let Some(topic_webhooks) = settings.topic_webhooks {
+        validate_topic_webhooks(topic_webhooks)?;
+    }
+    if let Some(sink) = settings.sink {
+        sink.validate().map_err(AppError::BadRequest)?;
+    }
+    if let Some(extract_fields) = settings.extract_fields {
+        validate_extract_fields(extract_fields)?;
+    }
+    if let Some(fields) = settings.dedup_ignore_fields {
+        validate_dedup_ignore_fields(fields)?;
+    }
+    if let Some(path) = settings.dedup_key_path.filter(|v| !v.is_empty()) {
+        validate_dedup_key_path(path)?;
+    }
+    if let Some(senders) = settings.allowed_senders {
+        validate_allowed_senders(senders)?;
+    }
+    if let Some(auth) = settings.webhook_auth {
+        validate_webhook_auth(auth)?;
+    }
+    if let Some(user_agent) = settings.webhook_user_agent.filter(|v| !v.is_empty()) {
+        validate_user_agent(user_agent)?;
+    }
+    if matches!(settings.max_backoff_secs, Some(s) if s < 1) {
+        return Err(AppError::BadRequest("max_backoff_secs must be at least 1".to_string()));
+    }
+    validate_webhook_timeouts(settings.webhook_timeout_secs, settings.webhook_connect_timeout_secs)?;
+    if let Some(response_match) = settings
+        .webhook_response_match
+        .filter(|m| !m.path.trim().is_empty())
+    {
+        validate_response_match(response_match)?;
+    }
+    if let Some(proxy) = settings.webhook_proxy.filter(|v| !v.is_empty()) {
+        validate_proxy(proxy).map_err(AppError::BadRequest)?;
+    }
+    Ok(())
+}
+
+/// Webhook URLs must be http(s)
+fn validate_webhook_url(url: &str) -> AppResult<()> {
+    if !url.starts_with("http://") && !url.starts_with("https://") {
         return Err(AppError::BadRequest("Invalid webhook URL".to_string()));
     }
+    Ok(())
+}
 
-    let topics = req.topics.clone();
-    let credential = Credential::new(req);
-    
-    // Save to database
-    state.repo.create_credential(&credential).await?;
-
-    // Save topics if provided
-    if !topics.is_empty() {
-        state.repo.set_credential_topics(&credential.id, &topics).await?;
+fn validate_lifecycle_webhook_url(url: &str) -> AppResult<()> {
+    if !url.starts_with("http://") && !url.starts_with("https://") {
+        return Err(AppError::BadRequest("Invalid lifecycle_webhook_url".to_string()));
     }
+    Ok(())
+}
 
-    // NOTE: Do NOT auto-start - user must call /start endpoint
-    info!("Created credential: {} ({}) - use /start to begin listening", credential.name, credential.id);
+/// Tell the credential's lifecycle webhook (or LIFECYCLE_WEBHOOK_URL) about a state change
+fn notify_lifecycle(state: &AppState, credential: &Credential, kind: LifecycleEventKind) {
+    state.lifecycle.notify(
+        LifecycleEvent::new(kind, &credential.id)
+            .with_details(serde_json::json!({ "name": credential.name })),
+        credential.lifecycle_webhook_url.as_deref(),
+    );
+}
 
-    Ok(Json(CreateCredentialResponse {
-        credential: credential.to_response(false),
-        message: "Credential created. Use POST /api/credentials/{id}/start to begin listening.".to_string(),
-    }))
+/// Every per-topic override needs a non-empty topic and an http(s) URL
+fn validate_topic_webhooks(topic_webhooks: &HashMap<String, String>) -> AppResult<()> {
+    for (topic, url) in topic_webhooks {
+        if topic.trim().is_empty() {
+            return Err(AppError::BadRequest("Topic name in topic_webhooks cannot be empty".to_string()));
+        }
+        if !url.starts_with("http://") && !url.starts_with("https://") {
+            return Err(AppError::BadRequest(format!("Invalid webhook URL for topic '{}'", topic)));
+        }
+    }
+    Ok(())
 }
 
-/// Update a credential (restarts worker if running to apply changes)
-#[utoipa::path(
-    put,
-    path = "/api/credentials/{id}",
-    tag = "credentials",
-    params(
-        ("id" = String, Path, description = "Credential ID")
-    ),
-    request_body = UpdateCredentialRequest,
-    security(
-        ("api_key" = []),
-        ("bearer_auth" = [])
-    ),
-    responses(
-        (status = 200, description = "Credential updated (worker restarted if running)", body = CredentialResponse),
-        (status = 400, description = "Invalid request"),
-        (status = 401, description = "Unauthorized"),
-        (status = 404, description = "Credential not found")
-    )
-)]
-pub async fn update_credential(
-    State(state): State<AppState>,
-    Path(id): Path<String>,
-    Json(req): Json<UpdateCredentialRequest>,
-) -> AppResult<Json<CredentialResponse>> {
-    // Check if exists
-    let _old_credential = state
-        .repo
-        .get_credential(&id)
-        .await?
-        .ok_or_else(|| AppError::NotFound(format!("Credential {} not found", id)))?;
+/// Headers set by the HTTP client or the server that custom headers may not override
+const PROTECTED_HEADERS: &[&str] = &[
+    "host",
+    "content-length",
+    "transfer-encoding",
+    "connection",
+    "x-webhook-signature",
+    "x-webhook-signature-ed25519",
+    "x-webhook-key-id",
+];
 
-    // Validate webhook URL if provided
-    if let Some(ref url) = req.webhook_url {
-        if !url.starts_with("http://") && !url.starts_with("https://") {
-            return Err(AppError::BadRequest("Invalid webhook URL".to_string()));
+/// Check custom webhook headers and return them with trimmed, lowercased names.
+/// Every offending entry is listed in the error rather than silently dropped at delivery.
+pub(crate) fn normalize_webhook_headers(
+    headers: &HashMap<String, String>,
+) -> AppResult<HashMap<String, String>> {
+    let mut normalized = HashMap::new();
+    let mut problems = Vec::new();
+    let strict_templates = get_header_templates_strict();
+
+    // Sorted so the error message is stable
+    let mut entries: Vec<_> = headers.iter().collect();
+    entries.sort();
+
+    for (name, value) in entries {
+        let key = name.trim().to_ascii_lowercase();
+        let value = value.trim();
+
+        if HeaderName::from_bytes(key.as_bytes()).is_err() {
+            problems.push(format!("'{}': invalid header name", name));
+        } else if PROTECTED_HEADERS.contains(&key.as_str()) {
+            problems.push(format!("'{}': header cannot be overridden", name));
+        } else if HeaderValue::from_str(value).is_err() {
+            problems.push(format!("'{}': invalid header value", name));
+        } else if let Some(unknown) = unknown_placeholders(value)
+            .first()
+            .filter(|_| strict_templates)
+        {
+            problems.push(format!("'{}': unknown placeholder '{{{{{}}}}}'", name, unknown));
+        } else if normalized.insert(key, value.to_string()).is_some() {
+            problems.push(format!("'{}': duplicate header name", name));
         }
     }
 
-    // Update in database
-    let webhook_headers_json = req
-        .webhook_headers
-        .as_ref()
-        .map(|h| serde_json::to_string(h).unwrap_or_default());
+    if problems.is_empty() {
+        Ok(normalized)
+    } else {
+        Err(AppError::BadRequest(format!(
+            "Invalid webhook_headers: {}",
+            problems.join("; ")
+        )))
+    }
+}
 
-    state
-        .repo
-        .update_credential(
-            &id,
-            req.name.as_deref(),
-            req.webhook_url.as_deref(),
-            webhook_headers_json.as_deref(),
-            req.is_active,
-            req.api_key.as_deref(),
-            req.app_id.as_deref(),
-            req.project_id.as_deref(),
-        )
-        .await?;
+/// Per-credential webhook timeouts, when set, are whole seconds of at least 1
+fn validate_webhook_timeouts(request: Option<i64>, connect: Option<i64>) -> AppResult<()> {
+    if matches!(request, Some(s) if s < 1) {
+        return Err(AppError::BadRequest("webhook_timeout_secs must be at least 1".to_string()));
+    }
+    if matches!(connect, Some(s) if s < 1) {
+        return Err(AppError::BadRequest(
+            "webhook_connect_timeout_secs must be at least 1".to_string(),
+        ));
+    }
+    Ok(())
+}
 
-    // Update topics if provided
-    if let Some(topics) = &req.topics {
-        state.repo.set_credential_topics(&id, topics).await?;
+/// A response match needs a path of non-empty keys ("$." prefix optional)
+fn validate_response_match(response_match: &ResponseMatch) -> AppResult<()> {
+    if response_match.keys().iter().any(|k| k.trim().is_empty()) {
+        return Err(AppError::BadRequest(format!(
+            "Invalid webhook_response_match path '{}': expected a dotted path such as $.ok",
+            response_match.path
+        )));
     }
+    Ok(())
+}
 
-    // Get updated credential
-    let updated_credential = state.repo.get_credential(&id).await?.unwrap();
-    let pool = state.listener_pool.read().await;
+/// A User-Agent override must be a valid header value, with known placeholders only when
+/// WEBHOOK_HEADER_TEMPLATES_STRICT is on (like webhook_headers)
+fn validate_user_agent(user_agent: &str) -> AppResult<()> {
+    if HeaderValue::from_str(user_agent.trim()).is_err() {
+        return Err(AppError::BadRequest("Invalid webhook_user_agent".to_string()));
+    }
+    if let Some(unknown) = unknown_placeholders(user_agent)
+        .first()
+        .filter(|_| get_header_templates_strict())
+    {
+        return Err(AppError::BadRequest(format!(
+            "Invalid webhook_user_agent: unknown placeholder '{{{{{}}}}}'",
+            unknown
+        )));
+    }
+    Ok(())
+}
 
-    // Check if worker was running - if so, restart to apply changes
-    let was_running = pool.is_running(&id).await;
-    if was_running {
-        info!("Restarting worker to apply credential changes: {}", id);
-        let _ = pool.restart_worker(&updated_credential).await;
+/// Field names must be non-empty without quotes; paths are dot-separated keys
+fn validate_extract_fields(extract_fields: &HashMap<String, String>) -> AppResult<()> {
+    for (name, path) in extract_fields {
+        if name.trim().is_empty() || name.contains('"') {
+            return Err(AppError::BadRequest(format!("Invalid extract field name '{}'", name)));
+        }
+        if path.split('.').any(|key| key.is_empty()) {
+            return Err(AppError::BadRequest(format!(
+                "Invalid path '{}' for extract field '{}'",
+                path, name
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Dedup paths are dot-separated keys, like extract_fields paths
+fn validate_dedup_ignore_fields(fields: &[String]) -> AppResult<()> {
+    for path in fields {
+        if path.split('.').any(|key| key.trim().is_empty()) {
+            return Err(AppError::BadRequest(format!(
+                "Invalid path '{}' in dedup_ignore_fields",
+                path
+            )));
+        }
     }
+    Ok(())
+}
 
-    // If is_active was set to false, stop the worker
-    if req.is_active == Some(false) && was_running {
-        let _ = pool.stop_worker(&id).await;
+fn validate_dedup_key_path(path: &str) -> AppResult<()> {
+    if path.split('.').any(|key| key.trim().is_empty()) {
+        return Err(AppError::BadRequest(format!("Invalid dedup_key_path '{}'", path)));
     }
+    Ok(())
+}
 
-    let is_listening = pool.is_running(&id).await;
-    info!("Updated credential: {} (was_running={}, is_listening={})", id, was_running, is_listening);
+/// Sender ids are compared exactly against the payload's "from", so blanks can never match
+fn validate_allowed_senders(senders: &[String]) -> AppResult<()> {
+    if senders.iter().any(|s| s.trim().is_empty() || s.trim() != s) {
+        return Err(AppError::BadRequest(
+            "allowed_senders entries must be non-empty without surrounding whitespace".to_string(),
+        ));
+    }
+    Ok(())
+}
 
-    Ok(Json(updated_credential.to_response(is_listening)))
+/// Scheme "none" only removes webhook_auth, so it needs no credentials
+fn validate_webhook_auth(auth: &WebhookAuth) -> AppResult<()> {
+    if auth.scheme == AuthScheme::None {
+        return Ok(());
+    }
+    auth.validate().map_err(AppError::BadRequest)
+}
+
+/// Both webhook_auth and an Authorization entry in webhook_headers are allowed, but only
+/// one header is sent; say which, since that is easy to miss
+fn warn_auth_header_conflict(
+    name: &str,
+    headers: Option<&HashMap<String, String>>,
+    auth: &WebhookAuth,
+) {
+    if auth.scheme == AuthScheme::None
+        || !headers.is_some_and(|h| h.keys().any(|k| k.eq_ignore_ascii_case("authorization")))
+    {
+        return;
+    }
+    let winner = match auth.precedence {
+        AuthPrecedence::Headers => "the webhook_headers entry",
+        AuthPrecedence::Auth => "webhook_auth",
+    };
+    warn!(
+        "Credential {} sets Authorization in both webhook_headers and webhook_auth; {} is sent",
+        name, winner
+    );
+}
+
+/// Load a credential and build its response with the current listening state
+async fn credential_response(state: &AppState, id: &str) -> AppResult<Json<CredentialResponse>> {
+    let credential = state
+        .repo
+        .get_credential(id)
+        .await?
+        .ok_or_else(|| AppError::NotFound(format!("Credential {} not found", id)))?;
+
+    let is_listening = state.listener_pool.read().await.is_running(id).await;
+
+    Ok(Json(credential.to_response(is_listening)))
 }
 
 /// Delete a credential
@@ -273,6 +1811,8 @@ pub async fn delete_credential(
 
     // Delete from database
     state.repo.delete_credential(&id).await?;
+    state.credential_cache.invalidate(&id);
+    state.webhook_client.debug().disable(&id);
 
     info!("Deleted credential: {} ({})", credential.name, id);
 
@@ -282,28 +1822,60 @@ pub async fn delete_credential(
     })))
 }
 
-/// Start listener for a credential
+/// Query parameters for starting a listener
+#[derive(Debug, Deserialize, IntoParams, ToSchema)]
+pub struct StartQuery {
+    /// Wait for the worker to connect to FCM before responding
+    #[serde(default)]
+    pub wait: bool,
+    /// How long to wait with `wait=true` (defaults to WORKER_START_TIMEOUT_SECS)
+    pub timeout_secs: Option<u64>,
+    /// Fail with 409 when the listener is already running instead of reporting
+    /// `already_running`
+    #[serde(default)]
+    pub strict: bool,
+}
+
+/// Whether a start found the listener already running. Outside strict mode that counts
+/// as success, so starting is safe to repeat.
+fn start_outcome(result: AppResult<()>, strict: bool) -> AppResult<bool> {
+    match result {
+        Ok(()) => Ok(false),
+        Err(AppError::WorkerAlreadyRunning(_)) if !strict => Ok(true),
+        Err(e) => Err(e),
+    }
+}
+
+/// Start listener for a credential. With `wait=true` the response is held until the
+/// worker connects; if it fails or doesn't connect in time an error is returned, and a
+/// worker started by this request is stopped. A listener that is already running is
+/// reported with `already_running: true` (or 409 with `strict=true`) and left running.
 #[utoipa::path(
     post,
     path = "/api/credentials/{id}/start",
     tag = "credentials",
     params(
-        ("id" = String, Path, description = "Credential ID")
+        ("id" = String, Path, description = "Credential ID"),
+        StartQuery
     ),
     security(
         ("api_key" = []),
         ("bearer_auth" = [])
     ),
     responses(
-        (status = 200, description = "Listener started"),
+        (status = 200, description = "Listener started or already running"),
         (status = 400, description = "Cannot start listener"),
         (status = 401, description = "Unauthorized"),
-        (status = 404, description = "Credential not found")
+        (status = 404, description = "Credential not found"),
+        (status = 409, description = "Listener already running (strict=true)"),
+        (status = 502, description = "Worker stopped before connecting"),
+        (status = 504, description = "Worker did not connect in time")
     )
 )]
 pub async fn start_listener(
     State(state): State<AppState>,
     Path(id): Path<String>,
+    Query(query): Query<StartQuery>,
 ) -> AppResult<Json<serde_json::Value>> {
     let credential = state
         .repo
@@ -323,14 +1895,36 @@ pub async fn start_listener(
         ));
     }
 
+    // Starting by hand is the intervention a credential needing attention waits for
     let pool = state.listener_pool.read().await;
-    pool.start_worker(&credential).await?;
+    pool.reset_registration_limit(&id);
+    let already_running = start_outcome(pool.start_worker(&credential).await, query.strict)?;
+
+    if query.wait {
+        let timeout = query
+            .timeout_secs
+            .filter(|n| *n > 0)
+            .map(Duration::from_secs)
+            .unwrap_or_else(get_worker_start_timeout);
+        // Only a worker this request started is stopped if it doesn't connect in time
+        pool.wait_for_connection(&id, timeout, !already_running).await?;
+    }
 
-    info!("Started listener for: {}", credential.name);
+    if already_running {
+        info!("Listener already running for: {}", credential.name);
+    } else {
+        info!("Started listener for: {}", credential.name);
+    }
 
     Ok(Json(serde_json::json!({
-        "message": format!("Listener started for credential {}", id),
-        "id": id
+        "message": if already_running {
+            format!("Listener already running for credential {}", id)
+        } else {
+            format!("Listener started for credential {}", id)
+        },
+        "id": id,
+        "connected": query.wait,
+        "already_running": already_running
     })))
 }
 
@@ -416,6 +2010,7 @@ pub async fn restart_listener(
     }
 
     let pool = state.listener_pool.read().await;
+    pool.reset_registration_limit(&id);
     pool.restart_worker(&credential).await?;
 
     info!("Restarted listener for: {}", credential.name);
@@ -426,13 +2021,23 @@ pub async fn restart_listener(
     })))
 }
 
-/// Suspend a credential (stops worker and prevents auto-start on server boot)
+/// Query parameters for suspending a credential
+#[derive(Debug, Default, Deserialize, IntoParams, ToSchema)]
+pub struct SuspendQuery {
+    /// Also delete all of the credential's messages, in the same transaction
+    #[serde(default)]
+    pub clear_messages: bool,
+}
+
+/// Suspend a credential (stops worker and prevents auto-start on server boot).
+/// With `clear_messages=true` its messages are deleted along with the suspension.
 #[utoipa::path(
     post,
     path = "/api/credentials/{id}/suspend",
     tag = "credentials",
     params(
-        ("id" = String, Path, description = "Credential ID")
+        ("id" = String, Path, description = "Credential ID"),
+        SuspendQuery
     ),
     security(
         ("api_key" = []),
@@ -447,6 +2052,7 @@ pub async fn restart_listener(
 pub async fn suspend_credential(
     State(state): State<AppState>,
     Path(id): Path<String>,
+    Query(query): Query<SuspendQuery>,
 ) -> AppResult<Json<serde_json::Value>> {
     // Check if exists
     let credential = state
@@ -459,8 +2065,37 @@ pub async fn suspend_credential(
     let pool = state.listener_pool.read().await;
     let _ = pool.stop_worker(&id).await;
 
+    if query.clear_messages {
+        // Mark as suspended and clear messages together, so there's no window where
+        // the credential is suspended but still holds its messages
+        let deleted = state
+            .repo
+            .suspend_and_clear_credential(&id)
+            .await?
+            .ok_or_else(|| AppError::NotFound(format!("Credential {} not found", id)))?;
+        state.credential_cache.invalidate(&id);
+        notify_lifecycle(&state, &credential, LifecycleEventKind::Suspended);
+
+        info!(
+            "Suspended credential: {} ({}) and cleared {} messages",
+            credential.name, id, deleted
+        );
+
+        return Ok(Json(serde_json::json!({
+            "message": format!(
+                "Credential {} suspended and {} messages cleared. Worker stopped and won't auto-start on server boot.",
+                id, deleted
+            ),
+            "id": id,
+            "is_suspended": true,
+            "deleted": deleted
+        })));
+    }
+
     // Mark as suspended
     state.repo.suspend_credential(&id).await?;
+    state.credential_cache.invalidate(&id);
+    notify_lifecycle(&state, &credential, LifecycleEventKind::Suspended);
 
     info!("Suspended credential: {} ({})", credential.name, id);
 
@@ -502,6 +2137,7 @@ pub async fn unsuspend_credential(
 
     // Mark as not suspended
     state.repo.unsuspend_credential(&id).await?;
+    state.credential_cache.invalidate(&id);
 
     info!("Unsuspended credential: {} ({})", credential.name, id);
 
@@ -511,3 +2147,565 @@ pub async fn unsuspend_credential(
         "is_suspended": false
     })))
 }
+
+/// Whether POST /api/credentials/{id}/inject is enabled (ENABLE_INJECT=true), default off
+pub fn get_inject_enabled() -> bool {
+    std::env::var("ENABLE_INJECT")
+        .map(|v| matches!(v.trim().to_lowercase().as_str(), "true" | "1" | "yes"))
+        .unwrap_or(false)
+}
+
+/// Result of injecting a payload
+#[derive(Debug, Serialize, ToSchema)]
+pub struct InjectResponse {
+    /// False when the delivery queue was full and its overflow policy discarded the payload
+    pub queued: bool,
+    /// Delivery queue after the payload was added
+    pub queue: Option<QueueStats>,
+}
+
+/// Feed a synthetic payload to a running listener as if it arrived from FCM: it goes through
+/// the same delivery queue, dedup, logging, cleanup and webhook delivery as a real message.
+/// Only available when ENABLE_INJECT=true.
+#[utoipa::path(
+    post,
+    path = "/api/credentials/{id}/inject",
+    tag = "credentials",
+    params(
+        ("id" = String, Path, description = "Credential ID")
+    ),
+    request_body(content = String, description = "Raw FCM payload (JSON)", content_type = "application/json"),
+    security(
+        ("api_key" = []),
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Payload queued", body = InjectResponse),
+        (status = 400, description = "Payload is not JSON or listener not running"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Credential not found or injection disabled")
+    )
+)]
+pub async fn inject_message(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    payload: String,
+) -> AppResult<Json<InjectResponse>> {
+    if !get_inject_enabled() {
+        return Err(AppError::NotFound(
+            "Message injection is disabled (set ENABLE_INJECT=true)".to_string(),
+        ));
+    }
+
+    if serde_json::from_str::<serde_json::Value>(&payload).is_err() {
+        return Err(AppError::BadRequest("Injected payload must be JSON".to_string()));
+    }
+
+    if state.repo.get_credential(&id).await?.is_none() {
+        return Err(AppError::NotFound(format!("Credential {} not found", id)));
+    }
+
+    let pool = state.listener_pool.read().await;
+    let queued = pool.inject(&id, payload.into_bytes()).await?;
+    let queue = pool.worker_status(&id).await.and_then(|s| s.queue);
+
+    info!("Injected payload for credential: {} (queued={})", id, queued);
+
+    Ok(Json(InjectResponse { queued, queue }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Repository;
+    use crate::models::{credential_id_for_external, mask_token};
+    use crate::workers::{ListenerPool, WorkerStatus};
+
+    async fn test_state() -> AppState {
+        let repo = Repository::new("sqlite::memory:").await.unwrap();
+        AppState::new(repo.clone(), ListenerPool::new(repo))
+    }
+
+    fn create_request(name: &str) -> CreateCredentialRequest {
+        CreateCredentialRequest {
+            name: name.to_string(),
+            api_key: "key".to_string(),
+            app_id: "1:123:android:abc".to_string(),
+            project_id: "project".to_string(),
+            webhook_url: Some("http://localhost/webhook".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_list_credentials_concurrently() {
+        let state = test_state().await;
+        for i in 0..50 {
+            let cred = Credential::new(create_request(&format!("cred-{}", i)));
+            state.repo.create_credential(&cred).await.unwrap();
+        }
+
+        let lists = (0..20).map(|_| {
+            list_credentials(
+                State(state.clone()),
+                Query(ListQuery { active_only: false }),
+            )
+        });
+        let results = tokio::time::timeout(
+            std::time::Duration::from_secs(10),
+            futures::future::join_all(lists),
+        )
+        .await
+        .expect("listing credentials concurrently should not stall");
+
+        for result in results {
+            let Json(list) = result.unwrap();
+            assert_eq!(list.total, 50);
+            assert!(list.credentials.iter().all(|c| !c.is_listening));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_list_credentials_includes_message_stats() {
+        let state = test_state().await;
+        let busy = Credential::new(create_request("busy"));
+        let quiet = Credential::new(create_request("quiet"));
+        for cred in [&busy, &quiet] {
+            state.repo.create_credential(cred).await.unwrap();
+        }
+        let mut latest = None;
+        for _ in 0..3 {
+            let log = crate::models::MessageLog::new(busy.id.clone(), None, "{}".to_string());
+            state.repo.create_message_log(&log).await.unwrap();
+            latest = Some(log.received_at);
+        }
+
+        let Json(list) =
+            list_credentials(State(state.clone()), Query(ListQuery { active_only: false }))
+                .await
+                .unwrap();
+        let by_id: HashMap<_, _> = list.credentials.iter().map(|c| (c.id.as_str(), c)).collect();
+        assert_eq!(by_id[busy.id.as_str()].message_count, Some(3));
+        assert_eq!(
+            by_id[busy.id.as_str()].last_message_at.map(|t| t.timestamp_millis()),
+            latest.map(|t| t.timestamp_millis())
+        );
+        assert_eq!(by_id[quiet.id.as_str()].message_count, Some(0));
+        assert!(by_id[quiet.id.as_str()].last_message_at.is_none());
+
+        // Single credentials don't pay for the aggregate
+        let Json(single) = get_credential(
+            State(state.clone()),
+            Path(busy.id.clone()),
+            Query(GetCredentialQuery { reveal: false }),
+        )
+        .await
+        .unwrap();
+        assert!(single.message_count.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_reset_dedup_clears_persistent_keys() {
+        let state = test_state().await;
+        let cred = Credential::new(create_request("dedup"));
+        state.repo.create_credential(&cred).await.unwrap();
+        for key in ["evt-1", "evt-2"] {
+            let mut log = crate::models::MessageLog::new(cred.id.clone(), None, "{}".to_string());
+            log.dedup_key = Some(key.to_string());
+            state.repo.create_message_log(&log).await.unwrap();
+        }
+
+        // In-memory only by default; no worker is running
+        let Json(reset) = reset_dedup(
+            State(state.clone()),
+            Path(cred.id.clone()),
+            Query(DedupResetQuery { persistent: false }),
+        )
+        .await
+        .unwrap();
+        assert_eq!((reset.in_memory_cleared, reset.persistent_cleared), (0, 0));
+        assert!(state.repo.is_dedup_key_duplicate(&cred.id, "evt-1").await.unwrap());
+
+        let Json(reset) = reset_dedup(
+            State(state.clone()),
+            Path(cred.id.clone()),
+            Query(DedupResetQuery { persistent: true }),
+        )
+        .await
+        .unwrap();
+        assert_eq!(reset.persistent_cleared, 2);
+        assert!(!state.repo.is_dedup_key_duplicate(&cred.id, "evt-1").await.unwrap());
+
+        let missing = reset_dedup(
+            State(state.clone()),
+            Path("missing".to_string()),
+            Query(DedupResetQuery { persistent: true }),
+        )
+        .await;
+        assert!(matches!(missing, Err(AppError::NotFound(_))));
+    }
+
+    #[test]
+    fn test_normalize_webhook_headers() {
+        let headers = HashMap::from([
+            (" X-Custom ".to_string(), " value ".to_string()),
+            ("Authorization".to_string(), "Bearer t".to_string()),
+        ]);
+        let normalized = normalize_webhook_headers(&headers).unwrap();
+        assert_eq!(normalized["x-custom"], "value");
+        assert_eq!(normalized["authorization"], "Bearer t");
+
+        let headers = HashMap::from([
+            ("Host".to_string(), "evil".to_string()),
+            ("Bad Name".to_string(), "v".to_string()),
+            ("X-Ok".to_string(), "line\nbreak".to_string()),
+            ("Content-Type".to_string(), "text/plain".to_string()),
+        ]);
+        let Err(AppError::BadRequest(message)) = normalize_webhook_headers(&headers) else {
+            panic!("expected BadRequest");
+        };
+        assert!(message.contains("'Host'"));
+        assert!(message.contains("'Bad Name'"));
+        assert!(message.contains("'X-Ok'"));
+        assert!(!message.contains("Content-Type"));
+    }
+
+    #[test]
+    fn test_start_is_idempotent_unless_strict() {
+        let running = || Err(AppError::WorkerAlreadyRunning("cred".to_string()));
+
+        assert!(!start_outcome(Ok(()), false).unwrap());
+        assert!(!start_outcome(Ok(()), true).unwrap());
+        assert!(start_outcome(running(), false).unwrap());
+        assert!(matches!(
+            start_outcome(running(), true),
+            Err(AppError::WorkerAlreadyRunning(_))
+        ));
+        // Other failures are never swallowed
+        assert!(matches!(
+            start_outcome(Err(AppError::BadRequest("x".to_string())), false),
+            Err(AppError::BadRequest(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_create_with_external_id_is_idempotent() {
+        let state = test_state().await;
+        let mut req = create_request("provisioned");
+        req.external_id = Some("tenant-1".to_string());
+        let Json(first) =
+            create_credential(State(state.clone()), Query(CreateQuery::default()), Json(req))
+                .await
+                .unwrap();
+        assert_eq!(first.credential.id, credential_id_for_external("tenant-1"));
+
+        let mut again = create_request("renamed");
+        again.external_id = Some(" tenant-1 ".to_string());
+        let Json(second) =
+            create_credential(State(state.clone()), Query(CreateQuery::default()), Json(again))
+                .await
+                .unwrap();
+        assert_eq!(second.credential.id, first.credential.id);
+        assert_eq!(second.credential.name, "provisioned");
+        assert_eq!(state.repo.list_credentials(false).await.unwrap().len(), 1);
+
+        let stored = state.repo.get_credential_by_external_id("tenant-1").await.unwrap().unwrap();
+        assert_eq!(stored.id, first.credential.id);
+    }
+
+    #[tokio::test]
+    async fn test_create_and_suspend_fire_lifecycle_events_once() {
+        use crate::workers::LifecycleNotifier;
+
+        let (tx, mut events) = tokio::sync::mpsc::unbounded_channel::<LifecycleEvent>();
+        let app = axum::Router::new().route(
+            "/events",
+            axum::routing::post(move |Json(event): Json<LifecycleEvent>| {
+                let _ = tx.send(event);
+                async { "ok" }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/events", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        // No LIFECYCLE_WEBHOOK_URL: only the credential's own URL gets events
+        let repo = Repository::new("sqlite::memory:").await.unwrap();
+        let notifier = LifecycleNotifier::new(None, Duration::from_secs(60));
+        let pool = ListenerPool::new(repo.clone()).with_lifecycle_notifier(notifier);
+        let state = AppState::new(repo, pool);
+
+        let mut req = create_request("mirrored");
+        req.lifecycle_webhook_url = Some(url);
+        let Json(created) =
+            create_credential(State(state.clone()), Query(CreateQuery::default()), Json(req))
+                .await
+                .unwrap();
+        let id = created.credential.id.clone();
+        let event = events.recv().await.unwrap();
+        assert_eq!(event.event, LifecycleEventKind::Created);
+        assert_eq!(event.credential_id, id);
+        assert_eq!(event.details["name"], "mirrored");
+
+        let silent = Json(create_request("silent"));
+        let _ = create_credential(State(state.clone()), Query(CreateQuery::default()), silent)
+            .await
+            .unwrap();
+
+        let _ = suspend_credential(State(state.clone()), Path(id.clone()), Query(SuspendQuery::default()))
+            .await
+            .unwrap();
+        let event = events.recv().await.unwrap();
+        assert_eq!(event.event, LifecycleEventKind::Suspended);
+        assert_eq!(event.credential_id, id);
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(events.try_recv().is_err());
+
+        let mut req = create_request("invalid");
+        req.lifecycle_webhook_url = Some("ftp://example.com".to_string());
+        let result =
+            create_credential(State(state.clone()), Query(CreateQuery::default()), Json(req)).await;
+        assert!(matches!(result, Err(AppError::BadRequest(_))));
+    }
+
+    #[tokio::test]
+    async fn test_create_pull_only_credential() {
+        let state = test_state().await;
+        let mut req = create_request("pull");
+        req.webhook_url = None;
+        let Json(created) =
+            create_credential(State(state.clone()), Query(CreateQuery::default()), Json(req))
+                .await
+                .unwrap();
+        assert!(created.credential.webhook_url.is_none());
+        let stored = state.repo.get_credential(&created.credential.id).await.unwrap().unwrap();
+        assert!(!stored.has_webhook());
+        assert!(!stored.webhook_blocked_unverified());
+
+        // A missing URL is fine, an invalid one isn't
+        let mut req = create_request("invalid");
+        req.webhook_url = Some("ftp://example.com".to_string());
+        let result =
+            create_credential(State(state.clone()), Query(CreateQuery::default()), Json(req)).await;
+        assert!(matches!(result, Err(AppError::BadRequest(_))));
+    }
+
+    #[tokio::test]
+    async fn test_credential_cache_invalidated_on_change() {
+        let state = test_state().await;
+        let cred = Credential::new(create_request("cached"));
+        state.repo.create_credential(&cred).await.unwrap();
+        let cached = state.credential_cache.get(&state.repo, &cred.id).await.unwrap().unwrap();
+        assert_eq!(cached.webhook_url, "http://localhost/webhook");
+
+        let req: UpdateCredentialRequest =
+            serde_json::from_value(serde_json::json!({"webhook_url": "http://localhost/new"})).unwrap();
+        let _ = update_credential(State(state.clone()), Path(cred.id.clone()), Json(req))
+            .await
+            .unwrap();
+        let cached = state.credential_cache.get(&state.repo, &cred.id).await.unwrap().unwrap();
+        assert_eq!(cached.webhook_url, "http://localhost/new");
+
+        let _ = delete_credential(State(state.clone()), Path(cred.id.clone()))
+            .await
+            .unwrap();
+        assert!(state.credential_cache.get(&state.repo, &cred.id).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_import_rejects_invalid_settings() {
+        let state = test_state().await;
+        let source = Credential::new(create_request("source"));
+        let export = || CredentialExport::new(&source, Vec::new(), false);
+
+        let mut invalid = export();
+        invalid.credential.max_backoff_secs = Some(0);
+        let err = import_credential(State(state.clone()), Json(invalid)).await.unwrap_err();
+        assert!(matches!(err, AppError::BadRequest(m) if m.contains("max_backoff_secs")));
+
+        for max_messages in [0, -5] {
+            let mut invalid = export();
+            invalid.credential.max_messages = Some(max_messages);
+            let err = import_credential(State(state.clone()), Json(invalid)).await.unwrap_err();
+            assert!(matches!(err, AppError::BadRequest(m) if m.contains("max_messages")));
+        }
+
+        let Json(imported) = import_credential(State(state.clone()), Json(export())).await.unwrap();
+        assert_eq!(imported.credential.name, "source");
+    }
+
+    #[tokio::test]
+    async fn test_clone_credential() {
+        let state = test_state().await;
+        let mut req = create_request("Shop");
+        req.topics = vec!["news".to_string()];
+        req.external_id = Some("shop".to_string());
+        let source = Credential::new(req);
+        state.repo.create_credential(&source).await.unwrap();
+        state.repo.set_credential_topics(&source.id, &["news".to_string()]).await.unwrap();
+        state.repo.rotate_webhook_secret(&source.id, "secret").await.unwrap();
+        state
+            .repo
+            .update_credential_registration(&source.id, "fcm", "gcm", 1, 2, "key", "auth")
+            .await
+            .unwrap();
+
+        let overrides = CloneCredentialRequest {
+            app_id: Some("1:123:android:other".to_string()),
+            ..Default::default()
+        };
+        let Json(cloned) =
+            clone_credential(State(state.clone()), Path(source.id.clone()), Some(Json(overrides)))
+                .await
+                .unwrap();
+        assert_ne!(cloned.credential.id, source.id);
+        assert_eq!(cloned.credential.name, "Shop copy");
+        assert_eq!(cloned.credential.app_id, "1:123:android:other");
+        assert_eq!(cloned.credential.webhook_url, Some(source.webhook_url.clone()));
+        assert!(!cloned.credential.is_listening);
+
+        let stored = state.repo.get_credential(&cloned.credential.id).await.unwrap().unwrap();
+        assert!(stored.fcm_token.is_none() && stored.private_key_base64.is_none());
+        assert!(stored.external_id.is_none());
+        assert_eq!(stored.webhook_secrets(), vec!["secret".to_string()]);
+        assert_eq!(
+            state.repo.get_credential_topics(&stored.id).await.unwrap(),
+            vec!["news".to_string()]
+        );
+
+        let Err(AppError::Conflict(_)) = clone_credential(
+            State(state.clone()),
+            Path(source.id.clone()),
+            Some(Json(CloneCredentialRequest {
+                external_id: Some("shop".to_string()),
+                ..Default::default()
+            })),
+        )
+        .await
+        else {
+            panic!("cloning onto a taken external_id should conflict");
+        };
+        assert!(matches!(
+            clone_credential(State(state.clone()), Path("missing".to_string()), None).await,
+            Err(AppError::NotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_gcm_token_masked_unless_revealed() {
+        let state = test_state().await;
+        let cred = Credential::new(create_request("registered"));
+        state.repo.create_credential(&cred).await.unwrap();
+        state
+            .repo
+            .update_credential_registration(&cred.id, "fcm", "gcm-token-1234abcd", 1, 2, "key", "auth")
+            .await
+            .unwrap();
+
+        let get = |reveal| {
+            get_credential(
+                State(state.clone()),
+                Path(cred.id.clone()),
+                Query(GetCredentialQuery { reveal }),
+            )
+        };
+        let Json(masked) = get(false).await.unwrap();
+        assert_eq!(masked.gcm_token.as_deref(), Some("****abcd"));
+        let Json(revealed) = get(true).await.unwrap();
+        assert_eq!(revealed.gcm_token.as_deref(), Some("gcm-token-1234abcd"));
+        assert!(masked.is_registered);
+        assert!(masked.registered_at.is_some());
+
+        assert_eq!(mask_token("short"), "****");
+        let unregistered = Credential::new(create_request("new")).to_response(false);
+        assert!(unregistered.gcm_token.is_none());
+        assert!(!unregistered.is_registered);
+        assert!(unregistered.registered_at.is_none());
+    }
+
+    #[test]
+    fn test_topic_statuses() {
+        let mut status = WorkerStatus::default();
+        status.reset_topics(&["news".to_string(), "promo".to_string()]);
+        status.record_topic("news", Ok(()));
+        status.record_topic("promo", Err("TOO_MANY_TOPICS".to_string()));
+        let topics = vec!["news".to_string(), "promo".to_string(), "added".to_string()];
+
+        let listening = topic_statuses(topics.clone(), true, &status.topics);
+        assert_eq!(listening[0].state, TopicState::Subscribed);
+        assert_eq!(listening[1].state, TopicState::Failed);
+        assert_eq!(listening[1].error.as_deref(), Some("TOO_MANY_TOPICS"));
+        assert_eq!(listening[2].state, TopicState::Pending);
+        assert_eq!(listening[2].attempts, 0);
+
+        let stopped = topic_statuses(topics, false, &status.topics);
+        assert!(stopped.iter().all(|t| t.state == TopicState::Inactive));
+        assert_eq!(stopped[0].attempts, 1);
+    }
+
+    #[tokio::test]
+    async fn test_webhook_secret_rotation() {
+        let state = test_state().await;
+        let cred = Credential::new(create_request("signed"));
+        state.repo.create_credential(&cred).await.unwrap();
+
+        let Json(first) = rotate_webhook_secret(State(state.clone()), Path(cred.id.clone()), None)
+            .await
+            .unwrap();
+        assert!(first.credential.webhook_signing);
+        assert!(!first.rotating);
+
+        let req = RotateWebhookSecretRequest { secret: Some("next".to_string()) };
+        let Json(second) =
+            rotate_webhook_secret(State(state.clone()), Path(cred.id.clone()), Some(Json(req)))
+                .await
+                .unwrap();
+        assert_eq!(second.secret, "next");
+        assert!(second.rotating);
+
+        let stored = state.repo.get_credential(&cred.id).await.unwrap().unwrap();
+        assert_eq!(stored.webhook_secrets(), vec!["next".to_string(), first.secret]);
+
+        let Json(finalized) = finalize_webhook_secret(State(state.clone()), Path(cred.id.clone()))
+            .await
+            .unwrap();
+        assert!(finalized.webhook_signing);
+        assert!(!finalized.webhook_secret_rotating);
+
+        let req = RotateWebhookSecretRequest { secret: Some(" ".to_string()) };
+        assert!(
+            rotate_webhook_secret(State(state.clone()), Path(cred.id.clone()), Some(Json(req)))
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_signing_key_lifecycle() {
+        let state = test_state().await;
+        let cred = Credential::new(create_request("ed25519"));
+        state.repo.create_credential(&cred).await.unwrap();
+
+        let Json(generated) = generate_signing_key(State(state.clone()), Path(cred.id.clone()))
+            .await
+            .unwrap();
+        assert!(generated.credential.own_signing_key);
+        assert_eq!(generated.credential.signing_key_id.as_deref(), Some(generated.public_key.key_id.as_str()));
+
+        let stored = state.repo.get_credential(&cred.id).await.unwrap().unwrap();
+        let signing = stored.webhook_signing();
+        assert_eq!(signing.key.unwrap().public_key().public_key, generated.public_key.public_key);
+
+        let Json(removed) = delete_signing_key(State(state.clone()), Path(cred.id.clone()))
+            .await
+            .unwrap();
+        assert!(!removed.own_signing_key);
+
+        assert!(generate_signing_key(State(state.clone()), Path("missing".to_string()))
+            .await
+            .is_err());
+    }
+}
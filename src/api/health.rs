@@ -1,7 +1,11 @@
 use crate::api::AppState;
 use crate::error::AppResult;
+use crate::models::{Credential, CredentialStateCounts};
+use crate::workers::{get_alert_reconnect_threshold, WatchdogStats, WorkerStatus};
 use axum::{extract::State, Json};
+use chrono::{DateTime, Duration, Utc};
 use serde::Serialize;
+use std::collections::HashMap;
 use utoipa::ToSchema;
 
 /// Health check response
@@ -42,6 +46,16 @@ pub struct StatsResponse {
     pub total_messages: i64,
     /// Messages received in last 24 hours
     pub messages_last_24h: i64,
+    /// Messages delivered (2xx) but not yet acknowledged by the receiver
+    pub delivered_unacknowledged: i64,
+    /// Credentials by whether they are started at boot, and why not
+    pub credential_states: CredentialStateCounts,
+    /// Webhook requests in flight across all credentials
+    pub webhooks_in_flight: usize,
+    /// Most webhook requests allowed in flight at once (WEBHOOK_GLOBAL_CONCURRENCY)
+    pub webhook_concurrency_limit: usize,
+    /// Live stream events skipped by subscribers that fell behind, since startup
+    pub stream_events_dropped: u64,
 }
 
 /// Get server statistics
@@ -62,19 +76,268 @@ pub async fn get_stats(State(state): State<AppState>) -> AppResult<Json<StatsRes
     let pool = state.listener_pool.read().await;
     let active_listeners = pool.active_count().await;
     
-    let all_credentials = state.repo.list_credentials(false).await?;
-    let active_credentials = state.repo.list_credentials(true).await?;
-    let total_messages = state.repo.count_message_logs(None).await?;
+    let credential_states = state.repo.credential_state_counts().await?;
+    let total_messages = state.message_store.count_message_logs(None, None, None).await?;
 
     // For messages in last 24h, we'd need a separate query
     // For now, just return total
     let messages_last_24h = total_messages;
+    let delivered_unacknowledged = state.repo.count_unacknowledged_delivered().await?;
 
     Ok(Json(StatsResponse {
         active_listeners,
-        total_credentials: all_credentials.len() as i64,
-        active_credentials: active_credentials.len() as i64,
+        total_credentials: credential_states.total,
+        active_credentials: credential_states.total - credential_states.inactive,
         total_messages,
         messages_last_24h,
+        delivered_unacknowledged,
+        credential_states,
+        webhooks_in_flight: state.webhook_client.limit().in_flight(),
+        webhook_concurrency_limit: state.webhook_client.limit().max(),
+        stream_events_dropped: state.webhook_client.events().dropped(),
     }))
 }
+
+/// Webhook success rate below which a credential is red
+const FAILING_SUCCESS_RATE: f64 = 0.5;
+
+/// Webhook success rate below which a credential is yellow
+const DEGRADED_SUCCESS_RATE: f64 = 0.9;
+
+/// How long without a message before a listening credential is yellow
+const QUIET_PERIOD_HOURS: i64 = 24;
+
+/// Overall condition of a credential
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    Green,
+    Yellow,
+    Red,
+}
+
+/// Health indicator with the reasons it isn't green
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct CredentialHealth {
+    pub status: HealthStatus,
+    /// Why the status isn't green (empty when green)
+    pub reasons: Vec<String>,
+}
+
+impl CredentialHealth {
+    fn flag(&mut self, status: HealthStatus, reason: String) {
+        self.status = self.status.max(status);
+        self.reasons.push(reason);
+    }
+}
+
+/// Roll the worker state, message recency, webhook success rate and reconnects
+/// of a credential into a green/yellow/red status
+pub fn compute_health(
+    credential: &Credential,
+    is_listening: bool,
+    worker: Option<&WorkerStatus>,
+    last_message_at: Option<DateTime<Utc>>,
+    webhook_success_rate: Option<f64>,
+    now: DateTime<Utc>,
+) -> CredentialHealth {
+    let mut health = CredentialHealth {
+        status: HealthStatus::Green,
+        reasons: Vec::new(),
+    };
+
+    if credential.is_suspended || !credential.is_active {
+        let state = if credential.is_suspended { "suspended" } else { "inactive" };
+        health.flag(HealthStatus::Yellow, format!("Credential is {}", state));
+    } else if !is_listening {
+        let reason = match worker.and_then(|w| w.last_error.as_ref()) {
+            Some(e) => format!("Listener is not running (last error: {})", e.message),
+            None => "Listener is not running".to_string(),
+        };
+        health.flag(HealthStatus::Red, reason);
+    }
+
+    if let Some(worker) = worker.filter(|_| is_listening) {
+        if worker.connected_since.is_none() {
+            health.flag(HealthStatus::Yellow, "Not connected to FCM".to_string());
+        }
+        if worker.retry_count >= get_alert_reconnect_threshold() {
+            health.flag(
+                HealthStatus::Yellow,
+                format!("{} reconnects since the worker started", worker.retry_count),
+            );
+        }
+    }
+
+    if let Some(rate) = webhook_success_rate {
+        let percent = (rate * 100.0).round();
+        if rate < FAILING_SUCCESS_RATE {
+            health.flag(HealthStatus::Red, format!("Webhook success rate is {}%", percent));
+        } else if rate < DEGRADED_SUCCESS_RATE {
+            health.flag(HealthStatus::Yellow, format!("Webhook success rate is {}%", percent));
+        }
+    }
+
+    if let Some(last) = last_message_at.filter(|_| is_listening) {
+        if now - last > Duration::hours(QUIET_PERIOD_HOURS) {
+            health.flag(
+                HealthStatus::Yellow,
+                format!("No message in the last {} hours", QUIET_PERIOD_HOURS),
+            );
+        }
+    }
+
+    health
+}
+
+/// Per-credential statistics
+#[derive(Debug, Serialize, ToSchema)]
+pub struct CredentialStats {
+    /// Credential ID
+    pub id: String,
+    /// Display name
+    pub name: String,
+    /// Whether FCM listener is currently running
+    pub is_listening: bool,
+    /// Messages currently logged
+    pub message_count: i64,
+    /// When the latest logged message arrived
+    pub last_message_at: Option<DateTime<Utc>>,
+    /// Share of delivery attempts that got a 2xx response (null when nothing was delivered)
+    pub webhook_success_rate: Option<f64>,
+    /// Reconnect attempts of the current worker
+    pub reconnect_count: u32,
+    /// Green/yellow/red summary of the figures above
+    pub health: CredentialHealth,
+}
+
+/// Detailed statistics response
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DetailedStatsResponse {
+    /// One entry per credential
+    pub credentials: Vec<CredentialStats>,
+    /// Drift found and healed by the worker watchdog since startup
+    pub watchdog: WatchdogStats,
+}
+
+/// Get per-credential statistics
+#[utoipa::path(
+    get,
+    path = "/api/stats/detailed",
+    tag = "health",
+    security(
+        ("api_key" = []),
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Per-credential statistics", body = DetailedStatsResponse),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+pub async fn get_detailed_stats(
+    State(state): State<AppState>,
+) -> AppResult<Json<DetailedStatsResponse>> {
+    // Two queries regardless of credential count, joined in memory
+    let credentials = state.repo.list_credentials(false).await?;
+    let mut message_stats: HashMap<String, _> = state
+        .repo
+        .message_stats_by_credential()
+        .await?
+        .into_iter()
+        .map(|s| (s.credential_id.clone(), s))
+        .collect();
+
+    let pool = state.listener_pool.read().await;
+    let running = pool.running_ids().await;
+    let statuses = pool.worker_statuses().await;
+    let now = Utc::now();
+
+    let credentials = credentials
+        .into_iter()
+        .map(|cred| {
+            let stats = message_stats.remove(&cred.id);
+            let is_listening = running.contains(&cred.id);
+            let worker = statuses.get(&cred.id);
+            let last_message_at = stats.as_ref().and_then(|s| s.last_received_at);
+            let webhook_success_rate = stats
+                .as_ref()
+                .filter(|s| s.attempted > 0)
+                .map(|s| s.succeeded as f64 / s.attempted as f64);
+            let health = compute_health(
+                &cred,
+                is_listening,
+                worker,
+                last_message_at,
+                webhook_success_rate,
+                now,
+            );
+
+            CredentialStats {
+                is_listening,
+                message_count: stats.as_ref().map_or(0, |s| s.message_count),
+                last_message_at,
+                webhook_success_rate,
+                reconnect_count: worker.map_or(0, |s| s.retry_count),
+                health,
+                id: cred.id,
+                name: cred.name,
+            }
+        })
+        .collect();
+
+    Ok(Json(DetailedStatsResponse {
+        credentials,
+        watchdog: pool.watchdog_stats(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::CreateCredentialRequest;
+
+    fn credential() -> Credential {
+        Credential::new(CreateCredentialRequest {
+            name: "Test".to_string(),
+            api_key: "key".to_string(),
+            app_id: "1:123:android:abc".to_string(),
+            project_id: "project".to_string(),
+            webhook_url: Some("http://localhost/webhook".to_string()),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_compute_health() {
+        let cred = credential();
+        let now = Utc::now();
+        let connected = WorkerStatus {
+            connected_since: Some(now),
+            ..Default::default()
+        };
+
+        let health = compute_health(&cred, true, Some(&connected), Some(now), Some(1.0), now);
+        assert_eq!(health.status, HealthStatus::Green);
+        assert!(health.reasons.is_empty());
+
+        // Degraded delivery and a quiet day are yellow
+        let stale = now - Duration::hours(QUIET_PERIOD_HOURS + 1);
+        let health = compute_health(&cred, true, Some(&connected), Some(stale), Some(0.8), now);
+        assert_eq!(health.status, HealthStatus::Yellow);
+        assert_eq!(health.reasons.len(), 2);
+
+        // A stopped listener is red whatever else holds
+        let health = compute_health(&cred, false, None, Some(now), Some(0.95), now);
+        assert_eq!(health.status, HealthStatus::Red);
+        assert!(health.reasons[0].contains("not running"));
+
+        // Failing webhooks are red
+        let health = compute_health(&cred, true, Some(&connected), None, Some(0.2), now);
+        assert_eq!(health.status, HealthStatus::Red);
+
+        let mut inactive = credential();
+        inactive.is_active = false;
+        let health = compute_health(&inactive, false, None, None, None, now);
+        assert_eq!(health.status, HealthStatus::Yellow);
+    }
+}
@@ -1,13 +1,29 @@
+use crate::api::credentials::normalize_webhook_headers;
 use crate::api::AppState;
+use crate::db::Repository;
 use crate::error::{AppError, AppResult};
-use crate::models::MessageLogResponse;
-use crate::workers::WebhookClient;
+use crate::models::{Credential, DeliveryState, MessageLog, MessageLogResponse};
+use crate::workers::{
+    get_bulk_retry_jitter, get_bulk_retry_rate, get_delivery_concurrency, signed_headers,
+    DeliveryPacer, PubSubSink, StreamEvent, WebhookClient,
+};
 use axum::{
     extract::{Path, Query, State},
+    http::HeaderMap,
+    response::sse::{Event, KeepAlive, Sse},
     Json,
 };
+use futures::Stream;
 use serde::{Deserialize, Serialize};
-use tracing::info;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::Instant;
+use subtle::ConstantTimeEq;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use tracing::{info, warn};
 use utoipa::{IntoParams, ToSchema};
 
 /// Query parameters for listing messages
@@ -15,6 +31,12 @@ use utoipa::{IntoParams, ToSchema};
 pub struct ListMessagesQuery {
     /// Filter by credential ID
     pub credential_id: Option<String>,
+    /// Filter by an extracted field name (requires value)
+    pub field: Option<String>,
+    /// Value the extracted field must equal
+    pub value: Option<String>,
+    /// Filter by delivery state (e.g. delivering or failed to find stuck messages)
+    pub delivery_state: Option<DeliveryState>,
     /// Number of messages to return (default: 50)
     #[serde(default = "default_limit")]
     pub limit: i64,
@@ -52,6 +74,7 @@ pub struct ListMessagesResponse {
     ),
     responses(
         (status = 200, description = "List of messages", body = ListMessagesResponse),
+        (status = 400, description = "field and value must be given together"),
         (status = 401, description = "Unauthorized")
     )
 )]
@@ -59,14 +82,108 @@ pub async fn list_messages(
     State(state): State<AppState>,
     Query(query): Query<ListMessagesQuery>,
 ) -> AppResult<Json<ListMessagesResponse>> {
+    let field = match (query.field.as_deref(), query.value.as_deref()) {
+        (Some(name), Some(value)) => Some((name, value)),
+        (None, None) => None,
+        _ => {
+            return Err(AppError::BadRequest(
+                "field and value must be given together".to_string(),
+            ))
+        }
+    };
+
+    let messages = state
+        .message_store
+        .list_message_logs(
+            query.credential_id.as_deref(),
+            field,
+            query.delivery_state,
+            query.limit,
+            query.offset,
+        )
+        .await?;
+
+    let total = state
+        .message_store
+        .count_message_logs(query.credential_id.as_deref(), field, query.delivery_state)
+        .await?;
+
+    let responses: Vec<MessageLogResponse> = messages.iter().map(|m| m.to_response()).collect();
+
+    Ok(Json(ListMessagesResponse {
+        messages: responses,
+        total,
+        limit: query.limit,
+        offset: query.offset,
+    }))
+}
+
+/// Ordering of search results
+#[derive(Debug, Clone, Copy, Default, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchOrder {
+    /// Best matches first
+    #[default]
+    Relevance,
+    /// Newest messages first
+    Recent,
+}
+
+/// Query parameters for searching messages
+#[derive(Debug, Deserialize, IntoParams, ToSchema)]
+pub struct SearchMessagesQuery {
+    /// Search terms; every term must appear in the payload
+    pub q: String,
+    /// Filter by credential ID
+    pub credential_id: Option<String>,
+    /// Result ordering (default: relevance)
+    #[serde(default)]
+    pub order: SearchOrder,
+    /// Number of messages to return (default: 50)
+    #[serde(default = "default_limit")]
+    pub limit: i64,
+    /// Offset for pagination
+    #[serde(default)]
+    pub offset: i64,
+}
+
+/// Full-text search over message payloads
+#[utoipa::path(
+    get,
+    path = "/api/messages/search",
+    tag = "messages",
+    params(SearchMessagesQuery),
+    security(
+        ("api_key" = []),
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Matching messages", body = ListMessagesResponse),
+        (status = 400, description = "Empty search query"),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+pub async fn search_messages(
+    State(state): State<AppState>,
+    Query(query): Query<SearchMessagesQuery>,
+) -> AppResult<Json<ListMessagesResponse>> {
+    let fts_query = fts_match_expression(&query.q)
+        .ok_or_else(|| AppError::BadRequest("Search query cannot be empty".to_string()))?;
+
     let messages = state
         .repo
-        .list_message_logs(query.credential_id.as_deref(), query.limit, query.offset)
+        .search_message_logs(
+            &fts_query,
+            query.credential_id.as_deref(),
+            matches!(query.order, SearchOrder::Recent),
+            query.limit,
+            query.offset,
+        )
         .await?;
 
     let total = state
         .repo
-        .count_message_logs(query.credential_id.as_deref())
+        .count_search_message_logs(&fts_query, query.credential_id.as_deref())
         .await?;
 
     let responses: Vec<MessageLogResponse> = messages.iter().map(|m| m.to_response()).collect();
@@ -79,6 +196,109 @@ pub async fn list_messages(
     }))
 }
 
+/// Turn free text into an FTS5 MATCH expression: each term is quoted so user input
+/// can't break the query syntax, and all terms must match
+fn fts_match_expression(q: &str) -> Option<String> {
+    let terms: Vec<String> = q
+        .split_whitespace()
+        .map(|term| format!("\"{}\"", term.replace('"', "\"\"")))
+        .collect();
+
+    if terms.is_empty() {
+        None
+    } else {
+        Some(terms.join(" "))
+    }
+}
+
+/// Query parameters for the live event stream
+#[derive(Debug, Deserialize, IntoParams, ToSchema)]
+pub struct StreamQuery {
+    /// Only events of this credential
+    pub credential_id: Option<String>,
+    /// Comma-separated event types to receive: message, delivery (default: both)
+    pub events: Option<String>,
+}
+
+impl StreamQuery {
+    /// Event names to send, rejecting unknown ones
+    fn event_names(&self) -> AppResult<Vec<&'static str>> {
+        let Some(events) = self.events.as_deref() else {
+            return Ok(vec!["message", "delivery"]);
+        };
+        events
+            .split(',')
+            .map(str::trim)
+            .filter(|e| !e.is_empty())
+            .map(|e| match e {
+                "message" => Ok("message"),
+                "delivery" => Ok("delivery"),
+                other => Err(AppError::BadRequest(format!(
+                    "Unknown event type '{}' (expected message or delivery)",
+                    other
+                ))),
+            })
+            .collect()
+    }
+
+    fn matches(&self, names: &[&str], event: &StreamEvent) -> bool {
+        names.contains(&event.name())
+            && self.credential_id.as_deref().is_none_or(|id| id == event.credential_id())
+    }
+}
+
+/// Live stream of received messages and webhook delivery outcomes (Server-Sent Events).
+/// Each event's SSE name is its type; events published while a client isn't connected
+/// or falls too far behind are not replayed.
+#[utoipa::path(
+    get,
+    path = "/api/messages/stream",
+    tag = "messages",
+    params(StreamQuery),
+    security(
+        ("api_key" = []),
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Event stream", body = StreamEvent, content_type = "text/event-stream"),
+        (status = 400, description = "Unknown event type"),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+pub async fn stream_messages(
+    State(state): State<AppState>,
+    Query(query): Query<StreamQuery>,
+) -> AppResult<Sse<impl Stream<Item = Result<Event, Infallible>>>> {
+    let names = query.event_names()?;
+    let events = state.webhook_client.events().clone();
+    let rx = events.subscribe();
+
+    let stream = futures::stream::unfold(
+        (rx, query, names, events),
+        |(mut rx, query, names, events)| async move {
+            loop {
+                match rx.recv().await {
+                    Ok(event) if query.matches(&names, &event) => {
+                        let sse = Event::default()
+                            .event(event.name())
+                            .json_data(&event)
+                            .unwrap_or_default();
+                        return Some((Ok(sse), (rx, query, names, events)));
+                    }
+                    Ok(_) => {}
+                    Err(RecvError::Lagged(skipped)) => {
+                        events.record_dropped(skipped);
+                        warn!("Event stream subscriber fell behind, skipped {} events", skipped);
+                    }
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        },
+    );
+
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
 /// Get a single message
 #[utoipa::path(
     get,
@@ -101,7 +321,7 @@ pub async fn get_message(
     State(state): State<AppState>,
     Path(id): Path<String>,
 ) -> AppResult<Json<MessageLogResponse>> {
-    let messages = state.repo.list_message_logs(None, 1, 0).await?;
+    let messages = state.repo.list_message_logs(None, None, None, 1, 0).await?;
     
     // Find the specific message (we need to query by ID)
     let message = messages
@@ -112,6 +332,30 @@ pub async fn get_message(
     Ok(Json(message.to_response()))
 }
 
+/// One-off delivery target for a single retry (the credential is not changed)
+#[derive(Debug, Default, Deserialize, ToSchema)]
+pub struct RetryWebhookRequest {
+    /// Deliver to this URL instead of the credential's webhook, topic route or sink
+    pub webhook_url: Option<String>,
+    /// Send these headers instead of the credential's webhook_headers
+    pub webhook_headers: Option<HashMap<String, String>>,
+}
+
+impl RetryWebhookRequest {
+    /// Validate like credential creation, normalizing header names
+    fn validate(mut self) -> AppResult<Self> {
+        if let Some(url) = &self.webhook_url {
+            if !url.starts_with("http://") && !url.starts_with("https://") {
+                return Err(AppError::BadRequest("Invalid webhook URL".to_string()));
+            }
+        }
+        if let Some(headers) = &self.webhook_headers {
+            self.webhook_headers = Some(normalize_webhook_headers(headers)?);
+        }
+        Ok(self)
+    }
+}
+
 /// Response for webhook retry
 #[derive(Debug, Serialize, ToSchema)]
 pub struct RetryWebhookResponse {
@@ -119,9 +363,11 @@ pub struct RetryWebhookResponse {
     pub message: String,
     /// HTTP status code from webhook (if available)
     pub status: Option<i32>,
+    /// URL the retry was delivered to (null when published to a sink)
+    pub webhook_url: Option<String>,
 }
 
-/// Retry webhook delivery for a failed message
+/// Retry webhook delivery for a failed message, optionally to a one-off URL and headers
 #[utoipa::path(
     post,
     path = "/api/messages/{id}/retry",
@@ -129,31 +375,41 @@ pub struct RetryWebhookResponse {
     params(
         ("id" = String, Path, description = "Message ID")
     ),
+    request_body = RetryWebhookRequest,
     security(
         ("api_key" = []),
         ("bearer_auth" = [])
     ),
     responses(
         (status = 200, description = "Webhook retry completed", body = RetryWebhookResponse),
+        (status = 400, description = "Invalid override URL or headers, or the payload was not stored"),
         (status = 401, description = "Unauthorized"),
-        (status = 404, description = "Message not found")
+        (status = 404, description = "Message not found"),
+        (status = 409, description = "A delivery for this message is already in progress")
     )
 )]
 pub async fn retry_webhook(
     State(state): State<AppState>,
     Path(id): Path<String>,
+    body: Option<Json<RetryWebhookRequest>>,
 ) -> AppResult<Json<RetryWebhookResponse>> {
-    // Get the message log
-    let messages = state.repo.list_message_logs(None, 1000, 0).await?;
-    let mut message = messages
-        .into_iter()
-        .find(|m| m.id == id)
+    let target = body.map(|Json(req)| req).unwrap_or_default().validate()?;
+
+    // Held until this handler returns, whichever way it exits
+    let _guard = state.in_flight.try_acquire(&id).ok_or_else(|| {
+        AppError::Conflict(format!("Webhook delivery for message {} already in progress", id))
+    })?;
+
+    let mut message = state
+        .repo
+        .get_message_log(&id)
+        .await?
         .ok_or_else(|| AppError::NotFound(format!("Message {} not found", id)))?;
 
     // Get the credential for webhook URL
     let credential = state
-        .repo
-        .get_credential(&message.credential_id)
+        .credential_cache
+        .get(&state.repo, &message.credential_id)
         .await?
         .ok_or_else(|| {
             AppError::NotFound(format!(
@@ -162,22 +418,332 @@ pub async fn retry_webhook(
             ))
         })?;
 
-    // Retry the webhook
-    let webhook_client = WebhookClient::new();
-    webhook_client
-        .retry_message(
-            &mut message,
-            &credential.webhook_url,
-            credential.get_webhook_headers().as_ref(),
-            &state.repo,
-        )
-        .await?;
+    // Retry the webhook, routed the same way as the original delivery unless overridden
+    redeliver(&state.webhook_client, &state.repo, &credential, &mut message, &target).await?;
 
-    info!("Retried webhook for message: {}", id);
+    match &target.webhook_url {
+        Some(url) => info!("Retried webhook for message {} via override URL {}", id, url),
+        None => info!("Retried webhook for message: {}", id),
+    }
 
     Ok(Json(RetryWebhookResponse {
         message: format!("Webhook retry completed for message {}", id),
         status: message.webhook_status,
+        webhook_url: message.webhook_url.clone(),
+    }))
+}
+
+/// Deliver a stored message again through the credential's current sink,
+/// routed by topic like the original delivery. An override URL delivers to that
+/// webhook instead; override headers replace the credential's.
+async fn redeliver(
+    webhook_client: &WebhookClient,
+    repo: &Repository,
+    credential: &Credential,
+    message: &mut MessageLog,
+    target: &RetryWebhookRequest,
+) -> AppResult<()> {
+    if !message.payload_stored {
+        return Err(AppError::BadRequest(format!(
+            "Message {} has no stored payload to deliver (credential {} has store_payload off)",
+            message.id, credential.name
+        )));
+    }
+
+    let payload = message.payload_bytes();
+    let body = credential.json_format().apply(&payload);
+
+    // Deliveries for credentials with their own proxy go through a dedicated client
+    let webhook_client = match credential.proxy_override() {
+        Some(proxy) => webhook_client.via_proxy(proxy),
+        None => webhook_client.clone(),
+    }
+    .with_retry_on(credential.retry_on())
+    // An override URL is not the credential's receiver, so it doesn't get its auth or
+    // response match
+    .with_auth(credential.webhook_auth().filter(|_| target.webhook_url.is_none()))
+    .with_user_agent(credential.webhook_user_agent.clone())
+    .with_timeouts(credential.webhook_timeouts())
+    .with_response_match(
+        credential
+            .webhook_response_match()
+            .filter(|_| target.webhook_url.is_none()),
+    );
+    let webhook_client = &webhook_client;
+
+    let sink = match target.webhook_url {
+        Some(_) => None,
+        None => PubSubSink::from_config(&credential.sink()),
+    };
+
+    match sink {
+        Some(sink) => {
+            sink.publish(
+                webhook_client,
+                &body,
+                credential.capture_webhook_response,
+                message,
+                repo,
+            )
+            .await
+        }
+        None => {
+            if target.webhook_url.is_none() && credential.webhook_blocked_unverified() {
+                return Err(AppError::BadRequest(format!(
+                    "Webhook for credential {} is not verified; use POST /api/credentials/{}/verify-webhook",
+                    credential.name, credential.id
+                )));
+            }
+
+            let url = target.webhook_url.clone().unwrap_or_else(|| {
+                let topic = MessageLog::extract_topic(&String::from_utf8_lossy(&payload));
+                credential.webhook_url_for_topic(topic.as_deref())
+            });
+            if url.is_empty() {
+                return Err(AppError::BadRequest(format!(
+                    "Credential {} has no webhook_url (pull-only); pass one in the request",
+                    credential.name
+                )));
+            }
+            let custom_headers = target
+                .webhook_headers
+                .clone()
+                .or_else(|| credential.get_webhook_headers());
+            let headers =
+                signed_headers(custom_headers.as_ref(), &credential.webhook_signing(), &body);
+            webhook_client
+                .retry_message(
+                    message,
+                    &url,
+                    &body,
+                    headers.as_ref(),
+                    credential.capture_webhook_response,
+                    repo,
+                )
+                .await
+        }
+    }
+}
+
+/// Query parameters for bulk retry of failed deliveries
+#[derive(Debug, Deserialize, IntoParams, ToSchema)]
+pub struct RetryFailedQuery {
+    /// Only retry messages of this credential
+    pub credential_id: Option<String>,
+    /// Maximum messages to retry (default: 100)
+    #[serde(default = "default_retry_failed_limit")]
+    pub limit: i64,
+    /// Messages dispatched per second (default: BULK_RETRY_RATE)
+    pub rate: Option<f64>,
+}
+
+fn default_retry_failed_limit() -> i64 {
+    100
+}
+
+/// Result of a bulk retry
+#[derive(Debug, Serialize, ToSchema)]
+pub struct RetryFailedResponse {
+    /// Messages re-delivered
+    pub attempted: usize,
+    /// Deliveries that got a 2xx response
+    pub succeeded: usize,
+    /// Deliveries that failed again
+    pub failed: usize,
+    /// Messages skipped (delivery already in progress or credential deleted)
+    pub skipped: usize,
+    /// Configured dispatch rate (messages per second)
+    pub target_rate: f64,
+    /// Achieved rate over the whole batch (messages per second)
+    pub effective_rate: f64,
+    /// Total duration in milliseconds
+    pub elapsed_ms: u64,
+}
+
+/// Retry failed webhook deliveries in bulk, paced to avoid overwhelming the receiver.
+/// Dispatch is spread at `rate` messages/second (with BULK_RETRY_JITTER) and limited
+/// to DELIVERY_CONCURRENCY deliveries in flight.
+#[utoipa::path(
+    post,
+    path = "/api/messages/retry-failed",
+    tag = "messages",
+    params(RetryFailedQuery),
+    security(
+        ("api_key" = []),
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Bulk retry completed", body = RetryFailedResponse),
+        (status = 400, description = "Invalid rate"),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+pub async fn retry_failed_messages(
+    State(state): State<AppState>,
+    Query(query): Query<RetryFailedQuery>,
+) -> AppResult<Json<RetryFailedResponse>> {
+    let rate = query.rate.unwrap_or_else(get_bulk_retry_rate);
+    if !rate.is_finite() || rate <= 0.0 {
+        return Err(AppError::BadRequest("rate must be a positive number".to_string()));
+    }
+
+    let pacer = DeliveryPacer::new(rate, get_bulk_retry_jitter());
+    let messages = state
+        .repo
+        .list_failed_message_logs(query.credential_id.as_deref(), query.limit)
+        .await?;
+
+    let webhook_client = state.webhook_client.clone();
+    let permits = Arc::new(Semaphore::new(get_delivery_concurrency()));
+    let mut credentials: HashMap<String, Option<Credential>> = HashMap::new();
+    let mut deliveries = JoinSet::new();
+    let mut skipped = 0;
+    let started = Instant::now();
+
+    for (i, mut message) in messages.into_iter().enumerate() {
+        if i > 0 {
+            tokio::time::sleep(pacer.next_delay()).await;
+        }
+
+        let Some(guard) = state.in_flight.try_acquire(&message.id) else {
+            skipped += 1;
+            continue;
+        };
+
+        let credential = match credentials.get(&message.credential_id) {
+            Some(cached) => cached.clone(),
+            None => {
+                let credential = state
+                    .credential_cache
+                    .get(&state.repo, &message.credential_id)
+                    .await?;
+                credentials.insert(message.credential_id.clone(), credential.clone());
+                credential
+            }
+        };
+        let Some(credential) = credential else {
+            skipped += 1;
+            continue;
+        };
+
+        // Waits here while DELIVERY_CONCURRENCY deliveries are still running
+        let permit = permits.clone().acquire_owned().await.unwrap();
+        let webhook_client = webhook_client.clone();
+        let repo = state.repo.clone();
+
+        deliveries.spawn(async move {
+            let _guard = guard;
+            let _permit = permit;
+            let target = RetryWebhookRequest::default();
+            redeliver(&webhook_client, &repo, &credential, &mut message, &target)
+                .await
+                .is_ok()
+                && matches!(message.webhook_status, Some(s) if (200..300).contains(&s))
+        });
+    }
+
+    let (mut succeeded, mut failed) = (0, 0);
+    while let Some(result) = deliveries.join_next().await {
+        match result {
+            Ok(true) => succeeded += 1,
+            _ => failed += 1,
+        }
+    }
+
+    let elapsed = started.elapsed();
+    let attempted = succeeded + failed;
+    let effective_rate = if elapsed.as_secs_f64() > 0.0 {
+        attempted as f64 / elapsed.as_secs_f64()
+    } else {
+        0.0
+    };
+
+    info!(
+        "Bulk retry finished: {} attempted, {} succeeded, {} failed, {} skipped ({:.2} msg/s)",
+        attempted, succeeded, failed, skipped, effective_rate
+    );
+
+    Ok(Json(RetryFailedResponse {
+        attempted,
+        succeeded,
+        failed,
+        skipped,
+        target_rate: rate,
+        effective_rate,
+        elapsed_ms: elapsed.as_millis() as u64,
+    }))
+}
+
+/// Response for message acknowledgment
+#[derive(Debug, Serialize, ToSchema)]
+pub struct AckMessageResponse {
+    /// Message ID
+    pub id: String,
+    /// Always true after a successful call
+    pub acknowledged: bool,
+    /// When the message was acknowledged
+    pub acknowledged_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Acknowledge that a downstream receiver processed a message.
+/// Accepts the master API key or the credential's ack token in the `X-Ack-Token` header.
+#[utoipa::path(
+    post,
+    path = "/api/messages/{id}/ack",
+    tag = "messages",
+    params(
+        ("id" = String, Path, description = "Message ID"),
+        ("X-Ack-Token" = Option<String>, Header, description = "Credential-scoped ack token")
+    ),
+    security(
+        ("api_key" = []),
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Message acknowledged", body = AckMessageResponse),
+        (status = 401, description = "Unauthorized, or unknown message with X-Ack-Token"),
+        (status = 404, description = "Message not found")
+    )
+)]
+pub async fn ack_message(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> AppResult<Json<AckMessageResponse>> {
+    let message = state.repo.get_message_log(&id).await?;
+
+    // With X-Ack-Token the auth middleware lets the request through, so the token is checked
+    // before anything else; without it the master API key was already checked. An unknown
+    // message fails like a wrong token, so a token holder can't probe for message ids.
+    if let Some(token) = headers.get("X-Ack-Token") {
+        let expected = match &message {
+            Some(message) => state
+                .credential_cache
+                .get(&state.repo, &message.credential_id)
+                .await?
+                .and_then(|c| c.ack_token),
+            None => None,
+        };
+        let valid = expected
+            .is_some_and(|expected| bool::from(expected.as_bytes().ct_eq(token.as_bytes())));
+        if !valid {
+            return Err(AppError::Unauthorized("Invalid ack token".to_string()));
+        }
+    }
+
+    let message =
+        message.ok_or_else(|| AppError::NotFound(format!("Message {} not found", id)))?;
+
+    if state.repo.acknowledge_message(&id).await? {
+        info!("Message acknowledged: {}", id);
+    }
+
+    let message = state.repo.get_message_log(&id).await?.unwrap_or(message);
+
+    Ok(Json(AckMessageResponse {
+        id,
+        acknowledged: message.acknowledged,
+        acknowledged_at: message.acknowledged_at,
     }))
 }
 
@@ -232,3 +798,429 @@ pub async fn clear_messages(
         message: format!("{} messages cleared for credential {}", deleted, id),
     }))
 }
+
+/// Response for message deletion
+#[derive(Debug, Serialize, ToSchema)]
+pub struct DeleteMessagesResponse {
+    /// Number of messages deleted
+    pub deleted: u64,
+}
+
+/// Delete a single message log
+#[utoipa::path(
+    delete,
+    path = "/api/messages/{id}",
+    tag = "messages",
+    params(
+        ("id" = String, Path, description = "Message ID")
+    ),
+    security(
+        ("api_key" = []),
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Message deleted", body = DeleteMessagesResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Message not found"),
+        (status = 409, description = "Webhook delivery in progress")
+    )
+)]
+pub async fn delete_message(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> AppResult<Json<DeleteMessagesResponse>> {
+    let _guard = state.in_flight.try_acquire(&id).ok_or_else(|| {
+        AppError::Conflict(format!("Webhook delivery for message {} in progress", id))
+    })?;
+
+    if !state.repo.delete_message_log(&id).await? {
+        return Err(AppError::NotFound(format!("Message {} not found", id)));
+    }
+
+    info!("Deleted message: {}", id);
+    Ok(Json(DeleteMessagesResponse { deleted: 1 }))
+}
+
+/// Query parameters for bulk message deletion; at least one filter is required
+#[derive(Debug, Deserialize, IntoParams, ToSchema)]
+pub struct DeleteMessagesQuery {
+    /// Only messages of this credential
+    pub credential_id: Option<String>,
+    /// Only messages in this delivery state (e.g. failed)
+    pub delivery_state: Option<DeliveryState>,
+    /// Only messages received before this time (RFC 3339)
+    pub before: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Delete the message logs matching all given filters. Refuses to run without a filter,
+/// use DELETE /api/credentials/{id}/messages to clear a credential.
+#[utoipa::path(
+    delete,
+    path = "/api/messages",
+    tag = "messages",
+    params(DeleteMessagesQuery),
+    security(
+        ("api_key" = []),
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Matching messages deleted", body = DeleteMessagesResponse),
+        (status = 400, description = "No filter given"),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+pub async fn delete_messages(
+    State(state): State<AppState>,
+    Query(query): Query<DeleteMessagesQuery>,
+) -> AppResult<Json<DeleteMessagesResponse>> {
+    if query.credential_id.is_none() && query.delivery_state.is_none() && query.before.is_none() {
+        return Err(AppError::BadRequest(
+            "At least one of credential_id, delivery_state or before is required".to_string(),
+        ));
+    }
+
+    let deleted = state
+        .repo
+        .delete_message_logs(query.credential_id.as_deref(), query.delivery_state, query.before)
+        .await?;
+
+    info!(
+        "Deleted {} messages (credential_id: {:?}, delivery_state: {:?}, before: {:?})",
+        deleted, query.credential_id, query.delivery_state, query.before
+    );
+
+    Ok(Json(DeleteMessagesResponse { deleted }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::CreateCredentialRequest;
+    use crate::workers::ListenerPool;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[tokio::test]
+    async fn test_stream_query_filters_events() {
+        let delivery = StreamEvent::Delivery {
+            message_id: "m1".to_string(),
+            credential_id: "cred-1".to_string(),
+            status: 200,
+            success: true,
+            attempts: 1,
+            error: None,
+        };
+        let message = StreamEvent::Message {
+            message_id: "m1".to_string(),
+            credential_id: "cred-1".to_string(),
+            fcm_message_id: None,
+            received_at: chrono::Utc::now(),
+        };
+
+        let all = StreamQuery {
+            credential_id: None,
+            events: None,
+        };
+        let names = all.event_names().unwrap();
+        assert!(all.matches(&names, &delivery) && all.matches(&names, &message));
+
+        let deliveries = StreamQuery {
+            credential_id: Some("cred-1".to_string()),
+            events: Some("delivery".to_string()),
+        };
+        let names = deliveries.event_names().unwrap();
+        assert!(deliveries.matches(&names, &delivery));
+        assert!(!deliveries.matches(&names, &message));
+
+        let other = StreamQuery {
+            credential_id: Some("cred-2".to_string()),
+            events: None,
+        };
+        assert!(!other.matches(&other.event_names().unwrap(), &delivery));
+
+        let unknown = StreamQuery {
+            credential_id: None,
+            events: Some("message,nope".to_string()),
+        };
+        assert!(matches!(unknown.event_names(), Err(AppError::BadRequest(_))));
+    }
+
+    #[tokio::test]
+    async fn test_search_messages() {
+        let repo = Repository::new("sqlite::memory:").await.unwrap();
+        let state = AppState::new(repo.clone(), ListenerPool::new(repo.clone()));
+
+        let credential = Credential::new(CreateCredentialRequest {
+            name: "Search".to_string(),
+            api_key: "key".to_string(),
+            app_id: "1:123:android:abc".to_string(),
+            project_id: "project".to_string(),
+            webhook_url: Some("http://localhost/webhook".to_string()),
+            ..Default::default()
+        });
+        repo.create_credential(&credential).await.unwrap();
+
+        for payload in [
+            r#"{"data":{"title":"Order shipped","body":"Your parcel is on its way"}}"#,
+            r#"{"data":{"title":"Promo","body":"50% off \"shipped\" items"}}"#,
+            r#"{"data":{"title":"Welcome","body":"Thanks for signing up"}}"#,
+        ] {
+            repo.create_message_log(&MessageLog::new(credential.id.clone(), None, payload.to_string()))
+                .await
+                .unwrap();
+        }
+
+        let search = |q: &str| SearchMessagesQuery {
+            q: q.to_string(),
+            credential_id: None,
+            order: SearchOrder::Relevance,
+            limit: 50,
+            offset: 0,
+        };
+
+        let result = search_messages(State(state.clone()), Query(search("shipped"))).await.unwrap();
+        assert_eq!(result.total, 2);
+
+        let result = search_messages(State(state.clone()), Query(search("parcel shipped"))).await.unwrap();
+        assert_eq!(result.total, 1);
+
+        // Query syntax characters are treated as plain text
+        let result = search_messages(State(state.clone()), Query(search("\"shipped AND"))).await.unwrap();
+        assert_eq!(result.total, 0);
+
+        // Deleted messages drop out of the index
+        repo.clear_credential_messages(&credential.id).await.unwrap();
+        let result = search_messages(State(state.clone()), Query(search("shipped"))).await.unwrap();
+        assert_eq!(result.total, 0);
+
+        let result = search_messages(State(state), Query(search("   "))).await;
+        assert!(matches!(result, Err(AppError::BadRequest(_))));
+    }
+
+    /// Webhook receiver on a random local port that accepts everything and counts requests
+    async fn counting_receiver() -> (String, Arc<AtomicUsize>) {
+        let hits = Arc::new(AtomicUsize::new(0));
+        let counter = hits.clone();
+        let app = axum::Router::new().route(
+            "/hook",
+            axum::routing::post(move || {
+                let counter = counter.clone();
+                async move {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    "ok"
+                }
+            }),
+        );
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        (url, hits)
+    }
+
+    #[tokio::test]
+    async fn test_retry_failed_is_paced() {
+        let repo = Repository::new("sqlite::memory:").await.unwrap();
+        let state = AppState::new(repo.clone(), ListenerPool::new(repo.clone()));
+        let (url, hits) = counting_receiver().await;
+
+        let credential = Credential::new(CreateCredentialRequest {
+            name: "Bulk".to_string(),
+            api_key: "key".to_string(),
+            app_id: "1:123:android:abc".to_string(),
+            project_id: "project".to_string(),
+            webhook_url: Some(url),
+            ..Default::default()
+        });
+        repo.create_credential(&credential).await.unwrap();
+
+        for status in [500, 0, 503, 200] {
+            let log = MessageLog::new(credential.id.clone(), None, "{}".to_string());
+            repo.create_message_log(&log).await.unwrap();
+            repo.update_message_webhook_status(&log.id, status, None, None)
+                .await
+                .unwrap();
+        }
+
+        let query = RetryFailedQuery {
+            credential_id: Some(credential.id.clone()),
+            limit: 100,
+            rate: Some(20.0),
+        };
+        let result = retry_failed_messages(State(state), Query(query)).await.unwrap();
+
+        // Only the three failed messages are re-delivered
+        assert_eq!(result.attempted, 3);
+        assert_eq!(result.succeeded, 3);
+        assert_eq!(hits.load(Ordering::SeqCst), 3);
+        // Two gaps of 50ms +/- 20% jitter between the three dispatches
+        assert!(result.elapsed_ms >= 80, "elapsed {}ms", result.elapsed_ms);
+        assert!(result.effective_rate > 0.0 && result.effective_rate < 40.0);
+
+        assert!(repo.list_failed_message_logs(None, 100).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_delete_messages() {
+        let repo = Repository::new("sqlite::memory:").await.unwrap();
+        let state = AppState::new(repo.clone(), ListenerPool::new(repo.clone()));
+        let credential = Credential::new(
+            serde_json::from_value::<CreateCredentialRequest>(serde_json::json!({
+                "name": "Cleanup",
+                "api_key": "key",
+                "app_id": "1:123:android:abc",
+                "project_id": "project"
+            }))
+            .unwrap(),
+        );
+        repo.create_credential(&credential).await.unwrap();
+
+        let now = chrono::Utc::now();
+        let mut ids = Vec::new();
+        for (delivery, age_days) in [
+            (DeliveryState::Failed, 10),
+            (DeliveryState::Failed, 1),
+            (DeliveryState::Delivered, 10),
+            (DeliveryState::Failed, 10),
+        ] {
+            let mut log = MessageLog::new(credential.id.clone(), None, "{}".to_string());
+            log.delivery_state = delivery.as_str().to_string();
+            log.received_at = now - chrono::Duration::days(age_days);
+            repo.create_message_log(&log).await.unwrap();
+            ids.push(log.id);
+        }
+
+        // A bulk delete needs a filter
+        let query = |delivery_state, before| DeleteMessagesQuery {
+            credential_id: None,
+            delivery_state,
+            before,
+        };
+        let result = delete_messages(State(state.clone()), Query(query(None, None))).await;
+        assert!(matches!(result, Err(AppError::BadRequest(_))));
+
+        // Failed ones older than five days
+        let before = Some(now - chrono::Duration::days(5));
+        let Json(deleted) =
+            delete_messages(State(state.clone()), Query(query(Some(DeliveryState::Failed), before)))
+                .await
+                .unwrap();
+        assert_eq!(deleted.deleted, 2);
+        assert_eq!(repo.count_message_logs(None, None, None).await.unwrap(), 2);
+        assert_eq!(repo.message_count(Some(&credential.id)).await.unwrap(), 2);
+
+        // Single message, not while it is being delivered
+        let guard = state.in_flight.try_acquire(&ids[1]).unwrap();
+        let result = delete_message(State(state.clone()), Path(ids[1].clone())).await;
+        assert!(matches!(result, Err(AppError::Conflict(_))));
+        drop(guard);
+        let Json(deleted) = delete_message(State(state.clone()), Path(ids[1].clone())).await.unwrap();
+        assert_eq!(deleted.deleted, 1);
+        let result = delete_message(State(state.clone()), Path(ids[1].clone())).await;
+        assert!(matches!(result, Err(AppError::NotFound(_))));
+        assert!(repo.get_message_log(&ids[2]).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_retry_of_same_message_conflicts() {
+        let repo = Repository::new("sqlite::memory:").await.unwrap();
+        let state = AppState::new(repo.clone(), ListenerPool::new(repo));
+
+        // Another delivery of the same message is in progress
+        let guard = state.in_flight.try_acquire("msg-1").unwrap();
+        let result = retry_webhook(State(state.clone()), Path("msg-1".to_string()), None).await;
+        assert!(matches!(result, Err(AppError::Conflict(_))));
+
+        // Once released, the retry proceeds (and fails on the unknown id)
+        drop(guard);
+        let result = retry_webhook(State(state.clone()), Path("msg-1".to_string()), None).await;
+        assert!(matches!(result, Err(AppError::NotFound(_))));
+
+        // The error path released the claim too
+        assert!(state.in_flight.try_acquire("msg-1").is_some());
+    }
+
+    #[tokio::test]
+    async fn test_ack_token_checked_first() {
+        let repo = Repository::new("sqlite::memory:").await.unwrap();
+        let state = AppState::new(repo.clone(), ListenerPool::new(repo.clone()));
+        let credential = Credential::new(CreateCredentialRequest {
+            name: "Acks".to_string(),
+            api_key: "key".to_string(),
+            app_id: "1:123:android:abc".to_string(),
+            project_id: "project".to_string(),
+            ..Default::default()
+        });
+        repo.create_credential(&credential).await.unwrap();
+        let log = MessageLog::new(credential.id.clone(), None, "{}".to_string());
+        repo.create_message_log(&log).await.unwrap();
+
+        let with_token = |token: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert("X-Ack-Token", token.parse().unwrap());
+            headers
+        };
+        let ack = |id: &str, headers: HeaderMap| {
+            ack_message(State(state.clone()), Path(id.to_string()), headers)
+        };
+        let token = credential.ack_token.clone().unwrap();
+
+        // A wrong token and an unknown message look the same to a token holder
+        let result = ack(&log.id, with_token("wrong")).await;
+        assert!(matches!(result, Err(AppError::Unauthorized(_))));
+        let result = ack("missing", with_token(&token)).await;
+        assert!(matches!(result, Err(AppError::Unauthorized(_))));
+        assert!(!repo.get_message_log(&log.id).await.unwrap().unwrap().acknowledged);
+
+        let Json(acked) = ack(&log.id, with_token(&token)).await.unwrap();
+        assert!(acked.acknowledged);
+
+        // With the master key (checked by the middleware) an unknown message is a 404
+        let result = ack("missing", HeaderMap::new()).await;
+        assert!(matches!(result, Err(AppError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn test_retry_to_override_url() {
+        let repo = Repository::new("sqlite::memory:").await.unwrap();
+        let state = AppState::new(repo.clone(), ListenerPool::new(repo.clone()));
+        let (url, hits) = counting_receiver().await;
+
+        let credential = Credential::new(CreateCredentialRequest {
+            name: "Override".to_string(),
+            api_key: "key".to_string(),
+            app_id: "1:123:android:abc".to_string(),
+            project_id: "project".to_string(),
+            webhook_url: Some("http://127.0.0.1:1/unreachable".to_string()),
+            ..Default::default()
+        });
+        repo.create_credential(&credential).await.unwrap();
+        let log = MessageLog::new(credential.id.clone(), None, "{}".to_string());
+        repo.create_message_log(&log).await.unwrap();
+
+        let invalid = RetryWebhookRequest {
+            webhook_url: Some("ftp://example.com".to_string()),
+            webhook_headers: None,
+        };
+        let result =
+            retry_webhook(State(state.clone()), Path(log.id.clone()), Some(Json(invalid))).await;
+        assert!(matches!(result, Err(AppError::BadRequest(_))));
+
+        let target = RetryWebhookRequest {
+            webhook_url: Some(url.clone()),
+            webhook_headers: Some(HashMap::from([("X-Batch".to_string(), "backlog".to_string())])),
+        };
+        let result = retry_webhook(State(state), Path(log.id.clone()), Some(Json(target)))
+            .await
+            .unwrap();
+        assert_eq!(result.status, Some(200));
+        assert_eq!(result.webhook_url.as_deref(), Some(url.as_str()));
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+
+        // Recorded on the message; the credential keeps its own URL
+        let stored = repo.get_message_log(&log.id).await.unwrap().unwrap();
+        assert_eq!(stored.webhook_url, Some(url));
+        let credential = repo.get_credential(&credential.id).await.unwrap().unwrap();
+        assert_eq!(credential.webhook_url, "http://127.0.0.1:1/unreachable");
+    }
+}
@@ -1,19 +1,25 @@
+pub mod admin;
 pub mod credentials;
 pub mod health;
 pub mod messages;
+pub mod workers;
 
-use crate::db::Repository;
+use crate::db::{CredentialCache, MessageStore, Repository};
 use crate::middleware::ApiKeyConfig;
-use crate::workers::ListenerPool;
+use crate::workers::{InFlightDeliveries, LifecycleNotifier, ListenerPool, WebhookClient};
 use axum::{
+    extract::DefaultBodyLimit,
+    http::{header, HeaderName, HeaderValue, Method},
     middleware,
-    routing::{delete, get, post, put},
+    routing::{delete, get, patch, post, put},
     Router,
 };
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::RwLock;
 use tower_http::cors::{Any, CorsLayer};
 use tower_http::trace::TraceLayer;
+use tracing::warn;
 use utoipa::OpenApi;
 use utoipa_swagger_ui::SwaggerUi;
 
@@ -32,41 +38,144 @@ use utoipa_swagger_ui::SwaggerUi;
     tags(
         (name = "health", description = "Health check and statistics"),
         (name = "credentials", description = "FCM credential management"),
-        (name = "messages", description = "Message log operations")
+        (name = "messages", description = "Message log operations"),
+        (name = "workers", description = "Bulk listener control"),
+        (name = "admin", description = "Server maintenance")
     ),
     paths(
         health::health_check,
         health::get_stats,
+        health::get_detailed_stats,
         credentials::list_credentials,
         credentials::create_credential,
         credentials::get_credential,
+        credentials::get_credential_status,
+        credentials::list_topics,
         credentials::update_credential,
         credentials::delete_credential,
+        credentials::get_delivery_settings,
+        credentials::update_delivery_settings,
+        credentials::set_schedule,
+        credentials::delete_schedule,
+        credentials::rotate_webhook_secret,
+        credentials::finalize_webhook_secret,
+        credentials::generate_signing_key,
+        credentials::inject_message,
+        credentials::verify_webhook,
+        credentials::debug_webhook,
+        credentials::reset_dedup,
+        credentials::delete_signing_key,
+        credentials::export_credential,
+        credentials::import_credential,
+        credentials::clone_credential,
         credentials::start_listener,
         credentials::stop_listener,
         credentials::restart_listener,
         credentials::suspend_credential,
         credentials::unsuspend_credential,
         messages::list_messages,
+        messages::search_messages,
+        messages::stream_messages,
         messages::get_message,
         messages::retry_webhook,
+        messages::retry_failed_messages,
+        messages::ack_message,
         messages::clear_messages,
+        messages::delete_message,
+        messages::delete_messages,
+        admin::vacuum,
+        admin::get_config,
+        admin::update_config,
+        admin::get_webhook_public_key,
+        workers::start_all,
+        workers::stop_all,
+        workers::restart_all,
     ),
     components(
         schemas(
             health::HealthResponse,
             health::StatsResponse,
+            crate::models::CredentialStateCounts,
+            health::DetailedStatsResponse,
+            health::CredentialStats,
+            health::CredentialHealth,
+            health::HealthStatus,
+            crate::workers::WatchdogStats,
             credentials::ListCredentialsResponse,
             credentials::CreateCredentialResponse,
             credentials::ListQuery,
+            credentials::CreateQuery,
+            credentials::ExportQuery,
+            credentials::CloneCredentialRequest,
+            credentials::GetCredentialQuery,
+            credentials::StartQuery,
+            credentials::DeliverySettingsResponse,
+            credentials::SuspendQuery,
+            credentials::RotateWebhookSecretRequest,
+            credentials::RotateWebhookSecretResponse,
+            credentials::VerifyWebhookResponse,
+            credentials::DebugWebhookQuery,
+            credentials::DebugWebhookResponse,
+            credentials::DedupResetQuery,
+            credentials::DedupResetResponse,
+            credentials::SigningKeyResponse,
+            credentials::InjectResponse,
+            crate::workers::ScheduleConfig,
+            crate::workers::ScheduleAction,
+            crate::workers::ScheduledTransition,
+            crate::workers::SinkConfig,
+            crate::workers::DeliveryAlertPolicy,
+            crate::workers::RetryOn,
+            crate::workers::ResponseMatch,
+            crate::workers::WebhookAuth,
+            crate::workers::AuthScheme,
+            crate::workers::AuthPrecedence,
+            crate::models::CredentialExport,
+            crate::models::ExportedCredential,
+            crate::models::ExportedRegistration,
+            credentials::WorkerStatusResponse,
+            credentials::ListTopicsResponse,
+            credentials::TopicStatus,
+            crate::workers::TopicState,
+            crate::workers::TopicSubscription,
+            crate::workers::WorkerError,
+            crate::workers::NeedsAttention,
+            crate::workers::QueueStats,
+            crate::workers::OverflowPolicy,
+            crate::workers::DeliverySettings,
+            crate::workers::DeliverySettingsUpdate,
+            crate::workers::WebhookVerification,
             crate::models::CreateCredentialRequest,
             crate::models::UpdateCredentialRequest,
             crate::models::CredentialResponse,
             messages::ListMessagesQuery,
             messages::ListMessagesResponse,
+            messages::SearchMessagesQuery,
+            messages::SearchOrder,
+            messages::StreamQuery,
+            crate::workers::StreamEvent,
+            messages::RetryWebhookRequest,
             messages::RetryWebhookResponse,
+            messages::RetryFailedQuery,
+            messages::RetryFailedResponse,
+            messages::AckMessageResponse,
             messages::ClearMessagesResponse,
+            messages::DeleteMessagesQuery,
+            messages::DeleteMessagesResponse,
             crate::models::MessageLogResponse,
+            crate::models::DeliveryState,
+            crate::models::MessageSummary,
+            crate::models::MessageKind,
+            admin::VacuumQuery,
+            admin::VacuumResponse,
+            admin::ConfigResponse,
+            admin::StartupConfig,
+            admin::PublicKeyQuery,
+            workers::BulkWorkerResponse,
+            crate::workers::BulkWorkerResult,
+            crate::workers::WebhookPublicKey,
+            crate::settings::RuntimeSettings,
+            crate::settings::SettingsUpdate,
         )
     ),
     modifiers(&SecurityAddon)
@@ -102,49 +211,238 @@ impl utoipa::Modify for SecurityAddon {
 #[derive(Clone)]
 pub struct AppState {
     pub repo: Repository,
+    /// Store the workers write received messages to, used for listing and counting them
+    pub message_store: Arc<dyn MessageStore>,
     pub listener_pool: Arc<RwLock<ListenerPool>>,
+    /// Messages with a webhook retry in progress
+    pub in_flight: InFlightDeliveries,
+    /// Webhook client shared with the workers, so retries see the same endpoint health
+    pub webhook_client: WebhookClient,
+    /// Credential configs for per-message request paths; invalidated when a credential changes
+    pub credential_cache: CredentialCache,
+    /// Lifecycle notifier shared with the pool, so one debounce covers API and worker events
+    pub lifecycle: LifecycleNotifier,
 }
 
 impl AppState {
     pub fn new(repo: Repository, listener_pool: ListenerPool) -> Self {
         Self {
             repo,
+            message_store: listener_pool.message_store(),
+            webhook_client: listener_pool.webhook_client(),
+            lifecycle: listener_pool.lifecycle(),
             listener_pool: Arc::new(RwLock::new(listener_pool)),
+            in_flight: InFlightDeliveries::new(),
+            credential_cache: CredentialCache::new(),
         }
     }
 }
 
+/// Get allowed CORS origins from environment (comma-separated), empty when unset
+pub fn get_cors_allowed_origins() -> Vec<String> {
+    std::env::var("CORS_ALLOWED_ORIGINS")
+        .unwrap_or_default()
+        .split(',')
+        .map(|s| s.trim().trim_end_matches('/').to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+/// Get the per-request handler timeout from environment (REQUEST_TIMEOUT_SECS), default 30s
+pub fn get_request_timeout() -> Duration {
+    let secs = std::env::var("REQUEST_TIMEOUT_SECS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .filter(|n| *n > 0)
+        .unwrap_or(30);
+    Duration::from_secs(secs)
+}
+
+/// Get the handler timeout of slow read endpoints (export, search, message listing) from
+/// environment (SLOW_REQUEST_TIMEOUT_SECS), default 300s
+pub fn get_slow_request_timeout() -> Duration {
+    let secs = std::env::var("SLOW_REQUEST_TIMEOUT_SECS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .filter(|n| *n > 0)
+        .unwrap_or(300);
+    Duration::from_secs(secs)
+}
+
+/// Get the largest accepted API request body from environment (MAX_REQUEST_BODY_BYTES),
+/// default 1 MiB
+pub fn get_max_request_body_bytes() -> usize {
+    std::env::var("MAX_REQUEST_BODY_BYTES")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .filter(|n| *n > 0)
+        .unwrap_or(1024 * 1024)
+}
+
+/// Get the largest accepted request body of credential imports from environment
+/// (MAX_BULK_REQUEST_BODY_BYTES), default 16 MiB
+pub fn get_max_bulk_request_body_bytes() -> usize {
+    std::env::var("MAX_BULK_REQUEST_BODY_BYTES")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .filter(|n| *n > 0)
+        .unwrap_or(16 * 1024 * 1024)
+}
+
+/// Reject the routes' request bodies over `limit` bytes with a 400. It replaces axum's
+/// default limit, which would otherwise cap bodies at 2 MB with its own error.
+fn with_body_limit<S: Clone + Send + Sync + 'static>(router: Router<S>, limit: usize) -> Router<S> {
+    router
+        .layer(middleware::from_fn_with_state(
+            limit,
+            crate::middleware::request_body_limit,
+        ))
+        .layer(DefaultBodyLimit::disable())
+}
+
+/// Fail the routes' handlers with a 504 once they run longer than `timeout`
+fn with_timeout<S: Clone + Send + Sync + 'static>(router: Router<S>, timeout: Duration) -> Router<S> {
+    router.layer(middleware::from_fn_with_state(
+        timeout,
+        crate::middleware::request_timeout,
+    ))
+}
+
+/// CORS policy: any origin when none are configured, otherwise only the listed
+/// origins, with credentials (cookies, Authorization) allowed
+fn cors_layer(origins: &[String]) -> CorsLayer {
+    // Browsers reject credentialed responses to any origin, and tower-http refuses to build
+    // such a layer, so `*` means any origin without credentials, like leaving it unset
+    if origins.iter().any(|o| o == "*") {
+        return any_origin_cors();
+    }
+
+    let origins: Vec<HeaderValue> = origins
+        .iter()
+        .filter_map(|o| match HeaderValue::from_str(o) {
+            Ok(v) => Some(v),
+            Err(_) => {
+                warn!("Ignoring invalid CORS origin: {}", o);
+                None
+            }
+        })
+        .collect();
+
+    if origins.is_empty() {
+        warn!("CORS_ALLOWED_ORIGINS is not set, allowing requests from any origin");
+        return any_origin_cors();
+    }
+
+    // Wildcards aren't allowed together with credentials, so list methods and headers
+    CorsLayer::new()
+        .allow_origin(origins)
+        .allow_methods([
+            Method::GET,
+            Method::POST,
+            Method::PUT,
+            Method::PATCH,
+            Method::DELETE,
+            Method::OPTIONS,
+        ])
+        .allow_headers([
+            header::CONTENT_TYPE,
+            header::AUTHORIZATION,
+            HeaderName::from_static("x-api-key"),
+            HeaderName::from_static("x-ack-token"),
+        ])
+        .allow_credentials(true)
+}
+
+fn any_origin_cors() -> CorsLayer {
+    CorsLayer::new()
+        .allow_origin(Any)
+        .allow_methods(Any)
+        .allow_headers(Any)
+}
+
 /// Build the API router
 pub fn create_router(state: AppState, api_key_config: ApiKeyConfig) -> Router {
     // CORS must be the outermost layer (applied last, runs first)
     // This ensures OPTIONS preflight requests get CORS headers before hitting auth
-    let cors = CorsLayer::new()
-        .allow_origin(Any)
-        .allow_methods(Any)
-        .allow_headers(Any);
+    let cors = cors_layer(&get_cors_allowed_origins());
 
-    let api_routes = Router::new()
+    // Most endpoints get REQUEST_TIMEOUT_SECS
+    let default_routes = Router::new()
         // Health endpoints
         .route("/health", get(health::health_check))
         .route("/api/stats", get(health::get_stats))
+        .route("/api/stats/detailed", get(health::get_detailed_stats))
         // Credential endpoints
         .route("/api/credentials", get(credentials::list_credentials))
         .route("/api/credentials", post(credentials::create_credential))
         .route("/api/credentials/:id", get(credentials::get_credential))
         .route("/api/credentials/:id", put(credentials::update_credential))
         .route("/api/credentials/:id", delete(credentials::delete_credential))
-        .route("/api/credentials/:id/start", post(credentials::start_listener))
+        .route("/api/credentials/:id/status", get(credentials::get_credential_status))
+        .route("/api/credentials/:id/topics", get(credentials::list_topics))
+        .route("/api/credentials/:id/clone", post(credentials::clone_credential))
+        .route("/api/credentials/:id/delivery", get(credentials::get_delivery_settings))
+        .route("/api/credentials/:id/delivery", patch(credentials::update_delivery_settings))
+        .route("/api/credentials/:id/schedule", put(credentials::set_schedule))
+        .route("/api/credentials/:id/schedule", delete(credentials::delete_schedule))
+        .route("/api/credentials/:id/webhook-secret", post(credentials::rotate_webhook_secret))
+        .route(
+            "/api/credentials/:id/webhook-secret/finalize",
+            post(credentials::finalize_webhook_secret),
+        )
+        .route("/api/credentials/:id/signing-key", post(credentials::generate_signing_key))
+        .route("/api/credentials/:id/signing-key", delete(credentials::delete_signing_key))
+        .route("/api/credentials/:id/inject", post(credentials::inject_message))
+        .route("/api/credentials/:id/verify-webhook", post(credentials::verify_webhook))
+        .route("/api/credentials/:id/debug-webhook", post(credentials::debug_webhook))
+        .route("/api/credentials/:id/dedup/reset", post(credentials::reset_dedup))
         .route("/api/credentials/:id/stop", post(credentials::stop_listener))
         .route("/api/credentials/:id/restart", post(credentials::restart_listener))
         .route("/api/credentials/:id/suspend", post(credentials::suspend_credential))
         .route("/api/credentials/:id/unsuspend", post(credentials::unsuspend_credential))
         .route("/api/credentials/:id/messages", delete(messages::clear_messages))
         // Message endpoints
-        .route("/api/messages", get(messages::list_messages))
         .route("/api/messages/:id", get(messages::get_message))
+        .route("/api/messages/:id", delete(messages::delete_message))
         .route("/api/messages/:id/retry", post(messages::retry_webhook))
+        .route("/api/messages/:id/ack", post(messages::ack_message))
+        // Admin endpoints
+        .route("/api/workers/stop-all", post(workers::stop_all))
+        .route("/api/admin/config", get(admin::get_config))
+        .route("/api/admin/config", patch(admin::update_config))
+        .route("/api/admin/webhook-public-key", get(admin::get_webhook_public_key));
+
+    // Imports carry a whole exported credential, so they may be larger than other requests
+    let bulk_routes =
+        Router::new().route("/api/credentials/import", post(credentials::import_credential));
+    let default_routes = with_body_limit(default_routes, get_max_request_body_bytes())
+        .merge(with_body_limit(bulk_routes, get_max_bulk_request_body_bytes()));
+
+    // Reads and bulk deletes that can legitimately scan huge tables get SLOW_REQUEST_TIMEOUT_SECS
+    let slow_routes = Router::new()
+        .route("/api/credentials/:id/export", get(credentials::export_credential))
+        .route("/api/messages", get(messages::list_messages))
+        .route("/api/messages", delete(messages::delete_messages))
+        .route("/api/messages/search", get(messages::search_messages));
+    let slow_routes = with_body_limit(slow_routes, get_max_request_body_bytes());
+
+    // Paced bulk jobs and streams run as long as they need; a start with `wait=true` is
+    // bounded by its own timeout_secs
+    let untimed_routes = Router::new()
+        .route("/api/credentials/:id/start", post(credentials::start_listener))
+        .route("/api/messages/stream", get(messages::stream_messages))
+        .route("/api/messages/retry-failed", post(messages::retry_failed_messages))
+        .route("/api/workers/start-all", post(workers::start_all))
+        .route("/api/workers/restart-all", post(workers::restart_all))
+        .route("/api/admin/vacuum", post(admin::vacuum));
+    let untimed_routes = with_body_limit(untimed_routes, get_max_request_body_bytes());
+
+    let api_routes = Router::new()
+        .merge(with_timeout(default_routes, get_request_timeout()))
+        .merge(with_timeout(slow_routes, get_slow_request_timeout()))
+        .merge(untimed_routes)
         // Layers: order matters! Applied in reverse (last applied runs first)
-        // 1. Auth middleware with state (runs after CORS)
+        // 1. Auth middleware with state (runs after CORS; the timeouts above run after it)
         .layer(middleware::from_fn_with_state(
             api_key_config,
             crate::middleware::api_key_auth,
@@ -160,3 +458,137 @@ pub fn create_router(state: AppState, api_key_config: ApiKeyConfig) -> Router {
         .merge(SwaggerUi::new("/swagger-ui").url("/api-docs/openapi.json", ApiDoc::openapi()))
         .merge(api_routes)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::Request;
+    use tower::ServiceExt;
+
+    async fn preflight(origins: &[String], origin: &str) -> axum::http::HeaderMap {
+        let app = Router::new()
+            .route("/health", get(health::health_check))
+            .layer(cors_layer(origins));
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method(Method::OPTIONS)
+                    .uri("/health")
+                    .header(header::ORIGIN, origin)
+                    .header(header::ACCESS_CONTROL_REQUEST_METHOD, "GET")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        response.headers().clone()
+    }
+
+    #[tokio::test]
+    async fn test_cors_configured_origins() {
+        let origins = vec!["https://admin.example.com".to_string()];
+
+        let headers = preflight(&origins, "https://admin.example.com").await;
+        assert_eq!(
+            headers.get(header::ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(),
+            "https://admin.example.com"
+        );
+        assert_eq!(headers.get(header::ACCESS_CONTROL_ALLOW_CREDENTIALS).unwrap(), "true");
+
+        let headers = preflight(&origins, "https://evil.example.com").await;
+        assert!(headers.get(header::ACCESS_CONTROL_ALLOW_ORIGIN).is_none());
+    }
+
+    async fn slow_handler() -> &'static str {
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        "done"
+    }
+
+    async fn timed_request(uri: &str) -> axum::http::StatusCode {
+        let default_routes = Router::new().route("/api/stats", get(slow_handler));
+        let slow_routes = Router::new().route("/api/messages/search", get(slow_handler));
+        let untimed_routes =
+            Router::new().route("/api/messages/retry-failed", post(slow_handler));
+        let app = Router::new()
+            .merge(with_timeout(default_routes, Duration::from_millis(50)))
+            .merge(with_timeout(slow_routes, Duration::from_millis(1000)))
+            .merge(untimed_routes);
+
+        let method = if uri.ends_with("retry-failed") { Method::POST } else { Method::GET };
+        app.oneshot(Request::builder().method(method).uri(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+            .status()
+    }
+
+    #[tokio::test]
+    async fn test_request_timeout() {
+        assert_eq!(timed_request("/api/stats").await, axum::http::StatusCode::GATEWAY_TIMEOUT);
+        // Slow reads get their own, longer limit
+        assert_eq!(timed_request("/api/messages/search").await, axum::http::StatusCode::OK);
+        // Long-running bulk jobs are exempt
+        assert_eq!(timed_request("/api/messages/retry-failed").await, axum::http::StatusCode::OK);
+    }
+
+    async fn body_request(uri: &str, body: Body) -> (axum::http::StatusCode, String) {
+        async fn echo(body: String) -> String {
+            body
+        }
+        let default_routes = Router::new().route("/api/credentials", post(echo));
+        let bulk_routes = Router::new().route("/api/credentials/import", post(echo));
+        let app = Router::new()
+            .merge(with_body_limit(default_routes, 16))
+            .merge(with_body_limit(bulk_routes, 64));
+
+        let response = app
+            .oneshot(Request::builder().method(Method::POST).uri(uri).body(body).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, String::from_utf8_lossy(&bytes).to_string())
+    }
+
+    #[tokio::test]
+    async fn test_request_body_limit() {
+        let (status, body) = body_request("/api/credentials", Body::from("small")).await;
+        assert_eq!(status, axum::http::StatusCode::OK);
+        assert_eq!(body, "small");
+
+        let oversized = "x".repeat(32);
+        let (status, body) = body_request("/api/credentials", Body::from(oversized.clone())).await;
+        assert_eq!(status, axum::http::StatusCode::BAD_REQUEST);
+        assert!(body.contains("Request body too large"), "{}", body);
+
+        // Streamed without a Content-Length, cut off once it passes the limit
+        let chunks = (0..4).map(|_| Ok::<_, std::io::Error>("x".repeat(10)));
+        let streamed = Body::from_stream(futures::stream::iter(chunks));
+        let (status, body) = body_request("/api/credentials", streamed).await;
+        assert_eq!(status, axum::http::StatusCode::BAD_REQUEST);
+        assert!(body.contains("Request body too large"), "{}", body);
+
+        // Imports have their own, larger limit
+        let (status, _) = body_request("/api/credentials/import", Body::from(oversized)).await;
+        assert_eq!(status, axum::http::StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_cors_defaults_to_any_origin() {
+        let headers = preflight(&[], "https://anywhere.example.com").await;
+        assert_eq!(headers.get(header::ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(), "*");
+        assert!(headers.get(header::ACCESS_CONTROL_ALLOW_CREDENTIALS).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_cors_wildcard_allows_any_origin_without_credentials() {
+        for origins in [vec!["*"], vec!["https://admin.example.com", "*"]] {
+            let origins: Vec<String> = origins.into_iter().map(String::from).collect();
+            let headers = preflight(&origins, "https://anywhere.example.com").await;
+            assert_eq!(headers.get(header::ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(), "*");
+            assert!(headers.get(header::ACCESS_CONTROL_ALLOW_CREDENTIALS).is_none());
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use crate::api::AppState;
+use crate::error::AppResult;
+use crate::workers::BulkWorkerResult;
+use axum::{extract::State, Json};
+use serde::Serialize;
+use tracing::info;
+use utoipa::ToSchema;
+
+/// Result of a bulk worker operation
+#[derive(Debug, Serialize, ToSchema)]
+pub struct BulkWorkerResponse {
+    /// Credentials the operation succeeded for
+    pub succeeded: usize,
+    /// Credentials the operation failed for (the rest of the batch still ran)
+    pub failed: usize,
+    /// Outcome per credential
+    pub results: Vec<BulkWorkerResult>,
+}
+
+impl BulkWorkerResponse {
+    fn new(results: Vec<BulkWorkerResult>) -> Self {
+        let succeeded = results.iter().filter(|r| r.success).count();
+        Self {
+            succeeded,
+            failed: results.len() - succeeded,
+            results,
+        }
+    }
+}
+
+/// Start every active, non-suspended credential that isn't already listening,
+/// one at a time WORKER_START_STAGGER_MS apart
+#[utoipa::path(
+    post,
+    path = "/api/workers/start-all",
+    tag = "workers",
+    security(
+        ("api_key" = []),
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Per-credential results", body = BulkWorkerResponse),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+pub async fn start_all(State(state): State<AppState>) -> AppResult<Json<BulkWorkerResponse>> {
+    let pool = state.listener_pool.read().await;
+    let response = BulkWorkerResponse::new(pool.start_all().await?);
+
+    info!("Bulk start: {} started, {} failed", response.succeeded, response.failed);
+    Ok(Json(response))
+}
+
+/// Stop every running worker
+#[utoipa::path(
+    post,
+    path = "/api/workers/stop-all",
+    tag = "workers",
+    security(
+        ("api_key" = []),
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Per-credential results", body = BulkWorkerResponse),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+pub async fn stop_all(State(state): State<AppState>) -> Json<BulkWorkerResponse> {
+    let pool = state.listener_pool.read().await;
+    let response = BulkWorkerResponse::new(pool.stop_all().await);
+
+    info!("Bulk stop: {} stopped, {} failed", response.succeeded, response.failed);
+    Json(response)
+}
+
+/// Restart every running worker with its current settings, one at a time
+/// WORKER_START_STAGGER_MS apart (e.g. after a downstream change)
+#[utoipa::path(
+    post,
+    path = "/api/workers/restart-all",
+    tag = "workers",
+    security(
+        ("api_key" = []),
+        ("bearer_auth" = [])
+    ),
+    responses(
+        (status = 200, description = "Per-credential results", body = BulkWorkerResponse),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+pub async fn restart_all(State(state): State<AppState>) -> AppResult<Json<BulkWorkerResponse>> {
+    let pool = state.listener_pool.read().await;
+    let response = BulkWorkerResponse::new(pool.restart_all().await?);
+
+    info!("Bulk restart: {} restarted, {} failed", response.succeeded, response.failed);
+    Ok(Json(response))
+}
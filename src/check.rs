@@ -0,0 +1,62 @@
+use crate::db::Repository;
+use crate::tls;
+use tracing::{error, info};
+
+/// Pre-flight validation of the environment (used by the `check` subcommand)
+pub async fn run_checks(database_url: &str, port: u16, api_key: Option<&str>) -> bool {
+    let mut ok = true;
+
+    // API key must be configured, otherwise every restart generates a new one
+    match api_key {
+        Some(key) if !key.trim().is_empty() => info!("[ok] API_KEY is set"),
+        _ => {
+            error!("[fail] API_KEY is not set");
+            ok = false;
+        }
+    }
+
+    // Database must be reachable
+    match Repository::check_connection(database_url).await {
+        Ok(_) => info!("[ok] Database reachable: {}", database_url),
+        Err(e) => {
+            error!("[fail] Cannot connect to database {}: {}", database_url, e);
+            ok = false;
+        }
+    }
+
+    // Migrations must apply cleanly to a fresh database
+    match Repository::new("sqlite::memory:").await {
+        Ok(_) => info!("[ok] Migrations apply cleanly"),
+        Err(e) => {
+            error!("[fail] Migrations failed on a temporary database: {}", e);
+            ok = false;
+        }
+    }
+
+    // TLS certificate and key must load when configured
+    match tls::get_tls_paths() {
+        Ok(None) => info!("[ok] TLS not configured, serving plain HTTP"),
+        Ok(Some(paths)) => match tls::load(&paths).await {
+            Ok(_) => info!("[ok] TLS certificate loads: {}", paths.cert),
+            Err(e) => {
+                error!("[fail] {:#}", e);
+                ok = false;
+            }
+        },
+        Err(e) => {
+            error!("[fail] {}", e);
+            ok = false;
+        }
+    }
+
+    // HTTP port must be free
+    match tokio::net::TcpListener::bind(("0.0.0.0", port)).await {
+        Ok(_) => info!("[ok] Port {} is bindable", port),
+        Err(e) => {
+            error!("[fail] Cannot bind port {}: {}", port, e);
+            ok = false;
+        }
+    }
+
+    ok
+}
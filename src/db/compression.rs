@@ -0,0 +1,32 @@
+use anyhow::Result;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+
+/// Payloads shorter than this are stored as-is; the zstd frame and base64 overhead outweigh the gain
+pub const COMPRESSION_MIN_BYTES: usize = 256;
+
+const ZSTD_LEVEL: i32 = 3;
+
+/// Whether new message payloads are stored zstd-compressed (PAYLOAD_COMPRESSION=true), default off
+pub fn get_payload_compression() -> bool {
+    std::env::var("PAYLOAD_COMPRESSION")
+        .map(|v| matches!(v.trim().to_lowercase().as_str(), "true" | "1" | "yes"))
+        .unwrap_or(false)
+}
+
+/// zstd-compress a payload for the TEXT payload column (base64 of the zstd frame).
+/// None when the payload is too small or wouldn't get smaller.
+pub fn compress_payload(payload: &str) -> Option<String> {
+    if payload.len() < COMPRESSION_MIN_BYTES {
+        return None;
+    }
+    let compressed = zstd::encode_all(payload.as_bytes(), ZSTD_LEVEL).ok()?;
+    let encoded = BASE64.encode(compressed);
+    (encoded.len() < payload.len()).then_some(encoded)
+}
+
+/// Original payload of a row stored by `compress_payload`
+pub fn decompress_payload(stored: &str) -> Result<String> {
+    let compressed = BASE64.decode(stored)?;
+    let bytes = zstd::decode_all(compressed.as_slice())?;
+    Ok(String::from_utf8(bytes)?)
+}
@@ -0,0 +1,99 @@
+use crate::db::Repository;
+use crate::models::Credential;
+use anyhow::Result;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// In-memory credential configs for hot request paths (retries, acks), read through
+/// from the database. Anything that changes a credential invalidates its entry.
+#[derive(Clone, Default)]
+pub struct CredentialCache {
+    state: Arc<Mutex<CacheState>>,
+}
+
+#[derive(Default)]
+struct CacheState {
+    credentials: HashMap<String, Credential>,
+    /// Bumped on every invalidation, so a read that raced with one isn't cached
+    generation: u64,
+}
+
+impl CredentialCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cached credential, loaded from the database on a miss. Missing credentials
+    /// aren't cached, so one created later is found on the next call.
+    pub async fn get(&self, repo: &Repository, id: &str) -> Result<Option<Credential>> {
+        let generation = {
+            let state = self.state.lock().unwrap();
+            if let Some(credential) = state.credentials.get(id) {
+                return Ok(Some(credential.clone()));
+            }
+            state.generation
+        };
+
+        let credential = repo.get_credential(id).await?;
+        if let Some(credential) = &credential {
+            let mut state = self.state.lock().unwrap();
+            // An invalidation during the read means the row may already be outdated
+            if state.generation == generation {
+                state.credentials.insert(id.to_string(), credential.clone());
+            }
+        }
+
+        Ok(credential)
+    }
+
+    /// Drop a credential after it was changed or deleted
+    pub fn invalidate(&self, id: &str) {
+        let mut state = self.state.lock().unwrap();
+        state.credentials.remove(id);
+        state.generation += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{CreateCredentialRequest, CredentialUpdate};
+
+    #[tokio::test]
+    async fn test_update_visible_after_invalidate() {
+        let repo = Repository::new("sqlite::memory:").await.unwrap();
+        let cred = Credential::new(CreateCredentialRequest {
+            name: "Cached".to_string(),
+            api_key: "key".to_string(),
+            app_id: "1:123:android:abc".to_string(),
+            project_id: "project".to_string(),
+            webhook_url: Some("http://localhost/old".to_string()),
+            ..Default::default()
+        });
+        let cache = CredentialCache::new();
+        assert!(cache.get(&repo, &cred.id).await.unwrap().is_none());
+
+        repo.create_credential(&cred).await.unwrap();
+        let cached = cache.get(&repo, &cred.id).await.unwrap().unwrap();
+        assert_eq!(cached.webhook_url, "http://localhost/old");
+
+        let update = CredentialUpdate {
+            webhook_url: Some("http://localhost/new".to_string()),
+            ..Default::default()
+        };
+        repo.update_credential(&cred.id, &update)
+            .await
+            .unwrap();
+        // Served from memory until invalidated
+        let cached = cache.get(&repo, &cred.id).await.unwrap().unwrap();
+        assert_eq!(cached.webhook_url, "http://localhost/old");
+
+        cache.invalidate(&cred.id);
+        let cached = cache.get(&repo, &cred.id).await.unwrap().unwrap();
+        assert_eq!(cached.webhook_url, "http://localhost/new");
+
+        repo.delete_credential(&cred.id).await.unwrap();
+        cache.invalidate(&cred.id);
+        assert!(cache.get(&repo, &cred.id).await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,234 @@
+use crate::db::MessageStore;
+use crate::models::MessageLog;
+use anyhow::{anyhow, Result};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+use tokio::time::Instant;
+use tracing::{debug, error};
+
+/// Get the most message logs written in one transaction, from environment
+/// (MESSAGE_LOG_BATCH_SIZE), default 0 (batching off, one INSERT per message)
+pub fn get_message_log_batch_size() -> usize {
+    std::env::var("MESSAGE_LOG_BATCH_SIZE")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(0)
+}
+
+/// Get how long a batch waits for more message logs before it is written, from environment
+/// (MESSAGE_LOG_BATCH_FLUSH_MS), default 20
+pub fn get_message_log_batch_flush_interval() -> Duration {
+    let ms = std::env::var("MESSAGE_LOG_BATCH_FLUSH_MS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(20);
+    Duration::from_millis(ms)
+}
+
+/// Get how often batched writes trim credentials to their max_messages, from environment
+/// (MESSAGE_LOG_CLEANUP_INTERVAL_SECS), default 5
+pub fn get_message_log_cleanup_interval() -> Duration {
+    let secs = std::env::var("MESSAGE_LOG_CLEANUP_INTERVAL_SECS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(5);
+    Duration::from_secs(secs)
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct LogBatchConfig {
+    pub batch_size: usize,
+    pub flush_interval: Duration,
+    pub cleanup_interval: Duration,
+}
+
+impl LogBatchConfig {
+    /// Batching settings from the environment, None when batching is off
+    pub fn from_env() -> Option<Self> {
+        let batch_size = get_message_log_batch_size();
+        (batch_size > 1).then(|| Self {
+            batch_size,
+            flush_interval: get_message_log_batch_flush_interval(),
+            cleanup_interval: get_message_log_cleanup_interval(),
+        })
+    }
+}
+
+struct PendingLog {
+    log: MessageLog,
+    max_messages: i64,
+    /// Whether the log was stored (false: dropped as a duplicate)
+    done: oneshot::Sender<std::result::Result<bool, String>>,
+}
+
+/// Writes message logs from all workers in batched transactions on a background task.
+/// `write` still waits until its row is committed, so delivery status updates always find it;
+/// the saving comes from concurrent messages sharing a transaction, from checking a batch's
+/// fcmMessageIds for duplicates in one lookup, and from trimming old messages every
+/// cleanup_interval instead of after each insert.
+#[derive(Clone)]
+pub struct MessageLogWriter {
+    tx: mpsc::Sender<PendingLog>,
+}
+
+impl MessageLogWriter {
+    /// Start the writer task; it stops once every clone of the writer is dropped
+    pub fn spawn(store: Arc<dyn MessageStore>, config: LogBatchConfig) -> Self {
+        let (tx, rx) = mpsc::channel(config.batch_size * 4);
+        tokio::spawn(Self::run(store, rx, config));
+        Self { tx }
+    }
+
+    /// Store `log` with the next batch, keeping its credential to `max_messages` later on.
+    /// Returns false when its fcmMessageId was already logged, so it wasn't stored again.
+    pub async fn write(&self, log: &MessageLog, max_messages: i64) -> Result<bool> {
+        let (done, committed) = oneshot::channel();
+        self.tx
+            .send(PendingLog {
+                log: log.clone(),
+                max_messages,
+                done,
+            })
+            .await
+            .map_err(|_| anyhow!("message log writer has stopped"))?;
+
+        committed
+            .await
+            .map_err(|_| anyhow!("message log writer has stopped"))?
+            .map_err(|e| anyhow!(e))
+    }
+
+    async fn run(
+        store: Arc<dyn MessageStore>,
+        mut rx: mpsc::Receiver<PendingLog>,
+        config: LogBatchConfig,
+    ) {
+        // Credentials written since the last cleanup, with their message limit
+        let mut written: HashMap<String, i64> = HashMap::new();
+        let mut last_cleanup = Instant::now();
+
+        while let Some(first) = rx.recv().await {
+            let mut batch = vec![first];
+            let deadline = Instant::now() + config.flush_interval;
+            while batch.len() < config.batch_size {
+                match tokio::time::timeout_at(deadline, rx.recv()).await {
+                    Ok(Some(pending)) => batch.push(pending),
+                    // Flush time reached, or every writer dropped
+                    _ => break,
+                }
+            }
+
+            let mut logs = Vec::with_capacity(batch.len());
+            let mut waiters = Vec::with_capacity(batch.len());
+            for pending in batch {
+                written.insert(pending.log.credential_id.clone(), pending.max_messages);
+                waiters.push((pending.log.id.clone(), pending.done));
+                logs.push(pending.log);
+            }
+
+            // Checked here, where every batched insert happens, so no duplicate can slip in
+            // between the check and the insert
+            let received = logs.len();
+            let logs = match store.filter_new_messages(logs.clone()).await {
+                Ok(new) => new,
+                Err(e) => {
+                    error!("Failed to check a batch of message logs for duplicates: {}", e);
+                    logs
+                }
+            };
+            let stored: HashSet<String> = logs.iter().map(|log| log.id.clone()).collect();
+
+            let result = store.create_message_logs(&logs).await.map_err(|e| e.to_string());
+            match &result {
+                Ok(()) => debug!(
+                    "Wrote a batch of {} message logs ({} duplicates dropped)",
+                    logs.len(),
+                    received - logs.len()
+                ),
+                Err(e) => error!("Failed to write a batch of {} message logs: {}", logs.len(), e),
+            }
+            for (id, done) in waiters {
+                let _ = done.send(result.clone().map(|()| stored.contains(&id)));
+            }
+
+            if last_cleanup.elapsed() >= config.cleanup_interval {
+                Self::cleanup(store.as_ref(), &mut written).await;
+                last_cleanup = Instant::now();
+            }
+        }
+
+        Self::cleanup(store.as_ref(), &mut written).await;
+    }
+
+    async fn cleanup(store: &dyn MessageStore, written: &mut HashMap<String, i64>) {
+        for (credential_id, max_messages) in written.drain() {
+            if let Err(e) = store.cleanup_old_messages(&credential_id, max_messages).await {
+                error!("Failed to cleanup old messages: {}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::Repository;
+    use crate::models::{CreateCredentialRequest, Credential};
+    use tokio::task::JoinSet;
+
+    async fn credential(repo: &Repository) -> Credential {
+        let credential = Credential::new(CreateCredentialRequest {
+            name: "Batched".to_string(),
+            api_key: "key".to_string(),
+            app_id: "1:123:android:abc".to_string(),
+            project_id: "project".to_string(),
+            webhook_url: Some("http://localhost/hook".to_string()),
+            ..Default::default()
+        });
+        repo.create_credential(&credential).await.unwrap();
+        credential
+    }
+
+    #[tokio::test]
+    async fn test_batched_writes_store_every_log() {
+        let repo = Repository::new("sqlite::memory:").await.unwrap();
+        let credential = credential(&repo).await;
+        let writer = MessageLogWriter::spawn(
+            Arc::new(repo.clone()),
+            LogBatchConfig {
+                batch_size: 50,
+                flush_interval: Duration::from_millis(20),
+                cleanup_interval: Duration::ZERO,
+            },
+        );
+
+        let mut writes = JoinSet::new();
+        for i in 0..500 {
+            let writer = writer.clone();
+            let log = MessageLog::new(credential.id.clone(), None, format!("{{\"n\":{}}}", i));
+            writes.spawn(async move {
+                writer.write(&log, 1000).await.unwrap();
+                // Committed by the time write returns
+                log.id
+            });
+        }
+        while let Some(id) = writes.join_next().await {
+            assert!(repo.get_message_log(&id.unwrap()).await.unwrap().is_some());
+        }
+
+        assert_eq!(repo.count_message_logs(Some(&credential.id), None, None).await.unwrap(), 500);
+
+        // Cleanup trims to max_messages after the flush, once the write has returned
+        let log = MessageLog::new(credential.id.clone(), None, "{}".to_string());
+        writer.write(&log, 10).await.unwrap();
+        tokio::time::timeout(Duration::from_secs(2), async {
+            while repo.count_message_logs(Some(&credential.id), None, None).await.unwrap() != 10 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("old messages should be cleaned up");
+    }
+}
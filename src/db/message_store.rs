@@ -0,0 +1,187 @@
+use crate::db::Repository;
+use crate::models::{DeliveryState, MessageLog};
+use anyhow::Result;
+use async_trait::async_trait;
+
+/// Where received messages are written and listed. `Repository` (the SQLite tables) is the
+/// default; another store (e.g. a search index or an archive) can be plugged into the
+/// listener pool without touching worker logic. Delivery status updates, acknowledgements,
+/// search, per-message lookups and retention cleanup still go through `Repository`.
+#[async_trait]
+pub trait MessageStore: Send + Sync {
+    async fn create_message_log(&self, log: &MessageLog) -> Result<()>;
+
+    /// Store several logs at once; none are stored if any fails
+    async fn create_message_logs(&self, logs: &[MessageLog]) -> Result<()>;
+
+    /// Drop logs that are already stored or repeat an earlier log of the batch (by credential
+    /// and fcmMessageId), checked by the batching writer right before it inserts.
+    /// Stores without a cheap lookup can keep them all.
+    async fn filter_new_messages(&self, logs: Vec<MessageLog>) -> Result<Vec<MessageLog>> {
+        Ok(logs)
+    }
+
+    /// Newest first, filtered by credential, extracted field (name, value) and delivery state
+    async fn list_message_logs(
+        &self,
+        credential_id: Option<&str>,
+        field: Option<(&str, &str)>,
+        delivery_state: Option<DeliveryState>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<MessageLog>>;
+
+    async fn count_message_logs(
+        &self,
+        credential_id: Option<&str>,
+        field: Option<(&str, &str)>,
+        delivery_state: Option<DeliveryState>,
+    ) -> Result<i64>;
+
+    /// Keep only the credential's `max_count` newest messages, returning how many were deleted
+    async fn cleanup_old_messages(&self, credential_id: &str, max_count: i64) -> Result<u64>;
+}
+
+#[async_trait]
+impl MessageStore for Repository {
+    async fn create_message_log(&self, log: &MessageLog) -> Result<()> {
+        Repository::create_message_log(self, log).await
+    }
+
+    async fn create_message_logs(&self, logs: &[MessageLog]) -> Result<()> {
+        Repository::create_message_logs(self, logs).await
+    }
+
+    async fn filter_new_messages(&self, logs: Vec<MessageLog>) -> Result<Vec<MessageLog>> {
+        Repository::filter_new_messages(self, logs).await
+    }
+
+    async fn list_message_logs(
+        &self,
+        credential_id: Option<&str>,
+        field: Option<(&str, &str)>,
+        delivery_state: Option<DeliveryState>,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<MessageLog>> {
+        Repository::list_message_logs(self, credential_id, field, delivery_state, limit, offset)
+            .await
+    }
+
+    async fn count_message_logs(
+        &self,
+        credential_id: Option<&str>,
+        field: Option<(&str, &str)>,
+        delivery_state: Option<DeliveryState>,
+    ) -> Result<i64> {
+        Repository::count_message_logs(self, credential_id, field, delivery_state).await
+    }
+
+    async fn cleanup_old_messages(&self, credential_id: &str, max_count: i64) -> Result<u64> {
+        Repository::cleanup_old_messages(self, credential_id, max_count).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::{LogBatchConfig, MessageLogWriter};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    /// Store keeping logs in memory, standing in for an external one
+    #[derive(Default)]
+    struct MemoryStore {
+        logs: Mutex<Vec<MessageLog>>,
+    }
+
+    #[async_trait]
+    impl MessageStore for MemoryStore {
+        async fn create_message_log(&self, log: &MessageLog) -> Result<()> {
+            self.logs.lock().unwrap().push(log.clone());
+            Ok(())
+        }
+
+        async fn create_message_logs(&self, logs: &[MessageLog]) -> Result<()> {
+            self.logs.lock().unwrap().extend_from_slice(logs);
+            Ok(())
+        }
+
+        async fn list_message_logs(
+            &self,
+            credential_id: Option<&str>,
+            _field: Option<(&str, &str)>,
+            _delivery_state: Option<DeliveryState>,
+            limit: i64,
+            offset: i64,
+        ) -> Result<Vec<MessageLog>> {
+            let logs = self.logs.lock().unwrap();
+            Ok(logs
+                .iter()
+                .rev()
+                .filter(|l| credential_id.is_none_or(|id| l.credential_id == id))
+                .skip(offset as usize)
+                .take(limit as usize)
+                .cloned()
+                .collect())
+        }
+
+        async fn count_message_logs(
+            &self,
+            credential_id: Option<&str>,
+            field: Option<(&str, &str)>,
+            delivery_state: Option<DeliveryState>,
+        ) -> Result<i64> {
+            let listed = self
+                .list_message_logs(credential_id, field, delivery_state, i64::MAX, 0)
+                .await?;
+            Ok(listed.len() as i64)
+        }
+
+        async fn cleanup_old_messages(&self, credential_id: &str, max_count: i64) -> Result<u64> {
+            let mut logs = self.logs.lock().unwrap();
+            let total = logs.iter().filter(|l| l.credential_id == credential_id).count();
+            let mut excess = total.saturating_sub(max_count as usize);
+            let deleted = excess as u64;
+            // Oldest first
+            logs.retain(|l| {
+                let drop = excess > 0 && l.credential_id == credential_id;
+                if drop {
+                    excess -= 1;
+                }
+                !drop
+            });
+            Ok(deleted)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_writer_uses_plugged_in_store() {
+        let store = Arc::new(MemoryStore::default());
+        let writer = MessageLogWriter::spawn(
+            store.clone(),
+            LogBatchConfig {
+                batch_size: 10,
+                flush_interval: Duration::from_millis(5),
+                cleanup_interval: Duration::ZERO,
+            },
+        );
+
+        for i in 0..5 {
+            let log = MessageLog::new("cred".to_string(), None, format!("{{\"n\":{}}}", i));
+            writer.write(&log, 3).await.unwrap();
+        }
+
+        // Trimmed to max_messages through the store after the flush, newest kept
+        let store: Arc<dyn MessageStore> = store;
+        tokio::time::timeout(Duration::from_secs(2), async {
+            while store.count_message_logs(Some("cred"), None, None).await.unwrap() != 3 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("old messages should be cleaned up");
+        let newest = store.list_message_logs(Some("cred"), None, None, 1, 0).await.unwrap();
+        assert_eq!(newest[0].payload, "{\"n\":4}");
+    }
+}
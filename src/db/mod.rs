@@ -1,3 +1,13 @@
+pub mod compression;
+pub mod credential_cache;
+pub mod log_writer;
+pub mod message_store;
+pub mod pool_config;
 pub mod repository;
 
+pub use compression::*;
+pub use credential_cache::*;
+pub use log_writer::*;
+pub use message_store::*;
+pub use pool_config::*;
 pub use repository::*;
@@ -0,0 +1,99 @@
+use anyhow::{anyhow, Result};
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// Connection pool settings applied to every database pool the Repository opens
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DbPoolConfig {
+    /// Most open connections per pool (DB_MAX_CONNECTIONS)
+    pub max_connections: u32,
+    /// How long opening or waiting for a pooled connection may take (DB_CONNECT_TIMEOUT_SECS)
+    pub connect_timeout: Duration,
+    /// How long a statement may wait before failing (DB_STATEMENT_TIMEOUT_SECS). SQLite has
+    /// no statement timeout, so this is its busy timeout: the wait for a locked database.
+    pub statement_timeout: Duration,
+}
+
+impl Default for DbPoolConfig {
+    /// sqlx's own defaults, so leaving the variables unset changes nothing
+    fn default() -> Self {
+        Self {
+            max_connections: 10,
+            connect_timeout: Duration::from_secs(30),
+            statement_timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+impl DbPoolConfig {
+    /// Pool settings from DB_MAX_CONNECTIONS, DB_CONNECT_TIMEOUT_SECS and
+    /// DB_STATEMENT_TIMEOUT_SECS. A value that isn't a positive integer is an error rather
+    /// than silently falling back, so a typo doesn't ship the default pool to production.
+    pub fn from_env() -> Result<Self> {
+        let defaults = Self::default();
+        Ok(Self {
+            max_connections: positive_var("DB_MAX_CONNECTIONS")?.unwrap_or(defaults.max_connections),
+            connect_timeout: positive_var("DB_CONNECT_TIMEOUT_SECS")?
+                .map(Duration::from_secs)
+                .unwrap_or(defaults.connect_timeout),
+            statement_timeout: positive_var("DB_STATEMENT_TIMEOUT_SECS")?
+                .map(Duration::from_secs)
+                .unwrap_or(defaults.statement_timeout),
+        })
+    }
+
+    pub fn pool_options(&self) -> SqlitePoolOptions {
+        SqlitePoolOptions::new()
+            .max_connections(self.max_connections)
+            .acquire_timeout(self.connect_timeout)
+    }
+
+    /// Connection options for `url` with the statement timeout applied
+    pub fn connect_options(&self, url: &str) -> Result<SqliteConnectOptions> {
+        Ok(SqliteConnectOptions::from_str(url)?.busy_timeout(self.statement_timeout))
+    }
+}
+
+impl std::fmt::Display for DbPoolConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "max {} connections, connect timeout {}s, statement timeout {}s",
+            self.max_connections,
+            self.connect_timeout.as_secs(),
+            self.statement_timeout.as_secs()
+        )
+    }
+}
+
+fn positive_var<T: FromStr + Default + PartialOrd>(name: &str) -> Result<Option<T>> {
+    parse_positive(name, std::env::var(name).ok())
+}
+
+/// Parse an optional setting that must be a positive integer when present
+fn parse_positive<T: FromStr + Default + PartialOrd>(name: &str, value: Option<String>) -> Result<Option<T>> {
+    let Some(value) = value else {
+        return Ok(None);
+    };
+    match value.trim().parse::<T>() {
+        Ok(n) if n > T::default() => Ok(Some(n)),
+        _ => Err(anyhow!("{} must be a positive integer, got '{}'", name, value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_positive() {
+        assert_eq!(parse_positive::<u32>("DB_MAX_CONNECTIONS", None).unwrap(), None);
+        assert_eq!(parse_positive::<u32>("DB_MAX_CONNECTIONS", Some(" 20 ".to_string())).unwrap(), Some(20));
+
+        for bad in ["0", "-1", "ten", ""] {
+            let err = parse_positive::<u64>("DB_CONNECT_TIMEOUT_SECS", Some(bad.to_string())).unwrap_err();
+            assert!(err.to_string().contains("DB_CONNECT_TIMEOUT_SECS must be a positive integer"));
+        }
+    }
+}
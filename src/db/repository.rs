@@ -1,22 +1,299 @@
-use crate::models::{Credential, MessageLog};
+use crate::db::{compress_payload, decompress_payload, get_payload_compression, DbPoolConfig};
+use crate::models::{Credential, CredentialStateCounts, CredentialUpdate, DeliveryState, MessageLog, MessageStats};
+use crate::workers::{DeliverySettings, ScheduleConfig};
 use anyhow::Result;
-use sqlx::{Row, SqlitePool};
+use chrono::{DateTime, Utc};
+use std::collections::{HashMap, HashSet};
+use sqlx::migrate::Migrator;
+use sqlx::sqlite::SqliteAutoVacuum;
+use sqlx::{QueryBuilder, Row, Sqlite, SqliteConnection, SqlitePool};
+use tracing::warn;
+
+/// Messages looked up per query by filter_new_messages (two bound parameters each)
+const FILTER_CHUNK_SIZE: usize = 400;
+
+/// Versioned schema migrations from migrations/, embedded at compile time
+static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+/// Migration that switched message search to a contentless index (015_message_search_contentless)
+const CONTENTLESS_SEARCH_MIGRATION: i64 = 15;
+
+/// Database size around a vacuum run
+#[derive(Debug, Clone, Copy)]
+pub struct VacuumReport {
+    /// Whether a full VACUUM ran (otherwise incremental)
+    pub full: bool,
+    pub size_before: i64,
+    pub size_after: i64,
+}
+
+impl VacuumReport {
+    pub fn reclaimed(&self) -> i64 {
+        (self.size_before - self.size_after).max(0)
+    }
+}
+
+/// Topics added and removed when a credential's topic list is replaced
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TopicChanges {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+impl TopicChanges {
+    /// Difference from `old` to `new` (duplicates in `new` are ignored)
+    pub fn between(old: &[String], new: &[String]) -> Self {
+        let mut added: Vec<String> = Vec::new();
+        for topic in new {
+            if !old.contains(topic) && !added.contains(topic) {
+                added.push(topic.clone());
+            }
+        }
+        let removed = old.iter().filter(|t| !new.contains(t)).cloned().collect();
+        Self { added, removed }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
 
 #[derive(Clone)]
 pub struct Repository {
     pool: SqlitePool,
+    /// Pool for listing, search and stats queries; the write pool unless a read URL is configured
+    read_pool: SqlitePool,
+    /// Store new message payloads zstd-compressed (PAYLOAD_COMPRESSION)
+    compress_payloads: bool,
 }
 
 impl Repository {
     pub async fn new(database_url: &str) -> Result<Self> {
+        Self::with_read_url(database_url, None).await
+    }
+
+    /// Connect with list/search/stats queries sent to `read_url` (e.g. a read replica).
+    /// Writes and single-row lookups, which callers expect to reflect their own writes,
+    /// stay on `database_url`. Migrations only run against `database_url`.
+    pub async fn with_read_url(database_url: &str, read_url: Option<&str>) -> Result<Self> {
+        Self::connect(database_url, read_url, DbPoolConfig::from_env()?).await
+    }
+
+    /// Like `with_read_url`, with both pools sized and timed out by `config`
+    pub async fn connect(
+        database_url: &str,
+        read_url: Option<&str>,
+        config: DbPoolConfig,
+    ) -> Result<Self> {
+        // auto_vacuum only takes effect before the first table is created, which for a new
+        // database is _sqlx_migrations rather than anything in 001_init.sql, so set it on connect
+        let options = config
+            .connect_options(database_url)?
+            .auto_vacuum(SqliteAutoVacuum::Incremental);
+        let in_memory = database_url.contains(":memory:");
+        let pool = if in_memory {
+            // Each connection gets its own in-memory database, so keep exactly one alive
+            config
+                .pool_options()
+                .max_connections(1)
+                .idle_timeout(None)
+                .max_lifetime(None)
+                .connect_with(options.clone())
+                .await?
+        } else {
+            config.pool_options().connect_with(options.clone()).await?
+        };
+
+        // Databases created before versioned migrations have tables from older versions of
+        // 001_init.sql; add their missing columns so the migrations, which only create what
+        // doesn't exist yet, can be applied and recorded on top
+        if Self::table_exists(&pool, "credentials").await?
+            && !Self::table_exists(&pool, "_sqlx_migrations").await?
+        {
+            Self::upgrade_unversioned_schema(&pool).await?;
+        }
+
+        // Rows compressed before the search index became contentless are left for us to index
+        let index_compressed = !Self::migration_applied(&pool, CONTENTLESS_SEARCH_MIGRATION).await?;
+
+        // Each file in migrations/ runs once, in version order, tracked in _sqlx_migrations
+        MIGRATOR.run(&pool).await?;
+
+        // A connection opened before the migrations keeps its old schema until it next steps a
+        // statement, and sqlx fixes a statement's column list when preparing it, so `SELECT *`
+        // on such a connection comes back short. Start over with fresh connections; the
+        // in-memory pool has only the one that ran the migrations.
+        let pool = if in_memory {
+            pool
+        } else {
+            pool.close().await;
+            config.pool_options().connect_with(options).await?
+        };
+        if index_compressed {
+            Self::index_compressed_payloads(&mut *pool.acquire().await?).await?;
+        }
+
+        let read_pool = match read_url.filter(|url| *url != database_url) {
+            Some(url) => config.pool_options().connect_with(config.connect_options(url)?).await?,
+            None => pool.clone(),
+        };
+
+        Ok(Self {
+            pool,
+            read_pool,
+            compress_payloads: get_payload_compression(),
+        })
+    }
+
+    /// Override PAYLOAD_COMPRESSION for message logs written through this repository
+    #[cfg(test)]
+    pub fn with_payload_compression(mut self, enabled: bool) -> Self {
+        self.compress_payloads = enabled;
+        self
+    }
+
+    /// Verify that the database can be opened without running migrations
+    pub async fn check_connection(database_url: &str) -> Result<()> {
         let pool = SqlitePool::connect(database_url).await?;
+        sqlx::query("SELECT 1").execute(&pool).await?;
+        pool.close().await;
+        Ok(())
+    }
+
+    /// Bring a database from before versioned migrations up to the columns of the first
+    /// migration. New schema changes go in a new migration file instead of here.
+    async fn upgrade_unversioned_schema(pool: &SqlitePool) -> Result<()> {
+        Self::add_column_if_missing(pool, "credentials", "max_messages", "INTEGER").await?;
+        Self::add_column_if_missing(
+            pool,
+            "credentials",
+            "capture_webhook_response",
+            "BOOLEAN NOT NULL DEFAULT 1",
+        )
+        .await?;
+        Self::add_column_if_missing(pool, "credentials", "ack_token", "TEXT").await?;
+        Self::add_column_if_missing(pool, "credentials", "schedule_start", "TEXT").await?;
+        Self::add_column_if_missing(pool, "credentials", "schedule_stop", "TEXT").await?;
+        Self::add_column_if_missing(pool, "credentials", "schedule_timezone", "TEXT").await?;
+        Self::add_column_if_missing(pool, "credentials", "topic_webhooks", "TEXT").await?;
+        Self::add_column_if_missing(pool, "credentials", "sink_config", "TEXT").await?;
+        Self::add_column_if_missing(pool, "credentials", "webhook_secret", "TEXT").await?;
+        Self::add_column_if_missing(pool, "credentials", "webhook_secret_previous", "TEXT").await?;
+        Self::add_column_if_missing(pool, "credentials", "extract_fields", "TEXT").await?;
+        Self::add_column_if_missing(
+            pool,
+            "credentials",
+            "webhook_enabled",
+            "BOOLEAN NOT NULL DEFAULT 1",
+        )
+        .await?;
+        Self::add_column_if_missing(pool, "credentials", "max_backoff_secs", "INTEGER").await?;
+        Self::add_column_if_missing(pool, "credentials", "webhook_proxy", "TEXT").await?;
+        Self::add_column_if_missing(pool, "credentials", "delivery_alerts", "TEXT").await?;
+        Self::add_column_if_missing(pool, "credentials", "external_id", "TEXT").await?;
+        Self::add_column_if_missing(pool, "credentials", "retry_on", "TEXT").await?;
+        Self::add_column_if_missing(pool, "credentials", "signing_key", "TEXT").await?;
+        Self::add_column_if_missing(pool, "credentials", "payload_encoding", "TEXT").await?;
+        Self::add_column_if_missing(pool, "credentials", "dedup_ignore_fields", "TEXT").await?;
+        Self::add_column_if_missing(pool, "credentials", "json_format", "TEXT").await?;
+        Self::add_column_if_missing(
+            pool,
+            "credentials",
+            "webhook_verified",
+            "BOOLEAN NOT NULL DEFAULT 0",
+        )
+        .await?;
+        Self::add_column_if_missing(pool, "credentials", "webhook_verified_at", "TIMESTAMP").await?;
+        Self::add_column_if_missing(
+            pool,
+            "credentials",
+            "store_payload",
+            "BOOLEAN NOT NULL DEFAULT 1",
+        )
+        .await?;
+        Self::add_column_if_missing(pool, "credentials", "dedup_key_path", "TEXT").await?;
+        Self::add_column_if_missing(pool, "credentials", "allowed_senders", "TEXT").await?;
+        Self::add_column_if_missing(pool, "credentials", "webhook_auth", "TEXT").await?;
+        Self::add_column_if_missing(pool, "message_logs", "webhook_response_json", "TEXT").await?;
+        Self::add_column_if_missing(pool, "message_logs", "extracted_fields", "TEXT").await?;
+        Self::add_column_if_missing(pool, "message_logs", "webhook_url", "TEXT").await?;
+        Self::add_column_if_missing(
+            pool,
+            "message_logs",
+            "acknowledged",
+            "BOOLEAN NOT NULL DEFAULT 0",
+        )
+        .await?;
+        Self::add_column_if_missing(pool, "message_logs", "acknowledged_at", "TIMESTAMP").await?;
+        Self::add_column_if_missing(
+            pool,
+            "message_logs",
+            "payload_encoding",
+            "TEXT NOT NULL DEFAULT 'utf8'",
+        )
+        .await?;
+        Self::add_column_if_missing(
+            pool,
+            "message_logs",
+            "payload_compressed",
+            "BOOLEAN NOT NULL DEFAULT 0",
+        )
+        .await?;
+        Self::add_column_if_missing(
+            pool,
+            "message_logs",
+            "payload_stored",
+            "BOOLEAN NOT NULL DEFAULT 1",
+        )
+        .await?;
+        Self::add_column_if_missing(pool, "message_logs", "dedup_key", "TEXT").await?;
+
+        Ok(())
+    }
+
+    /// Add a column to an existing table if it is not there yet
+    async fn add_column_if_missing(
+        pool: &SqlitePool,
+        table: &str,
+        column: &str,
+        definition: &str,
+    ) -> Result<()> {
+        let columns: Vec<String> = sqlx::query(&format!("PRAGMA table_info({})", table))
+            .fetch_all(pool)
+            .await?
+            .iter()
+            .map(|row| row.get::<String, _>("name"))
+            .collect();
+
+        if !columns.iter().any(|c| c == column) {
+            sqlx::query(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition))
+                .execute(pool)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn migration_applied(pool: &SqlitePool, version: i64) -> Result<bool> {
+        if !Self::table_exists(pool, "_sqlx_migrations").await? {
+            return Ok(false);
+        }
+        let applied: bool =
+            sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM _sqlx_migrations WHERE version = ? AND success)")
+                .bind(version)
+                .fetch_one(pool)
+                .await?;
+
+        Ok(applied)
+    }
 
-        // Run migrations
-        sqlx::query(include_str!("../../migrations/001_init.sql"))
-            .execute(&pool)
+    async fn table_exists(pool: &SqlitePool, table: &str) -> Result<bool> {
+        let row = sqlx::query("SELECT COUNT(*) as count FROM sqlite_master WHERE name = ?")
+            .bind(table)
+            .fetch_one(pool)
             .await?;
 
-        Ok(Self { pool })
+        Ok(row.get::<i64, _>("count") > 0)
     }
 
     // ========== Credential Operations ==========
@@ -28,8 +305,17 @@ impl Repository {
                 id, name, api_key, app_id, project_id,
                 fcm_token, gcm_token, android_id, security_token,
                 private_key_base64, auth_secret_base64,
-                webhook_url, webhook_headers, is_active, is_suspended, created_at, updated_at
-            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                webhook_url, webhook_headers, is_active, is_suspended, max_messages,
+                capture_webhook_response, ack_token, schedule_start, schedule_stop,
+                schedule_timezone, topic_webhooks, sink_config, webhook_secret,
+                webhook_secret_previous, extract_fields, webhook_enabled, max_backoff_secs,
+                webhook_proxy, delivery_alerts, external_id, retry_on, signing_key, payload_encoding,
+                dedup_ignore_fields, json_format, webhook_verified, webhook_verified_at,
+                store_payload, dedup_key_path, allowed_senders, webhook_auth, delivery_settings,
+                webhook_user_agent, dedup_group, webhook_timeout_secs, webhook_connect_timeout_secs,
+                webhook_response_match, retention_days, lifecycle_webhook_url, registered_at,
+                created_at, updated_at
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
             "#,
         )
         .bind(&cred.id)
@@ -47,6 +333,42 @@ impl Repository {
         .bind(&cred.webhook_headers)
         .bind(cred.is_active)
         .bind(cred.is_suspended)
+        .bind(cred.max_messages)
+        .bind(cred.capture_webhook_response)
+        .bind(&cred.ack_token)
+        .bind(&cred.schedule_start)
+        .bind(&cred.schedule_stop)
+        .bind(&cred.schedule_timezone)
+        .bind(&cred.topic_webhooks)
+        .bind(&cred.sink_config)
+        .bind(&cred.webhook_secret)
+        .bind(&cred.webhook_secret_previous)
+        .bind(&cred.extract_fields)
+        .bind(cred.webhook_enabled)
+        .bind(cred.max_backoff_secs)
+        .bind(&cred.webhook_proxy)
+        .bind(&cred.delivery_alerts)
+        .bind(&cred.external_id)
+        .bind(&cred.retry_on)
+        .bind(&cred.signing_key)
+        .bind(&cred.payload_encoding)
+        .bind(&cred.dedup_ignore_fields)
+        .bind(&cred.json_format)
+        .bind(cred.webhook_verified)
+        .bind(cred.webhook_verified_at)
+        .bind(cred.store_payload)
+        .bind(&cred.dedup_key_path)
+        .bind(&cred.allowed_senders)
+        .bind(&cred.webhook_auth)
+        .bind(&cred.delivery_settings)
+        .bind(&cred.webhook_user_agent)
+        .bind(&cred.dedup_group)
+        .bind(cred.webhook_timeout_secs)
+        .bind(cred.webhook_connect_timeout_secs)
+        .bind(&cred.webhook_response_match)
+        .bind(cred.retention_days)
+        .bind(&cred.lifecycle_webhook_url)
+        .bind(cred.registered_at)
         .bind(cred.created_at)
         .bind(cred.updated_at)
         .execute(&self.pool)
@@ -64,6 +386,16 @@ impl Repository {
         Ok(cred)
     }
 
+    /// Find the credential created with the given external_id
+    pub async fn get_credential_by_external_id(&self, external_id: &str) -> Result<Option<Credential>> {
+        let cred = sqlx::query_as::<_, Credential>("SELECT * FROM credentials WHERE external_id = ?")
+            .bind(external_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(cred)
+    }
+
     pub async fn list_credentials(&self, active_only: bool) -> Result<Vec<Credential>> {
         let query = if active_only {
             "SELECT * FROM credentials WHERE is_active = 1 ORDER BY created_at DESC"
@@ -72,7 +404,7 @@ impl Repository {
         };
 
         let creds = sqlx::query_as::<_, Credential>(query)
-            .fetch_all(&self.pool)
+            .fetch_all(&self.read_pool)
             .await?;
 
         Ok(creds)
@@ -89,69 +421,281 @@ impl Repository {
         Ok(creds)
     }
 
-    pub async fn update_credential(
-        &self,
-        id: &str,
-        name: Option<&str>,
-        webhook_url: Option<&str>,
-        webhook_headers: Option<&str>,
-        is_active: Option<bool>,
-        api_key: Option<&str>,
-        app_id: Option<&str>,
-        project_id: Option<&str>,
-    ) -> Result<bool> {
-        let mut query = String::from("UPDATE credentials SET updated_at = CURRENT_TIMESTAMP");
-        let mut params: Vec<String> = Vec::new();
+    /// Credential counts by runnable / inactive / suspended, in one query
+    pub async fn credential_state_counts(&self) -> Result<CredentialStateCounts> {
+        let counts = sqlx::query_as::<_, CredentialStateCounts>(
+            r#"
+            SELECT COUNT(*) AS total,
+                   COALESCE(SUM(is_active = 1 AND is_suspended = 0), 0) AS runnable,
+                   COALESCE(SUM(is_active = 0), 0) AS inactive,
+                   COALESCE(SUM(is_active = 1 AND is_suspended = 1), 0) AS suspended
+            FROM credentials
+            "#,
+        )
+        .fetch_one(&self.read_pool)
+        .await?;
+
+        Ok(counts)
+    }
 
-        if let Some(n) = name {
-            query.push_str(", name = ?");
-            params.push(n.to_string());
+    /// Apply the changed columns of `update` to the credential, returning false if it
+    /// doesn't exist
+    pub async fn update_credential(&self, id: &str, update: &CredentialUpdate) -> Result<bool> {
+        let mut query =
+            QueryBuilder::<Sqlite>::new("UPDATE credentials SET updated_at = CURRENT_TIMESTAMP");
+
+        if let Some(n) = &update.name {
+            query.push(", name = ").push_bind(n);
+        }
+        if let Some(w) = &update.webhook_url {
+            query.push(", webhook_url = ").push_bind(w);
+        }
+        if let Some(h) = &update.webhook_headers {
+            query.push(", webhook_headers = ").push_bind(h);
+        }
+        if let Some(a) = update.is_active {
+            query.push(", is_active = ").push_bind(a);
+        }
+        if let Some(k) = &update.api_key {
+            query.push(", api_key = ").push_bind(k);
+        }
+        if let Some(a) = &update.app_id {
+            query.push(", app_id = ").push_bind(a);
+        }
+        if let Some(p) = &update.project_id {
+            query.push(", project_id = ").push_bind(p);
+        }
+        if let Some(m) = update.max_messages {
+            query.push(", max_messages = ").push_bind(m);
         }
-        if let Some(w) = webhook_url {
-            query.push_str(", webhook_url = ?");
-            params.push(w.to_string());
+        if let Some(c) = update.capture_webhook_response {
+            query.push(", capture_webhook_response = ").push_bind(c);
         }
-        if let Some(h) = webhook_headers {
-            query.push_str(", webhook_headers = ?");
-            params.push(h.to_string());
+        if let Some(t) = &update.topic_webhooks {
+            query.push(", topic_webhooks = ").push_bind(t);
         }
-        if let Some(a) = is_active {
-            query.push_str(", is_active = ?");
-            params.push(a.to_string());
+        if let Some(s) = &update.sink_config {
+            query.push(", sink_config = ").push_bind(s);
         }
-        if let Some(k) = api_key {
-            query.push_str(", api_key = ?");
-            params.push(k.to_string());
+        if let Some(f) = &update.extract_fields {
+            query.push(", extract_fields = ").push_bind(f);
         }
-        if let Some(a) = app_id {
-            query.push_str(", app_id = ?");
-            params.push(a.to_string());
+        if let Some(e) = update.webhook_enabled {
+            query.push(", webhook_enabled = ").push_bind(e);
         }
-        if let Some(p) = project_id {
-            query.push_str(", project_id = ?");
-            params.push(p.to_string());
+        if let Some(b) = update.max_backoff_secs {
+            query.push(", max_backoff_secs = ").push_bind(b);
+        }
+        if let Some(p) = &update.webhook_proxy {
+            // Empty string clears the override
+            query
+                .push(", webhook_proxy = ")
+                .push_bind(Some(p).filter(|p| !p.is_empty()));
+        }
+        if let Some(a) = &update.delivery_alerts {
+            query.push(", delivery_alerts = ").push_bind(a);
+        }
+        if let Some(r) = &update.retry_on {
+            query.push(", retry_on = ").push_bind(r);
+        }
+        if let Some(e) = &update.payload_encoding {
+            query.push(", payload_encoding = ").push_bind(e);
+        }
+        if let Some(f) = &update.dedup_ignore_fields {
+            query.push(", dedup_ignore_fields = ").push_bind(f);
+        }
+        if let Some(f) = &update.json_format {
+            query.push(", json_format = ").push_bind(f);
+        }
+        if let Some(s) = update.store_payload {
+            query.push(", store_payload = ").push_bind(s);
+        }
+        if let Some(p) = &update.dedup_key_path {
+            // Empty string goes back to fcmMessageId
+            query
+                .push(", dedup_key_path = ")
+                .push_bind(Some(p).filter(|p| !p.is_empty()));
+        }
+        if let Some(s) = &update.allowed_senders {
+            query.push(", allowed_senders = ").push_bind(s);
+        }
+        if let Some(a) = &update.webhook_auth {
+            // Empty string removes it
+            query
+                .push(", webhook_auth = ")
+                .push_bind(Some(a).filter(|a| !a.is_empty()));
+        }
+        if let Some(u) = &update.webhook_user_agent {
+            // Empty string goes back to WEBHOOK_USER_AGENT
+            query
+                .push(", webhook_user_agent = ")
+                .push_bind(Some(u).filter(|u| !u.is_empty()));
+        }
+        if let Some(g) = &update.dedup_group {
+            // Empty string goes back to per-credential dedup
+            query
+                .push(", dedup_group = ")
+                .push_bind(Some(g).filter(|g| !g.is_empty()));
+        }
+        if let Some(t) = update.webhook_timeout_secs {
+            query.push(", webhook_timeout_secs = ").push_bind(t);
+        }
+        if let Some(t) = update.webhook_connect_timeout_secs {
+            query.push(", webhook_connect_timeout_secs = ").push_bind(t);
+        }
+        if let Some(m) = &update.webhook_response_match {
+            // Empty string removes it
+            query
+                .push(", webhook_response_match = ")
+                .push_bind(Some(m).filter(|m| !m.is_empty()));
+        }
+        if let Some(d) = update.retention_days {
+            query.push(", retention_days = ").push_bind(d);
+        }
+        if let Some(u) = &update.lifecycle_webhook_url {
+            // Empty string goes back to LIFECYCLE_WEBHOOK_URL
+            query
+                .push(", lifecycle_webhook_url = ")
+                .push_bind(Some(u).filter(|u| !u.is_empty()));
         }
 
-        query.push_str(" WHERE id = ?");
-        params.push(id.to_string());
+        query.push(" WHERE id = ").push_bind(id);
 
-        let mut q = sqlx::query(&query);
-        for param in &params[..params.len() - 1] {
-            q = q.bind(param);
-        }
-        q = q.bind(id);
+        let result = query.build().execute(&self.pool).await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Set or clear (None) the credential's own Ed25519 signing key
+    pub async fn set_signing_key(&self, id: &str, signing_key: Option<&str>) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE credentials SET signing_key = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+        )
+        .bind(signing_key)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Record the outcome of a webhook verification handshake (false also clears it,
+    /// e.g. when webhook_url changes)
+    pub async fn set_webhook_verified(&self, id: &str, verified: bool) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE credentials SET webhook_verified = ?, webhook_verified_at = ? WHERE id = ?",
+        )
+        .bind(verified)
+        .bind(verified.then(Utc::now))
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Install a new webhook signing secret, keeping the current one as previous
+    pub async fn rotate_webhook_secret(&self, id: &str, secret: &str) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            UPDATE credentials
+            SET webhook_secret_previous = webhook_secret, webhook_secret = ?, updated_at = CURRENT_TIMESTAMP
+            WHERE id = ?
+            "#,
+        )
+        .bind(secret)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Finish a secret rotation by dropping the previous secret
+    pub async fn clear_previous_webhook_secret(&self, id: &str) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE credentials SET webhook_secret_previous = NULL, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+        )
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// List runnable credentials that have a start/stop schedule
+    pub async fn list_scheduled_credentials(&self) -> Result<Vec<Credential>> {
+        let creds = sqlx::query_as::<_, Credential>(
+            "SELECT * FROM credentials WHERE is_active = 1 AND is_suspended = 0 AND schedule_start IS NOT NULL AND schedule_stop IS NOT NULL"
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(creds)
+    }
+
+    /// Set or clear (None) a credential's listening schedule
+    pub async fn set_credential_schedule(
+        &self,
+        id: &str,
+        schedule: Option<&ScheduleConfig>,
+    ) -> Result<bool> {
+        let result = sqlx::query(
+            r#"
+            UPDATE credentials
+            SET schedule_start = ?, schedule_stop = ?, schedule_timezone = ?, updated_at = CURRENT_TIMESTAMP
+            WHERE id = ?
+            "#,
+        )
+        .bind(schedule.map(|s| s.start.as_str()))
+        .bind(schedule.map(|s| s.stop.as_str()))
+        .bind(schedule.and_then(|s| s.timezone.as_deref()))
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Store a credential's delivery concurrency and queue settings
+    pub async fn set_delivery_settings(&self, id: &str, settings: &DeliverySettings) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE credentials SET delivery_settings = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+        )
+        .bind(serde_json::to_string(settings)?)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
 
-        let result = q.execute(&self.pool).await?;
         Ok(result.rows_affected() > 0)
     }
 
     /// Suspend a credential (prevent auto-start)
     pub async fn suspend_credential(&self, id: &str) -> Result<bool> {
+        self.mark_suspended(&self.pool, id).await
+    }
+
+    /// Suspend a credential and delete all its messages in one transaction.
+    /// Returns the number of deleted messages, or None if the credential doesn't exist.
+    pub async fn suspend_and_clear_credential(&self, id: &str) -> Result<Option<u64>> {
+        let mut tx = self.pool.begin().await?;
+        if !self.mark_suspended(&mut *tx, id).await? {
+            return Ok(None);
+        }
+        let deleted = self.delete_credential_messages(&mut *tx, id).await?;
+        tx.commit().await?;
+
+        Ok(Some(deleted))
+    }
+
+    async fn mark_suspended<'e, E>(&self, executor: E, id: &str) -> Result<bool>
+    where
+        E: sqlx::Executor<'e, Database = Sqlite>,
+    {
         let result = sqlx::query(
             "UPDATE credentials SET is_suspended = 1, updated_at = CURRENT_TIMESTAMP WHERE id = ?"
         )
         .bind(id)
-        .execute(&self.pool)
+        .execute(executor)
         .await?;
 
         Ok(result.rows_affected() > 0)
@@ -169,6 +713,7 @@ impl Repository {
         Ok(result.rows_affected() > 0)
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub async fn update_credential_registration(
         &self,
         id: &str,
@@ -183,7 +728,8 @@ impl Repository {
             r#"
             UPDATE credentials
             SET fcm_token = ?, gcm_token = ?, android_id = ?, security_token = ?,
-                private_key_base64 = ?, auth_secret_base64 = ?, updated_at = CURRENT_TIMESTAMP
+                private_key_base64 = ?, auth_secret_base64 = ?, registered_at = CURRENT_TIMESTAMP,
+                updated_at = CURRENT_TIMESTAMP
             WHERE id = ?
             "#,
         )
@@ -212,26 +758,122 @@ impl Repository {
     // ========== Message Log Operations ==========
 
     pub async fn create_message_log(&self, log: &MessageLog) -> Result<()> {
-        sqlx::query(
+        // A compressed payload is indexed by a second statement, so keep the two together
+        let mut tx = self.pool.begin().await?;
+        self.insert_message_log(&mut tx, log).await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Insert several message logs in one transaction; none are stored if any insert fails
+    pub async fn create_message_logs(&self, logs: &[MessageLog]) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        for log in logs {
+            self.insert_message_log(&mut tx, log).await?;
+        }
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    async fn insert_message_log(&self, conn: &mut SqliteConnection, log: &MessageLog) -> Result<()> {
+        let compressed = if self.compress_payloads {
+            compress_payload(&log.payload)
+        } else {
+            None
+        };
+
+        let result = sqlx::query(
             r#"
             INSERT INTO message_logs (
-                id, credential_id, fcm_message_id, payload, webhook_status, webhook_response, received_at
-            ) VALUES (?, ?, ?, ?, ?, ?, ?)
+                id, credential_id, fcm_message_id, payload, payload_encoding, payload_compressed,
+                payload_stored, dedup_key, webhook_status, delivery_state, webhook_response,
+                webhook_response_json, extracted_fields, acknowledged, acknowledged_at, sent_at,
+                priority, ttl_secs, received_at
+            ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
             "#,
         )
         .bind(&log.id)
         .bind(&log.credential_id)
         .bind(&log.fcm_message_id)
-        .bind(&log.payload)
+        .bind(compressed.as_deref().unwrap_or(&log.payload))
+        .bind(&log.payload_encoding)
+        .bind(compressed.is_some())
+        .bind(log.payload_stored)
+        .bind(&log.dedup_key)
         .bind(log.webhook_status)
+        .bind(&log.delivery_state)
         .bind(&log.webhook_response)
+        .bind(&log.webhook_response_json)
+        .bind(&log.extracted_fields)
+        .bind(log.acknowledged)
+        .bind(log.acknowledged_at)
+        .bind(log.sent_at)
+        .bind(&log.priority)
+        .bind(log.ttl_secs)
         .bind(log.received_at)
-        .execute(&self.pool)
+        .execute(&mut *conn)
         .await?;
 
+        // The search trigger only sees the stored form, so index compressed payloads as received
+        if compressed.is_some() {
+            sqlx::query("INSERT INTO message_logs_fts(rowid, payload) VALUES (?, ?)")
+                .bind(result.last_insert_rowid())
+                .bind(&log.payload)
+                .execute(&mut *conn)
+                .await?;
+        }
+
         Ok(())
     }
 
+    /// Add the compressed message payloads to the search index, which SQL alone can't, since
+    /// they have to be decompressed first
+    async fn index_compressed_payloads(conn: &mut SqliteConnection) -> Result<()> {
+        let mut after = 0;
+        loop {
+            let rows: Vec<(i64, String, String)> = sqlx::query_as(
+                "SELECT rowid, id, payload FROM message_logs WHERE payload_compressed AND rowid > ? \
+                 ORDER BY rowid LIMIT 500",
+            )
+            .bind(after)
+            .fetch_all(&mut *conn)
+            .await?;
+            let Some((last, _, _)) = rows.last() else {
+                return Ok(());
+            };
+            after = *last;
+
+            for (rowid, id, payload) in rows {
+                match decompress_payload(&payload) {
+                    Ok(payload) => {
+                        sqlx::query("INSERT INTO message_logs_fts(rowid, payload) VALUES (?, ?)")
+                            .bind(rowid)
+                            .bind(payload)
+                            .execute(&mut *conn)
+                            .await?;
+                    }
+                    Err(e) => warn!("Message {} has an unreadable compressed payload: {}", id, e),
+                }
+            }
+        }
+    }
+
+    /// Restore a compressed payload, so callers only ever see the payload as received
+    fn inflate(mut log: MessageLog) -> MessageLog {
+        if log.payload_compressed {
+            match decompress_payload(&log.payload) {
+                Ok(payload) => {
+                    log.payload = payload;
+                    log.payload_compressed = false;
+                }
+                Err(e) => warn!("Message {} has an unreadable compressed payload: {}", log.id, e),
+            }
+        }
+        log
+    }
+
     /// Check if fcmMessageId already exists for this credential
     pub async fn is_fcm_message_duplicate(&self, credential_id: &str, fcm_message_id: &str) -> Result<bool> {
         let count: i64 = sqlx::query_scalar(
@@ -245,6 +887,73 @@ impl Repository {
         Ok(count > 0)
     }
 
+    /// Drop logs whose fcmMessageId is already logged for their credential, or that repeat an
+    /// earlier log of the batch, looking the whole batch up at once instead of per message.
+    /// Logs with a dedup key (deduplicated by that instead) or without an fcmMessageId are kept.
+    pub async fn filter_new_messages(&self, logs: Vec<MessageLog>) -> Result<Vec<MessageLog>> {
+        let keyed: Vec<(&str, &str)> = logs
+            .iter()
+            .filter(|log| log.dedup_key.is_none())
+            .filter_map(|log| Some((log.credential_id.as_str(), log.fcm_message_id.as_deref()?)))
+            .collect();
+
+        let mut seen: HashSet<(String, String)> = HashSet::new();
+        // Both lists in one IN each so the (credential_id, fcm_message_id) index is used;
+        // chunked to stay below SQLite's bound parameter limit
+        for chunk in keyed.chunks(FILTER_CHUNK_SIZE) {
+            let mut query = QueryBuilder::<Sqlite>::new(
+                "SELECT credential_id, fcm_message_id FROM message_logs WHERE credential_id IN (",
+            );
+            let mut ids = query.separated(", ");
+            for (credential_id, _) in chunk {
+                ids.push_bind(*credential_id);
+            }
+            query.push(") AND fcm_message_id IN (");
+            let mut ids = query.separated(", ");
+            for (_, fcm_message_id) in chunk {
+                ids.push_bind(*fcm_message_id);
+            }
+            query.push(")");
+
+            let rows: Vec<(String, String)> = query.build_query_as().fetch_all(&self.pool).await?;
+            seen.extend(rows);
+        }
+
+        Ok(logs
+            .into_iter()
+            .filter(|log| match (&log.dedup_key, &log.fcm_message_id) {
+                (None, Some(fcm_id)) => seen.insert((log.credential_id.clone(), fcm_id.clone())),
+                _ => true,
+            })
+            .collect())
+    }
+
+    /// Check if a message with this dedup key (see the credential's dedup_key_path) was already logged
+    pub async fn is_dedup_key_duplicate(&self, credential_id: &str, dedup_key: &str) -> Result<bool> {
+        let count: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM message_logs WHERE credential_id = ? AND dedup_key = ?",
+        )
+        .bind(credential_id)
+        .bind(dedup_key)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(count > 0)
+    }
+
+    /// Forget the dedup keys of a credential's logged messages, so messages with those keys
+    /// are accepted again. Returns how many messages had one.
+    pub async fn clear_dedup_keys(&self, credential_id: &str) -> Result<u64> {
+        let result = sqlx::query(
+            "UPDATE message_logs SET dedup_key = NULL WHERE credential_id = ? AND dedup_key IS NOT NULL",
+        )
+        .bind(credential_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
     /// Delete oldest messages to keep only max_count per credential
     pub async fn cleanup_old_messages(&self, credential_id: &str, max_count: i64) -> Result<u64> {
         // Delete messages older than the Nth newest
@@ -271,25 +980,67 @@ impl Repository {
 
     /// Clear all messages for a credential
     pub async fn clear_credential_messages(&self, credential_id: &str) -> Result<u64> {
-        let result = sqlx::query("DELETE FROM message_logs WHERE credential_id = ?")
-            .bind(credential_id)
+        self.delete_credential_messages(&self.pool, credential_id).await
+    }
+
+    /// Delete one message log. Returns false if it doesn't exist.
+    pub async fn delete_message_log(&self, id: &str) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM message_logs WHERE id = ?")
+            .bind(id)
             .execute(&self.pool)
             .await?;
 
-        Ok(result.rows_affected())
+        Ok(result.rows_affected() > 0)
     }
 
-    pub async fn update_message_webhook_status(
+    /// Delete the message logs matching every given filter, received before `before` if set.
+    /// Callers make sure at least one filter is given.
+    pub async fn delete_message_logs(
         &self,
-        id: &str,
-        status: i32,
-        response: &str,
-    ) -> Result<()> {
-        sqlx::query(
-            "UPDATE message_logs SET webhook_status = ?, webhook_response = ? WHERE id = ?",
-        )
+        credential_id: Option<&str>,
+        delivery_state: Option<DeliveryState>,
+        before: Option<DateTime<Utc>>,
+    ) -> Result<u64> {
+        let mut query = QueryBuilder::<Sqlite>::new("DELETE FROM message_logs WHERE 1 = 1");
+        Self::push_message_filters(&mut query, credential_id, None, delivery_state);
+        if let Some(before) = before {
+            query
+                .push(" AND julianday(received_at) < julianday(")
+                .push_bind(before)
+                .push(")");
+        }
+
+        let result = query.build().execute(&self.pool).await?;
+        Ok(result.rows_affected())
+    }
+
+    async fn delete_credential_messages<'e, E>(&self, executor: E, credential_id: &str) -> Result<u64>
+    where
+        E: sqlx::Executor<'e, Database = Sqlite>,
+    {
+        let result = sqlx::query("DELETE FROM message_logs WHERE credential_id = ?")
+            .bind(credential_id)
+            .execute(executor)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    pub async fn update_message_webhook_status(
+        &self,
+        id: &str,
+        status: i32,
+        response: Option<&str>,
+        response_json: Option<&str>,
+    ) -> Result<()> {
+        sqlx::query(
+            "UPDATE message_logs SET webhook_status = ?, delivery_state = ?, webhook_response = ?, \
+             webhook_response_json = ? WHERE id = ?",
+        )
         .bind(status)
+        .bind(DeliveryState::after_status(status).as_str())
         .bind(response)
+        .bind(response_json)
         .bind(id)
         .execute(&self.pool)
         .await?;
@@ -297,84 +1048,400 @@ impl Repository {
         Ok(())
     }
 
+    /// Mark a message as being delivered, recording the URL it goes to
+    pub async fn start_message_delivery(&self, id: &str, url: &str) -> Result<()> {
+        sqlx::query("UPDATE message_logs SET webhook_url = ?, delivery_state = ? WHERE id = ?")
+            .bind(url)
+            .bind(DeliveryState::Delivering.as_str())
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Mark a message as not delivered, recording why. A `status` of 0 keeps it eligible
+    /// for retrying failed messages; without one that leaves it alone.
+    pub async fn skip_message_delivery(&self, id: &str, status: Option<i32>, reason: &str) -> Result<()> {
+        sqlx::query(
+            "UPDATE message_logs SET webhook_status = ?, delivery_state = ?, webhook_response = ? \
+             WHERE id = ?",
+        )
+        .bind(status)
+        .bind(DeliveryState::Skipped.as_str())
+        .bind(reason)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Oldest messages whose last delivery attempt did not get a 2xx response (only those
+    /// with a stored payload, since the rest can't be re-delivered)
+    pub async fn list_failed_message_logs(
+        &self,
+        credential_id: Option<&str>,
+        limit: i64,
+    ) -> Result<Vec<MessageLog>> {
+        let mut query = QueryBuilder::<Sqlite>::new(
+            "SELECT * FROM message_logs WHERE webhook_status IS NOT NULL \
+             AND (webhook_status < 200 OR webhook_status >= 300) AND payload_stored = 1",
+        );
+        if let Some(cid) = credential_id {
+            query.push(" AND credential_id = ").push_bind(cid);
+        }
+        query.push(" ORDER BY received_at ASC LIMIT ").push_bind(limit);
+
+        let logs = query
+            .build_query_as::<MessageLog>()
+            .fetch_all(&self.read_pool)
+            .await?;
+
+        Ok(logs.into_iter().map(Self::inflate).collect())
+    }
+
+    pub async fn get_message_log(&self, id: &str) -> Result<Option<MessageLog>> {
+        let log = sqlx::query_as::<_, MessageLog>("SELECT * FROM message_logs WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(log.map(Self::inflate))
+    }
+
+    /// Mark a message as acknowledged by the downstream receiver
+    pub async fn acknowledge_message(&self, id: &str) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE message_logs SET acknowledged = 1, acknowledged_at = ? WHERE id = ? AND acknowledged = 0",
+        )
+        .bind(Utc::now())
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Count messages delivered with a 2xx status that were never acknowledged
+    pub async fn count_unacknowledged_delivered(&self) -> Result<i64> {
+        let count: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM message_logs WHERE webhook_status BETWEEN 200 AND 299 AND acknowledged = 0",
+        )
+        .fetch_one(&self.read_pool)
+        .await?;
+
+        Ok(count)
+    }
+
+    /// Newest messages first, optionally filtered by credential and an extracted field value
     pub async fn list_message_logs(
         &self,
         credential_id: Option<&str>,
+        field: Option<(&str, &str)>,
+        delivery_state: Option<DeliveryState>,
         limit: i64,
         offset: i64,
     ) -> Result<Vec<MessageLog>> {
-        let logs = if let Some(cid) = credential_id {
-            sqlx::query_as::<_, MessageLog>(
-                "SELECT * FROM message_logs WHERE credential_id = ? ORDER BY received_at DESC LIMIT ? OFFSET ?"
-            )
-            .bind(cid)
-            .bind(limit)
-            .bind(offset)
-            .fetch_all(&self.pool)
+        let mut query = QueryBuilder::<Sqlite>::new("SELECT * FROM message_logs WHERE 1 = 1");
+        Self::push_message_filters(&mut query, credential_id, field, delivery_state);
+        query
+            .push(" ORDER BY received_at DESC LIMIT ")
+            .push_bind(limit)
+            .push(" OFFSET ")
+            .push_bind(offset);
+
+        let logs = query
+            .build_query_as::<MessageLog>()
+            .fetch_all(&self.read_pool)
+            .await?;
+
+        Ok(logs.into_iter().map(Self::inflate).collect())
+    }
+
+    pub async fn count_message_logs(
+        &self,
+        credential_id: Option<&str>,
+        field: Option<(&str, &str)>,
+        delivery_state: Option<DeliveryState>,
+    ) -> Result<i64> {
+        if field.is_none() && delivery_state.is_none() {
+            return self.message_count(credential_id).await;
+        }
+
+        let mut query =
+            QueryBuilder::<Sqlite>::new("SELECT COUNT(*) as count FROM message_logs WHERE 1 = 1");
+        Self::push_message_filters(&mut query, credential_id, field, delivery_state);
+
+        let count = query
+            .build()
+            .fetch_one(&self.read_pool)
             .await?
-        } else {
-            sqlx::query_as::<_, MessageLog>(
-                "SELECT * FROM message_logs ORDER BY received_at DESC LIMIT ? OFFSET ?",
-            )
-            .bind(limit)
-            .bind(offset)
-            .fetch_all(&self.pool)
+            .get::<i64, _>("count");
+
+        Ok(count)
+    }
+
+    /// Stored messages for one credential, or all of them, from the trigger-maintained
+    /// counters (no scan of message_logs)
+    pub async fn message_count(&self, credential_id: Option<&str>) -> Result<i64> {
+        let mut query = QueryBuilder::<Sqlite>::new(
+            "SELECT COALESCE(SUM(message_count), 0) AS count FROM message_counts WHERE 1 = 1",
+        );
+        if let Some(cid) = credential_id {
+            query.push(" AND credential_id = ").push_bind(cid);
+        }
+
+        let count = query
+            .build()
+            .fetch_one(&self.read_pool)
             .await?
-        };
+            .get::<i64, _>("count");
 
-        Ok(logs)
+        Ok(count)
     }
 
-    pub async fn count_message_logs(&self, credential_id: Option<&str>) -> Result<i64> {
-        let count = if let Some(cid) = credential_id {
-            sqlx::query("SELECT COUNT(*) as count FROM message_logs WHERE credential_id = ?")
-                .bind(cid)
-                .fetch_one(&self.pool)
-                .await?
-                .get::<i64, _>("count")
+    /// Stored message count of every credential that has messages
+    #[allow(dead_code)]
+    pub async fn message_counts(&self) -> Result<HashMap<String, i64>> {
+        let rows: Vec<(String, i64)> = sqlx::query_as(
+            "SELECT credential_id, message_count FROM message_counts WHERE message_count > 0",
+        )
+        .fetch_all(&self.read_pool)
+        .await?;
+
+        Ok(rows.into_iter().collect())
+    }
+
+    fn push_message_filters<'a>(
+        query: &mut QueryBuilder<'a, Sqlite>,
+        credential_id: Option<&'a str>,
+        field: Option<(&'a str, &'a str)>,
+        delivery_state: Option<DeliveryState>,
+    ) {
+        if let Some(cid) = credential_id {
+            query.push(" AND credential_id = ").push_bind(cid);
+        }
+        if let Some(state) = delivery_state {
+            query.push(" AND delivery_state = ").push_bind(state.as_str());
+        }
+        if let Some((name, value)) = field {
+            // Quoted key so names containing dots aren't read as nested paths
+            let path = format!("$.\"{}\"", name.replace('"', ""));
+            query
+                .push(" AND json_extract(extracted_fields, ")
+                .push_bind(path)
+                .push(") = ")
+                .push_bind(value);
+        }
+    }
+
+    /// Full-text search over payloads. `query` is an FTS5 MATCH expression.
+    /// Results are ordered by relevance (bm25), or newest first when `by_recency` is set.
+    pub async fn search_message_logs(
+        &self,
+        query: &str,
+        credential_id: Option<&str>,
+        by_recency: bool,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<MessageLog>> {
+        let mut sql = String::from(
+            "SELECT m.* FROM message_logs_fts JOIN message_logs m ON m.rowid = message_logs_fts.rowid \
+             WHERE message_logs_fts MATCH ?",
+        );
+        if credential_id.is_some() {
+            sql.push_str(" AND m.credential_id = ?");
+        }
+        sql.push_str(if by_recency {
+            " ORDER BY m.received_at DESC"
         } else {
-            sqlx::query("SELECT COUNT(*) as count FROM message_logs")
-                .fetch_one(&self.pool)
-                .await?
-                .get::<i64, _>("count")
-        };
+            " ORDER BY message_logs_fts.rank"
+        });
+        sql.push_str(" LIMIT ? OFFSET ?");
+
+        let mut q = sqlx::query_as::<_, MessageLog>(&sql).bind(query);
+        if let Some(cid) = credential_id {
+            q = q.bind(cid);
+        }
+        let logs = q.bind(limit).bind(offset).fetch_all(&self.read_pool).await?;
+
+        Ok(logs.into_iter().map(Self::inflate).collect())
+    }
+
+    pub async fn count_search_message_logs(
+        &self,
+        query: &str,
+        credential_id: Option<&str>,
+    ) -> Result<i64> {
+        let mut sql = String::from(
+            "SELECT COUNT(*) as count FROM message_logs_fts JOIN message_logs m ON m.rowid = message_logs_fts.rowid \
+             WHERE message_logs_fts MATCH ?",
+        );
+        if credential_id.is_some() {
+            sql.push_str(" AND m.credential_id = ?");
+        }
+
+        let mut q = sqlx::query(&sql).bind(query);
+        if let Some(cid) = credential_id {
+            q = q.bind(cid);
+        }
+        let count = q.fetch_one(&self.read_pool).await?.get::<i64, _>("count");
 
         Ok(count)
     }
 
-    #[allow(dead_code)]
-    pub async fn delete_old_message_logs(&self, days: i64) -> Result<u64> {
-        let result = sqlx::query(
-            "DELETE FROM message_logs WHERE received_at < datetime('now', ? || ' days')",
+    /// Message count, latest arrival and delivery outcomes for every credential with messages
+    pub async fn message_stats_by_credential(&self) -> Result<Vec<MessageStats>> {
+        let stats = sqlx::query_as::<_, MessageStats>(
+            r#"
+            SELECT credential_id,
+                   COUNT(*) AS message_count,
+                   MAX(received_at) AS last_received_at,
+                   COUNT(webhook_status) AS attempted,
+                   COALESCE(SUM(webhook_status BETWEEN 200 AND 299), 0) AS succeeded
+            FROM message_logs
+            GROUP BY credential_id
+            "#,
         )
-        .bind(format!("-{}", days))
+        .fetch_all(&self.read_pool)
+        .await?;
+
+        Ok(stats)
+    }
+
+    /// Delete a credential's messages received more than `days` days ago
+    pub async fn delete_old_message_logs(&self, credential_id: &str, days: i64) -> Result<u64> {
+        let cutoff = Utc::now() - chrono::Duration::days(days);
+        self.delete_message_logs(Some(credential_id), None, Some(cutoff))
+            .await
+    }
+
+    // ========== Maintenance ==========
+
+    /// Whether free pages can be reclaimed incrementally (auto_vacuum = INCREMENTAL)
+    pub async fn incremental_vacuum_enabled(&self) -> Result<bool> {
+        let mode: i64 = sqlx::query_scalar("PRAGMA auto_vacuum")
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(mode == 2)
+    }
+
+    async fn database_size(conn: &mut SqliteConnection) -> Result<i64> {
+        let size: i64 = sqlx::query_scalar(
+            "SELECT page_count * page_size FROM pragma_page_count(), pragma_page_size()",
+        )
+        .fetch_one(conn)
+        .await?;
+
+        Ok(size)
+    }
+
+    /// Reclaim free pages and refresh query planner statistics.
+    /// The incremental run only releases free pages and holds the write lock briefly;
+    /// `full` rewrites the whole database with VACUUM (blocking all writers meanwhile),
+    /// rebuilds the message search index and switches to incremental auto-vacuum for later runs.
+    pub async fn vacuum(&self, full: bool) -> Result<VacuumReport> {
+        // Pragmas apply per connection, so run everything on one
+        let mut conn = self.pool.acquire().await?;
+        let size_before = Self::database_size(&mut conn).await?;
+
+        if full {
+            sqlx::query("PRAGMA auto_vacuum = INCREMENTAL")
+                .execute(&mut *conn)
+                .await?;
+            sqlx::query("VACUUM").execute(&mut *conn).await?;
+            // VACUUM may renumber message_logs' implicit rowids, which the search index
+            // refers to, so rebuild it from the table
+            sqlx::query("INSERT INTO message_logs_fts(message_logs_fts) VALUES('delete-all')")
+                .execute(&mut *conn)
+                .await?;
+            sqlx::query(
+                "INSERT INTO message_logs_fts(rowid, payload) \
+                 SELECT rowid, payload FROM message_logs WHERE NOT payload_compressed",
+            )
+            .execute(&mut *conn)
+            .await?;
+            Self::index_compressed_payloads(&mut conn).await?;
+        } else {
+            sqlx::query("PRAGMA incremental_vacuum").execute(&mut *conn).await?;
+        }
+        sqlx::query("ANALYZE").execute(&mut *conn).await?;
+
+        let size_after = Self::database_size(&mut conn).await?;
+
+        Ok(VacuumReport {
+            full,
+            size_before,
+            size_after,
+        })
+    }
+
+    // ========== Settings ==========
+
+    pub async fn list_settings(&self) -> Result<Vec<(String, String)>> {
+        let rows = sqlx::query_as::<_, (String, String)>("SELECT key, value FROM settings ORDER BY key")
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows)
+    }
+
+    pub async fn set_setting(&self, key: &str, value: &str) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO settings (key, value, updated_at) VALUES (?, ?, CURRENT_TIMESTAMP)
+            ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at
+            "#,
+        )
+        .bind(key)
+        .bind(value)
         .execute(&self.pool)
         .await?;
 
-        Ok(result.rows_affected())
+        Ok(())
     }
 
     // ========== Topic Operations ==========
 
-    pub async fn set_credential_topics(&self, credential_id: &str, topics: &[String]) -> Result<()> {
-        // Delete existing topics
-        sqlx::query("DELETE FROM credential_topics WHERE credential_id = ?")
-            .bind(credential_id)
-            .execute(&self.pool)
-            .await?;
+    /// Replace a credential's topics with `topics`, touching only the rows that differ.
+    /// Returns what changed; setting the same topics again is a no-op.
+    pub async fn set_credential_topics(
+        &self,
+        credential_id: &str,
+        topics: &[String],
+    ) -> Result<TopicChanges> {
+        let mut tx = self.pool.begin().await?;
+
+        let existing: Vec<(String,)> =
+            sqlx::query_as("SELECT topic FROM credential_topics WHERE credential_id = ?")
+                .bind(credential_id)
+                .fetch_all(&mut *tx)
+                .await?;
+        let existing: Vec<String> = existing.into_iter().map(|(t,)| t).collect();
+        let changes = TopicChanges::between(&existing, topics);
+
+        for topic in &changes.removed {
+            sqlx::query("DELETE FROM credential_topics WHERE credential_id = ? AND topic = ?")
+                .bind(credential_id)
+                .bind(topic)
+                .execute(&mut *tx)
+                .await?;
+        }
 
-        // Insert new topics
-        for topic in topics {
+        for topic in &changes.added {
             sqlx::query(
-                "INSERT INTO credential_topics (credential_id, topic) VALUES (?, ?)"
+                "INSERT OR IGNORE INTO credential_topics (credential_id, topic) VALUES (?, ?)"
             )
             .bind(credential_id)
             .bind(topic)
-            .execute(&self.pool)
+            .execute(&mut *tx)
             .await?;
         }
 
-        Ok(())
+        tx.commit().await?;
+        Ok(changes)
     }
 
     pub async fn get_credential_topics(&self, credential_id: &str) -> Result<Vec<String>> {
@@ -414,3 +1481,663 @@ impl Repository {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::CreateCredentialRequest;
+
+    fn test_credential(max_messages: Option<i64>) -> Credential {
+        Credential::new(CreateCredentialRequest {
+            name: "Test".to_string(),
+            api_key: "key".to_string(),
+            app_id: "1:123:android:abc".to_string(),
+            project_id: "project".to_string(),
+            webhook_url: Some("http://localhost/webhook".to_string()),
+            max_messages,
+            ..Default::default()
+        })
+    }
+
+    #[tokio::test]
+    async fn test_update_only_is_active() {
+        let repo = Repository::new("sqlite::memory:").await.unwrap();
+        let cred = test_credential(Some(10));
+        repo.create_credential(&cred).await.unwrap();
+
+        let deactivate = CredentialUpdate {
+            is_active: Some(false),
+            ..Default::default()
+        };
+        let updated = repo.update_credential(&cred.id, &deactivate).await
+            .unwrap();
+        assert!(updated);
+
+        let stored = repo.get_credential(&cred.id).await.unwrap().unwrap();
+        assert!(!stored.is_active);
+        assert_eq!(stored.name, cred.name);
+        assert_eq!(stored.max_messages, Some(10));
+
+        // Stored as a real boolean, so integer comparisons in other queries see it
+        assert!(repo.list_credentials(true).await.unwrap().is_empty());
+        assert!(repo.list_runnable_credentials().await.unwrap().is_empty());
+
+        let activate = CredentialUpdate {
+            is_active: Some(true),
+            ..Default::default()
+        };
+        repo.update_credential(&cred.id, &activate).await
+            .unwrap();
+        assert_eq!(repo.list_credentials(true).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_credential_state_counts() {
+        let repo = Repository::new("sqlite::memory:").await.unwrap();
+        let counts = repo.credential_state_counts().await.unwrap();
+        assert_eq!((counts.total, counts.runnable), (0, 0));
+
+        let mut inactive = test_credential(None);
+        inactive.is_active = false;
+        let (suspended, runnable) = (test_credential(None), test_credential(None));
+        for cred in [&inactive, &suspended, &runnable] {
+            repo.create_credential(cred).await.unwrap();
+        }
+        repo.suspend_credential(&suspended.id).await.unwrap();
+        // Inactive takes precedence, so each credential is counted once
+        repo.suspend_credential(&inactive.id).await.unwrap();
+
+        let counts = repo.credential_state_counts().await.unwrap();
+        assert_eq!(
+            (counts.total, counts.runnable, counts.inactive, counts.suspended),
+            (3, 1, 1, 1)
+        );
+        assert_eq!(counts.runnable, repo.list_runnable_credentials().await.unwrap().len() as i64);
+    }
+
+    #[tokio::test]
+    async fn test_suspend_and_clear_credential() {
+        let repo = Repository::new("sqlite::memory:").await.unwrap();
+        let (cred, other) = (test_credential(None), test_credential(None));
+        for c in [&cred, &other] {
+            repo.create_credential(c).await.unwrap();
+            for _ in 0..2 {
+                let log = MessageLog::new(c.id.clone(), None, "{}".to_string());
+                repo.create_message_log(&log).await.unwrap();
+            }
+        }
+
+        assert_eq!(repo.suspend_and_clear_credential(&cred.id).await.unwrap(), Some(2));
+        assert!(repo.get_credential(&cred.id).await.unwrap().unwrap().is_suspended);
+        assert_eq!(repo.count_message_logs(Some(&cred.id), None, None).await.unwrap(), 0);
+        // Other credentials keep their messages
+        assert_eq!(repo.count_message_logs(Some(&other.id), None, None).await.unwrap(), 2);
+        assert_eq!(repo.suspend_and_clear_credential("missing").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_update_subset_of_fields() {
+        let repo = Repository::new("sqlite::memory:").await.unwrap();
+        let cred = test_credential(None);
+        repo.create_credential(&cred).await.unwrap();
+
+        let update = CredentialUpdate {
+            name: Some("Renamed".to_string()),
+            webhook_url: Some("https://example.com/hook".to_string()),
+            max_messages: Some(25),
+            capture_webhook_response: Some(false),
+            ..Default::default()
+        };
+        repo.update_credential(&cred.id, &update)
+        .await
+        .unwrap();
+
+        let stored = repo.get_credential(&cred.id).await.unwrap().unwrap();
+        assert_eq!(stored.name, "Renamed");
+        assert_eq!(stored.webhook_url, "https://example.com/hook");
+        assert_eq!(stored.max_messages, Some(25));
+        assert!(!stored.capture_webhook_response);
+        // Untouched fields keep their values
+        assert!(stored.is_active);
+        assert_eq!(stored.api_key, cred.api_key);
+        assert_eq!(stored.app_id, cred.app_id);
+
+        // Unknown id reports no update
+        let rename = CredentialUpdate {
+            name: Some("x".to_string()),
+            ..Default::default()
+        };
+        let updated = repo.update_credential("missing", &rename).await
+            .unwrap();
+        assert!(!updated);
+    }
+
+    #[tokio::test]
+    async fn test_external_id_is_unique() {
+        let repo = Repository::new("sqlite::memory:").await.unwrap();
+        let mut cred = test_credential(None);
+        cred.external_id = Some("tenant-1".to_string());
+        repo.create_credential(&cred).await.unwrap();
+
+        let found = repo.get_credential_by_external_id("tenant-1").await.unwrap().unwrap();
+        assert_eq!(found.id, cred.id);
+        assert!(repo.get_credential_by_external_id("tenant-2").await.unwrap().is_none());
+
+        let mut duplicate = test_credential(None);
+        duplicate.external_id = Some("tenant-1".to_string());
+        assert!(repo.create_credential(&duplicate).await.is_err());
+
+        // Credentials without an external_id don't collide with each other
+        repo.create_credential(&test_credential(None)).await.unwrap();
+        repo.create_credential(&test_credential(None)).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_separate_read_pool() {
+        let path = std::env::temp_dir().join(format!("fcm_read_pool_{}.db", std::process::id()));
+        let url = format!("sqlite:{}?mode=rwc", path.display());
+        let read_url = format!("sqlite:{}?mode=ro", path.display());
+        let repo = Repository::with_read_url(&url, Some(&read_url)).await.unwrap();
+
+        let cred = test_credential(None);
+        repo.create_credential(&cred).await.unwrap();
+        assert_eq!(repo.list_credentials(false).await.unwrap().len(), 1);
+
+        // Reads go through their own (here read-only) connection
+        assert!(sqlx::query("DELETE FROM credentials")
+            .execute(&repo.read_pool)
+            .await
+            .is_err());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_pool_respects_max_connections() {
+        use std::time::Duration;
+
+        let path = std::env::temp_dir().join(format!("fcm_pool_size_{}.db", std::process::id()));
+        let url = format!("sqlite:{}?mode=rwc", path.display());
+        let config = DbPoolConfig {
+            max_connections: 2,
+            connect_timeout: Duration::from_millis(200),
+            statement_timeout: Duration::from_secs(1),
+        };
+        let repo = Repository::connect(&url, None, config).await.unwrap();
+
+        let first = repo.pool.acquire().await.unwrap();
+        let _second = repo.pool.acquire().await.unwrap();
+        assert!(matches!(repo.pool.acquire().await, Err(sqlx::Error::PoolTimedOut)));
+
+        // A returned connection is handed out again
+        drop(first);
+        repo.pool.acquire().await.unwrap();
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_migrations_upgrade_unversioned_database() {
+        let path = std::env::temp_dir().join(format!("fcm_unversioned_{}.db", std::process::id()));
+        let url = format!("sqlite:{}?mode=rwc", path.display());
+
+        // Schema as created by an early release, before migrations were tracked
+        let pool = SqlitePool::connect(&url).await.unwrap();
+        sqlx::query(
+            r#"
+            CREATE TABLE credentials (
+                id TEXT PRIMARY KEY, name TEXT NOT NULL, api_key TEXT NOT NULL,
+                app_id TEXT NOT NULL, project_id TEXT NOT NULL, fcm_token TEXT, gcm_token TEXT,
+                android_id INTEGER, security_token INTEGER, private_key_base64 TEXT,
+                auth_secret_base64 TEXT, webhook_url TEXT NOT NULL, webhook_headers TEXT,
+                is_active BOOLEAN NOT NULL DEFAULT 1, is_suspended BOOLEAN NOT NULL DEFAULT 0,
+                created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+            );
+            CREATE TABLE message_logs (
+                id TEXT PRIMARY KEY, credential_id TEXT NOT NULL, fcm_message_id TEXT,
+                payload TEXT NOT NULL, webhook_status INTEGER, webhook_response TEXT,
+                received_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+            );
+            INSERT INTO credentials (id, name, api_key, app_id, project_id, webhook_url)
+            VALUES ('old', 'Old', 'key', 'app', 'project', 'http://localhost/hook');
+            INSERT INTO message_logs (id, credential_id, payload) VALUES ('m1', 'old', '{"n":1}');
+            INSERT INTO message_logs (id, credential_id, payload, webhook_status)
+            VALUES ('m2', 'old', '{"n":2}', 200), ('m3', 'old', '{"n":3}', 0), ('m4', 'old', '{"n":4}', 503);
+            "#,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        pool.close().await;
+
+        let repo = Repository::new(&url).await.unwrap();
+        let applied: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM _sqlx_migrations WHERE success")
+            .fetch_one(&repo.pool)
+            .await
+            .unwrap();
+        assert_eq!(applied, MIGRATOR.iter().count() as i64);
+
+        // Existing rows survive and are backfilled into the newer tables
+        let old = repo.get_credential("old").await.unwrap().unwrap();
+        assert!(old.webhook_enabled);
+        assert_eq!(repo.count_message_logs(Some("old"), None, None).await.unwrap(), 4);
+        let state = |id: &'static str| {
+            let repo = repo.clone();
+            async move { repo.get_message_log(id).await.unwrap().unwrap().state() }
+        };
+        assert_eq!(state("m1").await, DeliveryState::Pending);
+        assert_eq!(state("m2").await, DeliveryState::Delivered);
+        assert_eq!(state("m3").await, DeliveryState::Failed);
+        assert_eq!(state("m4").await, DeliveryState::Failed);
+        repo.create_credential(&test_credential(None)).await.unwrap();
+        drop(repo);
+
+        // Reopening finds every migration applied and runs none again
+        let repo = Repository::new(&url).await.unwrap();
+        assert_eq!(repo.list_credentials(false).await.unwrap().len(), 2);
+        drop(repo);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_rotate_webhook_secret() {
+        let repo = Repository::new("sqlite::memory:").await.unwrap();
+        let cred = test_credential(None);
+        repo.create_credential(&cred).await.unwrap();
+
+        repo.rotate_webhook_secret(&cred.id, "first").await.unwrap();
+        repo.rotate_webhook_secret(&cred.id, "second").await.unwrap();
+        let stored = repo.get_credential(&cred.id).await.unwrap().unwrap();
+        assert_eq!(stored.webhook_secret.as_deref(), Some("second"));
+        assert_eq!(stored.webhook_secret_previous.as_deref(), Some("first"));
+
+        repo.clear_previous_webhook_secret(&cred.id).await.unwrap();
+        let stored = repo.get_credential(&cred.id).await.unwrap().unwrap();
+        assert_eq!(stored.webhook_secret.as_deref(), Some("second"));
+        assert_eq!(stored.webhook_secret_previous, None);
+
+        assert!(!repo.rotate_webhook_secret("missing", "x").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_cleanup_respects_credential_cap() {
+        let repo = Repository::new("sqlite::memory:").await.unwrap();
+        let cred = test_credential(Some(3));
+        repo.create_credential(&cred).await.unwrap();
+
+        for i in 0..5 {
+            let log = MessageLog::new(cred.id.clone(), None, format!("{{\"n\":{}}}", i));
+            repo.create_message_log(&log).await.unwrap();
+            repo.cleanup_old_messages(&cred.id, cred.effective_max_messages())
+                .await
+                .unwrap();
+        }
+
+        assert_eq!(repo.count_message_logs(Some(&cred.id), None, None).await.unwrap(), 3);
+
+        let stored = repo.get_credential(&cred.id).await.unwrap().unwrap();
+        assert_eq!(stored.max_messages, Some(3));
+    }
+
+    #[tokio::test]
+    async fn test_incremental_vacuum_reclaims_space() {
+        let repo = Repository::new("sqlite::memory:").await.unwrap();
+        assert!(repo.incremental_vacuum_enabled().await.unwrap());
+
+        let cred = test_credential(None);
+        repo.create_credential(&cred).await.unwrap();
+        let payload = format!("{{\"blob\":\"{}\"}}", "x".repeat(8192));
+        for _ in 0..50 {
+            let log = MessageLog::new(cred.id.clone(), None, payload.clone());
+            repo.create_message_log(&log).await.unwrap();
+        }
+        repo.clear_credential_messages(&cred.id).await.unwrap();
+
+        let report = repo.vacuum(false).await.unwrap();
+        assert!(!report.full);
+        assert!(report.reclaimed() > 0);
+
+        let report = repo.vacuum(true).await.unwrap();
+        assert!(report.full);
+        assert!(repo.incremental_vacuum_enabled().await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_search_after_full_vacuum() {
+        let repo = Repository::new("sqlite::memory:").await.unwrap();
+        let cred = test_credential(None);
+        repo.create_credential(&cred).await.unwrap();
+
+        let mut logs = Vec::new();
+        for word in ["alpha", "beta", "gamma", "delta"] {
+            let log = MessageLog::new(cred.id.clone(), None, format!("{{\"word\":\"{}\"}}", word));
+            repo.create_message_log(&log).await.unwrap();
+            logs.push(log);
+        }
+        // Deleted rows leave gaps in the rowids, which VACUUM is free to close
+        repo.delete_message_log(&logs[0].id).await.unwrap();
+        repo.delete_message_log(&logs[2].id).await.unwrap();
+
+        let found = repo.search_message_logs("delta", None, false, 10, 0).await.unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id, logs[3].id);
+
+        repo.vacuum(true).await.unwrap();
+
+        let found = repo.search_message_logs("delta", None, false, 10, 0).await.unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id, logs[3].id);
+        let found = repo.search_message_logs("beta", None, false, 10, 0).await.unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id, logs[1].id);
+        assert!(repo.search_message_logs("alpha", None, false, 10, 0).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_filter_by_extracted_field() {
+        let repo = Repository::new("sqlite::memory:").await.unwrap();
+        let cred = test_credential(None);
+        repo.create_credential(&cred).await.unwrap();
+
+        for order in ["A-1", "A-2", "A-1"] {
+            let mut log = MessageLog::new(cred.id.clone(), None, "{}".to_string());
+            log.extracted_fields = Some(format!("{{\"order\":\"{}\"}}", order));
+            repo.create_message_log(&log).await.unwrap();
+        }
+        repo.create_message_log(&MessageLog::new(cred.id.clone(), None, "{}".to_string()))
+            .await
+            .unwrap();
+
+        let field = Some(("order", "A-1"));
+        assert_eq!(repo.count_message_logs(Some(&cred.id), field, None).await.unwrap(), 2);
+        assert_eq!(repo.list_message_logs(None, field, None, 10, 0).await.unwrap().len(), 2);
+        assert_eq!(repo.count_message_logs(None, Some(("order", "B")), None).await.unwrap(), 0);
+        assert_eq!(repo.count_message_logs(None, None, None).await.unwrap(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_per_credential_counts() {
+        let repo = Repository::new("sqlite::memory:").await.unwrap();
+        let (noisy, quiet) = (test_credential(None), test_credential(None));
+        repo.create_credential(&noisy).await.unwrap();
+        repo.create_credential(&quiet).await.unwrap();
+
+        for (cred, rows) in [(&noisy, 2_000), (&quiet, 50)] {
+            sqlx::query(
+                "WITH RECURSIVE seq(n) AS (SELECT 1 UNION ALL SELECT n + 1 FROM seq WHERE n < ?) \
+                 INSERT INTO message_logs (id, credential_id, payload, received_at) \
+                 SELECT ? || '-' || n, ?, '{}', datetime('now', '-' || n || ' seconds') FROM seq",
+            )
+            .bind(rows)
+            .bind(&cred.id)
+            .bind(&cred.id)
+            .execute(&repo.pool)
+            .await
+            .unwrap();
+        }
+        repo.create_message_log(&MessageLog::new(quiet.id.clone(), None, "{}".to_string()))
+            .await
+            .unwrap();
+        repo.cleanup_old_messages(&quiet.id, 40).await.unwrap();
+
+        // The counter agrees with a COUNT(*) over the credential's rows
+        let scanned: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM message_logs WHERE credential_id = ?")
+                .bind(&noisy.id)
+                .fetch_one(&repo.pool)
+                .await
+                .unwrap();
+        let counted = repo.message_count(Some(&noisy.id)).await.unwrap();
+
+        assert_eq!(counted, 2_000);
+        assert_eq!(counted, scanned);
+        assert_eq!(repo.count_message_logs(Some(&quiet.id), None, None).await.unwrap(), 40);
+        assert_eq!(repo.count_message_logs(None, None, None).await.unwrap(), 2_040);
+        let counts = repo.message_counts().await.unwrap();
+        assert_eq!((counts[&noisy.id], counts[&quiet.id]), (2_000, 40));
+
+        // A quiet credential's page reads its own index range, without sorting the noisy one's rows
+        let plan: Vec<String> = sqlx::query(
+            "EXPLAIN QUERY PLAN SELECT * FROM message_logs WHERE credential_id = ? \
+             ORDER BY received_at DESC LIMIT 50",
+        )
+        .bind(&quiet.id)
+        .fetch_all(&repo.pool)
+        .await
+        .unwrap()
+        .iter()
+        .map(|row| row.get::<String, _>("detail"))
+        .collect();
+        assert!(plan.iter().any(|d| d.contains("idx_message_logs_credential_received")), "{:?}", plan);
+        assert!(!plan.iter().any(|d| d.contains("TEMP B-TREE")), "{:?}", plan);
+
+        // Deleting the credential drops its counter along with its messages
+        repo.delete_credential(&quiet.id).await.unwrap();
+        assert_eq!(repo.message_count(Some(&quiet.id)).await.unwrap(), 0);
+        assert!(!repo.message_counts().await.unwrap().contains_key(&quiet.id));
+    }
+
+    #[tokio::test]
+    async fn test_message_stats_by_credential() {
+        let repo = Repository::new("sqlite::memory:").await.unwrap();
+        let cred = test_credential(None);
+        repo.create_credential(&cred).await.unwrap();
+
+        for status in [Some(200), Some(500), Some(204), None] {
+            let log = MessageLog::new(cred.id.clone(), None, "{}".to_string());
+            repo.create_message_log(&log).await.unwrap();
+            if let Some(status) = status {
+                repo.update_message_webhook_status(&log.id, status, None, None)
+                    .await
+                    .unwrap();
+            }
+        }
+
+        let stats = repo.message_stats_by_credential().await.unwrap();
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].credential_id, cred.id);
+        assert_eq!(stats[0].message_count, 4);
+        assert_eq!(stats[0].attempted, 3);
+        assert_eq!(stats[0].succeeded, 2);
+        assert!(stats[0].last_received_at.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_delivery_state_transitions() {
+        let repo = Repository::new("sqlite::memory:").await.unwrap();
+        let cred = test_credential(None);
+        repo.create_credential(&cred).await.unwrap();
+
+        let [delivered, retrying, failed, skipped, untouched] =
+            [(); 5].map(|_| MessageLog::new(cred.id.clone(), None, "{}".to_string()));
+        for log in [&delivered, &retrying, &failed, &skipped, &untouched] {
+            repo.create_message_log(log).await.unwrap();
+        }
+        for log in [&delivered, &retrying, &failed] {
+            repo.start_message_delivery(&log.id, "http://localhost/hook").await.unwrap();
+        }
+        repo.update_message_webhook_status(&delivered.id, 204, None, None).await.unwrap();
+        repo.update_message_webhook_status(&retrying.id, 503, None, None).await.unwrap();
+        repo.update_message_webhook_status(&failed.id, 0, Some("gave up"), None).await.unwrap();
+        repo.skip_message_delivery(&skipped.id, None, "no webhook").await.unwrap();
+
+        let in_state = |state| repo.list_message_logs(Some(&cred.id), None, Some(state), 10, 0);
+        assert_eq!(in_state(DeliveryState::Pending).await.unwrap()[0].id, untouched.id);
+        assert_eq!(in_state(DeliveryState::Delivering).await.unwrap()[0].id, retrying.id);
+        assert_eq!(in_state(DeliveryState::Delivered).await.unwrap()[0].id, delivered.id);
+        assert_eq!(in_state(DeliveryState::Failed).await.unwrap()[0].id, failed.id);
+        assert_eq!(in_state(DeliveryState::Skipped).await.unwrap()[0].id, skipped.id);
+        // Skipped without a status, so retrying failed messages leaves it out
+        let failed_logs = repo.list_failed_message_logs(Some(&cred.id), 10).await.unwrap();
+        assert!(failed_logs.iter().all(|l| l.id != skipped.id));
+        assert_eq!(
+            repo.count_message_logs(Some(&cred.id), None, Some(DeliveryState::Delivering))
+                .await
+                .unwrap(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_filter_new_messages() {
+        let repo = Repository::new("sqlite::memory:").await.unwrap();
+        let cred = test_credential(None);
+        let other = test_credential(None);
+        repo.create_credential(&cred).await.unwrap();
+        repo.create_credential(&other).await.unwrap();
+        let log = |cred: &Credential, fcm_id: Option<&str>| {
+            MessageLog::new(cred.id.clone(), fcm_id.map(str::to_string), "{}".to_string())
+        };
+        repo.create_message_log(&log(&cred, Some("0:known"))).await.unwrap();
+
+        let mut keyed = log(&cred, Some("0:known"));
+        keyed.dedup_key = Some("order-1".to_string());
+        let batch = vec![
+            log(&cred, Some("0:known")),  // already logged
+            log(&cred, Some("0:new")),
+            log(&cred, Some("0:new")),    // repeats the previous one
+            log(&other, Some("0:known")), // same id, other credential
+            log(&cred, None),
+            keyed,                        // deduplicated by its dedup key instead
+        ];
+        let expected: Vec<String> = [1, 3, 4, 5].iter().map(|i| batch[*i].id.clone()).collect();
+
+        let new = repo.filter_new_messages(batch).await.unwrap();
+        let ids: Vec<String> = new.iter().map(|l| l.id.clone()).collect();
+        assert_eq!(ids, expected);
+
+        assert!(repo.filter_new_messages(Vec::new()).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_acknowledge_message() {
+        let repo = Repository::new("sqlite::memory:").await.unwrap();
+        let cred = test_credential(None);
+        repo.create_credential(&cred).await.unwrap();
+
+        let log = MessageLog::new(cred.id.clone(), None, "{}".to_string());
+        repo.create_message_log(&log).await.unwrap();
+        repo.update_message_webhook_status(&log.id, 200, Some("ok"), None)
+            .await
+            .unwrap();
+        assert_eq!(repo.count_unacknowledged_delivered().await.unwrap(), 1);
+
+        assert!(repo.acknowledge_message(&log.id).await.unwrap());
+        // Second ack is a no-op
+        assert!(!repo.acknowledge_message(&log.id).await.unwrap());
+
+        let stored = repo.get_message_log(&log.id).await.unwrap().unwrap();
+        assert!(stored.acknowledged);
+        assert!(stored.acknowledged_at.is_some());
+        assert_eq!(repo.count_unacknowledged_delivered().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_set_credential_topics_only_applies_changes() {
+        let repo = Repository::new("sqlite::memory:").await.unwrap();
+        let cred = test_credential(None);
+        repo.create_credential(&cred).await.unwrap();
+
+        let topics = vec!["news".to_string(), "sports".to_string(), "news".to_string()];
+        let changes = repo.set_credential_topics(&cred.id, &topics).await.unwrap();
+        assert_eq!(changes.added, vec!["news".to_string(), "sports".to_string()]);
+        assert!(changes.removed.is_empty());
+
+        // Same set again (in another order) changes nothing
+        let same = vec!["sports".to_string(), "news".to_string()];
+        assert!(repo.set_credential_topics(&cred.id, &same).await.unwrap().is_empty());
+
+        let next = vec!["sports".to_string(), "weather".to_string()];
+        let changes = repo.set_credential_topics(&cred.id, &next).await.unwrap();
+        assert_eq!(changes.added, vec!["weather".to_string()]);
+        assert_eq!(changes.removed, vec!["news".to_string()]);
+
+        let mut stored = repo.get_credential_topics(&cred.id).await.unwrap();
+        stored.sort();
+        assert_eq!(stored, next);
+    }
+
+    #[tokio::test]
+    async fn test_payload_compression_round_trip() {
+        let repo = Repository::new("sqlite::memory:")
+            .await
+            .unwrap()
+            .with_payload_compression(true);
+        let cred = test_credential(None);
+        repo.create_credential(&cred).await.unwrap();
+
+        let items: Vec<String> = (0..100)
+            .map(|i| format!("{{\"id\":{},\"title\":\"Order update\",\"status\":\"shipped\"}}", i))
+            .collect();
+        let payload = format!("{{\"data\":{{\"items\":[{}]}}}}", items.join(","));
+        let large = MessageLog::new(cred.id.clone(), None, payload.clone());
+        let small = MessageLog::new(cred.id.clone(), None, "{\"a\":1}".to_string());
+        repo.create_message_log(&large).await.unwrap();
+        repo.create_message_log(&small).await.unwrap();
+
+        let (stored, compressed): (String, bool) = sqlx::query_as(
+            "SELECT payload, payload_compressed FROM message_logs WHERE id = ?",
+        )
+        .bind(&large.id)
+        .fetch_one(&repo.pool)
+        .await
+        .unwrap();
+        assert!(compressed);
+        assert!(stored.len() < payload.len());
+
+        let read = repo.get_message_log(&large.id).await.unwrap().unwrap();
+        assert_eq!(read.payload, payload);
+        assert!(!read.payload_compressed);
+        assert_eq!(read.payload_bytes(), payload.as_bytes());
+
+        // Small payloads, and rows written before compression was enabled, stay plain
+        let read = repo.get_message_log(&small.id).await.unwrap().unwrap();
+        assert_eq!(read.payload, "{\"a\":1}");
+        let listed = repo.list_message_logs(Some(&cred.id), None, None, 10, 0).await.unwrap();
+        assert!(listed.iter().all(|log| !log.payload_compressed));
+        assert!(listed.iter().any(|log| log.payload == payload));
+    }
+
+    #[tokio::test]
+    async fn test_search_compressed_payloads() {
+        let repo = Repository::new("sqlite::memory:")
+            .await
+            .unwrap()
+            .with_payload_compression(true);
+        let cred = test_credential(None);
+        repo.create_credential(&cred).await.unwrap();
+
+        let compressible = |word: &str| {
+            let items: Vec<String> = (0..50)
+                .map(|i| format!("{{\"id\":{},\"status\":\"{}\"}}", i, word))
+                .collect();
+            MessageLog::new(cred.id.clone(), None, format!("{{\"items\":[{}]}}", items.join(",")))
+        };
+        let (shipped, returned) = (compressible("shipped"), compressible("returned"));
+        repo.create_message_log(&shipped).await.unwrap();
+        repo.create_message_logs(std::slice::from_ref(&returned)).await.unwrap();
+        let compressed: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM message_logs WHERE payload_compressed")
+                .fetch_one(&repo.pool)
+                .await
+                .unwrap();
+        assert_eq!(compressed, 2);
+
+        // Found by the words of the original payload, not of the stored base64
+        let found = repo.search_message_logs("shipped", None, false, 10, 0).await.unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id, shipped.id);
+        assert_eq!(found[0].payload, shipped.payload);
+        assert_eq!(repo.count_search_message_logs("returned", None).await.unwrap(), 1);
+
+        // Deleting a message drops it from the index, and a full vacuum indexes the rest again
+        repo.delete_message_log(&shipped.id).await.unwrap();
+        assert!(repo.search_message_logs("shipped", None, false, 10, 0).await.unwrap().is_empty());
+        repo.vacuum(true).await.unwrap();
+        let found = repo.search_message_logs("returned", None, false, 10, 0).await.unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id, returned.id);
+    }
+}
@@ -1,11 +1,20 @@
 use axum::{
-    http::StatusCode,
+    http::{header, HeaderMap, HeaderValue, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
 use serde_json::json;
 use std::fmt;
 
+/// Usage against a limit, reported with limit errors so clients can back off programmatically
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LimitInfo {
+    pub limit: u64,
+    pub current: u64,
+    /// Seconds until a retry may succeed (sent as Retry-After)
+    pub retry_after_secs: Option<u64>,
+}
+
 /// Application-wide error types
 #[derive(Debug)]
 #[allow(dead_code)]
@@ -26,8 +35,13 @@ pub enum AppError {
     // API errors
     NotFound(String),
     BadRequest(String),
+    Unauthorized(String),
     Conflict(String),
     Internal(String),
+    /// Handler exceeded the request timeout
+    Timeout(String),
+    /// A rate limit was hit (429 with Retry-After)
+    TooManyRequests(String, LimitInfo),
 
     // Worker errors
     WorkerNotRunning(String),
@@ -46,8 +60,11 @@ impl fmt::Display for AppError {
             AppError::WebhookInvalidUrl(msg) => write!(f, "Invalid webhook URL: {}", msg),
             AppError::NotFound(msg) => write!(f, "Not found: {}", msg),
             AppError::BadRequest(msg) => write!(f, "Bad request: {}", msg),
+            AppError::Unauthorized(msg) => write!(f, "Unauthorized: {}", msg),
             AppError::Conflict(msg) => write!(f, "Conflict: {}", msg),
             AppError::Internal(msg) => write!(f, "Internal error: {}", msg),
+            AppError::Timeout(msg) => write!(f, "Request timeout: {}", msg),
+            AppError::TooManyRequests(msg, _) => write!(f, "Too many requests: {}", msg),
             AppError::WorkerNotRunning(msg) => write!(f, "Worker not running: {}", msg),
             AppError::WorkerAlreadyRunning(msg) => write!(f, "Worker already running: {}", msg),
         }
@@ -56,32 +73,123 @@ impl fmt::Display for AppError {
 
 impl std::error::Error for AppError {}
 
+impl AppError {
+    /// Machine-readable error type used in API responses
+    pub fn error_type(&self) -> &'static str {
+        match self {
+            AppError::Database(_) => "database_error",
+            AppError::FcmRegistration(_) => "fcm_registration_error",
+            AppError::FcmConnection(_) => "fcm_connection_error",
+            AppError::FcmDecryption(_) => "fcm_decryption_error",
+            AppError::WebhookRequest(_) => "webhook_error",
+            AppError::WebhookTimeout(_) => "webhook_timeout",
+            AppError::WebhookInvalidUrl(_) => "invalid_webhook_url",
+            AppError::NotFound(_) => "not_found",
+            AppError::BadRequest(_) => "bad_request",
+            AppError::Unauthorized(_) => "unauthorized",
+            AppError::Conflict(_) => "conflict",
+            AppError::Internal(_) => "internal_error",
+            AppError::Timeout(_) => "request_timeout",
+            AppError::TooManyRequests(..) => "rate_limited",
+            AppError::WorkerNotRunning(_) => "worker_not_running",
+            AppError::WorkerAlreadyRunning(_) => "worker_already_running",
+        }
+    }
+
+    /// Error message without the type prefix
+    pub fn message(&self) -> &str {
+        match self {
+            AppError::Database(msg)
+            | AppError::FcmRegistration(msg)
+            | AppError::FcmConnection(msg)
+            | AppError::FcmDecryption(msg)
+            | AppError::WebhookRequest(msg)
+            | AppError::WebhookTimeout(msg)
+            | AppError::WebhookInvalidUrl(msg)
+            | AppError::NotFound(msg)
+            | AppError::BadRequest(msg)
+            | AppError::Unauthorized(msg)
+            | AppError::Conflict(msg)
+            | AppError::Internal(msg)
+            | AppError::Timeout(msg)
+            | AppError::TooManyRequests(msg, _)
+            | AppError::WorkerNotRunning(msg)
+            | AppError::WorkerAlreadyRunning(msg) => msg,
+        }
+    }
+
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            AppError::Database(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            AppError::FcmRegistration(_) => StatusCode::BAD_GATEWAY,
+            AppError::FcmConnection(_) => StatusCode::BAD_GATEWAY,
+            AppError::FcmDecryption(_) => StatusCode::BAD_GATEWAY,
+            AppError::WebhookRequest(_) => StatusCode::BAD_GATEWAY,
+            AppError::WebhookTimeout(_) => StatusCode::GATEWAY_TIMEOUT,
+            AppError::WebhookInvalidUrl(_) => StatusCode::BAD_REQUEST,
+            AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            AppError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            AppError::Conflict(_) => StatusCode::CONFLICT,
+            AppError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            AppError::Timeout(_) => StatusCode::GATEWAY_TIMEOUT,
+            AppError::TooManyRequests(..) => StatusCode::TOO_MANY_REQUESTS,
+            AppError::WorkerNotRunning(_) => StatusCode::BAD_REQUEST,
+            AppError::WorkerAlreadyRunning(_) => StatusCode::CONFLICT,
+        }
+    }
+
+    /// Limit and current usage behind the error, for limit errors
+    pub fn limit_info(&self) -> Option<LimitInfo> {
+        match self {
+            AppError::TooManyRequests(_, info) => Some(*info),
+            // A credential runs at most one worker, and it is already taken
+            AppError::WorkerAlreadyRunning(_) => Some(LimitInfo {
+                limit: 1,
+                current: 1,
+                retry_after_secs: None,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Headers added to the error response (Retry-After and rate-limit headers on 429)
+    pub fn headers(&self) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        if let AppError::TooManyRequests(_, info) = self {
+            if let Some(secs) = info.retry_after_secs {
+                headers.insert(header::RETRY_AFTER, HeaderValue::from(secs));
+            }
+            headers.insert("x-ratelimit-limit", HeaderValue::from(info.limit));
+            headers.insert(
+                "x-ratelimit-remaining",
+                HeaderValue::from(info.limit.saturating_sub(info.current)),
+            );
+        }
+        headers
+    }
+
+    /// Whether retrying is unlikely to help (e.g. rejected credentials or corrupt keys)
+    pub fn is_permanent(&self) -> bool {
+        matches!(self, AppError::FcmRegistration(_) | AppError::FcmDecryption(_))
+    }
+}
+
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
-        let (status, error_type, message) = match &self {
-            AppError::Database(msg) => (StatusCode::INTERNAL_SERVER_ERROR, "database_error", msg.clone()),
-            AppError::FcmRegistration(msg) => (StatusCode::BAD_GATEWAY, "fcm_registration_error", msg.clone()),
-            AppError::FcmConnection(msg) => (StatusCode::BAD_GATEWAY, "fcm_connection_error", msg.clone()),
-            AppError::FcmDecryption(msg) => (StatusCode::BAD_GATEWAY, "fcm_decryption_error", msg.clone()),
-            AppError::WebhookRequest(msg) => (StatusCode::BAD_GATEWAY, "webhook_error", msg.clone()),
-            AppError::WebhookTimeout(msg) => (StatusCode::GATEWAY_TIMEOUT, "webhook_timeout", msg.clone()),
-            AppError::WebhookInvalidUrl(msg) => (StatusCode::BAD_REQUEST, "invalid_webhook_url", msg.clone()),
-            AppError::NotFound(msg) => (StatusCode::NOT_FOUND, "not_found", msg.clone()),
-            AppError::BadRequest(msg) => (StatusCode::BAD_REQUEST, "bad_request", msg.clone()),
-            AppError::Conflict(msg) => (StatusCode::CONFLICT, "conflict", msg.clone()),
-            AppError::Internal(msg) => (StatusCode::INTERNAL_SERVER_ERROR, "internal_error", msg.clone()),
-            AppError::WorkerNotRunning(msg) => (StatusCode::BAD_REQUEST, "worker_not_running", msg.clone()),
-            AppError::WorkerAlreadyRunning(msg) => (StatusCode::CONFLICT, "worker_already_running", msg.clone()),
-        };
-
-        let body = Json(json!({
-            "error": {
-                "type": error_type,
-                "message": message,
+        let mut error = json!({
+            "type": self.error_type(),
+            "message": self.message(),
+        });
+        if let Some(info) = self.limit_info() {
+            error["limit"] = json!(info.limit);
+            error["current"] = json!(info.current);
+            if let Some(secs) = info.retry_after_secs {
+                error["retry_after_secs"] = json!(secs);
             }
-        }));
+        }
 
-        (status, body).into_response()
+        (self.status_code(), self.headers(), Json(json!({ "error": error }))).into_response()
     }
 }
 
@@ -108,3 +216,56 @@ impl From<reqwest::Error> for AppError {
 }
 
 pub type AppResult<T> = Result<T, AppError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn body_json(response: Response) -> serde_json::Value {
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_too_many_requests_headers() {
+        let info = LimitInfo {
+            limit: 10,
+            current: 10,
+            retry_after_secs: Some(30),
+        };
+        let response = AppError::TooManyRequests("slow down".to_string(), info).into_response();
+
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers().get(header::RETRY_AFTER).unwrap(), "30");
+        assert_eq!(response.headers().get("x-ratelimit-limit").unwrap(), "10");
+        assert_eq!(response.headers().get("x-ratelimit-remaining").unwrap(), "0");
+
+        let body = body_json(response).await;
+        assert_eq!(body["error"]["type"], "rate_limited");
+        assert_eq!(body["error"]["limit"], 10);
+        assert_eq!(body["error"]["current"], 10);
+        assert_eq!(body["error"]["retry_after_secs"], 30);
+    }
+
+    #[tokio::test]
+    async fn test_worker_already_running_body() {
+        let response = AppError::WorkerAlreadyRunning("cred".to_string()).into_response();
+
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        assert!(response.headers().get(header::RETRY_AFTER).is_none());
+
+        let body = body_json(response).await;
+        assert_eq!(body["error"]["type"], "worker_already_running");
+        assert_eq!(body["error"]["limit"], 1);
+        assert_eq!(body["error"]["current"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_plain_errors_have_no_limit() {
+        let response = AppError::NotFound("missing".to_string()).into_response();
+        assert!(response.headers().get("x-ratelimit-limit").is_none());
+
+        let body = body_json(response).await;
+        assert!(body["error"].get("limit").is_none());
+    }
+}
@@ -0,0 +1,239 @@
+//! Sends real pushes through the FCM HTTP v1 API with a Firebase service account.
+//!
+//! Used by the `test_notification` bin and by end-to-end tests that check a push
+//! actually reaches a running listener.
+
+use anyhow::{Context, Result};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const MESSAGING_SCOPE: &str = "https://www.googleapis.com/auth/firebase.messaging";
+
+/// The fields of a service account key file (as downloaded from the Firebase console)
+/// needed to mint access tokens
+#[derive(Debug, Clone, Deserialize)]
+pub struct ServiceAccount {
+    pub project_id: String,
+    pub private_key: String,
+    pub client_email: String,
+    pub token_uri: String,
+}
+
+impl ServiceAccount {
+    /// Read a service account key file
+    pub fn from_file(path: &str) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Cannot read service account file {}", path))?;
+        serde_json::from_str(&contents)
+            .with_context(|| format!("Invalid service account file {}", path))
+    }
+
+    /// Read the key file named by FCM_SERVICE_ACCOUNT, or else by GOOGLE_APPLICATION_CREDENTIALS
+    pub fn from_env() -> Result<Self> {
+        let path = std::env::var("FCM_SERVICE_ACCOUNT")
+            .or_else(|_| std::env::var("GOOGLE_APPLICATION_CREDENTIALS"))
+            .context("Set FCM_SERVICE_ACCOUNT to the path of a service account key file")?;
+        Self::from_file(&path)
+    }
+
+    /// Build the signed JWT exchanged for an access token, valid for an hour from `now`
+    fn signed_jwt(&self, now: u64) -> Result<String> {
+        use rsa::pkcs1v15::SigningKey;
+        use rsa::pkcs8::DecodePrivateKey;
+        use rsa::signature::{SignatureEncoding, Signer};
+        use rsa::RsaPrivateKey;
+        use sha2::Sha256;
+
+        let private_key = RsaPrivateKey::from_pkcs8_pem(&self.private_key)
+            .context("Invalid private_key in service account")?;
+        let signing_key = SigningKey::<Sha256>::new(private_key);
+
+        let header = json!({ "typ": "JWT", "alg": "RS256" });
+        let claims = json!({
+            "iss": self.client_email,
+            "scope": MESSAGING_SCOPE,
+            "aud": self.token_uri,
+            "exp": now + 3600,
+            "iat": now
+        });
+
+        let unsigned = format!(
+            "{}.{}",
+            URL_SAFE_NO_PAD.encode(header.to_string()),
+            URL_SAFE_NO_PAD.encode(claims.to_string())
+        );
+        let signature = signing_key.sign(unsigned.as_bytes());
+
+        Ok(format!("{}.{}", unsigned, URL_SAFE_NO_PAD.encode(signature.to_bytes())))
+    }
+}
+
+/// Where a message is sent
+#[derive(Debug, Clone, PartialEq)]
+pub enum MessageTarget {
+    /// A single registration (a credential's `fcm_token`)
+    Token(String),
+    /// Every registration subscribed to the topic
+    Topic(String),
+}
+
+/// A push to send; data values are strings, as FCM requires
+#[derive(Debug, Clone)]
+pub struct Message {
+    pub target: MessageTarget,
+    pub notification: Option<(String, String)>,
+    pub data: BTreeMap<String, String>,
+}
+
+impl Message {
+    pub fn to_token(token: impl Into<String>) -> Self {
+        Self::new(MessageTarget::Token(token.into()))
+    }
+
+    pub fn to_topic(topic: impl Into<String>) -> Self {
+        Self::new(MessageTarget::Topic(topic.into()))
+    }
+
+    fn new(target: MessageTarget) -> Self {
+        Self {
+            target,
+            notification: None,
+            data: BTreeMap::new(),
+        }
+    }
+
+    /// Show a notification with this title and body
+    pub fn with_notification(mut self, title: impl Into<String>, body: impl Into<String>) -> Self {
+        self.notification = Some((title.into(), body.into()));
+        self
+    }
+
+    /// Add a data field
+    pub fn with_data(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.data.insert(key.into(), value.into());
+        self
+    }
+
+    /// Request body for `messages:send`
+    pub fn to_json(&self) -> serde_json::Value {
+        let mut message = serde_json::Map::new();
+        match &self.target {
+            MessageTarget::Token(token) => message.insert("token".to_string(), json!(token)),
+            MessageTarget::Topic(topic) => message.insert("topic".to_string(), json!(topic)),
+        };
+        if let Some((title, body)) = &self.notification {
+            message.insert("notification".to_string(), json!({ "title": title, "body": body }));
+        }
+        if !self.data.is_empty() {
+            message.insert("data".to_string(), json!(self.data));
+        }
+
+        json!({ "message": message })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct SendResponse {
+    name: String,
+}
+
+/// Sends messages on behalf of a service account's project
+pub struct FcmAdmin {
+    client: Client,
+    account: ServiceAccount,
+}
+
+impl FcmAdmin {
+    pub fn new(account: ServiceAccount) -> Self {
+        Self {
+            client: Client::new(),
+            account,
+        }
+    }
+
+    pub fn project_id(&self) -> &str {
+        &self.account.project_id
+    }
+
+    /// Exchange a signed JWT for an OAuth access token
+    pub async fn access_token(&self) -> Result<String> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let jwt = self.account.signed_jwt(now)?;
+
+        let response = self
+            .client
+            .post(&self.account.token_uri)
+            .form(&[
+                ("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer"),
+                ("assertion", jwt.as_str()),
+            ])
+            .send()
+            .await?
+            .error_for_status()
+            .context("Access token request rejected")?;
+
+        Ok(response.json::<TokenResponse>().await?.access_token)
+    }
+
+    /// Send a message and return its FCM name (`projects/<id>/messages/<message id>`)
+    pub async fn send(&self, message: &Message) -> Result<String> {
+        let access_token = self.access_token().await?;
+        let url = format!(
+            "https://fcm.googleapis.com/v1/projects/{}/messages:send",
+            self.account.project_id
+        );
+
+        let response = self
+            .client
+            .post(&url)
+            .bearer_auth(access_token)
+            .json(&message.to_json())
+            .send()
+            .await?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            anyhow::bail!("FCM send failed with {}: {}", status, body);
+        }
+
+        Ok(response.json::<SendResponse>().await?.name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_message_json() {
+        let message = Message::to_token("device-token")
+            .with_notification("Hello", "World")
+            .with_data("order_id", "A-1");
+        assert_eq!(
+            message.to_json(),
+            json!({
+                "message": {
+                    "token": "device-token",
+                    "notification": { "title": "Hello", "body": "World" },
+                    "data": { "order_id": "A-1" }
+                }
+            })
+        );
+
+        // Data-only pushes to a topic leave out the notification
+        let message = Message::to_topic("news").with_data("k", "v");
+        assert_eq!(
+            message.to_json(),
+            json!({ "message": { "topic": "news", "data": { "k": "v" } } })
+        );
+    }
+}
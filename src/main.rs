@@ -1,21 +1,47 @@
 mod api;
+mod check;
 mod db;
 mod error;
+#[cfg(test)]
+mod fcm_admin;
 mod middleware;
 mod models;
+mod secrets;
+mod settings;
+mod tls;
 mod workers;
 
 use api::{create_router, AppState};
-use db::Repository;
-use middleware::{generate_api_key, ApiKeyConfig};
+use clap::{Parser, Subcommand};
+use db::{DbPoolConfig, Repository};
+use middleware::{is_production, resolve_api_key, ApiKeyConfig};
+use std::future::IntoFuture;
 use std::net::SocketAddr;
 use tokio::signal;
 use tracing::{error, info, warn};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 use workers::ListenerPool;
 
+/// FCM Multi-Credential Receiver Server
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Run the HTTP server and FCM listeners (default)
+    Serve,
+    /// Validate the environment and exit without starting the server
+    Check,
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+
     // Load .env file if exists
     dotenv::dotenv().ok();
 
@@ -30,30 +56,78 @@ async fn main() -> anyhow::Result<()> {
 
     info!("FCM Multi-Credential Receiver Server v{}", env!("CARGO_PKG_VERSION"));
 
+    // Secrets may be mounted as files (<NAME>_FILE); refuse to start if one is unreadable
+    secrets::check_files()?;
+
     // Get configuration from environment
-    let database_url = std::env::var("DATABASE_URL")
-        .unwrap_or_else(|_| "sqlite:fcm_receiver.db?mode=rwc".to_string());
+    let database_url = secrets::var("DATABASE_URL")?
+        .unwrap_or_else(|| "sqlite:fcm_receiver.db?mode=rwc".to_string());
     let port: u16 = std::env::var("PORT")
         .unwrap_or_else(|_| "3000".to_string())
         .parse()
         .expect("PORT must be a number");
 
-    // Get or generate API key
-    let api_key = std::env::var("API_KEY").unwrap_or_else(|_| {
-        let key = generate_api_key();
-        warn!("API_KEY not set in environment. Generated temporary key: {}", key);
-        warn!("Add API_KEY={} to your .env file to persist this key", key);
-        key
-    });
+    if let Some(Command::Check) = cli.command {
+        let api_key = secrets::var("API_KEY")?;
+        if check::run_checks(&database_url, port, api_key.as_deref()).await {
+            info!("All checks passed");
+            return Ok(());
+        }
+        error!("Some checks failed");
+        std::process::exit(1);
+    }
+
+    // Serve HTTPS when a certificate is configured; a broken one is fatal
+    let tls_paths = match tls::get_tls_paths() {
+        Ok(paths) => paths,
+        Err(e) => {
+            error!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    let tls_config = match &tls_paths {
+        Some(paths) => match tls::load(paths).await {
+            Ok(config) => Some(config),
+            Err(e) => {
+                error!("{:#}", e);
+                std::process::exit(1);
+            }
+        },
+        None => None,
+    };
+
+    // Get or generate API key (production requires an explicit one)
+    let api_key = match resolve_api_key(secrets::var("API_KEY")?, is_production()) {
+        Ok(key) => key,
+        Err(e) => {
+            error!("{}", e);
+            std::process::exit(1);
+        }
+    };
 
     info!("API Key configured (use 'Authorization: Bearer <key>' or 'X-API-Key: <key>')");
 
     info!("Connecting to database: {}", database_url);
 
-    // Initialize repository
-    let repo = Repository::new(&database_url).await?;
+    // Initialize repository (list/search/stats queries may go to a separate read database)
+    let read_url = secrets::var("DATABASE_READ_URL")?;
+    // DATABASE_READ_URL may carry credentials, so only its presence is logged
+    if read_url.is_some() {
+        info!("Read queries use the separate DATABASE_READ_URL database");
+    }
+    let pool_config = DbPoolConfig::from_env()?;
+    info!("Database pool: {}", pool_config);
+    let repo = Repository::connect(&database_url, read_url.as_deref(), pool_config).await?;
     info!("Database connected and migrations applied");
 
+    settings::load(&repo).await?;
+    info!("Runtime settings: {:?}", settings::current());
+
+    info!("Webhook proxy: {}", workers::describe_proxy_config());
+    if let Some(key) = workers::get_webhook_signing_key() {
+        info!("Webhook bodies signed with Ed25519 key {}", key.key_id());
+    }
+
     // Initialize listener pool
     let listener_pool = ListenerPool::new(repo.clone());
     
@@ -63,6 +137,11 @@ async fn main() -> anyhow::Result<()> {
         error!("Failed to start some listeners: {}", e);
     }
 
+    listener_pool.start_scheduler();
+    listener_pool.start_reaper();
+    listener_pool.start_watchdog();
+    workers::start_maintenance(repo.clone());
+
     // Create app state and API key config
     let state = AppState::new(repo, listener_pool);
     let api_key_config = ApiKeyConfig::new(api_key);
@@ -73,21 +152,77 @@ async fn main() -> anyhow::Result<()> {
 
     // Start server
     let addr = SocketAddr::from(([0, 0, 0, 0], port));
-    info!("Starting HTTP server on http://{}", addr);
-    info!("Swagger UI available at http://{}/swagger-ui/", addr);
+    match &tls_paths {
+        Some(paths) => {
+            info!("Starting HTTPS server on https://{} (TLS certificate {})", addr, paths.cert);
+            info!("Swagger UI available at https://{}/swagger-ui/", addr);
+        }
+        None => {
+            info!("Starting HTTP server on http://{} (plain HTTP, TLS not configured)", addr);
+            info!("Swagger UI available at http://{}/swagger-ui/", addr);
+        }
+    }
 
-    let listener = tokio::net::TcpListener::bind(addr).await?;
+    // Run with graceful shutdown: after a signal, open requests drain while the listeners
+    // stop, and both get SHUTDOWN_GRACE_SECS
+    let grace = workers::get_shutdown_grace();
+    let (stopping_tx, stopping_rx) = tokio::sync::watch::channel(false);
+    let server: futures::future::BoxFuture<std::io::Result<()>> = match (tls_config, tls_paths) {
+        (Some(config), Some(paths)) => {
+            tls::reload_on_sighup(config.clone(), paths);
+
+            let handle = axum_server::Handle::new();
+            let shutdown = handle.clone();
+            tokio::spawn(async move {
+                shutdown_signal(stopping_tx).await;
+                shutdown.graceful_shutdown(None);
+            });
+
+            Box::pin(
+                axum_server::bind_rustls(addr, config)
+                    .handle(handle)
+                    .serve(app.into_make_service()),
+            )
+        }
+        _ => {
+            let listener = tokio::net::TcpListener::bind(addr).await?;
+            Box::pin(
+                axum::serve(listener, app)
+                    .with_graceful_shutdown(shutdown_signal(stopping_tx))
+                    .into_future(),
+            )
+        }
+    };
 
-    // Run with graceful shutdown
-    axum::serve(listener, app)
-        .with_graceful_shutdown(shutdown_signal(pool_ref))
-        .await?;
+    let http = async {
+        let mut stopping = stopping_rx.clone();
+        tokio::select! {
+            result = server => result,
+            _ = async {
+                let _ = stopping.wait_for(|s| *s).await;
+                tokio::time::sleep(grace).await;
+            } => {
+                warn!("HTTP requests still open {}s after shutdown signal, closing them", grace.as_secs_f64());
+                Ok(())
+            }
+        }
+    };
+    let listeners = async {
+        // Also runs if the server fails without a signal (the sender is dropped with it)
+        let mut stopping = stopping_rx.clone();
+        let _ = stopping.wait_for(|s| *s).await;
+        info!("Stopping listeners...");
+        pool_ref.read().await.shutdown_all().await;
+    };
+
+    let (result, _) = tokio::join!(http, listeners);
+    result?;
 
     info!("Server stopped");
     Ok(())
 }
 
-async fn shutdown_signal(pool: std::sync::Arc<tokio::sync::RwLock<ListenerPool>>) {
+async fn shutdown_signal(stopping: tokio::sync::watch::Sender<bool>) {
     let ctrl_c = async {
         signal::ctrl_c()
             .await
@@ -110,9 +245,6 @@ async fn shutdown_signal(pool: std::sync::Arc<tokio::sync::RwLock<ListenerPool>>
         _ = terminate => {},
     }
 
-    info!("Shutdown signal received, stopping listeners...");
-    
-    // Shutdown all listeners gracefully
-    let pool = pool.read().await;
-    pool.shutdown_all().await;
+    info!("Shutdown signal received");
+    let _ = stopping.send(true);
 }
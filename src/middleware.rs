@@ -1,12 +1,15 @@
+use crate::error::AppError;
 use axum::{
+    body::Body,
     extract::{Request, State},
     http::{header, StatusCode},
     middleware::Next,
-    response::Response,
+    response::{IntoResponse, Response},
     Json,
 };
 use serde_json::json;
 use std::sync::Arc;
+use std::time::Duration;
 
 /// API Key configuration
 #[derive(Clone)]
@@ -37,6 +40,19 @@ pub async fn api_key_auth(
         return Ok(next.run(request).await);
     }
 
+    // The webhook public key is meant for receivers, which shouldn't hold the API key
+    if path == "/api/admin/webhook-public-key" {
+        return Ok(next.run(request).await);
+    }
+
+    // Message acks may use a credential-scoped token instead (validated by the handler)
+    if path.starts_with("/api/messages/")
+        && path.ends_with("/ack")
+        && request.headers().contains_key("X-Ack-Token")
+    {
+        return Ok(next.run(request).await);
+    }
+
     let expected_key = &config.api_key;
 
     // Check Authorization header
@@ -82,6 +98,57 @@ pub async fn api_key_auth(
     }
 }
 
+/// Middleware that fails handlers running longer than `timeout` with a 504,
+/// so a stalled database can't hold connections open indefinitely. Applied per
+/// route group, so long-running endpoints get a longer timeout or none.
+pub async fn request_timeout(
+    State(timeout): State<Duration>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let path = request.uri().path().to_string();
+    match tokio::time::timeout(timeout, next.run(request)).await {
+        Ok(response) => response,
+        Err(_) => {
+            tracing::warn!("Request to {} timed out after {}s", path, timeout.as_secs_f64());
+            AppError::Timeout(format!("Request did not complete within {}s", timeout.as_secs_f64()))
+                .into_response()
+        }
+    }
+}
+
+/// Middleware that rejects request bodies larger than `limit` bytes with a 400, before the
+/// handler runs. A declared Content-Length is checked up front; other bodies are read up
+/// to the limit, so an endless upload can't exhaust memory.
+pub async fn request_body_limit(
+    State(limit): State<usize>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let too_large = |size: String| {
+        AppError::BadRequest(format!(
+            "Request body too large: {} exceeds the limit of {} bytes",
+            size, limit
+        ))
+        .into_response()
+    };
+
+    let declared = request
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok());
+    if let Some(length) = declared.filter(|length| *length > limit as u64) {
+        return too_large(format!("{} bytes", length));
+    }
+
+    let (parts, body) = request.into_parts();
+    match axum::body::to_bytes(body, limit).await {
+        Ok(bytes) => next.run(Request::from_parts(parts, Body::from(bytes))).await,
+        Err(_) => too_large("The body".to_string()),
+    }
+}
+
 /// Generate a random API key
 pub fn generate_api_key() -> String {
     use rand::Rng;
@@ -94,3 +161,48 @@ pub fn generate_api_key() -> String {
         })
         .collect()
 }
+
+/// Get whether the server runs in production, from environment (APP_ENV = production or prod),
+/// default false. Production refuses to start without an explicit API_KEY.
+pub fn is_production() -> bool {
+    std::env::var("APP_ENV")
+        .map(|v| matches!(v.trim().to_lowercase().as_str(), "production" | "prod"))
+        .unwrap_or(false)
+}
+
+/// The configured API key. Without one, production is an error, while development gets a
+/// temporary generated key that changes on every restart.
+pub fn resolve_api_key(configured: Option<String>, production: bool) -> anyhow::Result<String> {
+    match configured {
+        Some(key) if production && key.trim().is_empty() => {
+            anyhow::bail!("API_KEY is empty; set a non-empty API_KEY when APP_ENV=production")
+        }
+        Some(key) => Ok(key),
+        None if production => anyhow::bail!(
+            "API_KEY is not set; refusing to start with a temporary key when APP_ENV=production"
+        ),
+        None => {
+            let key = generate_api_key();
+            tracing::warn!("API_KEY not set in environment. Generated temporary key: {}", key);
+            tracing::warn!("Add API_KEY={} to your .env file to persist this key", key);
+            Ok(key)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_api_key() {
+        assert_eq!(resolve_api_key(Some("secret".to_string()), true).unwrap(), "secret");
+        assert_eq!(resolve_api_key(Some("secret".to_string()), false).unwrap(), "secret");
+
+        let generated = resolve_api_key(None, false).unwrap();
+        assert_eq!(generated.len(), 32);
+
+        assert!(resolve_api_key(None, true).unwrap_err().to_string().contains("APP_ENV=production"));
+        assert!(resolve_api_key(Some("  ".to_string()), true).is_err());
+    }
+}
@@ -1,3 +1,9 @@
+use crate::models::PayloadEncoding;
+use crate::workers::{
+    get_webhook_signing_key, get_webhook_verification, DeliveryAlertPolicy, DeliverySettings,
+    Ed25519Key, AuthScheme, JsonFormat, ResponseMatch, RetryOn, ScheduleConfig, SinkConfig, WebhookAuth, WebhookSigning,
+    WebhookTimeouts, WebhookVerification,
+};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -21,6 +27,42 @@ pub struct Credential {
     pub webhook_headers: Option<String>,
     pub is_active: bool,
     pub is_suspended: bool,
+    pub max_messages: Option<i64>,
+    pub capture_webhook_response: bool,
+    pub ack_token: Option<String>,
+    pub schedule_start: Option<String>,
+    pub schedule_stop: Option<String>,
+    pub schedule_timezone: Option<String>,
+    pub topic_webhooks: Option<String>,
+    pub sink_config: Option<String>,
+    pub webhook_secret: Option<String>,
+    pub webhook_secret_previous: Option<String>,
+    pub extract_fields: Option<String>,
+    pub webhook_enabled: bool,
+    pub max_backoff_secs: Option<i64>,
+    pub webhook_proxy: Option<String>,
+    pub delivery_alerts: Option<String>,
+    pub external_id: Option<String>,
+    pub retry_on: Option<String>,
+    pub signing_key: Option<String>,
+    pub payload_encoding: Option<String>,
+    pub dedup_ignore_fields: Option<String>,
+    pub json_format: Option<String>,
+    pub webhook_verified: bool,
+    pub webhook_verified_at: Option<DateTime<Utc>>,
+    pub store_payload: bool,
+    pub dedup_key_path: Option<String>,
+    pub allowed_senders: Option<String>,
+    pub webhook_auth: Option<String>,
+    pub delivery_settings: Option<String>,
+    pub webhook_user_agent: Option<String>,
+    pub dedup_group: Option<String>,
+    pub webhook_timeout_secs: Option<i64>,
+    pub webhook_connect_timeout_secs: Option<i64>,
+    pub webhook_response_match: Option<String>,
+    pub retention_days: Option<i64>,
+    pub lifecycle_webhook_url: Option<String>,
+    pub registered_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -40,9 +82,11 @@ pub struct CreateCredentialRequest {
     /// Firebase Project ID
     #[schema(example = "my-project-id")]
     pub project_id: String,
-    /// Webhook URL to call when messages arrive
+    /// Webhook URL to call when messages arrive; omit to only store messages for
+    /// polling (pull-only mode)
     #[schema(example = "https://webhook.site/xxx")]
-    pub webhook_url: String,
+    #[serde(default)]
+    pub webhook_url: Option<String>,
     /// Optional custom headers for webhook requests
     #[serde(default)]
     pub webhook_headers: Option<HashMap<String, String>>,
@@ -50,6 +94,160 @@ pub struct CreateCredentialRequest {
     #[serde(default)]
     #[schema(example = json!(["notifications", "promotions"]))]
     pub topics: Vec<String>,
+    /// Maximum message logs kept for this credential (defaults to MAX_MESSAGES_PER_CREDENTIAL)
+    #[serde(default)]
+    #[schema(example = 1000)]
+    pub max_messages: Option<i64>,
+    /// Store webhook response bodies (disable for receivers returning large bodies)
+    #[serde(default = "default_true")]
+    pub capture_webhook_response: bool,
+    /// Optional listening window; the scheduler starts/stops the listener at its transitions
+    #[serde(default)]
+    pub schedule: Option<ScheduleConfig>,
+    /// Optional per-topic webhook URLs; messages from other topics go to webhook_url
+    #[serde(default)]
+    #[schema(example = json!({"promotions": "https://example.com/promo-hook"}))]
+    pub topic_webhooks: Option<HashMap<String, String>>,
+    /// Delivery sink (default: HTTP webhook)
+    #[serde(default)]
+    pub sink: Option<SinkConfig>,
+    /// Payload fields copied into each message log at ingest (name -> dotted JSON path),
+    /// filterable via list_messages
+    #[serde(default)]
+    #[schema(example = json!({"order_id": "data.order_id"}))]
+    pub extract_fields: Option<HashMap<String, String>>,
+    /// Deliver messages live; when false they are only logged (marked skipped) for later retry
+    #[serde(default = "default_true")]
+    pub webhook_enabled: bool,
+    /// Ceiling on the reconnect delay in seconds (defaults to MAX_RECONNECT_BACKOFF_SECS)
+    #[serde(default)]
+    #[schema(example = 60)]
+    pub max_backoff_secs: Option<i64>,
+    /// Proxy for this credential's outbound deliveries (overrides WEBHOOK_PROXY)
+    #[serde(default)]
+    #[schema(example = "http://proxy.internal:3128")]
+    pub webhook_proxy: Option<String>,
+    /// Delivery outcomes sent to ALERT_WEBHOOK_URL (default: failure)
+    #[serde(default)]
+    pub delivery_alerts: Option<DeliveryAlertPolicy>,
+    /// Key from your own system; the credential ID is derived from it, so creating
+    /// again with the same external_id returns the existing credential
+    #[serde(default)]
+    #[schema(example = "tenant-42/android")]
+    pub external_id: Option<String>,
+    /// Failed delivery attempts that are retried (default: all)
+    #[serde(default)]
+    pub retry_on: Option<RetryOn>,
+    /// How received payloads are stored: utf8 (default) or base64 to keep binary
+    /// payloads byte-exact for delivery and replay
+    #[serde(default)]
+    pub payload_encoding: Option<PayloadEncoding>,
+    /// Dotted payload paths left out when detecting in-memory duplicates, for fields that
+    /// change between otherwise identical messages (default: none)
+    #[serde(default)]
+    #[schema(example = json!(["data.sent_time", "fcmMessageId"]))]
+    pub dedup_ignore_fields: Option<Vec<String>>,
+    /// Format of delivered JSON bodies: raw (default, as received), minify, or canonical
+    /// (minified with sorted keys, for receivers that verify signatures over re-serialized JSON)
+    #[serde(default)]
+    pub json_format: Option<JsonFormat>,
+    /// Keep message bodies in the log; when false only metadata, extracted fields and a
+    /// SHA-256 hash of the payload are stored, and those messages can't be retried
+    #[serde(default = "default_true")]
+    pub store_payload: bool,
+    /// Dotted payload path of a sender-provided message id; messages with a value already
+    /// logged are dropped as duplicates (default: fcmMessageId, falling back to it when the
+    /// path is missing from a payload)
+    #[serde(default)]
+    #[schema(example = "data.event_id")]
+    pub dedup_key_path: Option<String>,
+    /// Sender ids (the payload's "from") whose messages are accepted; others are dropped
+    /// before they are logged or delivered (default: empty, accept all)
+    #[serde(default)]
+    #[schema(example = json!(["123456789012"]))]
+    pub allowed_senders: Option<Vec<String>>,
+    /// Authorization sent with webhook requests (bearer token or basic user/password)
+    #[serde(default)]
+    pub webhook_auth: Option<WebhookAuth>,
+    /// User-Agent of webhook requests (overrides WEBHOOK_USER_AGENT); may use header
+    /// placeholders such as {{credential_id}}
+    #[serde(default)]
+    #[schema(example = "shop-backend/{{credential_id}}")]
+    pub webhook_user_agent: Option<String>,
+    /// Credentials with the same dedup group share in-memory dedup, so a message received
+    /// by several of them is logged and delivered once (default: per-credential dedup)
+    #[serde(default)]
+    #[schema(example = "shop-devices")]
+    pub dedup_group: Option<String>,
+    /// Seconds a webhook request may take in total (defaults to WEBHOOK_TIMEOUT_SECS)
+    #[serde(default)]
+    #[schema(example = 30)]
+    pub webhook_timeout_secs: Option<i64>,
+    /// Seconds allowed to connect to the webhook receiver (defaults to
+    /// WEBHOOK_CONNECT_TIMEOUT_SECS)
+    #[serde(default)]
+    #[schema(example = 2)]
+    pub webhook_connect_timeout_secs: Option<i64>,
+    /// Condition a 2xx response body must meet for a delivery to succeed, e.g.
+    /// {"path": "$.ok", "equals": true} (default: the status code alone decides)
+    #[serde(default)]
+    pub webhook_response_match: Option<ResponseMatch>,
+    /// Days messages are kept before the periodic cleanup deletes them (defaults to
+    /// MESSAGE_RETENTION_DAYS)
+    #[serde(default)]
+    #[schema(example = 7)]
+    pub retention_days: Option<i64>,
+    /// Where this credential's lifecycle events (created, started, stopped, ...) are POSTed
+    /// (defaults to LIFECYCLE_WEBHOOK_URL)
+    #[serde(default)]
+    #[schema(example = "https://example.com/credential-events")]
+    pub lifecycle_webhook_url: Option<String>,
+}
+
+impl Default for CreateCredentialRequest {
+    /// Every optional setting as if left out of the JSON body; name, api_key, app_id and
+    /// project_id are empty and have to be filled in
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            api_key: String::new(),
+            app_id: String::new(),
+            project_id: String::new(),
+            webhook_url: None,
+            webhook_headers: None,
+            topics: Vec::new(),
+            max_messages: None,
+            capture_webhook_response: true,
+            schedule: None,
+            topic_webhooks: None,
+            sink: None,
+            extract_fields: None,
+            webhook_enabled: true,
+            max_backoff_secs: None,
+            webhook_proxy: None,
+            delivery_alerts: None,
+            external_id: None,
+            retry_on: None,
+            payload_encoding: None,
+            dedup_ignore_fields: None,
+            json_format: None,
+            store_payload: true,
+            dedup_key_path: None,
+            allowed_senders: None,
+            webhook_auth: None,
+            webhook_user_agent: None,
+            dedup_group: None,
+            webhook_timeout_secs: None,
+            webhook_connect_timeout_secs: None,
+            webhook_response_match: None,
+            retention_days: None,
+            lifecycle_webhook_url: None,
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
 }
 
 /// Request to update an existing credential
@@ -57,7 +255,7 @@ pub struct CreateCredentialRequest {
 pub struct UpdateCredentialRequest {
     /// New display name
     pub name: Option<String>,
-    /// New webhook URL
+    /// New webhook URL; an empty string removes it (pull-only mode)
     pub webhook_url: Option<String>,
     /// New custom headers
     pub webhook_headers: Option<HashMap<String, String>>,
@@ -71,6 +269,182 @@ pub struct UpdateCredentialRequest {
     pub app_id: Option<String>,
     /// Firebase Project ID (update)
     pub project_id: Option<String>,
+    /// Maximum message logs kept for this credential
+    pub max_messages: Option<i64>,
+    /// Store webhook response bodies
+    pub capture_webhook_response: Option<bool>,
+    /// Replace the per-topic webhook URLs (an empty map removes all overrides)
+    pub topic_webhooks: Option<HashMap<String, String>>,
+    /// Delivery sink ({"type": "webhook"} switches back to HTTP webhook delivery)
+    pub sink: Option<SinkConfig>,
+    /// Replace the extracted payload fields (an empty map disables extraction)
+    pub extract_fields: Option<HashMap<String, String>>,
+    /// Enable or disable live delivery (messages are still logged)
+    pub webhook_enabled: Option<bool>,
+    /// Ceiling on the reconnect delay in seconds
+    pub max_backoff_secs: Option<i64>,
+    /// Delivery proxy override (empty string removes it)
+    pub webhook_proxy: Option<String>,
+    /// Delivery outcomes sent to ALERT_WEBHOOK_URL
+    pub delivery_alerts: Option<DeliveryAlertPolicy>,
+    /// Failed delivery attempts that are retried
+    pub retry_on: Option<RetryOn>,
+    /// How received payloads are stored (applies to new messages)
+    pub payload_encoding: Option<PayloadEncoding>,
+    /// Replace the payload paths ignored by dedup (an empty list hashes whole payloads)
+    pub dedup_ignore_fields: Option<Vec<String>>,
+    /// Format of delivered JSON bodies
+    pub json_format: Option<JsonFormat>,
+    /// Keep message bodies in the log (applies to new messages)
+    pub store_payload: Option<bool>,
+    /// Payload path used as the dedup key (empty string goes back to fcmMessageId)
+    pub dedup_key_path: Option<String>,
+    /// Replace the accepted sender ids (an empty list accepts all)
+    pub allowed_senders: Option<Vec<String>>,
+    /// Replace the webhook Authorization (scheme "none" removes it)
+    pub webhook_auth: Option<WebhookAuth>,
+    /// User-Agent override (empty string goes back to WEBHOOK_USER_AGENT)
+    pub webhook_user_agent: Option<String>,
+    /// Dedup group (empty string goes back to per-credential dedup)
+    pub dedup_group: Option<String>,
+    /// Webhook request timeout in seconds
+    pub webhook_timeout_secs: Option<i64>,
+    /// Webhook connect timeout in seconds
+    pub webhook_connect_timeout_secs: Option<i64>,
+    /// Replace the response body condition (an empty path removes it)
+    pub webhook_response_match: Option<ResponseMatch>,
+    /// Days messages are kept
+    pub retention_days: Option<i64>,
+    /// Lifecycle webhook (empty string goes back to LIFECYCLE_WEBHOOK_URL)
+    pub lifecycle_webhook_url: Option<String>,
+}
+
+impl UpdateCredentialRequest {
+    /// Whether anything besides the topic list is being changed
+    pub fn changes_settings(&self) -> bool {
+        self.name.is_some()
+            || self.webhook_url.is_some()
+            || self.webhook_headers.is_some()
+            || self.is_active.is_some()
+            || self.api_key.is_some()
+            || self.app_id.is_some()
+            || self.project_id.is_some()
+            || self.max_messages.is_some()
+            || self.capture_webhook_response.is_some()
+            || self.topic_webhooks.is_some()
+            || self.sink.is_some()
+            || self.extract_fields.is_some()
+            || self.webhook_enabled.is_some()
+            || self.max_backoff_secs.is_some()
+            || self.webhook_proxy.is_some()
+            || self.delivery_alerts.is_some()
+            || self.retry_on.is_some()
+            || self.payload_encoding.is_some()
+            || self.dedup_ignore_fields.is_some()
+            || self.json_format.is_some()
+            || self.store_payload.is_some()
+            || self.dedup_key_path.is_some()
+            || self.allowed_senders.is_some()
+            || self.webhook_auth.is_some()
+            || self.webhook_user_agent.is_some()
+            || self.dedup_group.is_some()
+            || self.webhook_timeout_secs.is_some()
+            || self.webhook_connect_timeout_secs.is_some()
+            || self.webhook_response_match.is_some()
+            || self.retention_days.is_some()
+            || self.lifecycle_webhook_url.is_some()
+    }
+
+    /// Column values to store for this request, with structured settings serialized.
+    /// webhook_headers is taken as given, so normalize the headers before calling this.
+    pub fn to_update(&self) -> CredentialUpdate {
+        fn json<T: Serialize>(value: &T) -> String {
+            serde_json::to_string(value).unwrap_or_default()
+        }
+
+        CredentialUpdate {
+            name: self.name.clone(),
+            webhook_url: self.webhook_url.clone(),
+            webhook_headers: self.webhook_headers.as_ref().map(json),
+            is_active: self.is_active,
+            api_key: self.api_key.clone(),
+            app_id: self.app_id.clone(),
+            project_id: self.project_id.clone(),
+            max_messages: self.max_messages,
+            capture_webhook_response: self.capture_webhook_response,
+            topic_webhooks: self.topic_webhooks.as_ref().map(json),
+            sink_config: self.sink.as_ref().map(json),
+            extract_fields: self.extract_fields.as_ref().map(json),
+            webhook_enabled: self.webhook_enabled,
+            max_backoff_secs: self.max_backoff_secs,
+            webhook_proxy: self.webhook_proxy.clone(),
+            delivery_alerts: self.delivery_alerts.as_ref().map(json),
+            retry_on: self.retry_on.as_ref().map(json),
+            payload_encoding: self.payload_encoding.map(|e| e.as_str().to_string()),
+            dedup_ignore_fields: self.dedup_ignore_fields.as_ref().map(json),
+            json_format: self.json_format.map(|f| f.as_str().to_string()),
+            store_payload: self.store_payload,
+            dedup_key_path: self.dedup_key_path.clone(),
+            allowed_senders: self.allowed_senders.as_ref().map(json),
+            // Scheme "none" is stored as an empty string, which removes it
+            webhook_auth: self.webhook_auth.as_ref().map(|a| match a.scheme {
+                AuthScheme::None => String::new(),
+                _ => json(a),
+            }),
+            webhook_user_agent: self.webhook_user_agent.clone(),
+            dedup_group: self.dedup_group.as_deref().map(|g| g.trim().to_string()),
+            webhook_timeout_secs: self.webhook_timeout_secs,
+            webhook_connect_timeout_secs: self.webhook_connect_timeout_secs,
+            // An empty path is stored as an empty string, which removes it
+            webhook_response_match: self.webhook_response_match.as_ref().map(|m| {
+                if m.path.trim().is_empty() {
+                    String::new()
+                } else {
+                    json(m)
+                }
+            }),
+            retention_days: self.retention_days,
+            lifecycle_webhook_url: self.lifecycle_webhook_url.clone(),
+        }
+    }
+}
+
+/// Credential columns changed by an update, as stored (structured settings serialized to
+/// JSON). None leaves a column as it is; for the optional overrides an empty string clears
+/// it. Build one with `UpdateCredentialRequest::to_update` or from `Default`.
+#[derive(Debug, Clone, Default)]
+pub struct CredentialUpdate {
+    pub name: Option<String>,
+    pub webhook_url: Option<String>,
+    pub webhook_headers: Option<String>,
+    pub is_active: Option<bool>,
+    pub api_key: Option<String>,
+    pub app_id: Option<String>,
+    pub project_id: Option<String>,
+    pub max_messages: Option<i64>,
+    pub capture_webhook_response: Option<bool>,
+    pub topic_webhooks: Option<String>,
+    pub sink_config: Option<String>,
+    pub extract_fields: Option<String>,
+    pub webhook_enabled: Option<bool>,
+    pub max_backoff_secs: Option<i64>,
+    pub webhook_proxy: Option<String>,
+    pub delivery_alerts: Option<String>,
+    pub retry_on: Option<String>,
+    pub payload_encoding: Option<String>,
+    pub dedup_ignore_fields: Option<String>,
+    pub json_format: Option<String>,
+    pub store_payload: Option<bool>,
+    pub dedup_key_path: Option<String>,
+    pub allowed_senders: Option<String>,
+    pub webhook_auth: Option<String>,
+    pub webhook_user_agent: Option<String>,
+    pub dedup_group: Option<String>,
+    pub webhook_timeout_secs: Option<i64>,
+    pub webhook_connect_timeout_secs: Option<i64>,
+    pub webhook_response_match: Option<String>,
+    pub retention_days: Option<i64>,
+    pub lifecycle_webhook_url: Option<String>,
 }
 
 /// Credential response with status
@@ -88,10 +462,19 @@ pub struct CredentialResponse {
     pub project_id: String,
     /// FCM token (generated after registration)
     pub fcm_token: Option<String>,
+    /// GCM token from registration, masked to its last 4 characters unless requested
+    /// with reveal=true
+    pub gcm_token: Option<String>,
     /// Android ID (generated after registration)
     pub android_id: Option<i64>,
-    /// Webhook URL
-    pub webhook_url: String,
+    /// Whether FCM registration has completed (token and keys stored); false while
+    /// registration is pending
+    pub is_registered: bool,
+    /// When FCM registration last completed (null if pending or registered before this
+    /// was tracked)
+    pub registered_at: Option<DateTime<Utc>>,
+    /// Webhook URL (absent in pull-only mode)
+    pub webhook_url: Option<String>,
     /// Custom webhook headers
     pub webhook_headers: Option<HashMap<String, String>>,
     /// Whether credential is active
@@ -100,17 +483,114 @@ pub struct CredentialResponse {
     pub is_suspended: bool,
     /// Whether FCM listener is currently running
     pub is_listening: bool,
+    /// Per-credential message log cap (null = global default)
+    pub max_messages: Option<i64>,
+    /// Whether webhook response bodies are stored
+    pub capture_webhook_response: bool,
+    /// Token for POST /api/messages/{id}/ack (X-Ack-Token header)
+    pub ack_token: Option<String>,
+    /// Listening schedule
+    pub schedule: Option<ScheduleConfig>,
+    /// Per-topic webhook URL overrides
+    pub topic_webhooks: Option<HashMap<String, String>>,
+    /// Delivery sink
+    pub sink: SinkConfig,
+    /// Whether webhook bodies are HMAC-signed (X-Webhook-Signature)
+    pub webhook_signing: bool,
+    /// Whether a secret rotation is in progress (previous secret still signed with)
+    pub webhook_secret_rotating: bool,
+    /// Payload fields extracted into message logs (name -> dotted JSON path)
+    pub extract_fields: Option<HashMap<String, String>>,
+    /// Whether messages are delivered live (false = logged only)
+    pub webhook_enabled: bool,
+    /// Per-credential reconnect delay ceiling in seconds (null = global default)
+    pub max_backoff_secs: Option<i64>,
+    /// Delivery proxy override (null = WEBHOOK_PROXY / HTTPS_PROXY)
+    pub webhook_proxy: Option<String>,
+    /// Delivery outcomes sent to the operator alert webhook
+    pub delivery_alerts: DeliveryAlertPolicy,
+    /// Caller-supplied key the ID was derived from
+    pub external_id: Option<String>,
+    /// Failed delivery attempts that are retried
+    pub retry_on: RetryOn,
+    /// How received payloads are stored
+    pub payload_encoding: PayloadEncoding,
+    /// Payload paths ignored when detecting duplicates
+    pub dedup_ignore_fields: Vec<String>,
+    /// Format of delivered JSON bodies
+    pub json_format: JsonFormat,
+    /// Whether the receiver at webhook_url passed the verification handshake
+    pub webhook_verified: bool,
+    /// When webhook_url was last verified
+    pub webhook_verified_at: Option<DateTime<Utc>>,
+    /// Whether message bodies are kept (false = metadata and a payload hash only)
+    pub store_payload: bool,
+    /// Payload path used as the dedup key (null = fcmMessageId)
+    pub dedup_key_path: Option<String>,
+    /// Sender ids whose messages are accepted (empty = all)
+    pub allowed_senders: Vec<String>,
+    /// Webhook Authorization, with the token and password masked
+    pub webhook_auth: Option<WebhookAuth>,
+    /// User-Agent override (null = WEBHOOK_USER_AGENT)
+    pub webhook_user_agent: Option<String>,
+    /// Group sharing in-memory dedup (null = per-credential)
+    pub dedup_group: Option<String>,
+    /// Webhook request timeout in seconds (null = WEBHOOK_TIMEOUT_SECS)
+    pub webhook_timeout_secs: Option<i64>,
+    /// Webhook connect timeout in seconds (null = WEBHOOK_CONNECT_TIMEOUT_SECS)
+    pub webhook_connect_timeout_secs: Option<i64>,
+    /// Condition on 2xx response bodies (null = status code only)
+    pub webhook_response_match: Option<ResponseMatch>,
+    /// Days messages are kept (null = MESSAGE_RETENTION_DAYS)
+    pub retention_days: Option<i64>,
+    /// Lifecycle webhook (null = LIFECYCLE_WEBHOOK_URL)
+    pub lifecycle_webhook_url: Option<String>,
+    /// Key id of the Ed25519 key signing webhook bodies (null = no asymmetric signing)
+    pub signing_key_id: Option<String>,
+    /// Whether the credential has its own Ed25519 key (otherwise WEBHOOK_SIGNING_KEY is used)
+    pub own_signing_key: bool,
+    /// Messages currently logged (filled in by the credential list)
+    #[serde(default)]
+    pub message_count: Option<i64>,
+    /// When the latest logged message arrived (filled in by the credential list)
+    #[serde(default)]
+    pub last_message_at: Option<DateTime<Utc>>,
     /// Creation timestamp
     pub created_at: DateTime<Utc>,
     /// Last update timestamp
     pub updated_at: DateTime<Utc>,
 }
 
+/// Credentials by why they would or wouldn't be started at boot. Each credential is counted
+/// once: an inactive credential counts as inactive even if it is also suspended.
+#[derive(Debug, Clone, Default, Serialize, ToSchema, sqlx::FromRow)]
+pub struct CredentialStateCounts {
+    pub total: i64,
+    /// Active and not suspended (started at boot)
+    pub runnable: i64,
+    /// Deactivated (is_active = false)
+    pub inactive: i64,
+    /// Active but suspended
+    pub suspended: i64,
+}
+
+/// Namespace for credential IDs derived from an external_id
+const EXTERNAL_ID_NAMESPACE: Uuid = Uuid::from_u128(0x6f1c_2a4e_8b3d_5c70_9e21_4d8a_b6f0_c3e5);
+
+/// Deterministic credential ID (UUIDv5) for an external_id
+pub fn credential_id_for_external(external_id: &str) -> String {
+    Uuid::new_v5(&EXTERNAL_ID_NAMESPACE, external_id.as_bytes()).to_string()
+}
+
 impl Credential {
     pub fn new(req: CreateCredentialRequest) -> Self {
         let now = Utc::now();
         Self {
-            id: Uuid::new_v4().to_string(),
+            id: req
+                .external_id
+                .as_deref()
+                .map(credential_id_for_external)
+                .unwrap_or_else(|| Uuid::new_v4().to_string()),
             name: req.name,
             api_key: req.api_key,
             app_id: req.app_id,
@@ -121,12 +601,69 @@ impl Credential {
             security_token: None,
             private_key_base64: None,
             auth_secret_base64: None,
-            webhook_url: req.webhook_url,
+            webhook_url: req.webhook_url.unwrap_or_default(),
             webhook_headers: req
                 .webhook_headers
                 .map(|h| serde_json::to_string(&h).unwrap_or_default()),
             is_active: true,
             is_suspended: false,
+            max_messages: req.max_messages,
+            capture_webhook_response: req.capture_webhook_response,
+            ack_token: Some(crate::middleware::generate_api_key()),
+            schedule_start: req.schedule.as_ref().map(|s| s.start.clone()),
+            schedule_stop: req.schedule.as_ref().map(|s| s.stop.clone()),
+            schedule_timezone: req.schedule.as_ref().and_then(|s| s.timezone.clone()),
+            topic_webhooks: req
+                .topic_webhooks
+                .map(|t| serde_json::to_string(&t).unwrap_or_default()),
+            sink_config: req
+                .sink
+                .map(|s| serde_json::to_string(&s).unwrap_or_default()),
+            webhook_secret: None,
+            webhook_secret_previous: None,
+            extract_fields: req
+                .extract_fields
+                .map(|f| serde_json::to_string(&f).unwrap_or_default()),
+            webhook_enabled: req.webhook_enabled,
+            max_backoff_secs: req.max_backoff_secs,
+            webhook_proxy: req.webhook_proxy.filter(|p| !p.is_empty()),
+            delivery_alerts: req
+                .delivery_alerts
+                .map(|p| serde_json::to_string(&p).unwrap_or_default()),
+            external_id: req.external_id,
+            retry_on: req
+                .retry_on
+                .map(|r| serde_json::to_string(&r).unwrap_or_default()),
+            signing_key: None,
+            payload_encoding: req.payload_encoding.map(|e| e.as_str().to_string()),
+            dedup_ignore_fields: req
+                .dedup_ignore_fields
+                .map(|f| serde_json::to_string(&f).unwrap_or_default()),
+            json_format: req.json_format.map(|f| f.as_str().to_string()),
+            webhook_verified: false,
+            webhook_verified_at: None,
+            store_payload: req.store_payload,
+            dedup_key_path: req.dedup_key_path.filter(|p| !p.is_empty()),
+            allowed_senders: req
+                .allowed_senders
+                .filter(|s| !s.is_empty())
+                .map(|s| serde_json::to_string(&s).unwrap_or_default()),
+            webhook_auth: req
+                .webhook_auth
+                .filter(|a| a.scheme != AuthScheme::None)
+                .map(|a| serde_json::to_string(&a).unwrap_or_default()),
+            delivery_settings: None,
+            webhook_user_agent: req.webhook_user_agent.filter(|u| !u.is_empty()),
+            dedup_group: req.dedup_group.filter(|g| !g.is_empty()),
+            webhook_timeout_secs: req.webhook_timeout_secs,
+            webhook_connect_timeout_secs: req.webhook_connect_timeout_secs,
+            webhook_response_match: req
+                .webhook_response_match
+                .filter(|m| !m.path.trim().is_empty())
+                .map(|m| serde_json::to_string(&m).unwrap_or_default()),
+            retention_days: req.retention_days,
+            lifecycle_webhook_url: req.lifecycle_webhook_url.filter(|u| !u.is_empty()),
+            registered_at: None,
             created_at: now,
             updated_at: now,
         }
@@ -138,6 +675,129 @@ impl Credential {
             .and_then(|h| serde_json::from_str(h).ok())
     }
 
+    pub fn get_topic_webhooks(&self) -> Option<HashMap<String, String>> {
+        self.topic_webhooks
+            .as_ref()
+            .and_then(|t| serde_json::from_str(t).ok())
+    }
+
+    pub fn get_extract_fields(&self) -> Option<HashMap<String, String>> {
+        self.extract_fields
+            .as_ref()
+            .and_then(|f| serde_json::from_str(f).ok())
+    }
+
+    /// Payload paths left out of the dedup hash (empty = whole payload)
+    pub fn dedup_ignore_fields(&self) -> Vec<String> {
+        self.dedup_ignore_fields
+            .as_ref()
+            .and_then(|f| serde_json::from_str(f).ok())
+            .unwrap_or_default()
+    }
+
+    /// Sender ids whose messages are accepted (empty = all)
+    pub fn allowed_senders(&self) -> Vec<String> {
+        self.allowed_senders
+            .as_ref()
+            .and_then(|s| serde_json::from_str(s).ok())
+            .unwrap_or_default()
+    }
+
+    /// Authorization added to webhook requests, if configured
+    pub fn webhook_auth(&self) -> Option<WebhookAuth> {
+        self.webhook_auth
+            .as_ref()
+            .and_then(|a| serde_json::from_str(a).ok())
+    }
+
+    /// Condition 2xx webhook response bodies must meet, if configured
+    pub fn webhook_response_match(&self) -> Option<ResponseMatch> {
+        self.webhook_response_match
+            .as_ref()
+            .and_then(|m| serde_json::from_str(m).ok())
+    }
+
+    /// Delivery concurrency and queue settings, defaulting to the environment
+    pub fn delivery_settings(&self) -> DeliverySettings {
+        self.delivery_settings
+            .as_ref()
+            .and_then(|s| serde_json::from_str(s).ok())
+            .unwrap_or_else(DeliverySettings::from_env)
+    }
+
+    /// Format of delivered JSON bodies, defaulting to the payload as received
+    pub fn json_format(&self) -> JsonFormat {
+        self.json_format
+            .as_deref()
+            .map(JsonFormat::parse)
+            .unwrap_or_default()
+    }
+
+    /// Whether live delivery is held back because WEBHOOK_VERIFICATION=require and
+    /// webhook_url hasn't passed the verification handshake (Pub/Sub sinks aren't affected)
+    pub fn webhook_blocked_unverified(&self) -> bool {
+        get_webhook_verification() == WebhookVerification::Require
+            && !self.webhook_verified
+            && self.sink() == SinkConfig::Webhook
+            && self.has_webhook()
+    }
+
+    /// Whether FCM registration has completed: the token and all keys the listener
+    /// needs are stored
+    pub fn is_registered(&self) -> bool {
+        self.fcm_token.is_some()
+            && self.android_id.is_some()
+            && self.security_token.is_some()
+            && self.private_key_base64.is_some()
+            && self.auth_secret_base64.is_some()
+    }
+
+    /// Whether a default webhook_url is set; without one the credential is pull-only and
+    /// messages (other than those of topics with their own webhook) are only stored
+    pub fn has_webhook(&self) -> bool {
+        !self.webhook_url.is_empty()
+    }
+
+    /// Delivery sink, defaulting to HTTP webhook
+    pub fn sink(&self) -> SinkConfig {
+        self.sink_config
+            .as_ref()
+            .and_then(|s| serde_json::from_str(s).ok())
+            .unwrap_or_default()
+    }
+
+    /// Active webhook signing secrets, current first (previous only while rotating)
+    pub fn webhook_secrets(&self) -> Vec<String> {
+        self.webhook_secret
+            .iter()
+            .chain(self.webhook_secret_previous.iter())
+            .cloned()
+            .collect()
+    }
+
+    /// Ed25519 key signing this credential's webhooks: its own, else the server-wide one
+    pub fn signing_key(&self) -> Option<Ed25519Key> {
+        self.signing_key
+            .as_deref()
+            .and_then(|k| Ed25519Key::from_base64(k).ok())
+            .or_else(get_webhook_signing_key)
+    }
+
+    /// Everything needed to sign this credential's webhook bodies
+    pub fn webhook_signing(&self) -> WebhookSigning {
+        WebhookSigning {
+            secrets: self.webhook_secrets(),
+            key: self.signing_key(),
+        }
+    }
+
+    /// Webhook URL for a message from `topic`, falling back to the default webhook_url
+    pub fn webhook_url_for_topic(&self, topic: Option<&str>) -> String {
+        topic
+            .and_then(|t| self.get_topic_webhooks()?.remove(t))
+            .unwrap_or_else(|| self.webhook_url.clone())
+    }
+
     pub fn to_response(&self, is_listening: bool) -> CredentialResponse {
         CredentialResponse {
             id: self.id.clone(),
@@ -146,20 +806,141 @@ impl Credential {
             app_id: self.app_id.clone(),
             project_id: self.project_id.clone(),
             fcm_token: self.fcm_token.clone(),
+            gcm_token: self.gcm_token.as_deref().map(mask_token),
             android_id: self.android_id,
-            webhook_url: self.webhook_url.clone(),
+            is_registered: self.is_registered(),
+            registered_at: self.registered_at,
+            webhook_url: self.has_webhook().then(|| self.webhook_url.clone()),
             webhook_headers: self.get_webhook_headers(),
             is_active: self.is_active,
             is_suspended: self.is_suspended,
             is_listening,
+            max_messages: self.max_messages,
+            capture_webhook_response: self.capture_webhook_response,
+            ack_token: self.ack_token.clone(),
+            schedule: self.schedule(),
+            topic_webhooks: self.get_topic_webhooks(),
+            sink: self.sink(),
+            webhook_signing: self.webhook_secret.is_some(),
+            webhook_secret_rotating: self.webhook_secret_previous.is_some(),
+            extract_fields: self.get_extract_fields(),
+            webhook_enabled: self.webhook_enabled,
+            max_backoff_secs: self.max_backoff_secs,
+            webhook_proxy: self.proxy_override().map(|p| p.to_string()),
+            delivery_alerts: self.delivery_alerts(),
+            external_id: self.external_id.clone(),
+            retry_on: self.retry_on(),
+            payload_encoding: self.payload_encoding(),
+            dedup_ignore_fields: self.dedup_ignore_fields(),
+            json_format: self.json_format(),
+            webhook_verified: self.webhook_verified,
+            webhook_verified_at: self.webhook_verified_at,
+            store_payload: self.store_payload,
+            dedup_key_path: self.dedup_key_path.clone(),
+            allowed_senders: self.allowed_senders(),
+            webhook_auth: self.webhook_auth().map(|a| a.masked()),
+            webhook_user_agent: self.webhook_user_agent.clone(),
+            dedup_group: self.dedup_group.clone(),
+            webhook_timeout_secs: self.webhook_timeout_secs,
+            webhook_connect_timeout_secs: self.webhook_connect_timeout_secs,
+            webhook_response_match: self.webhook_response_match(),
+            retention_days: self.retention_days,
+            lifecycle_webhook_url: self.lifecycle_webhook_url.clone(),
+            signing_key_id: self.signing_key().map(|k| k.key_id().to_string()),
+            own_signing_key: self.signing_key.is_some(),
+            message_count: None,
+            last_message_at: None,
             created_at: self.created_at,
             updated_at: self.updated_at,
         }
     }
 
+    /// Listening schedule, if both start and stop are set
+    pub fn schedule(&self) -> Option<ScheduleConfig> {
+        Some(ScheduleConfig {
+            start: self.schedule_start.clone()?,
+            stop: self.schedule_stop.clone()?,
+            timezone: self.schedule_timezone.clone(),
+        })
+    }
+
+    /// Message log cap for this credential, falling back to the global default
+    pub fn effective_max_messages(&self) -> i64 {
+        self.max_messages
+            .unwrap_or_else(crate::workers::get_max_messages_per_credential)
+    }
+
+    /// Days this credential's messages are kept, falling back to MESSAGE_RETENTION_DAYS;
+    /// None keeps them until the max_messages cap pushes them out
+    pub fn effective_retention_days(&self) -> Option<i64> {
+        self.retention_days
+            .filter(|d| *d > 0)
+            .or_else(crate::workers::get_message_retention_days)
+    }
+
+    /// Delivery outcomes that raise operator alerts, defaulting to failures only
+    pub fn delivery_alerts(&self) -> DeliveryAlertPolicy {
+        self.delivery_alerts
+            .as_ref()
+            .and_then(|p| serde_json::from_str(p).ok())
+            .unwrap_or_default()
+    }
+
+    /// Webhook retry policy, defaulting to retrying every failure
+    pub fn retry_on(&self) -> RetryOn {
+        self.retry_on
+            .as_ref()
+            .and_then(|r| serde_json::from_str(r).ok())
+            .unwrap_or_default()
+    }
+
+    /// How received payloads are stored, defaulting to UTF-8 text
+    pub fn payload_encoding(&self) -> PayloadEncoding {
+        self.payload_encoding
+            .as_deref()
+            .map(PayloadEncoding::parse)
+            .unwrap_or_default()
+    }
+
+    /// Per-credential delivery proxy, if one is set
+    pub fn proxy_override(&self) -> Option<&str> {
+        self.webhook_proxy.as_deref().filter(|p| !p.is_empty())
+    }
+
+    /// Reconnect delay ceiling for this credential, falling back to the global default
+    pub fn effective_max_backoff(&self) -> std::time::Duration {
+        self.max_backoff_secs
+            .filter(|s| *s > 0)
+            .map(|s| std::time::Duration::from_secs(s as u64))
+            .unwrap_or_else(crate::workers::get_max_reconnect_backoff)
+    }
+
+    /// Webhook request and connect timeouts for this credential, falling back to
+    /// WEBHOOK_TIMEOUT_SECS and WEBHOOK_CONNECT_TIMEOUT_SECS
+    pub fn webhook_timeouts(&self) -> WebhookTimeouts {
+        let secs = |s: Option<i64>| {
+            s.filter(|s| *s > 0)
+                .map(|s| std::time::Duration::from_secs(s as u64))
+        };
+        let defaults = WebhookTimeouts::from_env();
+        WebhookTimeouts {
+            request: secs(self.webhook_timeout_secs).unwrap_or(defaults.request),
+            connect: secs(self.webhook_connect_timeout_secs).unwrap_or(defaults.connect),
+        }
+    }
+
     /// Check if worker can be started (active and not suspended)
     #[allow(dead_code)]
     pub fn can_start(&self) -> bool {
         self.is_active && !self.is_suspended
     }
 }
+
+/// Token shown as `****` plus its last 4 characters (only `****` for short tokens)
+pub fn mask_token(token: &str) -> String {
+    let chars: Vec<char> = token.chars().collect();
+    if chars.len() <= 8 {
+        return "****".to_string();
+    }
+    format!("****{}", chars[chars.len() - 4..].iter().collect::<String>())
+}
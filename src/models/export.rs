@@ -0,0 +1,344 @@
+use crate::models::{credential_id_for_external, Credential, PayloadEncoding};
+use crate::workers::{
+    AuthScheme, DeliveryAlertPolicy, DeliverySettings, JsonFormat, ResponseMatch, RetryOn,
+    ScheduleConfig, SinkConfig, WebhookAuth,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Current version of the credential export format
+pub const EXPORT_SCHEMA_VERSION: u32 = 1;
+
+/// Portable credential configuration for moving between servers
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CredentialExport {
+    /// Export format version
+    #[schema(example = 1)]
+    pub schema_version: u32,
+    /// When the export was produced
+    pub exported_at: DateTime<Utc>,
+    /// Credential configuration
+    pub credential: ExportedCredential,
+    /// FCM device registration (only present when exported with include_secrets=true)
+    #[serde(default)]
+    pub registration: Option<ExportedRegistration>,
+}
+
+/// Credential settings included in an export
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ExportedCredential {
+    pub name: String,
+    pub api_key: String,
+    pub app_id: String,
+    pub project_id: String,
+    #[serde(default)]
+    pub webhook_url: Option<String>,
+    #[serde(default)]
+    pub webhook_headers: Option<HashMap<String, String>>,
+    #[serde(default)]
+    pub topics: Vec<String>,
+    #[serde(default)]
+    pub max_messages: Option<i64>,
+    #[serde(default = "default_true")]
+    pub capture_webhook_response: bool,
+    #[serde(default)]
+    pub schedule: Option<ScheduleConfig>,
+    #[serde(default)]
+    pub topic_webhooks: Option<HashMap<String, String>>,
+    #[serde(default)]
+    pub sink: SinkConfig,
+    /// Webhook signing secret (only present when exported with include_secrets=true)
+    #[serde(default)]
+    pub webhook_secret: Option<String>,
+    #[serde(default)]
+    pub extract_fields: Option<HashMap<String, String>>,
+    #[serde(default = "default_true")]
+    pub webhook_enabled: bool,
+    #[serde(default)]
+    pub max_backoff_secs: Option<i64>,
+    #[serde(default)]
+    pub webhook_proxy: Option<String>,
+    #[serde(default)]
+    pub delivery_alerts: DeliveryAlertPolicy,
+    #[serde(default)]
+    pub external_id: Option<String>,
+    #[serde(default)]
+    pub retry_on: RetryOn,
+    /// Ed25519 signing key seed (only present when exported with include_secrets=true)
+    #[serde(default)]
+    pub signing_key: Option<String>,
+    #[serde(default)]
+    pub payload_encoding: PayloadEncoding,
+    #[serde(default)]
+    pub dedup_ignore_fields: Vec<String>,
+    #[serde(default)]
+    pub json_format: JsonFormat,
+    #[serde(default = "default_true")]
+    pub store_payload: bool,
+    #[serde(default)]
+    pub dedup_key_path: Option<String>,
+    #[serde(default)]
+    pub allowed_senders: Vec<String>,
+    /// Webhook Authorization (only present when exported with include_secrets=true)
+    #[serde(default)]
+    pub webhook_auth: Option<WebhookAuth>,
+    /// Delivery concurrency and queue settings, if set for the credential
+    #[serde(default)]
+    pub delivery_settings: Option<DeliverySettings>,
+    #[serde(default)]
+    pub webhook_user_agent: Option<String>,
+    #[serde(default)]
+    pub dedup_group: Option<String>,
+    #[serde(default)]
+    pub webhook_timeout_secs: Option<i64>,
+    #[serde(default)]
+    pub webhook_connect_timeout_secs: Option<i64>,
+    #[serde(default)]
+    pub webhook_response_match: Option<ResponseMatch>,
+    #[serde(default)]
+    pub retention_days: Option<i64>,
+    #[serde(default)]
+    pub lifecycle_webhook_url: Option<String>,
+}
+
+/// FCM device registration, allowing the same device to be reused after import
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ExportedRegistration {
+    pub fcm_token: String,
+    pub gcm_token: Option<String>,
+    pub android_id: i64,
+    pub security_token: i64,
+    pub private_key_base64: String,
+    pub auth_secret_base64: String,
+    #[serde(default)]
+    pub registered_at: Option<DateTime<Utc>>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl CredentialExport {
+    pub fn new(credential: &Credential, topics: Vec<String>, include_secrets: bool) -> Self {
+        let registration = if include_secrets {
+            ExportedRegistration::from_credential(credential)
+        } else {
+            None
+        };
+
+        Self {
+            schema_version: EXPORT_SCHEMA_VERSION,
+            exported_at: Utc::now(),
+            credential: ExportedCredential {
+                name: credential.name.clone(),
+                api_key: credential.api_key.clone(),
+                app_id: credential.app_id.clone(),
+                project_id: credential.project_id.clone(),
+                webhook_url: credential
+                    .has_webhook()
+                    .then(|| credential.webhook_url.clone()),
+                webhook_headers: credential.get_webhook_headers(),
+                topics,
+                max_messages: credential.max_messages,
+                capture_webhook_response: credential.capture_webhook_response,
+                schedule: credential.schedule(),
+                topic_webhooks: credential.get_topic_webhooks(),
+                sink: credential.sink(),
+                webhook_secret: if include_secrets {
+                    credential.webhook_secret.clone()
+                } else {
+                    None
+                },
+                extract_fields: credential.get_extract_fields(),
+                webhook_enabled: credential.webhook_enabled,
+                max_backoff_secs: credential.max_backoff_secs,
+                webhook_proxy: credential.proxy_override().map(|p| p.to_string()),
+                delivery_alerts: credential.delivery_alerts(),
+                external_id: credential.external_id.clone(),
+                retry_on: credential.retry_on(),
+                payload_encoding: credential.payload_encoding(),
+                dedup_ignore_fields: credential.dedup_ignore_fields(),
+                json_format: credential.json_format(),
+                store_payload: credential.store_payload,
+                dedup_key_path: credential.dedup_key_path.clone(),
+                allowed_senders: credential.allowed_senders(),
+                webhook_auth: if include_secrets {
+                    credential.webhook_auth()
+                } else {
+                    None
+                },
+                delivery_settings: credential
+                    .delivery_settings
+                    .is_some()
+                    .then(|| credential.delivery_settings()),
+                webhook_user_agent: credential.webhook_user_agent.clone(),
+                dedup_group: credential.dedup_group.clone(),
+                webhook_timeout_secs: credential.webhook_timeout_secs,
+                webhook_connect_timeout_secs: credential.webhook_connect_timeout_secs,
+                webhook_response_match: credential.webhook_response_match(),
+                retention_days: credential.retention_days,
+                lifecycle_webhook_url: credential.lifecycle_webhook_url.clone(),
+                signing_key: if include_secrets {
+                    credential.signing_key.clone()
+                } else {
+                    None
+                },
+            },
+            registration,
+        }
+    }
+
+    /// Build a new credential from this export (fresh ID, or the one derived from its external_id)
+    pub fn to_credential(&self) -> Credential {
+        let now = Utc::now();
+        let cred = &self.credential;
+        let reg = self.registration.as_ref();
+
+        Credential {
+            id: cred
+                .external_id
+                .as_deref()
+                .map(credential_id_for_external)
+                .unwrap_or_else(|| Uuid::new_v4().to_string()),
+            name: cred.name.clone(),
+            api_key: cred.api_key.clone(),
+            app_id: cred.app_id.clone(),
+            project_id: cred.project_id.clone(),
+            fcm_token: reg.map(|r| r.fcm_token.clone()),
+            gcm_token: reg.and_then(|r| r.gcm_token.clone()),
+            android_id: reg.map(|r| r.android_id),
+            security_token: reg.map(|r| r.security_token),
+            private_key_base64: reg.map(|r| r.private_key_base64.clone()),
+            auth_secret_base64: reg.map(|r| r.auth_secret_base64.clone()),
+            webhook_url: cred.webhook_url.clone().unwrap_or_default(),
+            webhook_headers: cred
+                .webhook_headers
+                .as_ref()
+                .map(|h| serde_json::to_string(h).unwrap_or_default()),
+            is_active: true,
+            is_suspended: false,
+            max_messages: cred.max_messages,
+            capture_webhook_response: cred.capture_webhook_response,
+            ack_token: Some(crate::middleware::generate_api_key()),
+            schedule_start: cred.schedule.as_ref().map(|s| s.start.clone()),
+            schedule_stop: cred.schedule.as_ref().map(|s| s.stop.clone()),
+            schedule_timezone: cred.schedule.as_ref().and_then(|s| s.timezone.clone()),
+            topic_webhooks: cred
+                .topic_webhooks
+                .as_ref()
+                .map(|t| serde_json::to_string(t).unwrap_or_default()),
+            sink_config: Some(serde_json::to_string(&cred.sink).unwrap_or_default()),
+            webhook_secret: cred.webhook_secret.clone(),
+            webhook_secret_previous: None,
+            extract_fields: cred
+                .extract_fields
+                .as_ref()
+                .map(|f| serde_json::to_string(f).unwrap_or_default()),
+            webhook_enabled: cred.webhook_enabled,
+            max_backoff_secs: cred.max_backoff_secs,
+            webhook_proxy: cred.webhook_proxy.clone(),
+            delivery_alerts: Some(serde_json::to_string(&cred.delivery_alerts).unwrap_or_default()),
+            external_id: cred.external_id.clone(),
+            retry_on: Some(serde_json::to_string(&cred.retry_on).unwrap_or_default()),
+            signing_key: cred.signing_key.clone(),
+            payload_encoding: Some(cred.payload_encoding.as_str().to_string()),
+            dedup_ignore_fields: (!cred.dedup_ignore_fields.is_empty())
+                .then(|| serde_json::to_string(&cred.dedup_ignore_fields).unwrap_or_default()),
+            json_format: Some(cred.json_format.as_str().to_string()),
+            // The receiver is verified again on the importing server
+            webhook_verified: false,
+            webhook_verified_at: None,
+            store_payload: cred.store_payload,
+            dedup_key_path: cred.dedup_key_path.clone(),
+            allowed_senders: (!cred.allowed_senders.is_empty())
+                .then(|| serde_json::to_string(&cred.allowed_senders).unwrap_or_default()),
+            webhook_auth: cred
+                .webhook_auth
+                .as_ref()
+                .filter(|a| a.scheme != AuthScheme::None)
+                .map(|a| serde_json::to_string(a).unwrap_or_default()),
+            delivery_settings: cred
+                .delivery_settings
+                .map(|s| serde_json::to_string(&s).unwrap_or_default()),
+            webhook_user_agent: cred.webhook_user_agent.clone().filter(|u| !u.is_empty()),
+            dedup_group: cred.dedup_group.clone().filter(|g| !g.is_empty()),
+            webhook_timeout_secs: cred.webhook_timeout_secs,
+            webhook_connect_timeout_secs: cred.webhook_connect_timeout_secs,
+            webhook_response_match: cred
+                .webhook_response_match
+                .as_ref()
+                .filter(|m| !m.path.trim().is_empty())
+                .map(|m| serde_json::to_string(m).unwrap_or_default()),
+            retention_days: cred.retention_days,
+            lifecycle_webhook_url: cred.lifecycle_webhook_url.clone().filter(|u| !u.is_empty()),
+            registered_at: reg.and_then(|r| r.registered_at),
+            created_at: now,
+            updated_at: now,
+        }
+    }
+}
+
+impl ExportedRegistration {
+    /// Registration data, if the credential has completed FCM registration
+    fn from_credential(credential: &Credential) -> Option<Self> {
+        Some(Self {
+            fcm_token: credential.fcm_token.clone()?,
+            gcm_token: credential.gcm_token.clone(),
+            android_id: credential.android_id?,
+            security_token: credential.security_token?,
+            private_key_base64: credential.private_key_base64.clone()?,
+            auth_secret_base64: credential.auth_secret_base64.clone()?,
+            registered_at: credential.registered_at,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::CreateCredentialRequest;
+
+    fn registered_credential() -> Credential {
+        let mut cred = Credential::new(CreateCredentialRequest {
+            name: "Test".to_string(),
+            api_key: "key".to_string(),
+            app_id: "1:123:android:abc".to_string(),
+            project_id: "project".to_string(),
+            webhook_url: Some("http://localhost/webhook".to_string()),
+            max_messages: Some(10),
+            ..Default::default()
+        });
+        cred.fcm_token = Some("fcm".to_string());
+        cred.gcm_token = Some("gcm".to_string());
+        cred.android_id = Some(1);
+        cred.security_token = Some(2);
+        cred.private_key_base64 = Some("pk".to_string());
+        cred.auth_secret_base64 = Some("as".to_string());
+        cred
+    }
+
+    #[test]
+    fn test_export_omits_secrets_by_default() {
+        let export = CredentialExport::new(&registered_credential(), vec![], false);
+        assert!(export.registration.is_none());
+        assert!(export.to_credential().fcm_token.is_none());
+    }
+
+    #[test]
+    fn test_export_roundtrip_keeps_registration() {
+        let original = registered_credential();
+        let export = CredentialExport::new(&original, vec!["news".to_string()], true);
+        let json = serde_json::to_string(&export).unwrap();
+        let imported = serde_json::from_str::<CredentialExport>(&json)
+            .unwrap()
+            .to_credential();
+
+        assert_ne!(imported.id, original.id);
+        assert_eq!(imported.fcm_token, original.fcm_token);
+        assert_eq!(imported.private_key_base64, original.private_key_base64);
+        assert_eq!(imported.max_messages, Some(10));
+    }
+}
@@ -1,19 +1,141 @@
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
 use utoipa::ToSchema;
 use uuid::Uuid;
 
+/// How a received payload is stored in a message log
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum PayloadEncoding {
+    /// UTF-8 text; invalid bytes are replaced (U+FFFD)
+    #[default]
+    Utf8,
+    /// Base64 of the raw bytes, for binary payloads such as protobuf
+    Base64,
+}
+
+impl PayloadEncoding {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PayloadEncoding::Utf8 => "utf8",
+            PayloadEncoding::Base64 => "base64",
+        }
+    }
+
+    /// Encoding stored as `value`; anything unrecognized is treated as UTF-8
+    pub fn parse(value: &str) -> Self {
+        match value {
+            "base64" => PayloadEncoding::Base64,
+            _ => PayloadEncoding::Utf8,
+        }
+    }
+}
+
+/// Where a message is in the delivery lifecycle
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum DeliveryState {
+    /// Logged, no delivery started yet
+    #[default]
+    Pending,
+    /// A delivery is underway, including waits between retries
+    Delivering,
+    /// The last delivery got a 2xx response
+    Delivered,
+    /// The last delivery gave up
+    Failed,
+    /// Never delivered: the credential had no webhook, or delivery was disabled or blocked
+    /// when the message arrived
+    Skipped,
+}
+
+impl DeliveryState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeliveryState::Pending => "pending",
+            DeliveryState::Delivering => "delivering",
+            DeliveryState::Delivered => "delivered",
+            DeliveryState::Failed => "failed",
+            DeliveryState::Skipped => "skipped",
+        }
+    }
+
+    /// State stored as `value`; anything unrecognized is treated as pending
+    pub fn parse(value: &str) -> Self {
+        match value {
+            "delivering" => DeliveryState::Delivering,
+            "delivered" => DeliveryState::Delivered,
+            "failed" => DeliveryState::Failed,
+            "skipped" => DeliveryState::Skipped,
+            _ => DeliveryState::Pending,
+        }
+    }
+
+    /// State after recording a webhook status: 2xx delivers, 0 is the final failure,
+    /// anything else is an attempt that may still be retried
+    pub fn after_status(status: i32) -> Self {
+        match status {
+            200..=299 => DeliveryState::Delivered,
+            0 => DeliveryState::Failed,
+            _ => DeliveryState::Delivering,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
 pub struct MessageLog {
     pub id: String,
     pub credential_id: String,
     pub fcm_message_id: Option<String>,
     pub payload: String,
+    /// "utf8" or "base64" (see PayloadEncoding)
+    pub payload_encoding: String,
+    /// Whether `payload` still holds its compressed stored form (Repository inflates it on read)
+    #[serde(default)]
+    pub payload_compressed: bool,
+    /// False when the credential doesn't store payloads; `payload` then only holds
+    /// "sha256:<hex>" of the body
+    #[serde(default = "default_true")]
+    pub payload_stored: bool,
+    /// Value at the credential's dedup_key_path when the message was received
+    #[serde(default)]
+    pub dedup_key: Option<String>,
     pub webhook_status: Option<i32>,
+    /// Delivery lifecycle state (see DeliveryState)
+    #[serde(default = "default_delivery_state")]
+    pub delivery_state: String,
+    /// URL of the latest webhook delivery attempt (a retry may override the credential's)
+    pub webhook_url: Option<String>,
     pub webhook_response: Option<String>,
+    pub webhook_response_json: Option<String>,
+    pub extracted_fields: Option<String>,
+    pub acknowledged: bool,
+    pub acknowledged_at: Option<DateTime<Utc>>,
+    /// When the sender sent the message, if the payload says so (see `extract_sent_at`)
+    #[serde(default)]
+    pub sent_at: Option<DateTime<Utc>>,
+    /// FCM delivery priority from the payload, "high" or "normal" (see `extract_priority`)
+    #[serde(default)]
+    pub priority: Option<String>,
+    /// Time to live in seconds from the payload (see `extract_ttl_secs`)
+    #[serde(default)]
+    pub ttl_secs: Option<i64>,
     pub received_at: DateTime<Utc>,
 }
 
+/// Payload keys that may carry the send time, checked at the top level and then in `data`
+const SENT_TIME_KEYS: &[&str] = &["google.sent_time", "sent_time", "sentTime"];
+
+/// Payload keys that may carry the priority, checked at the top level and then in `data`
+const PRIORITY_KEYS: &[&str] =
+    &["priority", "google.delivered_priority", "google.original_priority"];
+
+/// Payload keys that may carry the time to live, checked at the top level and then in `data`
+const TTL_KEYS: &[&str] = &["ttl", "google.ttl", "time_to_live"];
+
 impl MessageLog {
     pub fn new(credential_id: String, fcm_message_id: Option<String>, payload: String) -> Self {
         Self {
@@ -21,18 +143,272 @@ impl MessageLog {
             credential_id,
             fcm_message_id,
             payload,
+            payload_encoding: PayloadEncoding::Utf8.as_str().to_string(),
+            payload_compressed: false,
+            payload_stored: true,
+            dedup_key: None,
             webhook_status: None,
+            delivery_state: DeliveryState::Pending.as_str().to_string(),
+            webhook_url: None,
             webhook_response: None,
+            webhook_response_json: None,
+            extracted_fields: None,
+            acknowledged: false,
+            acknowledged_at: None,
+            sent_at: None,
+            priority: None,
+            ttl_secs: None,
             received_at: Utc::now(),
         }
     }
 
+    /// Log for raw payload bytes, stored with `encoding`
+    pub fn from_bytes(
+        credential_id: String,
+        fcm_message_id: Option<String>,
+        bytes: &[u8],
+        encoding: PayloadEncoding,
+    ) -> Self {
+        let payload = match encoding {
+            PayloadEncoding::Utf8 => String::from_utf8_lossy(bytes).to_string(),
+            PayloadEncoding::Base64 => BASE64.encode(bytes),
+        };
+        Self {
+            payload_encoding: encoding.as_str().to_string(),
+            ..Self::new(credential_id, fcm_message_id, payload)
+        }
+    }
+
+    pub fn encoding(&self) -> PayloadEncoding {
+        PayloadEncoding::parse(&self.payload_encoding)
+    }
+
+    pub fn state(&self) -> DeliveryState {
+        DeliveryState::parse(&self.delivery_state)
+    }
+
+    /// Payload bytes as received (decoded when stored as base64), for delivery and replay
+    pub fn payload_bytes(&self) -> Vec<u8> {
+        match self.encoding() {
+            PayloadEncoding::Utf8 => self.payload.clone().into_bytes(),
+            PayloadEncoding::Base64 => BASE64
+                .decode(&self.payload)
+                .unwrap_or_else(|_| self.payload.clone().into_bytes()),
+        }
+    }
+
+    /// Replace the body with its SHA-256 hash, keeping only metadata in the log
+    pub fn omit_payload(&mut self) {
+        let digest = Sha256::digest(self.payload_bytes());
+        self.payload = format!("sha256:{}", hex::encode(digest));
+        self.payload_stored = false;
+    }
+
     /// Extract fcmMessageId from payload JSON
     pub fn extract_fcm_message_id(payload: &str) -> Option<String> {
         serde_json::from_str::<serde_json::Value>(payload)
             .ok()
             .and_then(|v| v.get("fcmMessageId").and_then(|id| id.as_str().map(|s| s.to_string())))
     }
+
+    /// Pull the configured fields (name -> dotted path, e.g. "data.order_id") out of the
+    /// payload as a JSON object of strings; None when the payload isn't JSON or nothing matched
+    pub fn extract_fields(payload: &str, paths: &HashMap<String, String>) -> Option<String> {
+        if paths.is_empty() {
+            return None;
+        }
+        let value = serde_json::from_str::<serde_json::Value>(payload).ok()?;
+
+        let fields: serde_json::Map<String, serde_json::Value> = paths
+            .iter()
+            .filter_map(|(name, path)| {
+                let text = Self::value_at(&value, path)?;
+                Some((name.clone(), serde_json::Value::String(text)))
+            })
+            .collect();
+
+        if fields.is_empty() {
+            None
+        } else {
+            Some(serde_json::Value::Object(fields).to_string())
+        }
+    }
+
+    /// Value at a dotted path (e.g. "data.event_id") of the payload as text; None when the
+    /// payload isn't JSON or the path is missing or null
+    pub fn extract_value(payload: &str, path: &str) -> Option<String> {
+        let value = serde_json::from_str::<serde_json::Value>(payload).ok()?;
+        Self::value_at(&value, path)
+    }
+
+    fn value_at(value: &serde_json::Value, path: &str) -> Option<String> {
+        let found = path.split('.').try_fold(value, |v, key| match v {
+            serde_json::Value::Array(items) => items.get(key.parse::<usize>().ok()?),
+            _ => v.get(key),
+        })?;
+        // Stored as text so filters compare against the raw query value
+        match found {
+            serde_json::Value::Null => None,
+            serde_json::Value::String(s) => Some(s.clone()),
+            other => Some(other.to_string()),
+        }
+    }
+
+    /// Send time from payload JSON: `google.sent_time`, `sent_time` or `sentTime`, at the
+    /// top level or in `data`, as epoch milliseconds (number or string) or RFC 3339 text
+    pub fn extract_sent_at(payload: &str) -> Option<DateTime<Utc>> {
+        let value = serde_json::from_str::<serde_json::Value>(payload).ok()?;
+        let data = value.get("data");
+
+        let sent_at = [Some(&value), data]
+            .into_iter()
+            .flatten()
+            .flat_map(|scope| SENT_TIME_KEYS.iter().filter_map(|key| scope.get(*key)))
+            .find_map(|v| match v {
+                serde_json::Value::Number(n) => n.as_i64().and_then(DateTime::from_timestamp_millis),
+                serde_json::Value::String(s) => match s.parse::<i64>() {
+                    Ok(millis) => DateTime::from_timestamp_millis(millis),
+                    Err(_) => DateTime::parse_from_rfc3339(s).ok().map(|t| t.with_timezone(&Utc)),
+                },
+                _ => None,
+            });
+        sent_at
+    }
+
+    /// Priority from payload JSON: `priority`, `google.delivered_priority` or
+    /// `google.original_priority`, at the top level or in `data`, as "high" or "normal"
+    pub fn extract_priority(payload: &str) -> Option<String> {
+        Self::find_metadata(payload, PRIORITY_KEYS, |v| {
+            match v.as_str()?.trim().to_lowercase().as_str() {
+                "high" => Some("high".to_string()),
+                "normal" => Some("normal".to_string()),
+                _ => None,
+            }
+        })
+    }
+
+    /// Time to live from payload JSON: `ttl`, `google.ttl` or `time_to_live`, at the top level
+    /// or in `data`, as seconds (number, numeric string or a duration such as "3600s")
+    pub fn extract_ttl_secs(payload: &str) -> Option<i64> {
+        Self::find_metadata(payload, TTL_KEYS, |v| match v {
+            serde_json::Value::Number(n) => n.as_i64(),
+            serde_json::Value::String(s) => {
+                let s = s.trim();
+                s.strip_suffix('s').unwrap_or(s).parse().ok()
+            }
+            _ => None,
+        })
+        .filter(|secs| *secs >= 0)
+    }
+
+    /// First of `keys` that `parse` accepts, at the top level of the payload and then in `data`
+    fn find_metadata<T>(
+        payload: &str,
+        keys: &[&str],
+        parse: impl Fn(&serde_json::Value) -> Option<T>,
+    ) -> Option<T> {
+        let value = serde_json::from_str::<serde_json::Value>(payload).ok()?;
+        let data = value.get("data");
+
+        let found = [Some(&value), data]
+            .into_iter()
+            .flatten()
+            .flat_map(|scope| keys.iter().filter_map(|key| scope.get(*key)))
+            .find_map(parse);
+        found
+    }
+
+    /// Extract the originating topic from payload JSON ("from": "/topics/<name>" or "topic")
+    pub fn extract_topic(payload: &str) -> Option<String> {
+        let value = serde_json::from_str::<serde_json::Value>(payload).ok()?;
+
+        value
+            .get("from")
+            .and_then(|f| f.as_str())
+            .and_then(|f| f.strip_prefix("/topics/"))
+            .or_else(|| value.get("topic").and_then(|t| t.as_str()))
+            .map(|t| t.to_string())
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_delivery_state() -> String {
+    DeliveryState::Pending.as_str().to_string()
+}
+
+/// Per-credential message aggregates from a single grouped query
+#[derive(Debug, Clone, sqlx::FromRow)]
+pub struct MessageStats {
+    pub credential_id: String,
+    pub message_count: i64,
+    pub last_received_at: Option<DateTime<Utc>>,
+    /// Messages with a recorded delivery attempt
+    pub attempted: i64,
+    /// Messages whose last delivery got a 2xx response
+    pub succeeded: i64,
+}
+
+/// Kind of FCM message, judged by the payload shape
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum MessageKind {
+    /// Has a notification block (title/body shown by the OS)
+    Notification,
+    /// Only a data block
+    Data,
+    /// Not JSON, or neither block present
+    Unknown,
+}
+
+/// Readable summary of a payload so clients don't have to parse it themselves
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct MessageSummary {
+    pub kind: MessageKind,
+    /// Notification title
+    pub title: Option<String>,
+    /// Notification body
+    pub body: Option<String>,
+    /// Keys of the data block, sorted
+    pub data_keys: Vec<String>,
+}
+
+impl MessageSummary {
+    /// Summarize an FCM payload; anything that isn't a JSON object is `Unknown`
+    pub fn parse(payload: &str) -> Self {
+        let value = serde_json::from_str::<serde_json::Value>(payload).unwrap_or_default();
+        let notification = value.get("notification").filter(|n| n.is_object());
+        let text = |key: &str| {
+            notification
+                .and_then(|n| n.get(key))
+                .and_then(|v| v.as_str())
+                .map(|s| s.to_string())
+        };
+
+        let mut data_keys: Vec<String> = value
+            .get("data")
+            .and_then(|d| d.as_object())
+            .map(|d| d.keys().cloned().collect())
+            .unwrap_or_default();
+        data_keys.sort();
+
+        let kind = if notification.is_some() {
+            MessageKind::Notification
+        } else if value.get("data").is_some_and(|d| d.is_object()) {
+            MessageKind::Data
+        } else {
+            MessageKind::Unknown
+        };
+
+        Self {
+            kind,
+            title: text("title"),
+            body: text("body"),
+            data_keys,
+        }
+    }
 }
 
 /// Message log response
@@ -44,12 +420,41 @@ pub struct MessageLogResponse {
     pub credential_id: String,
     /// FCM message ID for deduplication
     pub fcm_message_id: Option<String>,
-    /// FCM message payload
+    /// Sender's message id read from the credential's dedup_key_path, used for deduplication
+    pub dedup_key: Option<String>,
+    /// FCM message payload (a base64 string when payload_encoding is base64; null when
+    /// the payload wasn't stored)
     pub payload: serde_json::Value,
+    /// How the payload is stored
+    pub payload_encoding: PayloadEncoding,
+    /// Whether the payload body was stored (false = metadata only, can't be retried)
+    pub payload_stored: bool,
+    /// "sha256:<hex>" of the body when it wasn't stored
+    pub payload_hash: Option<String>,
+    /// Notification title/body and data keys parsed from the payload
+    pub parsed_summary: MessageSummary,
     /// HTTP status code from webhook delivery
     pub webhook_status: Option<i32>,
+    /// Where the message is in the delivery lifecycle
+    pub delivery_state: DeliveryState,
+    /// URL the latest webhook delivery attempt went to
+    pub webhook_url: Option<String>,
     /// Response body from webhook
     pub webhook_response: Option<String>,
+    /// Response body from a successful webhook delivery, when it is valid JSON
+    pub webhook_response_json: Option<serde_json::Value>,
+    /// Payload fields extracted at ingest (see the credential's extract_fields)
+    pub extracted_fields: Option<HashMap<String, String>>,
+    /// Whether the downstream receiver confirmed processing
+    pub acknowledged: bool,
+    /// When the message was acknowledged
+    pub acknowledged_at: Option<DateTime<Utc>>,
+    /// When the sender sent the message, if the payload carries a send time
+    pub sent_at: Option<DateTime<Utc>>,
+    /// FCM delivery priority, "high" or "normal" (null when the payload doesn't say)
+    pub priority: Option<String>,
+    /// Time to live in seconds (null when the payload doesn't say)
+    pub ttl_secs: Option<i64>,
     /// When the message was received
     pub received_at: DateTime<Utc>,
 }
@@ -60,10 +465,164 @@ impl MessageLog {
             id: self.id.clone(),
             credential_id: self.credential_id.clone(),
             fcm_message_id: self.fcm_message_id.clone(),
-            payload: serde_json::from_str(&self.payload).unwrap_or(serde_json::json!({})),
+            dedup_key: self.dedup_key.clone(),
+            payload: match self.encoding() {
+                _ if !self.payload_stored => serde_json::Value::Null,
+                PayloadEncoding::Utf8 => {
+                    serde_json::from_str(&self.payload).unwrap_or(serde_json::json!({}))
+                }
+                PayloadEncoding::Base64 => serde_json::Value::String(self.payload.clone()),
+            },
+            payload_encoding: self.encoding(),
+            payload_stored: self.payload_stored,
+            payload_hash: (!self.payload_stored).then(|| self.payload.clone()),
+            parsed_summary: MessageSummary::parse(&String::from_utf8_lossy(&self.payload_bytes())),
             webhook_status: self.webhook_status,
+            delivery_state: self.state(),
+            webhook_url: self.webhook_url.clone(),
             webhook_response: self.webhook_response.clone(),
+            webhook_response_json: self
+                .webhook_response_json
+                .as_ref()
+                .and_then(|r| serde_json::from_str(r).ok()),
+            extracted_fields: self
+                .extracted_fields
+                .as_ref()
+                .and_then(|f| serde_json::from_str(f).ok()),
+            acknowledged: self.acknowledged,
+            acknowledged_at: self.acknowledged_at,
+            sent_at: self.sent_at,
+            priority: self.priority.clone(),
+            ttl_secs: self.ttl_secs,
             received_at: self.received_at,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_fields() {
+        let paths = HashMap::from([
+            ("order".to_string(), "data.order_id".to_string()),
+            ("count".to_string(), "data.count".to_string()),
+            ("first_tag".to_string(), "data.tags.0".to_string()),
+            ("missing".to_string(), "data.nope".to_string()),
+        ]);
+        let payload = r#"{"data":{"order_id":"A-1","count":3,"tags":["x","y"]}}"#;
+
+        let fields: HashMap<String, String> =
+            serde_json::from_str(&MessageLog::extract_fields(payload, &paths).unwrap()).unwrap();
+        assert_eq!(fields.len(), 3);
+        assert_eq!(fields["order"], "A-1");
+        assert_eq!(fields["count"], "3");
+        assert_eq!(fields["first_tag"], "x");
+
+        assert_eq!(MessageLog::extract_fields("not json", &paths), None);
+        assert_eq!(MessageLog::extract_fields("{}", &paths), None);
+        assert_eq!(MessageLog::extract_fields(payload, &HashMap::new()), None);
+    }
+
+    #[test]
+    fn test_extract_value() {
+        let payload = r#"{"data":{"event_id":"evt-1","seq":7,"ids":[4,5],"none":null}}"#;
+        assert_eq!(MessageLog::extract_value(payload, "data.event_id").as_deref(), Some("evt-1"));
+        assert_eq!(MessageLog::extract_value(payload, "data.seq").as_deref(), Some("7"));
+        assert_eq!(MessageLog::extract_value(payload, "data.ids.1").as_deref(), Some("5"));
+        assert_eq!(MessageLog::extract_value(payload, "data.none"), None);
+        assert_eq!(MessageLog::extract_value(payload, "data.missing"), None);
+        assert_eq!(MessageLog::extract_value("not json", "data.event_id"), None);
+    }
+
+    #[test]
+    fn test_extract_sent_at() {
+        let expected = DateTime::parse_from_rfc3339("2024-05-01T12:00:00.250Z").unwrap();
+        for payload in [
+            r#"{"google.sent_time":1714564800250,"data":{}}"#,
+            r#"{"data":{"sent_time":"1714564800250"}}"#,
+            r#"{"data":{"sentTime":"2024-05-01T12:00:00.250Z"}}"#,
+        ] {
+            assert_eq!(MessageLog::extract_sent_at(payload), Some(expected.with_timezone(&Utc)));
+        }
+
+        // No send time, or one that can't be read: left null
+        assert_eq!(MessageLog::extract_sent_at(r#"{"fcmMessageId":"0:1","data":{"a":"b"}}"#), None);
+        assert_eq!(MessageLog::extract_sent_at(r#"{"data":{"sent_time":"yesterday"}}"#), None);
+        assert_eq!(MessageLog::extract_sent_at("not json"), None);
+    }
+
+    #[test]
+    fn test_extract_priority_and_ttl() {
+        let payload = r#"{"fcmMessageId":"0:1","priority":"HIGH","ttl":3600,"data":{"a":"b"}}"#;
+        assert_eq!(MessageLog::extract_priority(payload).as_deref(), Some("high"));
+        assert_eq!(MessageLog::extract_ttl_secs(payload), Some(3600));
+
+        // Inside data, with the google.* names and duration strings
+        let payload = r#"{"data":{"google.delivered_priority":"normal","google.ttl":"86400s"}}"#;
+        assert_eq!(MessageLog::extract_priority(payload).as_deref(), Some("normal"));
+        assert_eq!(MessageLog::extract_ttl_secs(payload), Some(86400));
+
+        // Notification-only messages carry neither
+        let payload = r#"{"fcmMessageId":"0:2","notification":{"title":"Hi","body":"There"}}"#;
+        assert_eq!(MessageLog::extract_priority(payload), None);
+        assert_eq!(MessageLog::extract_ttl_secs(payload), None);
+        let response = MessageLog::new("cred".to_string(), None, payload.to_string()).to_response();
+        let json = serde_json::to_value(&response).unwrap();
+        assert!(json["priority"].is_null());
+        assert!(json["ttl_secs"].is_null());
+
+        // Unknown values are ignored
+        let payload = r#"{"priority":"urgent","ttl":"soon"}"#;
+        assert_eq!(MessageLog::extract_priority(payload), None);
+        assert_eq!(MessageLog::extract_ttl_secs(payload), None);
+        assert_eq!(MessageLog::extract_priority("not json"), None);
+    }
+
+    #[test]
+    fn test_summary_notification() {
+        let summary = MessageSummary::parse(
+            r#"{"notification":{"title":"Hi","body":"There"},"data":{"b":"1","a":"2"}}"#,
+        );
+        assert_eq!(summary.kind, MessageKind::Notification);
+        assert_eq!(summary.title.as_deref(), Some("Hi"));
+        assert_eq!(summary.body.as_deref(), Some("There"));
+        assert_eq!(summary.data_keys, vec!["a", "b"]);
+    }
+
+    #[test]
+    fn test_summary_data() {
+        let summary = MessageSummary::parse(r#"{"from":"/topics/news","data":{"id":"7"}}"#);
+        assert_eq!(summary.kind, MessageKind::Data);
+        assert_eq!(summary.title, None);
+        assert_eq!(summary.data_keys, vec!["id"]);
+    }
+
+    #[test]
+    fn test_summary_malformed() {
+        let lossy = String::from_utf8_lossy(&[0xff, 0xfe, b'{']).to_string();
+        for payload in ["not json", "[1,2]", "{\"notification\":\"x\"}", lossy.as_str()] {
+            let summary = MessageSummary::parse(payload);
+            assert_eq!(summary.kind, MessageKind::Unknown);
+            assert!(summary.title.is_none() && summary.data_keys.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_base64_payload_keeps_raw_bytes() {
+        let bytes = [0x08, 0x96, 0x01, 0xff, 0x00];
+        let log = MessageLog::from_bytes("cred".to_string(), None, &bytes, PayloadEncoding::Base64);
+        assert_eq!(log.payload_encoding, "base64");
+        assert_eq!(log.payload_bytes(), bytes);
+        assert_eq!(log.to_response().payload, serde_json::json!(BASE64.encode(bytes)));
+
+        let text = MessageLog::from_bytes("cred".to_string(), None, b"{\"a\":1}", PayloadEncoding::Utf8);
+        assert_eq!(text.payload, "{\"a\":1}");
+        assert_eq!(text.payload_bytes(), b"{\"a\":1}");
+
+        // The default encoding replaces invalid bytes
+        let lossy = MessageLog::from_bytes("cred".to_string(), None, &bytes, PayloadEncoding::Utf8);
+        assert_ne!(lossy.payload_bytes(), bytes);
+    }
+}
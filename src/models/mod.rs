@@ -1,5 +1,7 @@
 pub mod credential;
+pub mod export;
 pub mod message;
 
 pub use credential::*;
+pub use export::*;
 pub use message::*;
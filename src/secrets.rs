@@ -0,0 +1,65 @@
+use anyhow::{Context, Result};
+use tracing::error;
+
+/// Settings that may hold secrets and can be supplied as `<NAME>_FILE`
+pub const SECRET_VARS: &[&str] = &[
+    "API_KEY",
+    "DATABASE_URL",
+    "DATABASE_READ_URL",
+    "WEBHOOK_PROXY",
+    "ALERT_WEBHOOK_URL",
+    "LIFECYCLE_WEBHOOK_URL",
+    "WEBHOOK_SIGNING_KEY",
+];
+
+/// Read a setting from `<name>_FILE` (trimmed file contents, e.g. a mounted secret)
+/// or else from `<name>`. The file takes precedence; an unreadable file is an error.
+pub fn var(name: &str) -> Result<Option<String>> {
+    if let Ok(path) = std::env::var(format!("{}_FILE", name)) {
+        let contents = std::fs::read_to_string(&path)
+            .with_context(|| format!("Cannot read {}_FILE ({})", name, path))?;
+        return Ok(Some(contents.trim().to_string()));
+    }
+
+    Ok(std::env::var(name).ok())
+}
+
+/// Like `var`, for readers that can't fail: logs the error and treats the setting as unset.
+/// Startup calls `check_files` first, so this only matters if a file disappears later.
+pub fn var_or_log(name: &str) -> Option<String> {
+    var(name).unwrap_or_else(|e| {
+        error!("{:#}", e);
+        None
+    })
+}
+
+/// Fail fast if any configured `<NAME>_FILE` can't be read
+pub fn check_files() -> Result<()> {
+    for name in SECRET_VARS {
+        var(name)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_takes_precedence() {
+        let path = std::env::temp_dir().join(format!("fcm_secret_{}", std::process::id()));
+        std::fs::write(&path, "from-file\n").unwrap();
+
+        std::env::set_var("TEST_SECRET_PRECEDENCE", "from-env");
+        assert_eq!(var("TEST_SECRET_PRECEDENCE").unwrap().as_deref(), Some("from-env"));
+
+        std::env::set_var("TEST_SECRET_PRECEDENCE_FILE", &path);
+        assert_eq!(var("TEST_SECRET_PRECEDENCE").unwrap().as_deref(), Some("from-file"));
+
+        std::env::set_var("TEST_SECRET_PRECEDENCE_FILE", path.with_extension("missing"));
+        let err = var("TEST_SECRET_PRECEDENCE").unwrap_err();
+        assert!(format!("{:#}", err).contains("TEST_SECRET_PRECEDENCE_FILE"));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,268 @@
+use crate::db::Repository;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::sync::RwLock;
+use tracing::{info, warn};
+use utoipa::ToSchema;
+
+/// Settings that can be changed at runtime via PATCH /api/admin/config.
+/// Values start from the environment and are overridden by those persisted in the settings table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct RuntimeSettings {
+    /// In-memory deduplication window in seconds (DEDUP_SECONDS)
+    pub dedup_ttl_secs: u64,
+    /// Message logs kept per credential without its own max_messages (MAX_MESSAGES_PER_CREDENTIAL)
+    pub max_messages_per_credential: i64,
+    /// Webhook delivery retries after the first attempt (WEBHOOK_MAX_RETRIES)
+    pub webhook_max_retries: u32,
+    /// Initial delay between webhook retries in milliseconds, doubled per retry
+    /// (WEBHOOK_RETRY_BASE_DELAY_MS, or its alias WEBHOOK_BASE_DELAY_MS)
+    pub webhook_retry_base_delay_ms: u64,
+}
+
+/// Partial update of the runtime settings
+#[derive(Debug, Default, Deserialize, ToSchema)]
+pub struct SettingsUpdate {
+    pub dedup_ttl_secs: Option<u64>,
+    pub max_messages_per_credential: Option<i64>,
+    pub webhook_max_retries: Option<u32>,
+    pub webhook_retry_base_delay_ms: Option<u64>,
+}
+
+static SETTINGS: RwLock<Option<RuntimeSettings>> = RwLock::new(None);
+
+/// `name` as returned by `var`, warning when it is set but not a valid number
+fn parse_var<T: std::str::FromStr>(var: &impl Fn(&str) -> Option<String>, name: &str) -> Option<T> {
+    let value = var(name)?;
+    let parsed = value.trim().parse().ok();
+    if parsed.is_none() {
+        warn!("Ignoring {}={}: not a valid number", name, value);
+    }
+    parsed
+}
+
+impl Default for RuntimeSettings {
+    fn default() -> Self {
+        Self {
+            dedup_ttl_secs: 5,
+            max_messages_per_credential: 50,
+            webhook_max_retries: 3,
+            webhook_retry_base_delay_ms: 1000,
+        }
+    }
+}
+
+impl RuntimeSettings {
+    pub fn from_env() -> Self {
+        Self::from_vars(|name| std::env::var(name).ok())
+    }
+
+    /// Settings from the variables `var` returns, checked like a PATCH: a value that is
+    /// unparsable or out of range falls back to its default with a warning
+    fn from_vars(var: impl Fn(&str) -> Option<String>) -> Self {
+        let fields = [
+            SettingsUpdate {
+                dedup_ttl_secs: parse_var(&var, "DEDUP_SECONDS"),
+                ..Default::default()
+            },
+            SettingsUpdate {
+                max_messages_per_credential: parse_var(&var, "MAX_MESSAGES_PER_CREDENTIAL"),
+                ..Default::default()
+            },
+            SettingsUpdate {
+                webhook_max_retries: parse_var(&var, "WEBHOOK_MAX_RETRIES"),
+                ..Default::default()
+            },
+            SettingsUpdate {
+                webhook_retry_base_delay_ms: parse_var(&var, "WEBHOOK_RETRY_BASE_DELAY_MS")
+                    .or_else(|| parse_var(&var, "WEBHOOK_BASE_DELAY_MS")),
+                ..Default::default()
+            },
+        ];
+
+        let mut settings = Self::default();
+        for field in &fields {
+            match settings.apply(field) {
+                Ok(applied) => settings = applied,
+                Err(e) => warn!("Ignoring environment setting: {}", e),
+            }
+        }
+        settings
+    }
+
+    /// Settings with `update` applied, or an error listing every invalid value
+    pub fn apply(&self, update: &SettingsUpdate) -> Result<Self, String> {
+        let mut errors = Vec::new();
+        if matches!(update.max_messages_per_credential, Some(m) if m < 1) {
+            errors.push("max_messages_per_credential must be at least 1");
+        }
+        if matches!(update.webhook_max_retries, Some(r) if r > 10) {
+            errors.push("webhook_max_retries must be at most 10");
+        }
+        if matches!(update.webhook_retry_base_delay_ms, Some(d) if !(1..=60_000).contains(&d)) {
+            errors.push("webhook_retry_base_delay_ms must be between 1 and 60000");
+        }
+        if !errors.is_empty() {
+            return Err(errors.join("; "));
+        }
+
+        Ok(Self {
+            dedup_ttl_secs: update.dedup_ttl_secs.unwrap_or(self.dedup_ttl_secs),
+            max_messages_per_credential: update
+                .max_messages_per_credential
+                .unwrap_or(self.max_messages_per_credential),
+            webhook_max_retries: update.webhook_max_retries.unwrap_or(self.webhook_max_retries),
+            webhook_retry_base_delay_ms: update
+                .webhook_retry_base_delay_ms
+                .unwrap_or(self.webhook_retry_base_delay_ms),
+        })
+    }
+
+    /// Settings table rows for the values set in `update`
+    fn changed_rows(update: &SettingsUpdate) -> Vec<(&'static str, String)> {
+        let mut rows = Vec::new();
+        if let Some(v) = update.dedup_ttl_secs {
+            rows.push(("dedup_ttl_secs", v.to_string()));
+        }
+        if let Some(v) = update.max_messages_per_credential {
+            rows.push(("max_messages_per_credential", v.to_string()));
+        }
+        if let Some(v) = update.webhook_max_retries {
+            rows.push(("webhook_max_retries", v.to_string()));
+        }
+        if let Some(v) = update.webhook_retry_base_delay_ms {
+            rows.push(("webhook_retry_base_delay_ms", v.to_string()));
+        }
+        rows
+    }
+
+    /// Update built from persisted settings rows (unknown or unparsable rows are skipped)
+    fn update_from_rows(rows: &[(String, String)]) -> SettingsUpdate {
+        let mut update = SettingsUpdate::default();
+        for (key, value) in rows {
+            let parsed = match key.as_str() {
+                "dedup_ttl_secs" => value.parse().map(|v| update.dedup_ttl_secs = Some(v)).is_ok(),
+                "max_messages_per_credential" => value
+                    .parse()
+                    .map(|v| update.max_messages_per_credential = Some(v))
+                    .is_ok(),
+                "webhook_max_retries" => {
+                    value.parse().map(|v| update.webhook_max_retries = Some(v)).is_ok()
+                }
+                "webhook_retry_base_delay_ms" => value
+                    .parse()
+                    .map(|v| update.webhook_retry_base_delay_ms = Some(v))
+                    .is_ok(),
+                _ => false,
+            };
+            if !parsed {
+                warn!("Ignoring stored setting {}={}", key, value);
+            }
+        }
+        update
+    }
+}
+
+/// Settings currently in force
+pub fn current() -> RuntimeSettings {
+    if let Some(settings) = *SETTINGS.read().unwrap() {
+        return settings;
+    }
+    *SETTINGS.write().unwrap().get_or_insert_with(RuntimeSettings::from_env)
+}
+
+/// Apply settings persisted in the database over the environment defaults
+pub async fn load(repo: &Repository) -> Result<()> {
+    let rows = repo.list_settings().await?;
+    let update = RuntimeSettings::update_from_rows(&rows);
+    let settings = RuntimeSettings::from_env()
+        .apply(&update)
+        .unwrap_or_else(|e| {
+            warn!("Ignoring stored settings: {}", e);
+            RuntimeSettings::from_env()
+        });
+
+    if !rows.is_empty() {
+        info!("Loaded {} stored runtime setting(s)", rows.len());
+    }
+    *SETTINGS.write().unwrap() = Some(settings);
+    Ok(())
+}
+
+/// Persist the values set in `update` and put `settings` (the result of applying it) in force
+pub async fn save(repo: &Repository, update: &SettingsUpdate, settings: RuntimeSettings) -> Result<()> {
+    for (key, value) in RuntimeSettings::changed_rows(update) {
+        repo.set_setting(key, &value).await?;
+    }
+
+    *SETTINGS.write().unwrap() = Some(settings);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_settings_round_trip() {
+        let base = RuntimeSettings::default();
+        let update = SettingsUpdate {
+            max_messages_per_credential: Some(200),
+            webhook_max_retries: Some(0),
+            ..Default::default()
+        };
+        let applied = base.apply(&update).unwrap();
+        assert_eq!(applied.max_messages_per_credential, 200);
+        assert_eq!(applied.webhook_max_retries, 0);
+        assert_eq!(applied.dedup_ttl_secs, 5);
+
+        let invalid = SettingsUpdate {
+            max_messages_per_credential: Some(0),
+            webhook_retry_base_delay_ms: Some(0),
+            ..Default::default()
+        };
+        let err = base.apply(&invalid).unwrap_err();
+        assert!(err.contains("max_messages_per_credential"));
+        assert!(err.contains("webhook_retry_base_delay_ms"));
+
+        // Persisted rows restore the same values
+        let repo = Repository::new("sqlite::memory:").await.unwrap();
+        for (key, value) in RuntimeSettings::changed_rows(&update) {
+            repo.set_setting(key, &value).await.unwrap();
+        }
+        let rows = repo.list_settings().await.unwrap();
+        assert_eq!(base.apply(&RuntimeSettings::update_from_rows(&rows)).unwrap(), applied);
+    }
+
+    #[test]
+    fn test_env_settings_validated() {
+        fn vars<'a>(pairs: &'a [(&str, &str)]) -> impl Fn(&str) -> Option<String> + 'a {
+            move |name| pairs.iter().find(|(k, _)| *k == name).map(|(_, v)| v.to_string())
+        }
+
+        let settings = RuntimeSettings::from_vars(vars(&[
+            ("DEDUP_SECONDS", "30"),
+            ("WEBHOOK_MAX_RETRIES", "7"),
+        ]));
+        assert_eq!(settings.dedup_ttl_secs, 30);
+        assert_eq!(settings.webhook_max_retries, 7);
+
+        // Out of range or unparsable: that value falls back, the others still apply
+        let settings = RuntimeSettings::from_vars(vars(&[
+            ("DEDUP_SECONDS", "30"),
+            ("WEBHOOK_MAX_RETRIES", "1000"),
+            ("MAX_MESSAGES_PER_CREDENTIAL", "lots"),
+        ]));
+        assert_eq!(settings.dedup_ttl_secs, 30);
+        assert_eq!(settings.webhook_max_retries, 3);
+        assert_eq!(settings.max_messages_per_credential, 50);
+
+        // The base delay alias gets the same range check, so 0 can't turn off the backoff
+        let settings = RuntimeSettings::from_vars(vars(&[("WEBHOOK_BASE_DELAY_MS", "250")]));
+        assert_eq!(settings.webhook_retry_base_delay_ms, 250);
+        for bad in ["0", "60001", "18446744073709551615"] {
+            let settings = RuntimeSettings::from_vars(vars(&[("WEBHOOK_BASE_DELAY_MS", bad)]));
+            assert_eq!(settings.webhook_retry_base_delay_ms, 1000);
+        }
+    }
+}
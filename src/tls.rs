@@ -0,0 +1,94 @@
+use anyhow::{bail, Context, Result};
+use axum_server::tls_rustls::RustlsConfig;
+use tracing::{error, info};
+
+/// Certificate chain and private key (PEM) the server terminates TLS with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TlsPaths {
+    pub cert: String,
+    pub key: String,
+}
+
+/// Get the TLS certificate and key paths from environment (TLS_CERT_PATH and TLS_KEY_PATH).
+/// None serves plain HTTP; setting only one of them is an error.
+pub fn get_tls_paths() -> Result<Option<TlsPaths>> {
+    let path = |name: &str| {
+        std::env::var(name)
+            .ok()
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+    };
+
+    match (path("TLS_CERT_PATH"), path("TLS_KEY_PATH")) {
+        (Some(cert), Some(key)) => Ok(Some(TlsPaths { cert, key })),
+        (None, None) => Ok(None),
+        (Some(_), None) => bail!("TLS_CERT_PATH is set but TLS_KEY_PATH is not"),
+        (None, Some(_)) => bail!("TLS_KEY_PATH is set but TLS_CERT_PATH is not"),
+    }
+}
+
+/// Load the certificate and key, failing with a message naming both files
+pub async fn load(paths: &TlsPaths) -> Result<RustlsConfig> {
+    RustlsConfig::from_pem_file(&paths.cert, &paths.key)
+        .await
+        .with_context(|| {
+            format!(
+                "Cannot load TLS certificate {} and key {}",
+                paths.cert, paths.key
+            )
+        })
+}
+
+/// Reload the certificate and key whenever the process receives SIGHUP (e.g. after a
+/// renewal). A reload that fails keeps serving the previous certificate.
+pub fn reload_on_sighup(config: RustlsConfig, paths: TlsPaths) {
+    #[cfg(unix)]
+    tokio::spawn(async move {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut hangup = match signal(SignalKind::hangup()) {
+            Ok(signal) => signal,
+            Err(e) => {
+                error!("Cannot listen for SIGHUP, TLS certificate reload disabled: {}", e);
+                return;
+            }
+        };
+
+        while hangup.recv().await.is_some() {
+            match config.reload_from_pem_file(&paths.cert, &paths.key).await {
+                Ok(()) => info!("TLS certificate reloaded from {}", paths.cert),
+                Err(e) => error!(
+                    "Failed to reload TLS certificate {} and key {}, keeping the current one: {}",
+                    paths.cert, paths.key, e
+                ),
+            }
+        }
+    });
+
+    #[cfg(not(unix))]
+    let _ = (config, paths);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_load_reports_unreadable_files() {
+        let dir = std::env::temp_dir().join(format!("fcm_tls_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let cert = dir.join("cert.pem");
+        std::fs::write(&cert, "not a certificate").unwrap();
+
+        let paths = TlsPaths {
+            cert: cert.display().to_string(),
+            key: dir.join("missing.pem").display().to_string(),
+        };
+        let err = load(&paths).await.unwrap_err();
+        let message = format!("{:#}", err);
+        assert!(message.contains("cert.pem"));
+        assert!(message.contains("missing.pem"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
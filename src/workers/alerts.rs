@@ -0,0 +1,209 @@
+use chrono::{DateTime, Utc};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
+use utoipa::ToSchema;
+
+/// Operational event worth telling an operator about
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertKind {
+    /// Worker gave up after exhausting its reconnect attempts
+    WorkerStopped,
+    /// Worker reached ALERT_RECONNECT_THRESHOLD consecutive reconnects
+    ReconnectThreshold,
+    /// FCM registration was rejected
+    RegistrationFailed,
+    /// A message could not be delivered after all retries
+    DeliveryFailed,
+    /// A message was delivered
+    DeliverySucceeded,
+}
+
+/// Which message delivery outcomes of a credential raise an operator alert
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum DeliveryAlertPolicy {
+    /// Only failed deliveries
+    #[default]
+    Failure,
+    /// Only successful deliveries
+    Success,
+    /// Every delivery
+    Both,
+    /// No delivery alerts
+    None,
+}
+
+impl DeliveryAlertPolicy {
+    /// Alert to raise for a delivery outcome, if the policy asks for one
+    pub fn alert_kind(self, delivered: bool) -> Option<AlertKind> {
+        match (self, delivered) {
+            (Self::Failure | Self::Both, false) => Some(AlertKind::DeliveryFailed),
+            (Self::Success | Self::Both, true) => Some(AlertKind::DeliverySucceeded),
+            _ => None,
+        }
+    }
+}
+
+/// JSON body POSTed to ALERT_WEBHOOK_URL
+#[derive(Debug, Clone, Serialize)]
+pub struct Alert {
+    pub kind: AlertKind,
+    pub credential_id: String,
+    pub credential_name: String,
+    /// Message log ID, for delivery alerts
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message_id: Option<String>,
+    pub error: Option<String>,
+    pub occurred_at: DateTime<Utc>,
+}
+
+impl Alert {
+    pub fn new(kind: AlertKind, credential_id: &str, credential_name: &str, error: Option<String>) -> Self {
+        Self {
+            kind,
+            credential_id: credential_id.to_string(),
+            credential_name: credential_name.to_string(),
+            message_id: None,
+            error,
+            occurred_at: Utc::now(),
+        }
+    }
+}
+
+/// Sends operator alerts to a separate webhook, at most once per credential and
+/// kind within the debounce window so a flapping worker doesn't spam
+#[derive(Clone)]
+pub struct AlertNotifier {
+    url: Option<Arc<String>>,
+    client: Client,
+    debounce: Duration,
+    last_sent: Arc<Mutex<HashMap<(String, AlertKind), Instant>>>,
+}
+
+impl AlertNotifier {
+    pub fn new(url: Option<String>, debounce: Duration) -> Self {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .expect("Failed to create HTTP client");
+
+        Self {
+            url: url.map(Arc::new),
+            client,
+            debounce,
+            last_sent: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Notifier configured from ALERT_WEBHOOK_URL and ALERT_DEBOUNCE_SECS (disabled when unset)
+    pub fn from_env() -> Self {
+        Self::new(get_alert_webhook_url(), Duration::from_secs(get_alert_debounce_secs()))
+    }
+
+    /// Record an alert as sent unless the same one went out within the debounce window
+    fn should_send(&self, alert: &Alert, now: Instant) -> bool {
+        let mut last_sent = self.last_sent.lock().unwrap();
+        let key = (alert.credential_id.clone(), alert.kind);
+        match last_sent.get(&key) {
+            Some(sent) if now.duration_since(*sent) < self.debounce => false,
+            _ => {
+                last_sent.insert(key, now);
+                true
+            }
+        }
+    }
+
+    /// Fire an alert in the background; a no-op when no alert webhook is configured
+    pub fn notify(&self, alert: Alert) {
+        let Some(url) = self.url.clone() else {
+            return;
+        };
+        if !self.should_send(&alert, Instant::now()) {
+            info!(
+                "Suppressing {:?} alert for credential {} (debounced)",
+                alert.kind, alert.credential_id
+            );
+            return;
+        }
+
+        let client = self.client.clone();
+        tokio::spawn(async move {
+            match client.post(url.as_str()).json(&alert).send().await {
+                Ok(response) if response.status().is_success() => {
+                    info!("Sent {:?} alert for credential {}", alert.kind, alert.credential_id);
+                }
+                Ok(response) => {
+                    warn!("Alert webhook returned HTTP {}", response.status());
+                }
+                Err(e) => error!("Failed to send alert: {}", e),
+            }
+        });
+    }
+}
+
+/// Get operator alert webhook URL from environment (ALERT_WEBHOOK_URL or ALERT_WEBHOOK_URL_FILE), if set
+pub fn get_alert_webhook_url() -> Option<String> {
+    crate::secrets::var_or_log("ALERT_WEBHOOK_URL")
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+/// Get alert debounce window from environment, default 300 seconds
+pub fn get_alert_debounce_secs() -> u64 {
+    std::env::var("ALERT_DEBOUNCE_SECS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(300)
+}
+
+/// Get consecutive reconnects that trigger an alert from environment, default 3
+pub fn get_alert_reconnect_threshold() -> u32 {
+    std::env::var("ALERT_RECONNECT_THRESHOLD")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .filter(|n| *n > 0)
+        .unwrap_or(3)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_alert_debounce() {
+        let notifier = AlertNotifier::new(None, Duration::from_secs(60));
+        let stopped = Alert::new(AlertKind::WorkerStopped, "cred", "Cred", None);
+        let now = Instant::now();
+
+        assert!(notifier.should_send(&stopped, now));
+        assert!(!notifier.should_send(&stopped, now + Duration::from_secs(30)));
+        assert!(notifier.should_send(&stopped, now + Duration::from_secs(61)));
+
+        // Other kinds and credentials are debounced independently
+        let reconnect = Alert::new(AlertKind::ReconnectThreshold, "cred", "Cred", None);
+        assert!(notifier.should_send(&reconnect, now));
+        let other = Alert::new(AlertKind::WorkerStopped, "other", "Other", None);
+        assert!(notifier.should_send(&other, now));
+    }
+
+    #[test]
+    fn test_delivery_alert_policy() {
+        let policy = DeliveryAlertPolicy::default();
+        assert_eq!(policy, DeliveryAlertPolicy::Failure);
+        assert_eq!(policy.alert_kind(false), Some(AlertKind::DeliveryFailed));
+        assert_eq!(policy.alert_kind(true), None);
+
+        assert_eq!(DeliveryAlertPolicy::Success.alert_kind(true), Some(AlertKind::DeliverySucceeded));
+        assert_eq!(DeliveryAlertPolicy::Success.alert_kind(false), None);
+        assert_eq!(DeliveryAlertPolicy::Both.alert_kind(false), Some(AlertKind::DeliveryFailed));
+        assert_eq!(DeliveryAlertPolicy::None.alert_kind(false), None);
+
+        let parsed: DeliveryAlertPolicy = serde_json::from_str("\"both\"").unwrap();
+        assert_eq!(parsed, DeliveryAlertPolicy::Both);
+    }
+}
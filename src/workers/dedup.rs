@@ -1,5 +1,5 @@
 use std::collections::HashMap;
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, RwLock};
 use std::time::{Duration, Instant};
 use tracing::debug;
 
@@ -66,26 +66,87 @@ impl DedupCache {
         hash
     }
 
+    /// Forget every remembered message, e.g. after the dedup settings changed. Returns
+    /// how many were still within the TTL (shared caches are cleared for the whole group).
+    pub fn clear(&self) -> usize {
+        let ttl = Duration::from_secs(self.ttl_seconds);
+        let now = Instant::now();
+        let mut cache = self.cache.write().unwrap();
+        let live = cache
+            .values()
+            .filter(|timestamp| now.duration_since(**timestamp) < ttl)
+            .count();
+        cache.clear();
+        live
+    }
+
     /// Get TTL in seconds
     pub fn ttl_seconds(&self) -> u64 {
         self.ttl_seconds
     }
 }
 
-/// Get dedup TTL from environment, default 5 seconds
+/// Dedup caches shared by the workers of each dedup group, so a message received by
+/// several credentials of a group is only processed once
+#[derive(Clone, Default)]
+pub struct DedupGroups {
+    caches: Arc<Mutex<HashMap<String, DedupCache>>>,
+}
+
+impl DedupGroups {
+    /// Cache for a worker: the group's shared one, or a fresh per-credential one without a
+    /// group. A group whose TTL no longer matches the setting gets a new cache.
+    pub fn cache_for(&self, group: Option<&str>, ttl_seconds: u64) -> DedupCache {
+        let Some(group) = group.filter(|g| !g.is_empty()) else {
+            return DedupCache::new(ttl_seconds);
+        };
+
+        let mut caches = self.caches.lock().unwrap();
+        let cache = caches
+            .entry(group.to_string())
+            .or_insert_with(|| DedupCache::new(ttl_seconds));
+        if cache.ttl_seconds() != ttl_seconds {
+            *cache = DedupCache::new(ttl_seconds);
+        }
+        cache.clone()
+    }
+}
+
+/// Get dedup TTL from the runtime settings (DEDUP_SECONDS), default 5 seconds
 pub fn get_dedup_ttl() -> u64 {
-    std::env::var("DEDUP_SECONDS")
-        .ok()
-        .and_then(|s| s.parse().ok())
-        .unwrap_or(5)
+    crate::settings::current().dedup_ttl_secs
 }
 
-/// Get max messages per credential from environment, default 50
+/// Get max messages per credential from the runtime settings (MAX_MESSAGES_PER_CREDENTIAL), default 50
 pub fn get_max_messages_per_credential() -> i64 {
-    std::env::var("MAX_MESSAGES_PER_CREDENTIAL")
-        .ok()
-        .and_then(|s| s.parse().ok())
-        .unwrap_or(50)
+    crate::settings::current().max_messages_per_credential
+}
+
+/// Payload as hashed for in-memory dedup: the JSON with `ignore_fields` (dotted paths) removed,
+/// so messages differing only in volatile fields such as timestamps count as duplicates.
+/// None when nothing is ignored or the payload isn't a JSON object (the raw payload is hashed).
+pub fn dedup_content(payload: &str, ignore_fields: &[String]) -> Option<String> {
+    if ignore_fields.is_empty() {
+        return None;
+    }
+
+    let mut value: serde_json::Value = serde_json::from_str(payload).ok()?;
+    if !value.is_object() {
+        return None;
+    }
+
+    for path in ignore_fields {
+        let mut keys: Vec<&str> = path.split('.').collect();
+        let Some(last) = keys.pop() else { continue };
+        let parent = keys
+            .iter()
+            .try_fold(&mut value, |v, key| v.get_mut(*key));
+        if let Some(serde_json::Value::Object(map)) = parent {
+            map.remove(last);
+        }
+    }
+
+    Some(value.to_string())
 }
 
 #[cfg(test)]
@@ -112,4 +173,34 @@ mod tests {
         // Same message should no longer be duplicate
         assert!(!cache.is_duplicate("test message"));
     }
+
+    #[test]
+    fn test_ignored_fields_are_stripped_before_hashing() {
+        let cache = DedupCache::new(60);
+        let ignore = vec!["data.sent_time".to_string(), "fcmMessageId".to_string()];
+        let first = r#"{"fcmMessageId":"a","data":{"order":"A-1","sent_time":"1700000000"}}"#;
+        let second = r#"{"data":{"sent_time":"1700000042","order":"A-1"},"fcmMessageId":"b"}"#;
+        let other = r#"{"fcmMessageId":"c","data":{"order":"A-2","sent_time":"1700000042"}}"#;
+
+        let key = |payload: &str| dedup_content(payload, &ignore).unwrap();
+        assert!(!cache.is_duplicate(&key(first)));
+        assert!(cache.is_duplicate(&key(second)));
+        assert!(!cache.is_duplicate(&key(other)));
+
+        // Nothing ignored, or not a JSON object: the raw payload is hashed
+        assert_eq!(dedup_content(first, &[]), None);
+        assert_eq!(dedup_content("not json", &ignore), None);
+    }
+
+    #[test]
+    fn test_dedup_cache_clear() {
+        let cache = DedupCache::new(60);
+        assert!(!cache.is_duplicate("a"));
+        assert!(!cache.is_duplicate("b"));
+        let shared = cache.clone();
+
+        assert_eq!(shared.clear(), 2);
+        assert!(!cache.is_duplicate("a"));
+        assert_eq!(cache.clear(), 1);
+    }
 }
@@ -0,0 +1,437 @@
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+use tokio::sync::{Notify, OwnedSemaphorePermit, Semaphore};
+use tracing::warn;
+use utoipa::ToSchema;
+
+/// What to do when a credential's delivery queue is full
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum OverflowPolicy {
+    /// Wait for space, applying backpressure to FCM ingestion
+    Block,
+    /// Discard the oldest queued message to make room
+    DropOldest,
+    /// Discard the incoming message
+    DropNewest,
+}
+
+impl FromStr for OverflowPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().replace('_', "-").as_str() {
+            "block" => Ok(Self::Block),
+            "drop-oldest" => Ok(Self::DropOldest),
+            "drop-newest" => Ok(Self::DropNewest),
+            other => Err(format!("Unknown overflow policy: {}", other)),
+        }
+    }
+}
+
+/// Delivery tuning of one credential: concurrent webhook deliveries and the queue in front of them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct DeliverySettings {
+    /// Messages delivered at the same time (DELIVERY_CONCURRENCY)
+    pub concurrency: usize,
+    /// Maximum queued messages (DELIVERY_QUEUE_CAPACITY)
+    pub queue_capacity: usize,
+    /// Policy applied when the queue is full (DELIVERY_QUEUE_POLICY)
+    pub queue_policy: OverflowPolicy,
+}
+
+/// Partial update of a credential's delivery settings
+#[derive(Debug, Default, Deserialize, ToSchema)]
+pub struct DeliverySettingsUpdate {
+    pub concurrency: Option<usize>,
+    pub queue_capacity: Option<usize>,
+    pub queue_policy: Option<OverflowPolicy>,
+}
+
+impl DeliverySettings {
+    pub fn from_env() -> Self {
+        Self {
+            concurrency: get_delivery_concurrency(),
+            queue_capacity: get_delivery_queue_capacity(),
+            queue_policy: get_delivery_queue_policy(),
+        }
+    }
+
+    /// Settings with `update` applied, or an error listing every invalid value
+    pub fn apply(&self, update: &DeliverySettingsUpdate) -> Result<Self, String> {
+        let mut errors = Vec::new();
+        if matches!(update.concurrency, Some(n) if !(1..=1024).contains(&n)) {
+            errors.push("concurrency must be between 1 and 1024");
+        }
+        if matches!(update.queue_capacity, Some(0)) {
+            errors.push("queue_capacity must be at least 1");
+        }
+        if !errors.is_empty() {
+            return Err(errors.join("; "));
+        }
+
+        Ok(Self {
+            concurrency: update.concurrency.unwrap_or(self.concurrency),
+            queue_capacity: update.queue_capacity.unwrap_or(self.queue_capacity),
+            queue_policy: update.queue_policy.unwrap_or(self.queue_policy),
+        })
+    }
+}
+
+/// Snapshot of a delivery queue for diagnostics
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct QueueStats {
+    /// Messages waiting for delivery
+    pub depth: usize,
+    /// Maximum queued messages
+    pub capacity: usize,
+    /// Policy applied when the queue is full
+    pub policy: OverflowPolicy,
+    /// Messages discarded because the queue was full
+    pub dropped: u64,
+    /// Messages being delivered right now
+    pub in_flight: usize,
+    /// Maximum messages delivered at the same time
+    pub concurrency: usize,
+}
+
+/// Concurrency limit that can be raised or lowered while deliveries hold permits.
+/// Lowering it takes permits out of circulation as deliveries return them.
+struct DeliveryPermits {
+    semaphore: Arc<Semaphore>,
+    limit: Mutex<usize>,
+    /// Permits still to be taken out of circulation after a lowered limit
+    retiring: Arc<AtomicUsize>,
+}
+
+impl DeliveryPermits {
+    fn new(limit: usize) -> Self {
+        let limit = limit.max(1);
+        Self {
+            semaphore: Arc::new(Semaphore::new(limit)),
+            limit: Mutex::new(limit),
+            retiring: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    fn limit(&self) -> usize {
+        *self.limit.lock().unwrap()
+    }
+
+    fn set_limit(&self, new_limit: usize) {
+        let new_limit = new_limit.max(1);
+        let mut limit = self.limit.lock().unwrap();
+        if new_limit > *limit {
+            self.semaphore.add_permits(new_limit - *limit);
+        } else if new_limit < *limit {
+            let excess = *limit - new_limit;
+            // Free permits go right away; the rest once the deliveries holding them finish
+            let pending = excess - self.semaphore.forget_permits(excess);
+            if pending > 0 {
+                self.retiring.fetch_add(pending, Ordering::SeqCst);
+                let semaphore = self.semaphore.clone();
+                let retiring = self.retiring.clone();
+                tokio::spawn(async move {
+                    if let Ok(permits) = semaphore.acquire_many_owned(pending as u32).await {
+                        permits.forget();
+                    }
+                    retiring.fetch_sub(pending, Ordering::SeqCst);
+                });
+            }
+        }
+        *limit = new_limit;
+    }
+
+    fn in_flight(&self) -> usize {
+        let outstanding = self.limit() + self.retiring.load(Ordering::SeqCst);
+        outstanding.saturating_sub(self.semaphore.available_permits())
+    }
+}
+
+/// Bounded queue between the FCM listener (blocking thread) and webhook delivery (async)
+#[derive(Clone)]
+pub struct DeliveryQueue {
+    inner: Arc<QueueInner>,
+}
+
+struct QueueInner {
+    items: Mutex<VecDeque<Vec<u8>>>,
+    not_full: Condvar,
+    not_empty: Notify,
+    capacity: AtomicUsize,
+    policy: Mutex<OverflowPolicy>,
+    dropped: AtomicU64,
+    closed: AtomicBool,
+    permits: DeliveryPermits,
+}
+
+impl DeliveryQueue {
+    pub fn new(settings: DeliverySettings) -> Self {
+        Self {
+            inner: Arc::new(QueueInner {
+                items: Mutex::new(VecDeque::new()),
+                not_full: Condvar::new(),
+                not_empty: Notify::new(),
+                capacity: AtomicUsize::new(settings.queue_capacity.max(1)),
+                policy: Mutex::new(settings.queue_policy),
+                dropped: AtomicU64::new(0),
+                closed: AtomicBool::new(false),
+                permits: DeliveryPermits::new(settings.concurrency),
+            }),
+        }
+    }
+
+    /// Current capacity, policy and concurrency
+    pub fn settings(&self) -> DeliverySettings {
+        DeliverySettings {
+            concurrency: self.inner.permits.limit(),
+            queue_capacity: self.inner.capacity.load(Ordering::SeqCst),
+            queue_policy: *self.inner.policy.lock().unwrap(),
+        }
+    }
+
+    /// Change capacity, policy and concurrency of a live queue. Deliveries in progress
+    /// finish; a smaller capacity applies to the next push.
+    pub fn apply(&self, settings: DeliverySettings) {
+        let inner = &self.inner;
+        {
+            let _items = inner.items.lock().unwrap();
+            inner.capacity.store(settings.queue_capacity.max(1), Ordering::SeqCst);
+            *inner.policy.lock().unwrap() = settings.queue_policy;
+        }
+        inner.not_full.notify_all();
+        inner.permits.set_limit(settings.concurrency);
+    }
+
+    /// Wait for a free delivery slot
+    pub async fn acquire_permit(&self) -> OwnedSemaphorePermit {
+        self.inner.permits.semaphore.clone().acquire_owned().await.unwrap()
+    }
+
+    /// Wait until every delivery slot has been returned
+    pub async fn wait_idle(&self) {
+        let _ = self.inner.permits.semaphore.acquire_many(self.inner.permits.limit() as u32).await;
+    }
+
+    /// Enqueue a payload, applying the overflow policy when full.
+    /// May block the calling thread with `OverflowPolicy::Block`, so never call it from async code.
+    /// Returns false if the payload was discarded.
+    pub fn push(&self, payload: Vec<u8>) -> bool {
+        let inner = &self.inner;
+        let mut items = inner.items.lock().unwrap();
+
+        loop {
+            let capacity = inner.capacity.load(Ordering::SeqCst);
+            if items.len() < capacity {
+                break;
+            }
+            if inner.closed.load(Ordering::SeqCst) {
+                return false;
+            }
+
+            let policy = *inner.policy.lock().unwrap();
+            match policy {
+                OverflowPolicy::Block => {
+                    // Wake up periodically so a closed queue never strands the listener
+                    items = inner
+                        .not_full
+                        .wait_timeout(items, Duration::from_secs(1))
+                        .unwrap()
+                        .0;
+                }
+                OverflowPolicy::DropOldest => {
+                    items.pop_front();
+                    let dropped = inner.dropped.fetch_add(1, Ordering::Relaxed) + 1;
+                    warn!(
+                        "Delivery queue full ({}), dropped oldest message (total dropped: {})",
+                        capacity, dropped
+                    );
+                }
+                OverflowPolicy::DropNewest => {
+                    let dropped = inner.dropped.fetch_add(1, Ordering::Relaxed) + 1;
+                    warn!(
+                        "Delivery queue full ({}), dropped incoming message (total dropped: {})",
+                        capacity, dropped
+                    );
+                    return false;
+                }
+            }
+        }
+
+        items.push_back(payload);
+        drop(items);
+        inner.not_empty.notify_one();
+        true
+    }
+
+    /// Wait for the next payload. Returns None once the queue is closed and drained.
+    pub async fn pop(&self) -> Option<Vec<u8>> {
+        loop {
+            {
+                let mut items = self.inner.items.lock().unwrap();
+                if let Some(payload) = items.pop_front() {
+                    self.inner.not_full.notify_one();
+                    return Some(payload);
+                }
+                if self.inner.closed.load(Ordering::SeqCst) {
+                    return None;
+                }
+            }
+            self.inner.not_empty.notified().await;
+        }
+    }
+
+    /// Stop accepting payloads and wake up any waiters
+    pub fn close(&self) {
+        self.inner.closed.store(true, Ordering::SeqCst);
+        self.inner.not_full.notify_all();
+        self.inner.not_empty.notify_one();
+    }
+
+    pub fn stats(&self) -> QueueStats {
+        let settings = self.settings();
+        QueueStats {
+            depth: self.inner.items.lock().unwrap().len(),
+            capacity: settings.queue_capacity,
+            policy: settings.queue_policy,
+            dropped: self.inner.dropped.load(Ordering::Relaxed),
+            in_flight: self.inner.permits.in_flight(),
+            concurrency: settings.concurrency,
+        }
+    }
+}
+
+/// Get delivery queue capacity per credential from environment, default 10000
+pub fn get_delivery_queue_capacity() -> usize {
+    std::env::var("DELIVERY_QUEUE_CAPACITY")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(10_000)
+}
+
+/// Get delivery queue overflow policy from environment, default drop-oldest
+pub fn get_delivery_queue_policy() -> OverflowPolicy {
+    std::env::var("DELIVERY_QUEUE_POLICY")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(OverflowPolicy::DropOldest)
+}
+
+/// Get max concurrent webhook deliveries per credential from environment, default 8
+pub fn get_delivery_concurrency() -> usize {
+    std::env::var("DELIVERY_CONCURRENCY")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .filter(|n| *n > 0)
+        .unwrap_or(8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_queue(capacity: usize, policy: OverflowPolicy) -> DeliveryQueue {
+        DeliveryQueue::new(DeliverySettings {
+            concurrency: 1,
+            queue_capacity: capacity,
+            queue_policy: policy,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_overflow_policies() {
+        let queue = new_queue(2, OverflowPolicy::DropOldest);
+        assert!(queue.push(b"1".to_vec()));
+        assert!(queue.push(b"2".to_vec()));
+        assert!(queue.push(b"3".to_vec()));
+        assert_eq!(queue.stats().dropped, 1);
+        assert_eq!(queue.pop().await, Some(b"2".to_vec()));
+
+        let queue = new_queue(2, OverflowPolicy::DropNewest);
+        assert!(queue.push(b"1".to_vec()));
+        assert!(queue.push(b"2".to_vec()));
+        assert!(!queue.push(b"3".to_vec()));
+        assert_eq!(queue.stats().dropped, 1);
+        assert_eq!(queue.pop().await, Some(b"1".to_vec()));
+    }
+
+    #[tokio::test]
+    async fn test_block_policy_waits_for_space() {
+        let queue = new_queue(1, OverflowPolicy::Block);
+        assert!(queue.push(b"1".to_vec()));
+
+        let producer = {
+            let queue = queue.clone();
+            std::thread::spawn(move || queue.push(b"2".to_vec()))
+        };
+
+        assert_eq!(queue.pop().await, Some(b"1".to_vec()));
+        assert!(producer.join().unwrap());
+        assert_eq!(queue.pop().await, Some(b"2".to_vec()));
+        assert_eq!(queue.stats().dropped, 0);
+
+        queue.close();
+        assert_eq!(queue.pop().await, None);
+    }
+
+    #[tokio::test]
+    async fn test_adjust_concurrency_at_runtime() {
+        let queue = DeliveryQueue::new(DeliverySettings {
+            concurrency: 2,
+            queue_capacity: 10,
+            queue_policy: OverflowPolicy::Block,
+        });
+        let first = queue.acquire_permit().await;
+        let second = queue.acquire_permit().await;
+        assert_eq!((queue.stats().in_flight, queue.stats().concurrency), (2, 2));
+
+        // Raising the limit frees a slot while both deliveries are still running
+        let raised = DeliverySettings { concurrency: 3, ..queue.settings() };
+        queue.apply(raised);
+        let third = tokio::time::timeout(Duration::from_millis(100), queue.acquire_permit())
+            .await
+            .expect("raised limit should free a slot");
+        assert_eq!((queue.stats().in_flight, queue.stats().concurrency), (3, 3));
+
+        // Lowering it to 1 waits for running deliveries instead of cancelling them
+        queue.apply(DeliverySettings { concurrency: 1, ..queue.settings() });
+        assert_eq!(queue.stats().in_flight, 3);
+        drop(first);
+        drop(second);
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert_eq!((queue.stats().in_flight, queue.stats().concurrency), (1, 1));
+        let blocked = tokio::time::timeout(Duration::from_millis(100), queue.acquire_permit()).await;
+        assert!(blocked.is_err(), "only one delivery may run after lowering the limit");
+
+        drop(third);
+        tokio::time::timeout(Duration::from_millis(100), queue.wait_idle())
+            .await
+            .expect("queue should be idle once every delivery finished");
+    }
+
+    #[test]
+    fn test_delivery_settings_update() {
+        let settings = DeliverySettings {
+            concurrency: 8,
+            queue_capacity: 100,
+            queue_policy: OverflowPolicy::DropOldest,
+        };
+        let update = DeliverySettingsUpdate {
+            concurrency: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(settings.apply(&update).unwrap(), DeliverySettings { concurrency: 2, ..settings });
+
+        let invalid = DeliverySettingsUpdate {
+            concurrency: Some(0),
+            queue_capacity: Some(0),
+            queue_policy: None,
+        };
+        let err = settings.apply(&invalid).unwrap_err();
+        assert!(err.contains("concurrency") && err.contains("queue_capacity"));
+    }
+}
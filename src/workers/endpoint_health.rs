@@ -0,0 +1,144 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Outcomes remembered per endpoint
+const WINDOW_SIZE: usize = 20;
+
+/// Outcomes older than this no longer count
+const WINDOW_AGE: Duration = Duration::from_secs(300);
+
+/// Recent failures needed before deliveries to an endpoint are slowed down
+const MIN_FAILURES: usize = 3;
+
+/// Delay once an endpoint starts being slowed down; doubles with every further recent failure
+const BASE_DELAY: Duration = Duration::from_millis(500);
+
+/// Get the ceiling on the shared per-endpoint delay from environment
+/// (ENDPOINT_BACKOFF_MAX_SECS), default 30 seconds; 0 disables endpoint backoff
+pub fn get_endpoint_backoff_max() -> Duration {
+    let secs = std::env::var("ENDPOINT_BACKOFF_MAX_SECS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(30);
+    Duration::from_secs(secs)
+}
+
+/// Last WINDOW_SIZE delivery attempts to one URL: when each happened and whether it succeeded
+type OutcomeWindow = VecDeque<(Instant, bool)>;
+
+/// Recent delivery outcomes per webhook URL, shared by every message and credential
+/// delivering to it, so a struggling receiver is paced as a whole instead of each
+/// message retrying on its own
+#[derive(Clone)]
+pub struct EndpointHealth {
+    outcomes: Arc<Mutex<HashMap<String, OutcomeWindow>>>,
+    max_delay: Duration,
+}
+
+impl EndpointHealth {
+    pub fn new(max_delay: Duration) -> Self {
+        Self {
+            outcomes: Arc::new(Mutex::new(HashMap::new())),
+            max_delay,
+        }
+    }
+
+    /// Record whether a delivery attempt to `url` succeeded
+    pub fn record(&self, url: &str, success: bool) {
+        self.record_at(url, success, Instant::now());
+    }
+
+    fn record_at(&self, url: &str, success: bool, now: Instant) {
+        let mut outcomes = self.outcomes.lock().unwrap();
+        let window = outcomes.entry(url.to_string()).or_default();
+        window.push_back((now, success));
+        if window.len() > WINDOW_SIZE {
+            window.pop_front();
+        }
+    }
+
+    /// How long to wait before the next attempt to `url`. Zero while fewer than
+    /// MIN_FAILURES of the recent attempts failed or most of them succeeded,
+    /// then doubling per additional failure up to the configured ceiling.
+    pub fn delay(&self, url: &str) -> Duration {
+        self.delay_at(url, Instant::now())
+    }
+
+    fn delay_at(&self, url: &str, now: Instant) -> Duration {
+        if self.max_delay.is_zero() {
+            return Duration::ZERO;
+        }
+
+        let mut outcomes = self.outcomes.lock().unwrap();
+        let Some(window) = outcomes.get_mut(url) else {
+            return Duration::ZERO;
+        };
+
+        while matches!(window.front(), Some((at, _)) if now.duration_since(*at) > WINDOW_AGE) {
+            window.pop_front();
+        }
+        if window.is_empty() {
+            outcomes.remove(url);
+            return Duration::ZERO;
+        }
+
+        let failures = window.iter().filter(|(_, success)| !success).count();
+        if failures < MIN_FAILURES || failures * 2 < window.len() {
+            return Duration::ZERO;
+        }
+
+        let exponent = (failures - MIN_FAILURES).min(16) as u32;
+        BASE_DELAY.saturating_mul(2u32.pow(exponent)).min(self.max_delay)
+    }
+}
+
+impl Default for EndpointHealth {
+    fn default() -> Self {
+        Self::new(get_endpoint_backoff_max())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delay_follows_recent_failures() {
+        let health = EndpointHealth::new(Duration::from_secs(4));
+        let url = "http://receiver/hook";
+        let now = Instant::now();
+
+        assert_eq!(health.delay_at(url, now), Duration::ZERO);
+
+        for _ in 0..2 {
+            health.record_at(url, false, now);
+        }
+        assert_eq!(health.delay_at(url, now), Duration::ZERO);
+
+        health.record_at(url, false, now);
+        assert_eq!(health.delay_at(url, now), BASE_DELAY);
+        health.record_at(url, false, now);
+        assert_eq!(health.delay_at(url, now), BASE_DELAY * 2);
+        for _ in 0..5 {
+            health.record_at(url, false, now);
+        }
+        assert_eq!(health.delay_at(url, now), Duration::from_secs(4));
+
+        // Other endpoints are unaffected
+        assert_eq!(health.delay_at("http://other/hook", now), Duration::ZERO);
+
+        // Mostly successful again: no delay
+        for _ in 0..10 {
+            health.record_at(url, true, now);
+        }
+        assert_eq!(health.delay_at(url, now), Duration::ZERO);
+
+        // Old failures expire
+        let fresh = EndpointHealth::new(Duration::from_secs(4));
+        for _ in 0..5 {
+            fresh.record_at(url, false, now);
+        }
+        assert_eq!(fresh.delay_at(url, now + WINDOW_AGE * 2), Duration::ZERO);
+    }
+}
@@ -0,0 +1,179 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::sync::broadcast;
+use utoipa::ToSchema;
+
+/// Get the number of live events buffered per stream subscriber from environment
+/// (EVENT_STREAM_CAPACITY), default 1024. A subscriber further behind skips the oldest ones.
+pub fn get_event_stream_capacity() -> usize {
+    std::env::var("EVENT_STREAM_CAPACITY")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .filter(|n| *n > 0)
+        .unwrap_or(1024)
+}
+
+/// Live event sent on GET /api/stream, named by its `type` in the SSE `event:` field
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StreamEvent {
+    /// A message was received and logged
+    Message {
+        message_id: String,
+        credential_id: String,
+        fcm_message_id: Option<String>,
+        received_at: DateTime<Utc>,
+    },
+    /// A webhook delivery of a message finished, after its last attempt
+    Delivery {
+        message_id: String,
+        credential_id: String,
+        /// Final HTTP status (0 = failed without a usable response)
+        status: i32,
+        success: bool,
+        /// Requests sent, including retries
+        attempts: u32,
+        /// Last error when the delivery failed
+        error: Option<String>,
+    },
+}
+
+impl StreamEvent {
+    /// SSE event name
+    pub fn name(&self) -> &'static str {
+        match self {
+            StreamEvent::Message { .. } => "message",
+            StreamEvent::Delivery { .. } => "delivery",
+        }
+    }
+
+    pub fn credential_id(&self) -> &str {
+        match self {
+            StreamEvent::Message { credential_id, .. } | StreamEvent::Delivery { credential_id, .. } => {
+                credential_id
+            }
+        }
+    }
+}
+
+/// Fan-out of live events to stream subscribers. Publishing never waits, so a slow
+/// subscriber can't hold up message ingestion: with nobody listening events are dropped,
+/// and lagging subscribers lose the oldest ones (counted in `dropped`).
+#[derive(Clone)]
+pub struct EventBus {
+    tx: broadcast::Sender<StreamEvent>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new(get_event_stream_capacity())
+    }
+}
+
+impl EventBus {
+    /// Bus buffering `capacity` events per subscriber (at least 1)
+    pub fn new(capacity: usize) -> Self {
+        let (tx, _) = broadcast::channel(capacity.max(1));
+        Self {
+            tx,
+            dropped: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    pub fn publish(&self, event: StreamEvent) {
+        // Only fails when there are no subscribers; never blocks on a full buffer
+        let _ = self.tx.send(event);
+    }
+
+    /// Record events a lagging subscriber skipped
+    pub fn record_dropped(&self, count: u64) {
+        self.dropped.fetch_add(count, Ordering::Relaxed);
+    }
+
+    /// Events skipped by lagging subscribers since startup
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<StreamEvent> {
+        self.tx.subscribe()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_events_reach_subscribers() {
+        let bus = EventBus::default();
+        // No subscribers: dropped without error
+        bus.publish(StreamEvent::Delivery {
+            message_id: "m0".to_string(),
+            credential_id: "cred".to_string(),
+            status: 200,
+            success: true,
+            attempts: 1,
+            error: None,
+        });
+
+        let mut rx = bus.subscribe();
+        let event = StreamEvent::Delivery {
+            message_id: "m1".to_string(),
+            credential_id: "cred".to_string(),
+            status: 0,
+            success: false,
+            attempts: 3,
+            error: Some("HTTP 500".to_string()),
+        };
+        bus.publish(event.clone());
+
+        let received = rx.recv().await.unwrap();
+        assert_eq!(received, event);
+        assert_eq!(received.name(), "delivery");
+        assert_eq!(received.credential_id(), "cred");
+
+        let json = serde_json::to_value(&received).unwrap();
+        assert_eq!(json["type"], "delivery");
+        assert_eq!(json["attempts"], 3);
+    }
+
+    #[tokio::test]
+    async fn test_slow_subscriber_does_not_block_publish() {
+        let bus = EventBus::new(8);
+        // Subscribed but never reading while a burst is published
+        let mut slow = bus.subscribe();
+
+        let burst = async {
+            for i in 0..1000 {
+                bus.publish(StreamEvent::Message {
+                    message_id: format!("m{}", i),
+                    credential_id: "cred".to_string(),
+                    fcm_message_id: None,
+                    received_at: Utc::now(),
+                });
+            }
+        };
+        tokio::time::timeout(std::time::Duration::from_secs(1), burst)
+            .await
+            .expect("publishing blocked on a slow subscriber");
+
+        // The subscriber skipped all but the newest buffered events
+        match slow.recv().await {
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                assert_eq!(skipped, 992);
+                bus.record_dropped(skipped);
+            }
+            other => panic!("expected lag, got {:?}", other),
+        }
+        assert_eq!(bus.dropped(), 992);
+
+        let StreamEvent::Message { message_id, .. } = slow.recv().await.unwrap() else {
+            panic!("expected a message event");
+        };
+        assert_eq!(message_id, "m992");
+    }
+}
@@ -1,14 +1,24 @@
-use crate::db::Repository;
-use crate::models::{Credential, MessageLog};
-use crate::workers::{WebhookClient, DedupCache, get_dedup_ttl};
+use crate::db::{MessageLogWriter, MessageStore, Repository};
+use crate::error::{AppError, AppResult};
+use crate::models::{Credential, MessageLog, PayloadEncoding};
+use crate::workers::{
+    dedup_content, get_alert_reconnect_threshold, get_dedup_ttl, get_log_payloads,
+    signed_headers, Alert, AlertKind, AlertNotifier, DedupCache, DeliveryAlertPolicy,
+    DeliveryQueue, JsonFormat, LifecycleEvent, LifecycleEventKind, LifecycleNotifier, PubSubSink,
+    QueueStats, RegistrationLimiter, SinkConfig, StreamEvent, WebhookClient, WebhookSigning,
+};
+use chrono::{DateTime, Utc};
 use fcm_receiver_rs::client::FcmClient;
-use std::sync::Arc;
-use std::time::Duration;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
 use tokio::sync::watch;
-use tracing::{debug, error, info, warn};
+use tracing::{debug, error, info, instrument, warn, Span};
+use utoipa::ToSchema;
 
 /// Registration result from FCM
-struct FcmRegistration {
+pub struct FcmRegistration {
     fcm_token: String,
     gcm_token: String,
     android_id: u64,
@@ -17,13 +27,315 @@ struct FcmRegistration {
     auth_secret_b64: String,
 }
 
+impl FcmRegistration {
+    /// Copy the device tokens and keys onto a credential (not saved)
+    pub fn apply_to(self, credential: &mut Credential) {
+        credential.fcm_token = Some(self.fcm_token);
+        credential.gcm_token = Some(self.gcm_token);
+        credential.android_id = Some(self.android_id as i64);
+        credential.security_token = Some(self.security_token as i64);
+        credential.private_key_base64 = Some(self.private_key_b64);
+        credential.auth_secret_base64 = Some(self.auth_secret_b64);
+    }
+}
+
+/// Register a new FCM device for a credential. Registration is blocking, so it runs on the
+/// credential's own thread.
+pub async fn register_device(credential: &Credential) -> AppResult<FcmRegistration> {
+    let api_key = credential.api_key.clone();
+    let app_id = credential.app_id.clone();
+    let project_id = credential.project_id.clone();
+
+    run_blocking_named(&credential.id, move || -> anyhow::Result<FcmRegistration> {
+        let mut client = FcmClient::new(api_key, app_id, project_id)?;
+
+        let (private_key_b64, auth_secret_b64) = client.create_new_keys()?;
+        client.load_keys(&private_key_b64, &auth_secret_b64)?;
+
+        let (fcm_token, gcm_token, android_id, security_token) = client.register()?;
+
+        Ok(FcmRegistration {
+            fcm_token,
+            gcm_token,
+            android_id,
+            security_token,
+            private_key_b64,
+            auth_secret_b64,
+        })
+    })
+    .await?
+    .map_err(|e| FcmWorker::classify_error(e, AppError::FcmRegistration))
+}
+
+/// Last classified error reported by a worker
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct WorkerError {
+    /// Error type (fcm_registration_error, fcm_connection_error, fcm_decryption_error, ...)
+    pub kind: String,
+    /// Error message
+    pub message: String,
+    /// Whether retrying is unlikely to help (e.g. rejected credentials)
+    pub permanent: bool,
+    /// When the error occurred
+    pub occurred_at: DateTime<Utc>,
+}
+
+impl From<&AppError> for WorkerError {
+    fn from(err: &AppError) -> Self {
+        Self {
+            kind: err.error_type().to_string(),
+            message: err.message().to_string(),
+            permanent: err.is_permanent(),
+            occurred_at: Utc::now(),
+        }
+    }
+}
+
+/// Get the default reconnect delay ceiling from environment (MAX_RECONNECT_BACKOFF_SECS), default 320s
+pub fn get_max_reconnect_backoff() -> Duration {
+    let secs = std::env::var("MAX_RECONNECT_BACKOFF_SECS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .filter(|n| *n > 0)
+        .unwrap_or(320);
+    Duration::from_secs(secs)
+}
+
+/// Exponential reconnect delay for `attempt` (1-based), never exceeding `ceiling`
+pub fn reconnect_delay(base: Duration, attempt: u32, ceiling: Duration) -> Duration {
+    let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+    base.saturating_mul(factor).min(ceiling)
+}
+
+/// Name of the thread running a credential's blocking FCM client work. Linux keeps only
+/// the first 15 bytes of a thread name, so the id is shortened to fit.
+pub fn worker_thread_name(cred_id: &str) -> String {
+    format!("fcm-{}", cred_id.chars().take(11).collect::<String>())
+}
+
+/// Run blocking FCM client work on a thread named after the credential, inside the
+/// caller's tracing span so its logs carry the credential fields
+async fn run_blocking_named<T, F>(cred_id: &str, work: F) -> AppResult<T>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    let span = Span::current();
+    let (tx, rx) = tokio::sync::oneshot::channel();
+
+    std::thread::Builder::new()
+        .name(worker_thread_name(cred_id))
+        .spawn(move || {
+            let _entered = span.enter();
+            let _ = tx.send(work());
+        })
+        .map_err(|e| AppError::Internal(format!("Failed to spawn FCM client thread: {}", e)))?;
+
+    rx.await
+        .map_err(|_| AppError::Internal("FCM client thread panicked".to_string()))
+}
+
+/// Window used for the per-worker message rate
+pub const MESSAGE_RATE_WINDOW: Duration = Duration::from_secs(60);
+
+/// Sliding-window count of received messages, kept in one-second buckets
+#[derive(Debug, Clone, Default)]
+pub struct MessageRate {
+    buckets: VecDeque<(Instant, u64)>,
+}
+
+impl MessageRate {
+    pub fn record(&mut self, now: Instant) {
+        match self.buckets.back_mut() {
+            Some((start, count)) if now.duration_since(*start) < Duration::from_secs(1) => {
+                *count += 1;
+            }
+            _ => self.buckets.push_back((now, 1)),
+        }
+
+        while let Some((start, _)) = self.buckets.front() {
+            if now.duration_since(*start) < MESSAGE_RATE_WINDOW {
+                break;
+            }
+            self.buckets.pop_front();
+        }
+    }
+
+    /// Messages received within the window ending at `now`
+    pub fn count(&self, now: Instant) -> u64 {
+        self.buckets
+            .iter()
+            .filter(|(start, _)| now.duration_since(*start) < MESSAGE_RATE_WINDOW)
+            .map(|(_, count)| count)
+            .sum()
+    }
+
+    /// Average messages per second over the window ending at `now`
+    pub fn per_second(&self, now: Instant) -> f64 {
+        self.count(now) as f64 / MESSAGE_RATE_WINDOW.as_secs_f64()
+    }
+}
+
+/// Get how often failed topic subscriptions are retried while connected, from environment
+/// (TOPIC_RETRY_INTERVAL_SECS), default 60s; 0 disables retries (topics are still
+/// subscribed again on every reconnect)
+pub fn get_topic_retry_interval() -> Option<Duration> {
+    let secs = std::env::var("TOPIC_RETRY_INTERVAL_SECS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(60);
+    (secs > 0).then(|| Duration::from_secs(secs))
+}
+
+/// Subscription state of a topic
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum TopicState {
+    /// Not attempted yet on the current connection
+    Pending,
+    Subscribed,
+    /// Last attempt failed; retried every TOPIC_RETRY_INTERVAL_SECS
+    Failed,
+    /// No worker is running, so nothing is received for the topic
+    Inactive,
+}
+
+/// Outcome of subscribing a worker to one topic
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct TopicSubscription {
+    pub state: TopicState,
+    /// Error of the last failed attempt
+    pub error: Option<String>,
+    /// Attempts since the worker started
+    pub attempts: u32,
+    /// When the state last changed
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Runtime status of a worker, shared with the listener pool
+#[derive(Debug, Clone, Default)]
+pub struct WorkerStatus {
+    pub retry_count: u32,
+    pub last_error: Option<WorkerError>,
+    pub queue: Option<QueueStats>,
+    /// When the current FCM connection started listening (None while disconnected)
+    pub connected_since: Option<DateTime<Utc>>,
+    pub message_rate: MessageRate,
+    /// Subscription state per topic
+    pub topics: BTreeMap<String, TopicSubscription>,
+}
+
+impl WorkerStatus {
+    /// Start tracking `topics` on a new connection, keeping attempt counts of known ones
+    pub fn reset_topics(&mut self, topics: &[String]) {
+        let now = Utc::now();
+        let previous = std::mem::take(&mut self.topics);
+        self.topics = topics
+            .iter()
+            .map(|topic| {
+                let attempts = previous.get(topic).map(|s| s.attempts).unwrap_or(0);
+                let subscription = TopicSubscription {
+                    state: TopicState::Pending,
+                    error: None,
+                    attempts,
+                    updated_at: now,
+                };
+                (topic.clone(), subscription)
+            })
+            .collect();
+    }
+
+    /// Record the outcome of a subscription attempt
+    pub fn record_topic(&mut self, topic: &str, result: Result<(), String>) {
+        let subscription = self
+            .topics
+            .entry(topic.to_string())
+            .or_insert_with(|| TopicSubscription {
+                state: TopicState::Pending,
+                error: None,
+                attempts: 0,
+                updated_at: Utc::now(),
+            });
+        subscription.attempts += 1;
+        subscription.updated_at = Utc::now();
+        match result {
+            Ok(()) => {
+                subscription.state = TopicState::Subscribed;
+                subscription.error = None;
+            }
+            Err(e) => {
+                subscription.state = TopicState::Failed;
+                subscription.error = Some(e);
+            }
+        }
+    }
+}
+
+pub type SharedWorkerStatus = Arc<RwLock<WorkerStatus>>;
+
 /// Individual FCM listener worker for a single credential
 pub struct FcmWorker {
     credential: Credential,
     repo: Repository,
     webhook_client: WebhookClient,
+    alerts: AlertNotifier,
     shutdown_rx: watch::Receiver<bool>,
     dedup_cache: DedupCache,
+    status: SharedWorkerStatus,
+    queue: DeliveryQueue,
+    message_store: Arc<dyn MessageStore>,
+    log_writer: Option<MessageLogWriter>,
+    registrations: RegistrationLimiter,
+    lifecycle: LifecycleNotifier,
+}
+
+/// Webhook response recorded for messages received while delivery is disabled.
+/// They are skipped with status 0, which keeps them eligible for POST /api/messages/retry-failed once re-enabled.
+pub const DELIVERY_SKIPPED: &str = "Delivery skipped: webhook delivery disabled for this credential";
+
+/// Response recorded on messages of a credential without a webhook
+pub const DELIVERY_NO_WEBHOOK: &str = "Delivery skipped: no webhook configured for this credential";
+
+/// Processes received payloads for one credential: dedup, logging and webhook delivery
+#[derive(Clone)]
+struct MessageHandler {
+    cred_id: String,
+    cred_name: String,
+    webhook_url: String,
+    topic_webhooks: HashMap<String, String>,
+    /// Set when the credential publishes to Pub/Sub instead of calling webhooks
+    pubsub: Option<PubSubSink>,
+    webhook_headers: Option<HashMap<String, String>>,
+    /// HMAC secrets and Ed25519 key used to sign webhook bodies
+    signing: WebhookSigning,
+    capture_response: bool,
+    /// Payload fields copied into each message log (name -> dotted JSON path)
+    extract_fields: HashMap<String, String>,
+    /// When false, messages are logged but not delivered
+    webhook_enabled: bool,
+    /// How payloads are stored; base64 keeps binary payloads byte-exact
+    payload_encoding: PayloadEncoding,
+    /// Payload paths left out of the in-memory dedup hash
+    dedup_ignore_fields: Vec<String>,
+    /// Format of delivered JSON bodies
+    json_format: JsonFormat,
+    /// When false, the log keeps metadata and a payload hash; delivery uses the in-memory body
+    store_payload: bool,
+    /// Payload path of the sender's message id, used for dedup instead of fcmMessageId
+    dedup_key_path: Option<String>,
+    /// Accepted "from" values; empty accepts every sender
+    allowed_senders: Vec<String>,
+    max_messages: i64,
+    /// Where received messages are written
+    message_store: Arc<dyn MessageStore>,
+    repo: Repository,
+    /// Batches log inserts and runs the max_messages cleanup itself when set
+    log_writer: Option<MessageLogWriter>,
+    webhook_client: WebhookClient,
+    dedup_cache: DedupCache,
+    alerts: AlertNotifier,
+    /// Delivery outcomes reported to the operator alert webhook
+    delivery_alerts: DeliveryAlertPolicy,
 }
 
 impl FcmWorker {
@@ -31,30 +343,126 @@ impl FcmWorker {
         credential: Credential,
         repo: Repository,
         webhook_client: WebhookClient,
+        alerts: AlertNotifier,
         shutdown_rx: watch::Receiver<bool>,
+        status: SharedWorkerStatus,
+        queue: DeliveryQueue,
     ) -> Self {
         let dedup_ttl = get_dedup_ttl();
         info!("Dedup TTL: {} seconds", dedup_ttl);
         
         Self {
             credential,
+            message_store: Arc::new(repo.clone()),
             repo,
             webhook_client,
+            alerts,
             shutdown_rx,
             dedup_cache: DedupCache::new(dedup_ttl),
+            status,
+            queue,
+            log_writer: None,
+            registrations: RegistrationLimiter::from_env(),
+            lifecycle: LifecycleNotifier::from_env(),
         }
     }
 
-    /// Main worker loop
+    /// Dedup against `dedup_cache` instead of a cache of this worker's own, e.g. one shared
+    /// by the credential's dedup group
+    pub fn with_dedup_cache(mut self, dedup_cache: DedupCache) -> Self {
+        self.dedup_cache = dedup_cache;
+        self
+    }
+
+    /// Share registration attempt limits with the pool's other workers, so they survive restarts
+    pub fn with_registration_limiter(mut self, registrations: RegistrationLimiter) -> Self {
+        self.registrations = registrations;
+        self
+    }
+
+    /// Write received messages to `store` instead of the SQLite tables
+    pub fn with_message_store(mut self, store: Arc<dyn MessageStore>) -> Self {
+        self.message_store = store;
+        self
+    }
+
+    /// Report registrations and failures through the pool's lifecycle notifier, so its
+    /// debounce covers every event of the credential
+    pub fn with_lifecycle_notifier(mut self, lifecycle: LifecycleNotifier) -> Self {
+        self.lifecycle = lifecycle;
+        self
+    }
+
+    /// Write message logs through a shared batching writer instead of one INSERT each
+    pub fn with_log_writer(mut self, log_writer: Option<MessageLogWriter>) -> Self {
+        self.log_writer = log_writer;
+        self
+    }
+
+    /// Main worker loop. Everything it logs, including delivery, carries the credential id and name.
+    #[instrument(
+        name = "worker",
+        skip_all,
+        fields(credential_id = %self.credential.id, name = %self.credential.name)
+    )]
     pub async fn run(mut self) {
         let cred_id = self.credential.id.clone();
         let cred_name = self.credential.name.clone();
         
         info!("Starting FCM worker for credential: {} ({})", cred_name, cred_id);
+        if self.credential.webhook_enabled && self.credential.webhook_blocked_unverified() {
+            warn!(
+                "Webhook for {} is not verified; messages are logged but not delivered",
+                cred_name
+            );
+        }
+        if !self.credential.has_webhook() && self.credential.sink() == SinkConfig::Webhook {
+            info!("No webhook for {}; messages are logged for polling only", cred_name);
+        }
+
+        // Deliver queued messages in the background for the lifetime of the worker
+        let handler = MessageHandler {
+            cred_id: cred_id.clone(),
+            cred_name: cred_name.clone(),
+            webhook_url: self.credential.webhook_url.clone(),
+            topic_webhooks: self.credential.get_topic_webhooks().unwrap_or_default(),
+            pubsub: PubSubSink::from_config(&self.credential.sink()),
+            webhook_headers: self.credential.get_webhook_headers(),
+            signing: self.credential.webhook_signing(),
+            capture_response: self.credential.capture_webhook_response,
+            extract_fields: self.credential.get_extract_fields().unwrap_or_default(),
+            webhook_enabled: self.credential.webhook_enabled
+                && !self.credential.webhook_blocked_unverified(),
+            payload_encoding: self.credential.payload_encoding(),
+            dedup_ignore_fields: self.credential.dedup_ignore_fields(),
+            json_format: self.credential.json_format(),
+            store_payload: self.credential.store_payload,
+            dedup_key_path: self.credential.dedup_key_path.clone(),
+            allowed_senders: self.credential.allowed_senders(),
+            max_messages: self.credential.effective_max_messages(),
+            message_store: self.message_store.clone(),
+            repo: self.repo.clone(),
+            log_writer: self.log_writer.clone(),
+            webhook_client: match self.credential.proxy_override() {
+                Some(proxy) => self.webhook_client.via_proxy(proxy),
+                None => self.webhook_client.clone(),
+            }
+            .with_retry_on(self.credential.retry_on())
+            .with_auth(self.credential.webhook_auth())
+            .with_user_agent(self.credential.webhook_user_agent.clone())
+            .with_timeouts(self.credential.webhook_timeouts())
+            .with_response_match(self.credential.webhook_response_match()),
+            dedup_cache: self.dedup_cache.clone(),
+            alerts: self.alerts.clone(),
+            delivery_alerts: self.credential.delivery_alerts(),
+        };
+        let delivery = tokio::spawn(Self::run_delivery(self.queue.clone(), handler));
 
         let mut retry_count = 0;
         let max_retries = 10;
         let base_delay = Duration::from_secs(5);
+        let max_delay = self.credential.effective_max_backoff();
+        let alert_threshold = get_alert_reconnect_threshold();
 
         loop {
             // Check for shutdown
@@ -72,12 +480,41 @@ impl FcmWorker {
                     error!("Listener error for {}: {}", cred_name, e);
                     retry_count += 1;
 
+                    {
+                        let mut status = self.status.write().unwrap();
+                        status.retry_count = retry_count;
+                        status.last_error = Some(WorkerError::from(&e));
+                    }
+
+                    if matches!(e, AppError::FcmRegistration(_)) {
+                        self.alert(AlertKind::RegistrationFailed, &e);
+                    }
+
+                    if self.registrations.needs_attention(&cred_id).is_some() {
+                        error!("Registration keeps failing for {}. Worker stopping; needs attention.", cred_name);
+                        self.alert(AlertKind::WorkerStopped, &e);
+                        self.notify_failed(&e);
+                        break;
+                    }
+
                     if retry_count > max_retries {
                         error!("Max retries ({}) reached for {}. Worker stopping.", max_retries, cred_name);
+                        self.alert(AlertKind::WorkerStopped, &e);
+                        self.notify_failed(&e);
                         break;
                     }
 
-                    let delay = base_delay * 2u32.pow((retry_count - 1).min(6));
+                    if retry_count == alert_threshold {
+                        self.alert(AlertKind::ReconnectThreshold, &e);
+                    }
+
+                    // Don't come back before a registration cooldown has passed
+                    let cooldown = e
+                        .limit_info()
+                        .and_then(|info| info.retry_after_secs)
+                        .map(Duration::from_secs)
+                        .unwrap_or_default();
+                    let delay = reconnect_delay(base_delay, retry_count, max_delay).max(cooldown);
                     warn!(
                         "Reconnecting {} in {:?} (attempt {}/{})",
                         cred_name, delay, retry_count, max_retries
@@ -97,88 +534,76 @@ impl FcmWorker {
             }
         }
 
+        // Let the delivery task drain what is already queued and in flight, then exit.
+        // The pool stops waiting after SHUTDOWN_GRACE_SECS.
+        self.queue.close();
+        let _ = delivery.await;
+
         info!("FCM worker stopped for: {} ({})", cred_name, cred_id);
     }
 
-    async fn run_listener(&mut self) -> anyhow::Result<()> {
-        // Clone all needed values upfront
+    /// Notify the operator alert webhook (if configured) about this worker
+    fn alert(&self, kind: AlertKind, error: &AppError) {
+        self.alerts.notify(Alert::new(
+            kind,
+            &self.credential.id,
+            &self.credential.name,
+            Some(error.to_string()),
+        ));
+    }
+
+    /// Send a lifecycle event for this credential (to its own lifecycle webhook, if it has one)
+    fn notify_lifecycle(&self, kind: LifecycleEventKind, details: serde_json::Value) {
+        self.lifecycle.notify(
+            LifecycleEvent::new(kind, &self.credential.id).with_details(details),
+            self.credential.lifecycle_webhook_url.as_deref(),
+        );
+    }
+
+    /// Report that the worker gave up and won't reconnect on its own
+    fn notify_failed(&self, error: &AppError) {
+        self.notify_lifecycle(
+            LifecycleEventKind::WorkerFailed,
+            serde_json::json!({ "name": self.credential.name, "error": error.to_string() }),
+        );
+    }
+
+    /// Pull payloads off the queue and process them, at most the queue's concurrency at a
+    /// time (DELIVERY_CONCURRENCY unless the credential overrides it)
+    async fn run_delivery(queue: DeliveryQueue, handler: MessageHandler) {
+        loop {
+            // Take a permit first so the queue fills up while every slot is busy
+            let permit = queue.acquire_permit().await;
+            let Some(payload) = queue.pop().await else {
+                break;
+            };
+
+            let handler = handler.clone();
+            tokio::spawn(async move {
+                handler.handle(payload).await;
+                drop(permit);
+            });
+        }
+
+        // Queue closed: wait for deliveries still in progress
+        queue.wait_idle().await;
+    }
+
+    #[instrument(name = "listener", skip_all)]
+    async fn run_listener(&mut self) -> AppResult<()> {
         let cred_id = self.credential.id.clone();
         let cred_name = self.credential.name.clone();
-        let api_key = self.credential.api_key.clone();
-        let app_id = self.credential.app_id.clone();
-        let project_id = self.credential.project_id.clone();
         let has_fcm_token = self.credential.fcm_token.is_some() && self.credential.private_key_base64.is_some();
-        
-        // Clone existing credentials if we have them
-        let existing_fcm_token = self.credential.fcm_token.clone();
-        let existing_gcm_token = self.credential.gcm_token.clone();
-        let existing_android_id = self.credential.android_id;
-        let existing_security_token = self.credential.security_token;
-        let existing_private_key = self.credential.private_key_base64.clone();
-        let existing_auth_secret = self.credential.auth_secret_base64.clone();
 
         // Check if we need to register or load existing credentials
         if has_fcm_token {
             debug!("Loading existing FCM credentials for: {}", cred_name);
-            
-            // Run client setup and listening in blocking task
-            let webhook_url = self.credential.webhook_url.clone();
-            let webhook_headers = self.credential.get_webhook_headers();
-            let repo = self.repo.clone();
-            let webhook_client = self.webhook_client.clone();
-            let dedup_cache = self.dedup_cache.clone();
-            let topics = self.repo.get_credential_topics(&cred_id).await?;
-            
-            // Use spawn_blocking for FCM client operations
-            let result = tokio::task::spawn_blocking(move || {
-                Self::run_fcm_client_existing(
-                    api_key,
-                    app_id,
-                    project_id,
-                    existing_fcm_token.unwrap(),
-                    existing_gcm_token,
-                    existing_android_id.unwrap_or(0) as u64,
-                    existing_security_token.unwrap_or(0) as u64,
-                    existing_private_key.unwrap(),
-                    existing_auth_secret.unwrap(),
-                    cred_id,
-                    cred_name,
-                    webhook_url,
-                    webhook_headers,
-                    repo,
-                    webhook_client,
-                    dedup_cache,
-                    topics,
-                )
-            }).await??;
-            
-            Ok(result)
         } else {
-            // Register new device - this is blocking so use spawn_blocking
             info!("Registering new FCM device for: {}", cred_name);
-            
-            let api_key_clone = api_key.clone();
-            let app_id_clone = app_id.clone();
-            let project_id_clone = project_id.clone();
-            
-            let registration = tokio::task::spawn_blocking(move || -> anyhow::Result<FcmRegistration> {
-                let mut client = FcmClient::new(api_key_clone, app_id_clone, project_id_clone)?;
-                
-                let (private_key_b64, auth_secret_b64) = client.create_new_keys()?;
-                client.load_keys(&private_key_b64, &auth_secret_b64)?;
-                
-                let (fcm_token, gcm_token, android_id, security_token) = client.register()?;
-                
-                Ok(FcmRegistration {
-                    fcm_token,
-                    gcm_token,
-                    android_id,
-                    security_token,
-                    private_key_b64,
-                    auth_secret_b64,
-                })
-            }).await??;
-            
+            self.registrations.try_acquire(&cred_id)?;
+            let registration = register_device(&self.credential).await?;
+            self.registrations.reset(&cred_id);
+
             // Save registration to database
             self.repo
                 .update_credential_registration(
@@ -193,167 +618,778 @@ impl FcmWorker {
                 .await?;
 
             // Update local credential
-            self.credential.fcm_token = Some(registration.fcm_token.clone());
-            self.credential.gcm_token = Some(registration.gcm_token.clone());
-            self.credential.android_id = Some(registration.android_id as i64);
-            self.credential.security_token = Some(registration.security_token as i64);
-            self.credential.private_key_base64 = Some(registration.private_key_b64.clone());
-            self.credential.auth_secret_base64 = Some(registration.auth_secret_b64.clone());
+            registration.apply_to(&mut self.credential);
+            self.notify_lifecycle(
+                LifecycleEventKind::RegistrationCompleted,
+                serde_json::json!({ "name": cred_name }),
+            );
 
             info!("FCM device registered successfully for: {}", cred_name);
-            info!("FCM Token: {}", registration.fcm_token);
-
-            // Now start listening with the registered credentials
-            let webhook_url = self.credential.webhook_url.clone();
-            let webhook_headers = self.credential.get_webhook_headers();
-            let repo = self.repo.clone();
-            let webhook_client = self.webhook_client.clone();
-            let dedup_cache = self.dedup_cache.clone();
-            let topics = self.repo.get_credential_topics(&cred_id).await?;
-            
-            let cred_id_for_listener = cred_id.clone();
-            let cred_name_for_listener = cred_name.clone();
-            
-            // Use spawn_blocking for the listener
-            tokio::task::spawn_blocking(move || {
-                Self::run_fcm_client_existing(
-                    api_key,
-                    app_id,
-                    project_id,
-                    registration.fcm_token,
-                    Some(registration.gcm_token),
-                    registration.android_id,
-                    registration.security_token,
-                    registration.private_key_b64,
-                    registration.auth_secret_b64,
-                    cred_id_for_listener,
-                    cred_name_for_listener,
-                    webhook_url,
-                    webhook_headers,
-                    repo,
-                    webhook_client,
-                    dedup_cache,
-                    topics,
-                )
-            }).await??;
-            
-            Ok(())
+            info!("FCM Token: {}", self.credential.fcm_token.as_deref().unwrap_or_default());
+        }
+
+        // Start listening with the stored (or freshly registered) credentials
+        let credential = self.credential.clone();
+        let queue = self.queue.clone();
+        let status = self.status.clone();
+        let topics = self.repo.get_credential_topics(&cred_id).await?;
+
+        // The FCM client blocks for the lifetime of the connection
+        run_blocking_named(&cred_id, move || {
+            Self::run_fcm_client_existing(credential, queue, status, topics)
+        })
+        .await?
+    }
+
+    /// Map an FCM client error to an AppError variant.
+    /// Network failures are always connection errors; anything else falls back to `default`.
+    fn classify_error(err: anyhow::Error, default: fn(String) -> AppError) -> AppError {
+        let message = format!("{:#}", err);
+        let lower = message.to_lowercase();
+
+        if lower.contains("decrypt") {
+            AppError::FcmDecryption(message)
+        } else if ["connect", "timed out", "timeout", "dns", "network", "broken pipe", "reset by peer", "eof"]
+            .iter()
+            .any(|k| lower.contains(k))
+        {
+            AppError::FcmConnection(message)
+        } else {
+            default(message)
         }
     }
 
-    /// Run FCM client with existing credentials (blocking function for spawn_blocking)
+    /// Run FCM client with existing credentials (blocking, runs on the credential's thread)
     fn run_fcm_client_existing(
-        api_key: String,
-        app_id: String,
-        project_id: String,
-        fcm_token: String,
-        gcm_token: Option<String>,
-        android_id: u64,
-        security_token: u64,
-        private_key_b64: String,
-        auth_secret_b64: String,
-        cred_id: String,
-        cred_name: String,
-        webhook_url: String,
-        webhook_headers: Option<std::collections::HashMap<String, String>>,
-        repo: Repository,
-        webhook_client: WebhookClient,
-        dedup_cache: DedupCache,
+        credential: Credential,
+        queue: DeliveryQueue,
+        status: SharedWorkerStatus,
         topics: Vec<String>,
-    ) -> anyhow::Result<()> {
-        let mut client = FcmClient::new(api_key, app_id, project_id)?;
-        
-        // Load existing credentials
-        client.fcm_token = Some(fcm_token);
-        client.gcm_token = gcm_token;
-        client.android_id = android_id;
-        client.security_token = security_token;
-        client.load_keys(&private_key_b64, &auth_secret_b64)?;
+    ) -> AppResult<()> {
+        let mut client = Self::load_client(&credential)?;
+        let cred_name = credential.name.clone();
 
-        // Subscribe to topics
-        for topic in &topics {
-            match client.subscribe_to_topic(topic) {
-                Ok(_) => info!("Subscribed to topic '{}' for: {}", topic, cred_name),
-                Err(e) => warn!("Failed to subscribe to topic '{}': {}", topic, e),
+        // Subscribe to topics, retrying failures in the background while connected
+        status.write().unwrap().reset_topics(&topics);
+        let failed = Self::subscribe_topics(&mut client, &topics, &status, &cred_name);
+        let (stop_retries, retries_stopped) = std::sync::mpsc::channel::<()>();
+        if let (false, Some(interval)) = (failed.is_empty(), get_topic_retry_interval()) {
+            let credential = credential.clone();
+            let status = status.clone();
+            let span = Span::current();
+            let spawned = std::thread::Builder::new()
+                .name(worker_thread_name(&credential.id))
+                .spawn(move || {
+                    let _entered = span.enter();
+                    Self::retry_topic_subscriptions(
+                        credential,
+                        failed,
+                        status,
+                        interval,
+                        retries_stopped,
+                    )
+                });
+            if let Err(e) = spawned {
+                warn!("Failed to start topic subscription retries for {}: {}", cred_name, e);
             }
         }
 
-        // Set up message handler with dedup
-        let cred_id_handler = cred_id.clone();
-        let dedup_ttl = dedup_cache.ttl_seconds();
-        let max_messages = crate::workers::get_max_messages_per_credential();
-        
+        // Hand payloads to the delivery queue (may block when the queue policy is "block")
+        let rate_status = status.clone();
         client.on_data_message = Some(Arc::new(move |payload| {
-            let text = String::from_utf8_lossy(&payload).to_string();
-            let cred_id = cred_id_handler.clone();
-            let webhook_url = webhook_url.clone();
-            let webhook_headers = webhook_headers.clone();
-            let repo = repo.clone();
-            let webhook_client = webhook_client.clone();
-            let dedup_cache = dedup_cache.clone();
-
-            // Spawn async task for message handling
-            tokio::spawn(async move {
-                debug!("Received FCM message for credential {}: {}", cred_id, text);
+            rate_status.write().unwrap().message_rate.record(Instant::now());
+            queue.push(payload);
+        }));
+
+        // Start listening (this blocks until connection drops)
+        info!("Starting FCM listener for: {}", cred_name);
+        status.write().unwrap().connected_since = Some(Utc::now());
+        let result = client.start_listening();
+        status.write().unwrap().connected_since = None;
+        drop(stop_retries);
 
-                // Extract fcmMessageId for persistent dedup
-                let fcm_message_id = MessageLog::extract_fcm_message_id(&text);
+        result.map_err(|e| Self::classify_error(e.into(), AppError::FcmConnection))?;
 
-                // Check for duplicate using fcmMessageId (persistent in DB)
-                if let Some(ref fcm_id) = fcm_message_id {
-                    match repo.is_fcm_message_duplicate(&cred_id, fcm_id).await {
-                        Ok(true) => {
-                            debug!("Duplicate fcmMessageId detected: {}, skipping", fcm_id);
-                            return;
-                        }
-                        Err(e) => {
-                            error!("Failed to check fcmMessageId duplicate: {}", e);
-                            // Continue processing anyway
-                        }
-                        _ => {}
-                    }
+        Ok(())
+    }
+
+    /// FCM client loaded with the credential's stored registration
+    fn load_client(credential: &Credential) -> AppResult<FcmClient> {
+        let mut client = FcmClient::new(
+            credential.api_key.clone(),
+            credential.app_id.clone(),
+            credential.project_id.clone(),
+        )
+        .map_err(|e| Self::classify_error(e.into(), AppError::FcmRegistration))?;
+
+        client.fcm_token = credential.fcm_token.clone();
+        client.gcm_token = credential.gcm_token.clone();
+        client.android_id = credential.android_id.unwrap_or(0) as u64;
+        client.security_token = credential.security_token.unwrap_or(0) as u64;
+        client
+            .load_keys(
+                credential.private_key_base64.as_deref().unwrap_or_default(),
+                credential.auth_secret_base64.as_deref().unwrap_or_default(),
+            )
+            .map_err(|e| Self::classify_error(e.into(), AppError::FcmDecryption))?;
+
+        Ok(client)
+    }
+
+    /// Subscribe to each topic and record the outcome on the worker status.
+    /// Returns the topics that failed.
+    fn subscribe_topics(
+        client: &mut FcmClient,
+        topics: &[String],
+        status: &SharedWorkerStatus,
+        cred_name: &str,
+    ) -> Vec<String> {
+        let mut failed = Vec::new();
+        for topic in topics {
+            let result = match client.subscribe_to_topic(topic) {
+                Ok(_) => {
+                    info!("Subscribed to topic '{}' for: {}", topic, cred_name);
+                    Ok(())
                 }
+                Err(e) => {
+                    warn!("Failed to subscribe to topic '{}' for {}: {}", topic, cred_name, e);
+                    failed.push(topic.clone());
+                    Err(e.to_string())
+                }
+            };
+            status.write().unwrap().record_topic(topic, result);
+        }
+        failed
+    }
 
-                // Also check for duplicate in memory (for rapid fire duplicates)
-                if dedup_cache.is_duplicate(&text) {
-                    warn!("Duplicate message detected in memory (within {} seconds), skipping", dedup_ttl);
-                    return;
+    /// Retry failed subscriptions every `interval` until they all succeed or the
+    /// listener drops the sender of `stop` (runs on its own thread)
+    fn retry_topic_subscriptions(
+        credential: Credential,
+        mut failed: Vec<String>,
+        status: SharedWorkerStatus,
+        interval: Duration,
+        stop: std::sync::mpsc::Receiver<()>,
+    ) {
+        while !failed.is_empty() {
+            if !matches!(
+                stop.recv_timeout(interval),
+                Err(std::sync::mpsc::RecvTimeoutError::Timeout)
+            ) {
+                return;
+            }
+
+            info!("Retrying {} topic subscription(s) for: {}", failed.len(), credential.name);
+            match Self::load_client(&credential) {
+                Ok(mut client) => {
+                    failed = Self::subscribe_topics(&mut client, &failed, &status, &credential.name);
                 }
+                Err(e) => warn!("Failed to load FCM client for topic retries: {}", e),
+            }
+        }
+        info!("All topic subscriptions succeeded for: {}", credential.name);
+    }
+}
+
+impl MessageHandler {
+    /// Webhook URL for a payload: the override for its topic, if any, otherwise the default
+    fn webhook_url_for(&self, payload: &str) -> &str {
+        MessageLog::extract_topic(payload)
+            .and_then(|topic| self.topic_webhooks.get(&topic))
+            .unwrap_or(&self.webhook_url)
+    }
 
-                // Create message log with fcmMessageId
-                let mut log = MessageLog::new(cred_id.clone(), fcm_message_id, text.clone());
-                
-                // Save to database
-                if let Err(e) = repo.create_message_log(&log).await {
-                    error!("Failed to save message log: {}", e);
+    /// Runs in its own task, so the span repeats the credential fields of the worker span
+    #[instrument(
+        name = "message",
+        skip_all,
+        fields(credential_id = %self.cred_id, name = %self.cred_name)
+    )]
+    async fn handle(&self, payload: Vec<u8>) {
+        // Metadata (fcmMessageId, topic, extracted fields) is read from a text view;
+        // what is stored and delivered depends on the payload encoding
+        let text = String::from_utf8_lossy(&payload).to_string();
+        let cred_id = &self.cred_id;
+        let repo = &self.repo;
+
+        // Extract fcmMessageId for persistent dedup
+        let fcm_message_id = MessageLog::extract_fcm_message_id(&text);
+
+        if get_log_payloads() {
+            debug!("Received FCM message for credential {}: {}", cred_id, text);
+        } else {
+            debug!(
+                "Received FCM message {} for credential {} ({} bytes)",
+                fcm_message_id.as_deref().unwrap_or("(no fcmMessageId)"),
+                cred_id,
+                payload.len()
+            );
+        }
+
+        // Drop messages from senders the credential doesn't expect
+        if !self.allowed_senders.is_empty() {
+            let from = MessageLog::extract_value(&text, "from");
+            if !from.as_ref().is_some_and(|f| self.allowed_senders.contains(f)) {
+                warn!(
+                    "Dropping message from unexpected sender {} for credential {}",
+                    from.as_deref().unwrap_or("(none)"),
+                    cred_id
+                );
+                return;
+            }
+        }
+
+        // The sender's own message id, when configured and present in this payload
+        let dedup_key = self
+            .dedup_key_path
+            .as_deref()
+            .and_then(|path| MessageLog::extract_value(&text, path));
+
+        // Check for duplicate using the dedup key, else fcmMessageId (persistent in DB)
+        if let Some(ref key) = dedup_key {
+            match repo.is_dedup_key_duplicate(cred_id, key).await {
+                Ok(true) => {
+                    debug!("Duplicate dedup key detected: {}, skipping", key);
                     return;
                 }
-
-                // Cleanup old messages to keep only max_messages
-                if let Err(e) = repo.cleanup_old_messages(&cred_id, max_messages).await {
-                    error!("Failed to cleanup old messages: {}", e);
+                Err(e) => {
+                    error!("Failed to check dedup key duplicate: {}", e);
+                    // Continue processing anyway
+                }
+                _ => {}
+            }
+        } else if let Some(fcm_id) = fcm_message_id.as_ref().filter(|_| self.log_writer.is_none()) {
+            // The batching writer checks the fcmMessageIds of a whole batch at once instead
+            match repo.is_fcm_message_duplicate(cred_id, fcm_id).await {
+                Ok(true) => {
+                    debug!("Duplicate fcmMessageId detected: {}, skipping", fcm_id);
+                    return;
                 }
+                Err(e) => {
+                    error!("Failed to check fcmMessageId duplicate: {}", e);
+                    // Continue processing anyway
+                }
+                _ => {}
+            }
+        }
+
+        // Create message log with fcmMessageId
+        let mut log =
+            MessageLog::from_bytes(cred_id.clone(), fcm_message_id, &payload, self.payload_encoding);
+        log.dedup_key = dedup_key;
+
+        // Also check for duplicate in memory (for rapid fire duplicates), by the dedup key when
+        // there is one, otherwise by content ignoring volatile fields
+        let dedup_content = match &log.dedup_key {
+            Some(key) => Some(format!("key:{}", key)),
+            None => dedup_content(&log.payload, &self.dedup_ignore_fields),
+        };
+        if self.dedup_cache.is_duplicate(dedup_content.as_deref().unwrap_or(&log.payload)) {
+            warn!(
+                "Duplicate message detected in memory (within {} seconds), skipping",
+                self.dedup_cache.ttl_seconds()
+            );
+            return;
+        }
+
+        log.extracted_fields = MessageLog::extract_fields(&text, &self.extract_fields);
+        log.sent_at = MessageLog::extract_sent_at(&text);
+        log.priority = MessageLog::extract_priority(&text);
+        log.ttl_secs = MessageLog::extract_ttl_secs(&text);
+        if !self.store_payload {
+            log.omit_payload();
+        }
+
+        // Save to database; the batching writer reports duplicates it dropped, so none
+        // reaches the webhook
+        let saved = match &self.log_writer {
+            Some(writer) => writer.write(&log, self.max_messages).await,
+            None => self.message_store.create_message_log(&log).await.map(|()| true),
+        };
+        match saved {
+            Ok(true) => {}
+            Ok(false) => {
+                debug!(
+                    "Duplicate fcmMessageId detected: {}, skipping",
+                    log.fcm_message_id.as_deref().unwrap_or_default()
+                );
+                return;
+            }
+            Err(e) => {
+                error!("Failed to save message log: {}", e);
+                return;
+            }
+        }
+        self.webhook_client.events().publish(StreamEvent::Message {
+            message_id: log.id.clone(),
+            credential_id: cred_id.clone(),
+            fcm_message_id: log.fcm_message_id.clone(),
+            received_at: log.received_at,
+        });
+
+        // Cleanup old messages to keep only max_messages (the batching writer does it periodically)
+        if self.log_writer.is_none() {
+            if let Err(e) = self
+                .message_store
+                .cleanup_old_messages(cred_id, self.max_messages)
+                .await
+            {
+                error!("Failed to cleanup old messages: {}", e);
+            }
+        }
 
-                // Send webhook
-                if let Err(e) = webhook_client
+        // Keep the message for history/backfill without calling the receiver
+        if !self.webhook_enabled {
+            debug!("Webhook delivery disabled for credential {}, skipping message {}", cred_id, log.id);
+            if let Err(e) = repo.skip_message_delivery(&log.id, Some(0), DELIVERY_SKIPPED).await {
+                error!("Failed to mark message as delivery-skipped: {}", e);
+            }
+            return;
+        }
+
+        // Pull-only: without a webhook the message is kept for clients polling the API
+        if self.pubsub.is_none() && self.webhook_url_for(&text).is_empty() {
+            debug!("No webhook for credential {}, message {} kept for polling", cred_id, log.id);
+            if let Err(e) = repo.skip_message_delivery(&log.id, None, DELIVERY_NO_WEBHOOK).await {
+                error!("Failed to mark message as delivery-skipped: {}", e);
+            }
+            return;
+        }
+
+        // Deliver to the configured sink, signing the body exactly as sent
+        let body = self.json_format.apply(&payload);
+        let result = match &self.pubsub {
+            Some(sink) => {
+                sink.publish(&self.webhook_client, &body, self.capture_response, &mut log, repo)
+                    .await
+            }
+            None => {
+                let headers = signed_headers(self.webhook_headers.as_ref(), &self.signing, &body);
+                self.webhook_client
                     .send(
-                        &webhook_url,
-                        &text,
-                        webhook_headers.as_ref(),
+                        self.webhook_url_for(&text),
+                        &body,
+                        headers.as_ref(),
+                        self.capture_response,
                         &mut log,
-                        &repo,
+                        repo,
                     )
                     .await
-                {
-                    error!("Webhook delivery failed: {}", e);
-                }
-            });
-        }));
+            }
+        };
+        if let Err(e) = result {
+            error!("Webhook delivery failed: {}", e);
+        }
 
-        // Start listening (this blocks until connection drops)
-        info!("Starting FCM listener for: {}", cred_name);
-        client.start_listening()?;
+        let delivered = matches!(log.webhook_status, Some(200..=299));
+        if let Some(kind) = self.delivery_alerts.alert_kind(delivered) {
+            let error = if delivered { None } else { log.webhook_response.clone() };
+            let mut alert = Alert::new(kind, cred_id, &self.cred_name, error);
+            alert.message_id = Some(log.id.clone());
+            self.alerts.notify(alert);
+        }
+    }
+}
 
-        Ok(())
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::DeliveryState;
+    use sha2::{Digest, Sha256};
+
+    /// Handler for `credential` as the worker builds it, without a batching writer, proxy
+    /// or signing; tests override the fields they care about
+    fn test_handler(repo: &Repository, credential: &Credential) -> MessageHandler {
+        MessageHandler {
+            cred_id: credential.id.clone(),
+            cred_name: credential.name.clone(),
+            webhook_url: credential.webhook_url.clone(),
+            topic_webhooks: credential.get_topic_webhooks().unwrap_or_default(),
+            pubsub: None,
+            webhook_headers: None,
+            signing: WebhookSigning::default(),
+            capture_response: true,
+            extract_fields: credential.get_extract_fields().unwrap_or_default(),
+            webhook_enabled: credential.webhook_enabled,
+            payload_encoding: PayloadEncoding::Utf8,
+            dedup_ignore_fields: Vec::new(),
+            json_format: JsonFormat::Raw,
+            store_payload: credential.store_payload,
+            dedup_key_path: credential.dedup_key_path.clone(),
+            allowed_senders: credential.allowed_senders(),
+            max_messages: 100,
+            message_store: Arc::new(repo.clone()),
+            repo: repo.clone(),
+            log_writer: None,
+            webhook_client: WebhookClient::new(),
+            dedup_cache: DedupCache::new(60),
+            alerts: AlertNotifier::new(None, Duration::from_secs(60)),
+            delivery_alerts: DeliveryAlertPolicy::default(),
+        }
+    }
+
+    #[test]
+    fn test_classify_error() {
+        let err = FcmWorker::classify_error(
+            anyhow::anyhow!("registration rejected: PHONE_REGISTRATION_ERROR"),
+            AppError::FcmRegistration,
+        );
+        assert!(matches!(err, AppError::FcmRegistration(_)));
+        assert!(err.is_permanent());
+
+        let err = FcmWorker::classify_error(
+            anyhow::anyhow!("failed to connect to mtalk.google.com: connection timed out"),
+            AppError::FcmRegistration,
+        );
+        assert!(matches!(err, AppError::FcmConnection(_)));
+        assert!(!err.is_permanent());
+
+        let err = FcmWorker::classify_error(
+            anyhow::anyhow!("failed to decrypt message"),
+            AppError::FcmConnection,
+        );
+        assert!(matches!(err, AppError::FcmDecryption(_)));
+    }
+
+    #[test]
+    fn test_message_rate_window() {
+        let start = Instant::now();
+        let mut rate = MessageRate::default();
+
+        for i in 0..30 {
+            rate.record(start + Duration::from_millis(i * 100));
+        }
+        assert_eq!(rate.count(start + Duration::from_secs(3)), 30);
+        assert_eq!(rate.per_second(start + Duration::from_secs(3)), 0.5);
+
+        // Messages older than the window no longer count
+        let later = start + MESSAGE_RATE_WINDOW + Duration::from_secs(5);
+        rate.record(later);
+        assert_eq!(rate.count(later), 1);
+        assert_eq!(rate.count(later + MESSAGE_RATE_WINDOW), 0);
+    }
+
+    #[test]
+    fn test_topic_subscription_tracking() {
+        let mut status = WorkerStatus::default();
+        status.reset_topics(&["news".to_string(), "promo".to_string()]);
+        assert!(status.topics.values().all(|s| s.state == TopicState::Pending));
+
+        status.record_topic("news", Ok(()));
+        status.record_topic("promo", Err("SERVICE_NOT_AVAILABLE".to_string()));
+        assert_eq!(status.topics["news"].state, TopicState::Subscribed);
+        assert_eq!(status.topics["promo"].state, TopicState::Failed);
+        assert_eq!(status.topics["promo"].error.as_deref(), Some("SERVICE_NOT_AVAILABLE"));
+
+        // A retry that succeeds clears the error
+        status.record_topic("promo", Ok(()));
+        assert_eq!(status.topics["promo"].state, TopicState::Subscribed);
+        assert_eq!(status.topics["promo"].error, None);
+        assert_eq!(status.topics["promo"].attempts, 2);
+
+        // On reconnect, removed topics are dropped and attempt counts kept
+        status.reset_topics(&["promo".to_string()]);
+        assert_eq!(status.topics.len(), 1);
+        assert_eq!(status.topics["promo"].state, TopicState::Pending);
+        assert_eq!(status.topics["promo"].attempts, 2);
+    }
+
+    #[tokio::test]
+    async fn test_blocking_work_runs_on_named_thread() {
+        let id = "5f0c1d2e-3a4b-4c5d-8e9f-0a1b2c3d4e5f";
+        assert_eq!(worker_thread_name(id), "fcm-5f0c1d2e-3a");
+        assert!(worker_thread_name(id).len() <= 15);
+
+        let name = run_blocking_named(id, || std::thread::current().name().map(String::from))
+            .await
+            .unwrap();
+        assert_eq!(name.as_deref(), Some("fcm-5f0c1d2e-3a"));
+    }
+
+    #[test]
+    fn test_reconnect_delay_ceiling() {
+        let base = Duration::from_secs(5);
+        let ceiling = Duration::from_secs(60);
+
+        assert_eq!(reconnect_delay(base, 1, ceiling), Duration::from_secs(5));
+        assert_eq!(reconnect_delay(base, 4, ceiling), Duration::from_secs(40));
+        for attempt in 1..=100 {
+            assert!(reconnect_delay(base, attempt, ceiling) <= ceiling);
+        }
+        assert_eq!(reconnect_delay(base, 100, ceiling), ceiling);
+        // A high ceiling lets the delay keep growing past the old 320s cap
+        assert_eq!(
+            reconnect_delay(base, 9, Duration::from_secs(3600)),
+            Duration::from_secs(1280)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_topic_webhook_routing() {
+        let repo = Repository::new("sqlite::memory:").await.unwrap();
+        let credential = Credential::new(crate::models::CreateCredentialRequest {
+            name: "Cred".to_string(),
+            api_key: "key".to_string(),
+            app_id: "1:123:android:abc".to_string(),
+            project_id: "project".to_string(),
+            webhook_url: Some("http://default/hook".to_string()),
+            topic_webhooks: Some(HashMap::from([(
+                "promotions".to_string(),
+                "http://promo/hook".to_string(),
+            )])),
+            ..Default::default()
+        });
+        let handler = test_handler(&repo, &credential);
+
+        // Routed by topic
+        assert_eq!(
+            handler.webhook_url_for(r#"{"from":"/topics/promotions","data":{}}"#),
+            "http://promo/hook"
+        );
+        assert_eq!(
+            handler.webhook_url_for(r#"{"topic":"promotions","data":{}}"#),
+            "http://promo/hook"
+        );
+
+        // Default: topic without override, direct message, non-JSON payload
+        assert_eq!(
+            handler.webhook_url_for(r#"{"from":"/topics/news","data":{}}"#),
+            "http://default/hook"
+        );
+        assert_eq!(
+            handler.webhook_url_for(r#"{"from":"123456789","data":{}}"#),
+            "http://default/hook"
+        );
+        assert_eq!(handler.webhook_url_for("not json"), "http://default/hook");
+    }
+
+    #[tokio::test]
+    async fn test_disabled_delivery_logs_and_skips() {
+        let repo = Repository::new("sqlite::memory:").await.unwrap();
+        let credential = Credential::new(crate::models::CreateCredentialRequest {
+            name: "Test".to_string(),
+            api_key: "key".to_string(),
+            app_id: "1:123:android:abc".to_string(),
+            project_id: "project".to_string(),
+            // Unroutable: the test fails on timeout if delivery is attempted
+            webhook_url: Some("http://192.0.2.1/hook".to_string()),
+            webhook_enabled: false,
+            ..Default::default()
+        });
+        repo.create_credential(&credential).await.unwrap();
+
+        let handler = test_handler(&repo, &credential);
+
+        tokio::time::timeout(Duration::from_secs(1), handler.handle(b"{\"n\":1}".to_vec()))
+            .await
+            .expect("disabled delivery should not call the webhook");
+
+        let logs = repo.list_message_logs(Some(&credential.id), None, None, 10, 0).await.unwrap();
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].webhook_status, Some(0));
+        assert_eq!(logs[0].delivery_state, DeliveryState::Skipped.as_str());
+        assert_eq!(logs[0].webhook_response.as_deref(), Some(DELIVERY_SKIPPED));
+    }
+
+    #[tokio::test]
+    async fn test_pull_only_logs_without_delivery() {
+        let repo = Repository::new("sqlite::memory:").await.unwrap();
+        let credential = Credential::new(crate::models::CreateCredentialRequest {
+            name: "Test".to_string(),
+            api_key: "key".to_string(),
+            app_id: "1:123:android:abc".to_string(),
+            project_id: "project".to_string(),
+            ..Default::default()
+        });
+        repo.create_credential(&credential).await.unwrap();
+
+        let handler = test_handler(&repo, &credential);
+
+        handler.handle(b"{\"fcmMessageId\":\"m1\"}".to_vec()).await;
+        // Still deduplicated
+        handler.handle(b"{\"fcmMessageId\":\"m1\"}".to_vec()).await;
+
+        // Stored for polling clients, with nothing left to deliver
+        let logs = repo.list_message_logs(Some(&credential.id), None, None, 10, 0).await.unwrap();
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].webhook_status, None);
+        assert_eq!(logs[0].delivery_state, DeliveryState::Skipped.as_str());
+        assert_eq!(logs[0].webhook_response.as_deref(), Some(DELIVERY_NO_WEBHOOK));
+    }
+
+    #[tokio::test]
+    async fn test_dedup_key_path_suppresses_resent_messages() {
+        let repo = Repository::new("sqlite::memory:").await.unwrap();
+        let credential = Credential::new(crate::models::CreateCredentialRequest {
+            name: "Test".to_string(),
+            api_key: "key".to_string(),
+            app_id: "1:123:android:abc".to_string(),
+            project_id: "project".to_string(),
+            webhook_url: Some("http://192.0.2.1/hook".to_string()),
+            webhook_enabled: false,
+            dedup_key_path: Some("data.event_id".to_string()),
+            ..Default::default()
+        });
+        repo.create_credential(&credential).await.unwrap();
+
+        let mut handler = test_handler(&repo, &credential);
+
+        // Re-sent with a new fcmMessageId and timestamp: still the same event
+        handler
+            .handle(br#"{"fcmMessageId":"0:1","data":{"event_id":"evt-1","sent":1}}"#.to_vec())
+            .await;
+        handler
+            .handle(br#"{"fcmMessageId":"0:2","data":{"event_id":"evt-1","sent":2}}"#.to_vec())
+            .await;
+        // Caught by the database once the in-memory window is gone
+        handler.dedup_cache = DedupCache::new(60);
+        handler
+            .handle(br#"{"fcmMessageId":"0:3","data":{"event_id":"evt-1","sent":3}}"#.to_vec())
+            .await;
+
+        let logs = repo.list_message_logs(Some(&credential.id), None, None, 10, 0).await.unwrap();
+        assert_eq!(logs.len(), 1);
+        assert_eq!(logs[0].fcm_message_id.as_deref(), Some("0:1"));
+        assert_eq!(logs[0].dedup_key.as_deref(), Some("evt-1"));
+
+        // Without the field, dedup falls back to fcmMessageId
+        handler.handle(br#"{"fcmMessageId":"0:4","data":{}}"#.to_vec()).await;
+        handler.dedup_cache = DedupCache::new(60);
+        handler.handle(br#"{"fcmMessageId":"0:4","data":{"x":1}}"#.to_vec()).await;
+        handler
+            .handle(br#"{"fcmMessageId":"0:5","data":{"event_id":"evt-2"}}"#.to_vec())
+            .await;
+
+        let logs = repo.list_message_logs(Some(&credential.id), None, None, 10, 0).await.unwrap();
+        assert_eq!(logs.len(), 3);
+        let fallback = logs.iter().find(|l| l.fcm_message_id.as_deref() == Some("0:4")).unwrap();
+        assert!(fallback.dedup_key.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_dedup_group_shares_cache_between_workers() {
+        let repo = Repository::new("sqlite::memory:").await.unwrap();
+        let mut credentials = Vec::new();
+        for name in ["first", "second", "alone"] {
+            // Pull-only, so nothing is delivered
+            let req = serde_json::from_value(serde_json::json!({
+                "name": name,
+                "api_key": "key",
+                "app_id": "1:123:android:abc",
+                "project_id": "project"
+            }))
+            .unwrap();
+            let credential = Credential::new(req);
+            repo.create_credential(&credential).await.unwrap();
+            credentials.push(credential);
+        }
+        let handler_for = |credential: &Credential, dedup_cache: DedupCache| MessageHandler {
+            dedup_cache,
+            ..test_handler(&repo, credential)
+        };
+
+        let groups = crate::workers::DedupGroups::default();
+        let first = handler_for(&credentials[0], groups.cache_for(Some("devices"), 60));
+        let second = handler_for(&credentials[1], groups.cache_for(Some("devices"), 60));
+        let alone = handler_for(&credentials[2], groups.cache_for(None, 60));
+
+        // The same push reaching every device of the group is logged once
+        let payload = br#"{"data":{"order":"A-1"}}"#;
+        first.handle(payload.to_vec()).await;
+        second.handle(payload.to_vec()).await;
+        // Credentials outside the group dedup on their own
+        alone.handle(payload.to_vec()).await;
+
+        let mut counts = Vec::new();
+        for credential in &credentials {
+            counts.push(repo.message_count(Some(&credential.id)).await.unwrap());
+        }
+        assert_eq!(counts, [1, 0, 1]);
+    }
+
+    #[tokio::test]
+    async fn test_allowed_senders_drop_unexpected_messages() {
+        let repo = Repository::new("sqlite::memory:").await.unwrap();
+        let credential = Credential::new(crate::models::CreateCredentialRequest {
+            name: "Test".to_string(),
+            api_key: "key".to_string(),
+            app_id: "1:123:android:abc".to_string(),
+            project_id: "project".to_string(),
+            webhook_url: Some("http://192.0.2.1/hook".to_string()),
+            webhook_enabled: false,
+            allowed_senders: Some(vec!["111".to_string(), "/topics/news".to_string()]),
+            ..Default::default()
+        });
+        repo.create_credential(&credential).await.unwrap();
+
+        let handler = test_handler(&repo, &credential);
+
+        handler.handle(br#"{"from":"111","data":{"n":1}}"#.to_vec()).await;
+        handler.handle(br#"{"from":"/topics/news","data":{"n":2}}"#.to_vec()).await;
+        handler.handle(br#"{"from":"222","data":{"n":3}}"#.to_vec()).await;
+        handler.handle(br#"{"data":{"n":4}}"#.to_vec()).await;
+
+        let logs = repo.list_message_logs(Some(&credential.id), None, None, 10, 0).await.unwrap();
+        let mut accepted: Vec<_> = logs
+            .iter()
+            .map(|l| MessageLog::extract_value(&l.payload, "from").unwrap())
+            .collect();
+        accepted.sort();
+        assert_eq!(accepted, ["/topics/news", "111"]);
+    }
+
+    #[tokio::test]
+    async fn test_metadata_only_storage_delivers_body() {
+        let received = Arc::new(std::sync::Mutex::new(None::<String>));
+        let app = {
+            let received = received.clone();
+            axum::Router::new().route(
+                "/",
+                axum::routing::post(move |body: String| {
+                    let received = received.clone();
+                    async move {
+                        *received.lock().unwrap() = Some(body);
+                        "ok"
+                    }
+                }),
+            )
+        };
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        let repo = Repository::new("sqlite::memory:").await.unwrap();
+        let credential = Credential::new(crate::models::CreateCredentialRequest {
+            name: "Test".to_string(),
+            api_key: "key".to_string(),
+            app_id: "1:123:android:abc".to_string(),
+            project_id: "project".to_string(),
+            webhook_url: Some(url),
+            store_payload: false,
+            extract_fields: Some(HashMap::from([("order".to_string(), "data.order_id".to_string())])),
+            ..Default::default()
+        });
+        repo.create_credential(&credential).await.unwrap();
+
+        let handler = test_handler(&repo, &credential);
+
+        let payload = r#"{"fcmMessageId":"m-1","data":{"order_id":"A-1","ssn":"123-45-6789"}}"#;
+        handler.handle(payload.as_bytes().to_vec()).await;
+
+        // Delivered in full from memory
+        assert_eq!(received.lock().unwrap().as_deref(), Some(payload));
+
+        // Only metadata and a hash of the body reach the database
+        let logs = repo.list_message_logs(Some(&credential.id), None, None, 10, 0).await.unwrap();
+        assert_eq!(logs.len(), 1);
+        assert!(!logs[0].payload_stored);
+        assert!(!logs[0].payload.contains("123-45-6789"));
+        assert_eq!(logs[0].payload, format!("sha256:{}", hex::encode(Sha256::digest(payload))));
+        assert_eq!(logs[0].fcm_message_id.as_deref(), Some("m-1"));
+        assert_eq!(logs[0].webhook_status, Some(200));
+        assert_eq!(logs[0].extracted_fields.as_deref(), Some(r#"{"order":"A-1"}"#));
+        assert_eq!(logs[0].to_response().payload, serde_json::Value::Null);
     }
 }
@@ -0,0 +1,123 @@
+use crate::models::MessageLog;
+use std::collections::HashMap;
+
+/// Placeholders available in webhook header values, besides `field.<name>`
+/// (a value extracted per the credential's extract_fields)
+pub const HEADER_PLACEHOLDERS: &[&str] =
+    &["message_id", "fcm_message_id", "credential_id", "received_at"];
+
+const FIELD_PREFIX: &str = "field.";
+
+/// Whether a header value contains `{{...}}` placeholders
+pub fn is_header_template(value: &str) -> bool {
+    value.contains("{{")
+}
+
+/// Placeholder names used in a header value, in order
+fn placeholder_names(template: &str) -> Vec<&str> {
+    let mut names = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        names.push(rest[start + 2..start + 2 + len].trim());
+        rest = &rest[start + 2 + len + 2..];
+    }
+    names
+}
+
+fn is_known(name: &str) -> bool {
+    HEADER_PLACEHOLDERS.contains(&name)
+        || name.strip_prefix(FIELD_PREFIX).is_some_and(|f| !f.is_empty())
+}
+
+/// Placeholders in a header value that don't name a message variable
+pub fn unknown_placeholders(template: &str) -> Vec<&str> {
+    placeholder_names(template)
+        .into_iter()
+        .filter(|name| !is_known(name))
+        .collect()
+}
+
+/// Fill in a header value from the message being delivered. Missing values (no FCM message
+/// id, field not extracted) become empty. Unknown placeholders are kept literally, or with
+/// `strict` make the whole value an error.
+pub fn render_header(template: &str, log: &MessageLog, strict: bool) -> Result<String, String> {
+    let fields: HashMap<String, String> = log
+        .extracted_fields
+        .as_deref()
+        .and_then(|f| serde_json::from_str(f).ok())
+        .unwrap_or_default();
+
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        let end = start + 2 + len + 2;
+        rendered.push_str(&rest[..start]);
+
+        let name = rest[start + 2..start + 2 + len].trim();
+        let value = match name {
+            "message_id" => Some(log.id.clone()),
+            "fcm_message_id" => Some(log.fcm_message_id.clone().unwrap_or_default()),
+            "credential_id" => Some(log.credential_id.clone()),
+            "received_at" => Some(log.received_at.to_rfc3339()),
+            _ => name
+                .strip_prefix(FIELD_PREFIX)
+                .filter(|f| !f.is_empty())
+                .map(|f| fields.get(f).cloned().unwrap_or_default()),
+        };
+
+        match value {
+            Some(value) => rendered.push_str(&value),
+            None if strict => return Err(format!("unknown placeholder '{{{{{}}}}}'", name)),
+            None => rendered.push_str(&rest[start..end]),
+        }
+        rest = &rest[end..];
+    }
+    rendered.push_str(rest);
+
+    Ok(rendered)
+}
+
+/// Get whether unknown header placeholders are rejected (when creating or updating a
+/// credential) instead of sent literally, from environment (WEBHOOK_HEADER_TEMPLATES_STRICT),
+/// default false
+pub fn get_header_templates_strict() -> bool {
+    std::env::var("WEBHOOK_HEADER_TEMPLATES_STRICT")
+        .map(|v| matches!(v.trim().to_lowercase().as_str(), "true" | "1" | "yes"))
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_header() {
+        let mut log = MessageLog::new("cred-1".to_string(), Some("0:123".to_string()), "{}".to_string());
+        log.extracted_fields = Some("{\"order_id\":\"A-1\"}".to_string());
+
+        let value = render_header("{{ fcm_message_id }}/{{credential_id}}", &log, false).unwrap();
+        assert_eq!(value, "0:123/cred-1");
+        assert_eq!(render_header("{{message_id}}", &log, false).unwrap(), log.id);
+        assert_eq!(
+            render_header("{{received_at}}", &log, false).unwrap(),
+            log.received_at.to_rfc3339()
+        );
+        assert_eq!(render_header("order {{field.order_id}}", &log, false).unwrap(), "order A-1");
+        assert_eq!(render_header("[{{field.missing}}]", &log, false).unwrap(), "[]");
+        assert_eq!(render_header("static", &log, true).unwrap(), "static");
+        assert_eq!(render_header("{{message_id", &log, true).unwrap(), "{{message_id");
+
+        // Unknown: literal by default, an error when strict
+        assert_eq!(render_header("a {{nope}} b", &log, false).unwrap(), "a {{nope}} b");
+        assert!(render_header("a {{nope}} b", &log, true).unwrap_err().contains("{{nope}}"));
+
+        assert_eq!(unknown_placeholders("{{nope}}-{{message_id}}-{{field.}}"), ["nope", "field."]);
+        assert!(unknown_placeholders("{{field.order_id}} {{received_at}}").is_empty());
+    }
+}
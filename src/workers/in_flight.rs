@@ -0,0 +1,77 @@
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+
+/// Message ids with a webhook delivery currently in progress.
+/// Shared by every path that re-delivers a stored message so the same
+/// message is never sent twice concurrently.
+#[derive(Clone, Default)]
+pub struct InFlightDeliveries {
+    ids: Arc<Mutex<HashSet<String>>>,
+}
+
+impl InFlightDeliveries {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Claim a message for delivery. Returns None if it is already being delivered.
+    /// The claim is released when the returned guard is dropped.
+    pub fn try_acquire(&self, message_id: &str) -> Option<InFlightGuard> {
+        let mut ids = self.ids.lock().unwrap();
+        if !ids.insert(message_id.to_string()) {
+            return None;
+        }
+
+        Some(InFlightGuard {
+            ids: self.ids.clone(),
+            message_id: message_id.to_string(),
+        })
+    }
+}
+
+/// Releases the in-flight claim on drop, so early returns and errors can't leak it
+pub struct InFlightGuard {
+    ids: Arc<Mutex<HashSet<String>>>,
+    message_id: String,
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.ids.lock().unwrap().remove(&self.message_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_concurrent_acquire_same_id() {
+        let in_flight = InFlightDeliveries::new();
+        let barrier = Arc::new(tokio::sync::Barrier::new(8));
+
+        let tasks: Vec<_> = (0..8)
+            .map(|_| {
+                let in_flight = in_flight.clone();
+                let barrier = barrier.clone();
+                tokio::spawn(async move {
+                    barrier.wait().await;
+                    let guard = in_flight.try_acquire("msg-1");
+                    // Hold the claim long enough for the other tasks to collide with it
+                    tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+                    guard.is_some()
+                })
+            })
+            .collect();
+
+        let mut acquired = 0;
+        for task in tasks {
+            if task.await.unwrap() {
+                acquired += 1;
+            }
+        }
+
+        assert_eq!(acquired, 1);
+        assert!(in_flight.try_acquire("msg-1").is_some());
+    }
+}
@@ -0,0 +1,222 @@
+use chrono::{DateTime, Utc};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{debug, error, warn};
+
+/// Change in a credential's state that an external system may want to mirror
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LifecycleEventKind {
+    /// Credential was created (directly, imported or cloned)
+    Created,
+    /// Listener worker was started
+    Started,
+    /// Listener worker was stopped on request (API, schedule, restart)
+    Stopped,
+    /// Credential was suspended
+    Suspended,
+    /// FCM device registration succeeded
+    RegistrationCompleted,
+    /// Worker gave up after exhausting its reconnect or registration attempts
+    WorkerFailed,
+}
+
+/// JSON body POSTed to the lifecycle webhook
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LifecycleEvent {
+    pub event: LifecycleEventKind,
+    pub credential_id: String,
+    pub timestamp: DateTime<Utc>,
+    /// Event specific context, e.g. the credential name or the error a worker failed with
+    pub details: serde_json::Value,
+}
+
+impl LifecycleEvent {
+    pub fn new(event: LifecycleEventKind, credential_id: &str) -> Self {
+        Self {
+            event,
+            credential_id: credential_id.to_string(),
+            timestamp: Utc::now(),
+            details: serde_json::json!({}),
+        }
+    }
+
+    pub fn with_details(mut self, details: serde_json::Value) -> Self {
+        self.details = details;
+        self
+    }
+}
+
+/// Sends credential lifecycle events to LIFECYCLE_WEBHOOK_URL or a credential's own
+/// lifecycle_webhook_url. A repeat of a credential's last event within the debounce window
+/// is dropped so a flapping worker doesn't spam; a different event always goes out, so a
+/// mirror of the credential's state never misses a transition.
+#[derive(Clone)]
+pub struct LifecycleNotifier {
+    url: Option<Arc<String>>,
+    client: Client,
+    debounce: Duration,
+    last_sent: Arc<Mutex<HashMap<String, (LifecycleEventKind, Instant)>>>,
+}
+
+impl LifecycleNotifier {
+    pub fn new(url: Option<String>, debounce: Duration) -> Self {
+        let client = Client::builder()
+            .timeout(Duration::from_secs(10))
+            .build()
+            .expect("Failed to create HTTP client");
+
+        Self {
+            url: url.map(Arc::new),
+            client,
+            debounce,
+            last_sent: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Notifier configured from LIFECYCLE_WEBHOOK_URL and LIFECYCLE_DEBOUNCE_SECS (only
+    /// credentials with their own lifecycle_webhook_url get events when unset)
+    pub fn from_env() -> Self {
+        Self::new(
+            get_lifecycle_webhook_url(),
+            Duration::from_secs(get_lifecycle_debounce_secs()),
+        )
+    }
+
+    /// Record an event as sent unless it repeats the credential's last event within the
+    /// debounce window
+    fn should_send(&self, event: &LifecycleEvent, now: Instant) -> bool {
+        let mut last_sent = self.last_sent.lock().unwrap();
+        let repeated = last_sent
+            .get(&event.credential_id)
+            .is_some_and(|(kind, sent)| *kind == event.event && now.duration_since(*sent) < self.debounce);
+        if !repeated {
+            last_sent.insert(event.credential_id.clone(), (event.event, now));
+        }
+        !repeated
+    }
+
+    /// Fire an event in the background to `credential_url`, falling back to
+    /// LIFECYCLE_WEBHOOK_URL; a no-op when neither is set
+    pub fn notify(&self, event: LifecycleEvent, credential_url: Option<&str>) {
+        let url = match credential_url.filter(|u| !u.is_empty()) {
+            Some(url) => url.to_string(),
+            None => match &self.url {
+                Some(url) => url.to_string(),
+                None => return,
+            },
+        };
+        if !self.should_send(&event, Instant::now()) {
+            debug!(
+                "Suppressing {:?} lifecycle event for credential {} (debounced)",
+                event.event, event.credential_id
+            );
+            return;
+        }
+
+        let client = self.client.clone();
+        tokio::spawn(async move {
+            match client.post(&url).json(&event).send().await {
+                Ok(response) if response.status().is_success() => {
+                    debug!("Sent {:?} lifecycle event for credential {}", event.event, event.credential_id);
+                }
+                Ok(response) => {
+                    warn!("Lifecycle webhook returned HTTP {}", response.status());
+                }
+                Err(e) => error!("Failed to send lifecycle event: {}", e),
+            }
+        });
+    }
+}
+
+/// Get credential lifecycle webhook URL from environment (LIFECYCLE_WEBHOOK_URL or
+/// LIFECYCLE_WEBHOOK_URL_FILE), if set
+pub fn get_lifecycle_webhook_url() -> Option<String> {
+    crate::secrets::var_or_log("LIFECYCLE_WEBHOOK_URL")
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+/// Get the window in which a repeated lifecycle event is dropped from environment
+/// (LIFECYCLE_DEBOUNCE_SECS), default 5 seconds
+pub fn get_lifecycle_debounce_secs() -> u64 {
+    std::env::var("LIFECYCLE_DEBOUNCE_SECS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(5)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::sync::mpsc;
+
+    #[test]
+    fn test_lifecycle_debounce() {
+        let notifier = LifecycleNotifier::new(None, Duration::from_secs(5));
+        let started = LifecycleEvent::new(LifecycleEventKind::Started, "cred");
+        let stopped = LifecycleEvent::new(LifecycleEventKind::Stopped, "cred");
+        let now = Instant::now();
+
+        assert!(notifier.should_send(&started, now));
+        assert!(!notifier.should_send(&started, now + Duration::from_secs(1)));
+        assert!(notifier.should_send(&started, now + Duration::from_secs(6)));
+
+        // A transition always goes out, even within the window
+        assert!(notifier.should_send(&stopped, now + Duration::from_secs(7)));
+        assert!(notifier.should_send(&started, now + Duration::from_secs(8)));
+
+        // Credentials are debounced independently
+        let other = LifecycleEvent::new(LifecycleEventKind::Started, "other");
+        assert!(notifier.should_send(&other, now + Duration::from_secs(8)));
+    }
+
+    #[tokio::test]
+    async fn test_notify_posts_to_credential_or_global_url() {
+        let (tx, mut rx) = mpsc::unbounded_channel::<(String, LifecycleEvent)>();
+        let app = axum::Router::new().route(
+            "/:target",
+            axum::routing::post(
+                move |axum::extract::Path(target): axum::extract::Path<String>,
+                      axum::Json(event): axum::Json<LifecycleEvent>| {
+                    let _ = tx.send((target, event));
+                    async { "ok" }
+                },
+            ),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        let notifier = LifecycleNotifier::new(Some(format!("{}/global", base)), Duration::from_secs(5));
+        let event = LifecycleEvent::new(LifecycleEventKind::WorkerFailed, "cred")
+            .with_details(serde_json::json!({"error": "gave up"}));
+        notifier.notify(event.clone(), None);
+        let (target, received) = rx.recv().await.unwrap();
+        assert_eq!(target, "global");
+        assert_eq!(received.event, LifecycleEventKind::WorkerFailed);
+        assert_eq!(received.credential_id, "cred");
+        assert_eq!(received.details["error"], "gave up");
+
+        // Repeated within the window: dropped
+        notifier.notify(event, None);
+
+        let own = format!("{}/own", base);
+        notifier.notify(LifecycleEvent::new(LifecycleEventKind::Created, "other"), Some(&own));
+        let (target, received) = rx.recv().await.unwrap();
+        assert_eq!(target, "own");
+        assert_eq!(received.event, LifecycleEventKind::Created);
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(rx.try_recv().is_err());
+
+        // Without any URL nothing is sent
+        LifecycleNotifier::new(None, Duration::ZERO)
+            .notify(LifecycleEvent::new(LifecycleEventKind::Created, "cred"), None);
+    }
+}
@@ -1,103 +1,617 @@
-use crate::db::Repository;
+use crate::db::{LogBatchConfig, MessageLogWriter, MessageStore, Repository};
 use crate::error::{AppError, AppResult};
 use crate::models::Credential;
-use crate::workers::{FcmWorker, WebhookClient};
-use std::collections::HashMap;
-use std::sync::Arc;
+use crate::workers::{
+    get_dedup_ttl, AlertNotifier, CredentialSchedule, DedupCache, DedupGroups, DeliveryQueue, DeliverySettings,
+    FcmWorker, LifecycleEvent, LifecycleEventKind, LifecycleNotifier, NeedsAttention, RegistrationLimiter,
+    ScheduleAction, SharedWorkerStatus, WebhookClient, WorkerStatus,
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use tokio::sync::{watch, RwLock};
 use tokio::task::JoinHandle;
 use tracing::{error, info, warn};
+use utoipa::ToSchema;
+
+/// How often `wait_for_connection` checks whether a worker has connected
+const CONNECT_POLL_INTERVAL: Duration = Duration::from_millis(100);
 
 /// Manages a pool of FCM listener workers
+#[derive(Clone)]
 pub struct ListenerPool {
     repo: Repository,
     webhook_client: WebhookClient,
+    alerts: AlertNotifier,
+    /// Credential lifecycle events (LIFECYCLE_WEBHOOK_URL or the credential's own URL)
+    lifecycle: LifecycleNotifier,
     workers: Arc<RwLock<HashMap<String, WorkerHandle>>>,
-    global_shutdown_tx: watch::Sender<bool>,
+    global_shutdown_tx: Arc<watch::Sender<bool>>,
+    /// Credentials stopped on purpose (API, schedule, bulk stop), left alone by the watchdog
+    manually_stopped: Arc<Mutex<HashSet<String>>>,
+    watchdog_stats: Arc<Mutex<WatchdogStats>>,
+    /// How long stopping workers get to drain before they're left behind
+    shutdown_grace: Duration,
+    /// Where workers write received messages (the SQLite tables unless replaced)
+    message_store: Arc<dyn MessageStore>,
+    /// Batched message log writes shared by all workers (MESSAGE_LOG_BATCH_SIZE)
+    log_writer: Option<MessageLogWriter>,
+    /// FCM registration attempts per credential, kept across worker restarts
+    registrations: RegistrationLimiter,
+    /// In-memory dedup caches shared within each dedup group
+    dedup_groups: DedupGroups,
+}
+
+/// Running totals of the watchdog's reconciliation passes
+#[derive(Debug, Clone, Default, Serialize, ToSchema)]
+pub struct WatchdogStats {
+    /// Completed passes
+    pub runs: u64,
+    /// Runnable credentials found without a running worker
+    pub missing: u64,
+    /// Running workers whose credential is no longer runnable (logged, not stopped)
+    pub unexpected: u64,
+    /// Missing workers started again
+    pub healed: u64,
+    /// Restarts that failed (retried on the next pass)
+    pub heal_failures: u64,
+    pub last_run_at: Option<DateTime<Utc>>,
+}
+
+/// Outcome of a bulk start/stop/restart for one credential
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct BulkWorkerResult {
+    pub credential_id: String,
+    pub name: String,
+    pub success: bool,
+    /// Why the operation failed for this credential
+    pub error: Option<String>,
+}
+
+impl BulkWorkerResult {
+    fn new(credential_id: &str, name: &str, result: AppResult<()>) -> Self {
+        Self {
+            credential_id: credential_id.to_string(),
+            name: name.to_string(),
+            success: result.is_ok(),
+            error: result.err().map(|e| e.to_string()),
+        }
+    }
 }
 
 struct WorkerHandle {
     handle: JoinHandle<()>,
     shutdown_tx: watch::Sender<bool>,
     credential_name: String,
+    status: SharedWorkerStatus,
+    queue: DeliveryQueue,
+    dedup_cache: DedupCache,
+    /// The credential's own lifecycle webhook, used for its stopped event
+    lifecycle_url: Option<String>,
 }
 
 impl ListenerPool {
     pub fn new(repo: Repository) -> Self {
         let (global_shutdown_tx, _) = watch::channel(false);
-        
+        let message_store: Arc<dyn MessageStore> = Arc::new(repo.clone());
+        let log_writer = LogBatchConfig::from_env()
+            .map(|config| MessageLogWriter::spawn(message_store.clone(), config));
+
         Self {
             repo,
             webhook_client: WebhookClient::new(),
+            alerts: AlertNotifier::from_env(),
+            lifecycle: LifecycleNotifier::from_env(),
             workers: Arc::new(RwLock::new(HashMap::new())),
-            global_shutdown_tx,
+            global_shutdown_tx: Arc::new(global_shutdown_tx),
+            manually_stopped: Arc::new(Mutex::new(HashSet::new())),
+            watchdog_stats: Arc::new(Mutex::new(WatchdogStats::default())),
+            shutdown_grace: get_shutdown_grace(),
+            message_store,
+            log_writer,
+            registrations: RegistrationLimiter::from_env(),
+            dedup_groups: DedupGroups::default(),
         }
     }
 
+    /// Override SHUTDOWN_GRACE_SECS for workers stopped through this pool
+    #[allow(dead_code)]
+    pub fn with_shutdown_grace(mut self, grace: Duration) -> Self {
+        self.shutdown_grace = grace;
+        self
+    }
+
+    /// Write received messages to `store` instead of the SQLite tables
+    #[allow(dead_code)]
+    pub fn with_message_store(mut self, store: Arc<dyn MessageStore>) -> Self {
+        self.log_writer = LogBatchConfig::from_env()
+            .map(|config| MessageLogWriter::spawn(store.clone(), config));
+        self.message_store = store;
+        self
+    }
+
+    /// Send lifecycle events through `notifier` instead of one configured from the environment
+    #[allow(dead_code)]
+    pub fn with_lifecycle_notifier(mut self, notifier: LifecycleNotifier) -> Self {
+        self.lifecycle = notifier;
+        self
+    }
+
+    /// Lifecycle notifier shared by the pool, its workers and the API handlers
+    pub fn lifecycle(&self) -> LifecycleNotifier {
+        self.lifecycle.clone()
+    }
+
+    /// Store received messages are written to and listed from
+    pub fn message_store(&self) -> Arc<dyn MessageStore> {
+        self.message_store.clone()
+    }
+
+    /// Webhook client shared by all workers (and their per-endpoint health)
+    pub fn webhook_client(&self) -> WebhookClient {
+        self.webhook_client.clone()
+    }
+
+    /// Spawn the background task that applies credential start/stop schedules
+    pub fn start_scheduler(&self) -> JoinHandle<()> {
+        let pool = self.clone();
+        tokio::spawn(async move { pool.run_scheduler().await })
+    }
+
+    /// Spawn the background task that drops handles of workers whose task has exited
+    pub fn start_reaper(&self) -> JoinHandle<()> {
+        let pool = self.clone();
+        tokio::spawn(async move { pool.run_reaper().await })
+    }
+
+    /// Spawn the background task that restarts missing workers every WATCHDOG_INTERVAL_SECS
+    pub fn start_watchdog(&self) -> Option<JoinHandle<()>> {
+        let Some(period) = get_watchdog_interval() else {
+            info!("Worker watchdog disabled (WATCHDOG_INTERVAL_SECS=0)");
+            return None;
+        };
+
+        let pool = self.clone();
+        Some(tokio::spawn(async move { pool.run_watchdog(period).await }))
+    }
+
+    async fn run_watchdog(self, period: Duration) {
+        let mut shutdown_rx = self.global_shutdown_tx.subscribe();
+        // The first tick completes immediately; boot already started every runnable credential
+        let mut interval = tokio::time::interval(period);
+        interval.tick().await;
+
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = shutdown_rx.changed() => break,
+            }
+
+            if let Err(e) = self.reconcile().await {
+                error!("Worker watchdog pass failed: {}", e);
+            }
+        }
+
+        info!("Worker watchdog stopped");
+    }
+
+    /// Compare runnable credentials with running workers and start the missing ones,
+    /// except those stopped on purpose. Returns how many workers were started.
+    pub async fn reconcile(&self) -> AppResult<usize> {
+        let runnable = self.repo.list_runnable_credentials().await?;
+        let running = self.running_ids().await;
+        let runnable_ids: HashSet<&str> = runnable.iter().map(|c| c.id.as_str()).collect();
+
+        // A marker only means something while the credential is runnable; once it's been
+        // deactivated, reactivating it (even directly in the database) should start it again
+        let stopped = {
+            let mut stopped = self.manually_stopped.lock().unwrap();
+            stopped.retain(|id| runnable_ids.contains(id.as_str()));
+            stopped.clone()
+        };
+
+        let unexpected = running
+            .iter()
+            .filter(|id| !runnable_ids.contains(id.as_str()))
+            .count();
+        if unexpected > 0 {
+            warn!(
+                "Watchdog: {} worker(s) running for credentials that are no longer runnable",
+                unexpected
+            );
+        }
+
+        // Workers that gave up registering wait for an operator instead
+        let missing: Vec<&Credential> = runnable
+            .iter()
+            .filter(|c| !running.contains(&c.id) && !stopped.contains(&c.id))
+            .filter(|c| self.registrations.needs_attention(&c.id).is_none())
+            .collect();
+
+        let mut healed = 0;
+        for cred in &missing {
+            warn!("Watchdog: worker for {} ({}) is not running, starting it", cred.name, cred.id);
+            match self.start_worker(cred).await {
+                Ok(()) => healed += 1,
+                Err(e) => error!("Watchdog failed to start worker for {}: {}", cred.name, e),
+            }
+        }
+
+        let mut stats = self.watchdog_stats.lock().unwrap();
+        stats.runs += 1;
+        stats.missing += missing.len() as u64;
+        stats.unexpected += unexpected as u64;
+        stats.healed += healed as u64;
+        stats.heal_failures += (missing.len() - healed) as u64;
+        stats.last_run_at = Some(Utc::now());
+
+        Ok(healed)
+    }
+
+    /// Set when the credential's worker stopped after too many failed FCM registrations
+    pub fn needs_attention(&self, credential_id: &str) -> Option<NeedsAttention> {
+        self.registrations.needs_attention(credential_id)
+    }
+
+    /// Allow registering the credential again, e.g. when an operator starts it by hand
+    pub fn reset_registration_limit(&self, credential_id: &str) {
+        self.registrations.reset(credential_id);
+    }
+
+    pub fn watchdog_stats(&self) -> WatchdogStats {
+        self.watchdog_stats.lock().unwrap().clone()
+    }
+
+    async fn run_reaper(self) {
+        let mut shutdown_rx = self.global_shutdown_tx.subscribe();
+        let mut interval =
+            tokio::time::interval(std::time::Duration::from_secs(get_worker_reap_interval()));
+
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = shutdown_rx.changed() => break,
+            }
+
+            self.reap_finished().await;
+        }
+
+        info!("Worker reaper stopped");
+    }
+
+    /// Remove handles of finished workers, returning how many were removed
+    pub async fn reap_finished(&self) -> usize {
+        let mut workers = self.workers.write().await;
+        let finished: Vec<String> = workers
+            .iter()
+            .filter(|(_, h)| h.handle.is_finished())
+            .map(|(id, _)| id.clone())
+            .collect();
+
+        for id in &finished {
+            if let Some(h) = workers.remove(id) {
+                let last_error = h.status.read().unwrap().last_error.clone();
+                match last_error {
+                    Some(e) => warn!(
+                        "Reaped finished worker: {} (last error: {})",
+                        h.credential_name, e.message
+                    ),
+                    None => info!("Reaped finished worker: {}", h.credential_name),
+                }
+            }
+        }
+
+        finished.len()
+    }
+
+    /// Evaluate schedules every minute, acting only on transitions that fired since the
+    /// previous tick so manual start/stop in between stays in effect
+    async fn run_scheduler(self) {
+        let mut shutdown_rx = self.global_shutdown_tx.subscribe();
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(60));
+        let mut last_tick = Utc::now();
+
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = shutdown_rx.changed() => break,
+            }
+
+            let now = Utc::now();
+            if let Err(e) = self.apply_schedules(last_tick, now).await {
+                error!("Failed to apply credential schedules: {}", e);
+            }
+            last_tick = now;
+        }
+
+        info!("Credential scheduler stopped");
+    }
+
+    async fn apply_schedules(
+        &self,
+        from: chrono::DateTime<Utc>,
+        to: chrono::DateTime<Utc>,
+    ) -> AppResult<()> {
+        for cred in self.repo.list_scheduled_credentials().await? {
+            let Some(config) = cred.schedule() else { continue };
+            let schedule = match CredentialSchedule::parse(&config) {
+                Ok(s) => s,
+                Err(e) => {
+                    warn!("Invalid schedule for {}: {}", cred.name, e);
+                    continue;
+                }
+            };
+
+            match schedule.transition_between(from, to) {
+                Some(ScheduleAction::Start) if !self.is_running(&cred.id).await => {
+                    info!("Schedule window opened, starting worker: {}", cred.name);
+                    if let Err(e) = self.start_worker(&cred).await {
+                        error!("Scheduled start failed for {}: {}", cred.name, e);
+                    }
+                }
+                Some(ScheduleAction::Stop) if self.is_running(&cred.id).await => {
+                    info!("Schedule window closed, stopping worker: {}", cred.name);
+                    if let Err(e) = self.stop_worker(&cred.id).await {
+                        error!("Scheduled stop failed for {}: {}", cred.name, e);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
     /// Start all runnable credentials on server boot (active and not suspended)
     pub async fn start_all_active(&self) -> AppResult<()> {
         let credentials = self.repo.list_runnable_credentials().await?;
         info!("Starting {} runnable credential listeners (active and not suspended)", credentials.len());
+        match self.repo.credential_state_counts().await {
+            Ok(counts) => info!(
+                "Credentials: {} total, {} runnable, {} skipped (inactive), {} skipped (suspended)",
+                counts.total, counts.runnable, counts.inactive, counts.suspended
+            ),
+            Err(e) => warn!("Failed to count credentials by state: {}", e),
+        }
 
-        for cred in credentials {
-            if let Err(e) = self.start_worker(&cred).await {
-                error!("Failed to start worker for {}: {}", cred.name, e);
+        for result in self.start_staggered(&credentials).await {
+            if let Some(e) = result.error {
+                error!("Failed to start worker for {}: {}", result.name, e);
             }
         }
 
         Ok(())
     }
 
+    /// Start workers one at a time, WORKER_START_STAGGER_MS apart, so FCM isn't hit
+    /// by every connection at once. A failure doesn't stop the rest.
+    async fn start_staggered(&self, credentials: &[Credential]) -> Vec<BulkWorkerResult> {
+        let stagger = std::time::Duration::from_millis(get_worker_start_stagger_ms());
+        let mut results = Vec::with_capacity(credentials.len());
+
+        for (i, cred) in credentials.iter().enumerate() {
+            if i > 0 && !stagger.is_zero() {
+                tokio::time::sleep(stagger).await;
+            }
+            results.push(BulkWorkerResult::new(&cred.id, &cred.name, self.start_worker(cred).await));
+        }
+
+        results
+    }
+
+    /// Start every runnable credential that isn't already running
+    pub async fn start_all(&self) -> AppResult<Vec<BulkWorkerResult>> {
+        let running = self.running_ids().await;
+        let credentials: Vec<Credential> = self
+            .repo
+            .list_runnable_credentials()
+            .await?
+            .into_iter()
+            .filter(|c| !running.contains(&c.id))
+            .collect();
+
+        info!("Bulk start of {} worker(s)", credentials.len());
+        Ok(self.start_staggered(&credentials).await)
+    }
+
+    /// Restart every running worker, reloading each credential from the database
+    pub async fn restart_all(&self) -> AppResult<Vec<BulkWorkerResult>> {
+        let running = self.running_ids().await;
+        let credentials: Vec<Credential> = self
+            .repo
+            .list_credentials(false)
+            .await?
+            .into_iter()
+            .filter(|c| running.contains(&c.id))
+            .collect();
+
+        info!("Bulk restart of {} worker(s)", credentials.len());
+        let stagger = std::time::Duration::from_millis(get_worker_start_stagger_ms());
+        let mut results = Vec::with_capacity(credentials.len());
+
+        // One at a time, so only one credential is offline at any moment
+        for (i, cred) in credentials.iter().enumerate() {
+            if i > 0 && !stagger.is_zero() {
+                tokio::time::sleep(stagger).await;
+            }
+            results.push(BulkWorkerResult::new(&cred.id, &cred.name, self.restart_worker(cred).await));
+        }
+
+        Ok(results)
+    }
+
+    /// Stop every running worker
+    pub async fn stop_all(&self) -> Vec<BulkWorkerResult> {
+        let running: Vec<(String, String)> = {
+            let workers = self.workers.read().await;
+            workers
+                .iter()
+                .filter(|(_, h)| !h.handle.is_finished())
+                .map(|(id, h)| (id.clone(), h.credential_name.clone()))
+                .collect()
+        };
+
+        info!("Bulk stop of {} worker(s)", running.len());
+        let mut results = Vec::with_capacity(running.len());
+        for (id, name) in running {
+            results.push(BulkWorkerResult::new(&id, &name, self.stop_worker(&id).await));
+        }
+        results
+    }
+
     /// Start a worker for a specific credential
     pub async fn start_worker(&self, credential: &Credential) -> AppResult<()> {
+        let repo = self.repo.clone();
+        let webhook_client = self.webhook_client.clone();
+        let alerts = self.alerts.clone();
+        let lifecycle = self.lifecycle.clone();
+        let message_store = self.message_store.clone();
+        let log_writer = self.log_writer.clone();
+        let registrations = self.registrations.clone();
+        let worker_credential = credential.clone();
+
+        self.spawn_worker(credential, move |shutdown_rx, status, queue, dedup_cache| {
+            let worker = FcmWorker::new(
+                worker_credential,
+                repo,
+                webhook_client,
+                alerts,
+                shutdown_rx,
+                status,
+                queue,
+            )
+            .with_message_store(message_store)
+            .with_log_writer(log_writer)
+            .with_registration_limiter(registrations)
+            .with_lifecycle_notifier(lifecycle)
+            .with_dedup_cache(dedup_cache);
+            tokio::spawn(async move {
+                worker.run().await;
+            })
+        })
+        .await
+    }
+
+    /// Register a worker task for a credential. A handle whose task has already exited
+    /// (crashed or gave up retrying) counts as not running and is replaced.
+    async fn spawn_worker<F>(&self, credential: &Credential, spawn: F) -> AppResult<()>
+    where
+        F: FnOnce(
+            watch::Receiver<bool>,
+            SharedWorkerStatus,
+            DeliveryQueue,
+            DedupCache,
+        ) -> JoinHandle<()>,
+    {
         let cred_id = &credential.id;
-        
-        // Check if already running
-        {
-            let workers = self.workers.read().await;
-            if workers.contains_key(cred_id) {
+
+        // Hold the write lock across check and insert so concurrent starts can't both succeed
+        let mut workers = self.workers.write().await;
+        if let Some(existing) = workers.get(cred_id) {
+            if !existing.handle.is_finished() {
                 return Err(AppError::WorkerAlreadyRunning(format!(
                     "Worker for credential {} is already running",
                     credential.name
                 )));
             }
+            info!("Replacing finished worker for credential: {}", credential.name);
         }
 
         // Create shutdown channel for this worker
         let (shutdown_tx, shutdown_rx) = watch::channel(false);
+        let status = SharedWorkerStatus::default();
+        let queue = DeliveryQueue::new(credential.delivery_settings());
+        let dedup_cache = self
+            .dedup_groups
+            .cache_for(credential.dedup_group.as_deref(), get_dedup_ttl());
 
-        // Create and spawn worker
-        let worker = FcmWorker::new(
-            credential.clone(),
-            self.repo.clone(),
-            self.webhook_client.clone(),
-            shutdown_rx,
+        let handle = spawn(shutdown_rx, status.clone(), queue.clone(), dedup_cache.clone());
+        self.manually_stopped.lock().unwrap().remove(cred_id);
+
+        workers.insert(
+            cred_id.clone(),
+            WorkerHandle {
+                handle,
+                shutdown_tx,
+                credential_name: credential.name.clone(),
+                status,
+                queue,
+                dedup_cache,
+                lifecycle_url: credential.lifecycle_webhook_url.clone(),
+            },
         );
 
-        let cred_name = credential.name.clone();
-        let handle = tokio::spawn(async move {
-            worker.run().await;
-        });
+        info!("Worker started for credential: {} ({})", credential.name, cred_id);
+        self.lifecycle.notify(
+            LifecycleEvent::new(LifecycleEventKind::Started, cred_id)
+                .with_details(serde_json::json!({ "name": credential.name })),
+            credential.lifecycle_webhook_url.as_deref(),
+        );
+        Ok(())
+    }
 
-        // Store handle
-        {
-            let mut workers = self.workers.write().await;
-            workers.insert(
-                cred_id.clone(),
-                WorkerHandle {
-                    handle,
-                    shutdown_tx,
-                    credential_name: cred_name.clone(),
-                },
-            );
-        }
+    /// Wait for a started worker to connect to FCM. Fails as soon as the worker exits; on
+    /// timeout the worker is stopped if `stop_on_timeout`, so one started for this wait isn't
+    /// left half-started behind an error. A worker that was already running is left alone.
+    pub async fn wait_for_connection(
+        &self,
+        credential_id: &str,
+        timeout: Duration,
+        stop_on_timeout: bool,
+    ) -> AppResult<()> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            let (name, connected, finished, last_error) = {
+                let workers = self.workers.read().await;
+                let handle = workers.get(credential_id).ok_or_else(|| {
+                    AppError::WorkerNotRunning(format!("No worker for credential {}", credential_id))
+                })?;
+                let status = handle.status.read().unwrap();
+                (
+                    handle.credential_name.clone(),
+                    status.connected_since.is_some(),
+                    handle.handle.is_finished(),
+                    status.last_error.as_ref().map(|e| e.message.clone()),
+                )
+            };
 
-        info!("Worker started for credential: {} ({})", cred_name, cred_id);
-        Ok(())
+            if connected {
+                return Ok(());
+            }
+            if finished {
+                return Err(AppError::FcmConnection(format!(
+                    "Worker for {} stopped before connecting: {}",
+                    name,
+                    last_error.unwrap_or_else(|| "exited".to_string())
+                )));
+            }
+            if Instant::now() >= deadline {
+                if stop_on_timeout {
+                    warn!("Worker for {} did not connect within {}s, stopping it", name, timeout.as_secs());
+                    let _ = self.stop_worker(credential_id).await;
+                }
+                let detail = last_error.map(|e| format!(" (last error: {})", e)).unwrap_or_default();
+                return Err(AppError::Timeout(format!(
+                    "Worker for {} did not connect within {}s{}",
+                    name,
+                    timeout.as_secs(),
+                    detail
+                )));
+            }
+
+            tokio::time::sleep(CONNECT_POLL_INTERVAL).await;
+        }
     }
 
-    /// Stop a specific worker
+    /// Stop a specific worker. The watchdog leaves it stopped until it's started again.
     pub async fn stop_worker(&self, credential_id: &str) -> AppResult<()> {
+        self.manually_stopped
+            .lock()
+            .unwrap()
+            .insert(credential_id.to_string());
+
         let handle = {
             let mut workers = self.workers.write().await;
             workers.remove(credential_id)
@@ -110,17 +624,22 @@ impl ListenerPool {
                 // Signal shutdown
                 let _ = worker_handle.shutdown_tx.send(true);
                 
-                // Wait for worker to finish (with timeout)
-                tokio::select! {
-                    _ = worker_handle.handle => {
-                        info!("Worker stopped gracefully: {}", worker_handle.credential_name);
-                    }
-                    _ = tokio::time::sleep(std::time::Duration::from_secs(3)) => {
-                        warn!("Worker shutdown timed out, aborting: {}", worker_handle.credential_name);
-                        // Note: The blocking task will be cleaned up when the runtime shuts down
-                    }
+                // Wait for the worker and its in-flight deliveries to finish
+                match tokio::time::timeout(self.shutdown_grace, worker_handle.handle).await {
+                    Ok(_) => info!("Worker stopped gracefully: {}", worker_handle.credential_name),
+                    // Blocking FCM tasks can't be aborted; it exits when its connection drops
+                    Err(_) => warn!(
+                        "Worker {} did not drain within {}s, leaving it behind",
+                        worker_handle.credential_name,
+                        self.shutdown_grace.as_secs_f64()
+                    ),
                 }
-                
+
+                self.lifecycle.notify(
+                    LifecycleEvent::new(LifecycleEventKind::Stopped, credential_id)
+                        .with_details(serde_json::json!({ "name": worker_handle.credential_name })),
+                    worker_handle.lifecycle_url.as_deref(),
+                );
                 Ok(())
             }
             None => Err(AppError::WorkerNotRunning(format!(
@@ -149,6 +668,40 @@ impl ListenerPool {
         }
     }
 
+    /// Feed a payload into a running worker's delivery queue as if it had arrived from FCM.
+    /// Returns false when the queue's overflow policy discarded it.
+    pub async fn inject(&self, credential_id: &str, payload: Vec<u8>) -> AppResult<bool> {
+        let (status, queue) = {
+            let workers = self.workers.read().await;
+            match workers.get(credential_id) {
+                Some(h) if !h.handle.is_finished() => (h.status.clone(), h.queue.clone()),
+                _ => {
+                    return Err(AppError::WorkerNotRunning(format!(
+                        "No worker running for credential {}",
+                        credential_id
+                    )))
+                }
+            }
+        };
+
+        status.write().unwrap().message_rate.record(Instant::now());
+
+        // Pushing may block while the queue is full under the "block" policy
+        tokio::task::spawn_blocking(move || queue.push(payload))
+            .await
+            .map_err(|e| AppError::Internal(e.to_string()))
+    }
+
+    /// Snapshot of credential IDs with a running worker
+    pub async fn running_ids(&self) -> HashSet<String> {
+        let workers = self.workers.read().await;
+        workers
+            .iter()
+            .filter(|(_, h)| !h.handle.is_finished())
+            .map(|(id, _)| id.clone())
+            .collect()
+    }
+
     /// Get status of all workers
     #[allow(dead_code)]
     pub async fn get_status(&self) -> HashMap<String, bool> {
@@ -159,6 +712,50 @@ impl ListenerPool {
             .collect()
     }
 
+    /// Get runtime status (retries, last error, queue) of a worker, if one has been started
+    pub async fn worker_status(&self, credential_id: &str) -> Option<WorkerStatus> {
+        let workers = self.workers.read().await;
+        workers.get(credential_id).map(|h| {
+            let mut status = h.status.read().unwrap().clone();
+            status.queue = Some(h.queue.stats());
+            status
+        })
+    }
+
+    /// Apply delivery settings to a running worker's queue without restarting it.
+    /// Returns false if the credential has no running worker.
+    pub async fn apply_delivery_settings(&self, credential_id: &str, settings: DeliverySettings) -> bool {
+        let workers = self.workers.read().await;
+        match workers.get(credential_id).filter(|h| !h.handle.is_finished()) {
+            Some(h) => {
+                h.queue.apply(settings);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Clear a running worker's in-memory dedup cache (the whole group's when it shares
+    /// one), so messages seen before a dedup settings change aren't dropped under the new
+    /// scheme. Returns how many entries were cleared, 0 without a running worker.
+    pub async fn reset_dedup(&self, credential_id: &str) -> usize {
+        let workers = self.workers.read().await;
+        workers
+            .get(credential_id)
+            .filter(|h| !h.handle.is_finished())
+            .map(|h| h.dedup_cache.clear())
+            .unwrap_or(0)
+    }
+
+    /// Status of every worker that has been started, keyed by credential ID
+    pub async fn worker_statuses(&self) -> HashMap<String, WorkerStatus> {
+        let workers = self.workers.read().await;
+        workers
+            .iter()
+            .map(|(id, h)| (id.clone(), h.status.read().unwrap().clone()))
+            .collect()
+    }
+
     /// Get count of active workers
     pub async fn active_count(&self) -> usize {
         let workers = self.workers.read().await;
@@ -178,21 +775,383 @@ impl ListenerPool {
             workers.drain().map(|(_, h)| h).collect()
         };
 
-        // Signal each worker and wait with short timeout
-        for handle in handles {
+        // Signal every worker first so they all drain within one grace period
+        for handle in &handles {
             let _ = handle.shutdown_tx.send(true);
-            
-            tokio::select! {
-                _ = handle.handle => {
-                    info!("Worker {} stopped gracefully", handle.credential_name);
-                }
-                _ = tokio::time::sleep(std::time::Duration::from_secs(2)) => {
-                    // Blocking tasks can't be aborted, just move on
-                    warn!("Worker {} shutdown timed out (blocking task)", handle.credential_name);
-                }
+        }
+
+        let deadline = tokio::time::Instant::now() + self.shutdown_grace;
+        let mut undrained = Vec::new();
+        for handle in handles {
+            match tokio::time::timeout_at(deadline, handle.handle).await {
+                Ok(_) => info!("Worker {} stopped gracefully", handle.credential_name),
+                // Blocking tasks can't be aborted, just move on
+                Err(_) => undrained.push(handle.credential_name),
             }
         }
 
-        info!("All FCM workers stopped");
+        if undrained.is_empty() {
+            info!("All FCM workers stopped");
+        } else {
+            warn!(
+                "{} worker(s) did not drain within {}s: {}",
+                undrained.len(),
+                self.shutdown_grace.as_secs_f64(),
+                undrained.join(", ")
+            );
+        }
+    }
+}
+
+/// Get the delay between consecutive worker starts at boot and in bulk operations
+/// from environment (WORKER_START_STAGGER_MS), default 250ms; 0 starts back to back
+pub fn get_worker_start_stagger_ms() -> u64 {
+    std::env::var("WORKER_START_STAGGER_MS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(250)
+}
+
+/// Get how long stopping workers (and, at shutdown, open HTTP requests) get to finish from
+/// environment (SHUTDOWN_GRACE_SECS), default 10s
+pub fn get_shutdown_grace() -> Duration {
+    let secs = std::env::var("SHUTDOWN_GRACE_SECS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(10);
+    Duration::from_secs(secs)
+}
+
+/// Get how long `POST /api/credentials/{id}/start?wait=true` waits for the worker to connect
+/// from environment (WORKER_START_TIMEOUT_SECS), default 15s
+pub fn get_worker_start_timeout() -> Duration {
+    let secs = std::env::var("WORKER_START_TIMEOUT_SECS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .filter(|n| *n > 0)
+        .unwrap_or(15);
+    Duration::from_secs(secs)
+}
+
+/// Get the interval between watchdog passes from environment (WATCHDOG_INTERVAL_SECS),
+/// default 60s; 0 disables the watchdog
+pub fn get_watchdog_interval() -> Option<Duration> {
+    let secs = std::env::var("WATCHDOG_INTERVAL_SECS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(60);
+    (secs > 0).then(|| Duration::from_secs(secs))
+}
+
+/// Get interval between sweeps for finished worker handles from environment, default 30s
+pub fn get_worker_reap_interval() -> u64 {
+    std::env::var("WORKER_REAP_INTERVAL_SECS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .filter(|n| *n > 0)
+        .unwrap_or(30)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::CreateCredentialRequest;
+    use crate::workers::WorkerError;
+    use tokio::sync::mpsc;
+
+    fn credential() -> Credential {
+        Credential::new(CreateCredentialRequest {
+            name: "Test".to_string(),
+            api_key: "key".to_string(),
+            app_id: "1:123:android:abc".to_string(),
+            project_id: "project".to_string(),
+            webhook_url: Some("http://localhost/webhook".to_string()),
+            ..Default::default()
+        })
+    }
+
+    /// Stand-in for a worker that runs until told to shut down
+    fn idle_worker(
+        mut shutdown_rx: watch::Receiver<bool>,
+        _status: SharedWorkerStatus,
+        _queue: DeliveryQueue,
+        _dedup_cache: DedupCache,
+    ) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let _ = shutdown_rx.changed().await;
+        })
+    }
+
+    /// Stand-in for a worker that ignores shutdown, like one blocked on its FCM connection
+    fn stuck_worker(
+        _shutdown_rx: watch::Receiver<bool>,
+        _status: SharedWorkerStatus,
+        _queue: DeliveryQueue,
+        _dedup_cache: DedupCache,
+    ) -> JoinHandle<()> {
+        tokio::spawn(std::future::pending())
+    }
+
+    async fn wait_until_finished(pool: &ListenerPool, id: &str) {
+        while pool.is_running(id).await {
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_crashed_worker_can_be_restarted() {
+        let repo = Repository::new("sqlite::memory:").await.unwrap();
+        let pool = ListenerPool::new(repo);
+        let cred = credential();
+
+        pool.spawn_worker(&cred, |_, _, _, _| tokio::spawn(async { panic!("worker crashed") }))
+            .await
+            .unwrap();
+        wait_until_finished(&pool, &cred.id).await;
+
+        // No manual stop needed: the finished handle is replaced
+        pool.spawn_worker(&cred, idle_worker).await.unwrap();
+        assert!(pool.is_running(&cred.id).await);
+
+        // A live worker is still protected
+        let result = pool.spawn_worker(&cred, idle_worker).await;
+        assert!(matches!(result, Err(AppError::WorkerAlreadyRunning(_))));
+
+        pool.stop_worker(&cred.id).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_reap_finished_workers() {
+        let repo = Repository::new("sqlite::memory:").await.unwrap();
+        let pool = ListenerPool::new(repo);
+        let (finished, running) = (credential(), credential());
+
+        pool.spawn_worker(&finished, |_, _, _, _| tokio::spawn(async {}))
+            .await
+            .unwrap();
+        pool.spawn_worker(&running, idle_worker).await.unwrap();
+        wait_until_finished(&pool, &finished.id).await;
+
+        assert_eq!(pool.reap_finished().await, 1);
+        assert!(pool.worker_status(&finished.id).await.is_none());
+        assert!(pool.worker_status(&running.id).await.is_some());
+
+        pool.stop_worker(&running.id).await.unwrap();
+    }
+
+    /// Lifecycle notifier posting to a local receiver, and the events it received
+    async fn lifecycle_receiver() -> (LifecycleNotifier, mpsc::UnboundedReceiver<LifecycleEvent>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let app = axum::Router::new().route(
+            "/",
+            axum::routing::post(move |axum::Json(event): axum::Json<LifecycleEvent>| {
+                let _ = tx.send(event);
+                async { "ok" }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            axum::serve(listener, app).await.unwrap();
+        });
+
+        (LifecycleNotifier::new(Some(url), Duration::from_secs(60)), rx)
+    }
+
+    #[tokio::test]
+    async fn test_start_and_stop_fire_lifecycle_events_once() {
+        let repo = Repository::new("sqlite::memory:").await.unwrap();
+        let (notifier, mut events) = lifecycle_receiver().await;
+        let pool = ListenerPool::new(repo).with_lifecycle_notifier(notifier);
+        let cred = credential();
+
+        pool.spawn_worker(&cred, idle_worker).await.unwrap();
+        assert!(pool.spawn_worker(&cred, idle_worker).await.is_err());
+        let started = events.recv().await.unwrap();
+        assert_eq!(started.event, LifecycleEventKind::Started);
+        assert_eq!(started.credential_id, cred.id);
+        assert_eq!(started.details["name"], "Test");
+
+        pool.stop_worker(&cred.id).await.unwrap();
+        assert!(pool.stop_worker(&cred.id).await.is_err());
+        assert_eq!(events.recv().await.unwrap().event, LifecycleEventKind::Stopped);
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(events.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_stop_all_reports_each_worker() {
+        let repo = Repository::new("sqlite::memory:").await.unwrap();
+        let pool = ListenerPool::new(repo);
+        let (first, second) = (credential(), credential());
+
+        pool.spawn_worker(&first, idle_worker).await.unwrap();
+        pool.spawn_worker(&second, idle_worker).await.unwrap();
+
+        let results = pool.stop_all().await;
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|r| r.success && r.error.is_none()));
+        assert_eq!(pool.active_count().await, 0);
+        assert!(pool.stop_all().await.is_empty());
+
+        let failed = BulkWorkerResult::new("id", "name", Err(AppError::WorkerNotRunning("x".to_string())));
+        assert!(!failed.success);
+        assert!(failed.error.unwrap().contains("not running"));
+    }
+
+    #[tokio::test]
+    async fn test_inject_enqueues_for_running_worker() {
+        let repo = Repository::new("sqlite::memory:").await.unwrap();
+        let pool = ListenerPool::new(repo);
+        let cred = credential();
+
+        let result = pool.inject(&cred.id, b"{}".to_vec()).await;
+        assert!(matches!(result, Err(AppError::WorkerNotRunning(_))));
+
+        pool.spawn_worker(&cred, idle_worker).await.unwrap();
+        assert!(pool.inject(&cred.id, b"{}".to_vec()).await.unwrap());
+
+        let status = pool.worker_status(&cred.id).await.unwrap();
+        assert_eq!(status.queue.unwrap().depth, 1);
+        assert_eq!(status.message_rate.count(Instant::now()), 1);
+
+        pool.stop_worker(&cred.id).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_grace_bounds_stop() {
+        let repo = Repository::new("sqlite::memory:").await.unwrap();
+        let pool = ListenerPool::new(repo).with_shutdown_grace(Duration::from_millis(200));
+        let (stuck, idle) = (credential(), credential());
+
+        pool.spawn_worker(&stuck, stuck_worker).await.unwrap();
+        let started = Instant::now();
+        pool.stop_worker(&stuck.id).await.unwrap();
+        assert!(started.elapsed() < Duration::from_secs(2));
+        assert!(!pool.is_running(&stuck.id).await);
+
+        // One grace period for all workers, not one each
+        pool.spawn_worker(&stuck, stuck_worker).await.unwrap();
+        pool.spawn_worker(&credential(), stuck_worker).await.unwrap();
+        pool.spawn_worker(&idle, idle_worker).await.unwrap();
+        let started = Instant::now();
+        pool.shutdown_all().await;
+        assert!(started.elapsed() < Duration::from_millis(400));
+        assert_eq!(pool.active_count().await, 0);
+    }
+
+    #[tokio::test]
+    #[ignore = "sends a real push; needs FCM_SERVICE_ACCOUNT, FCM_E2E_API_KEY and FCM_E2E_APP_ID"]
+    async fn test_push_is_received_end_to_end() {
+        use crate::fcm_admin::{FcmAdmin, Message, ServiceAccount};
+        use crate::workers::register_device;
+
+        let admin = FcmAdmin::new(ServiceAccount::from_env().unwrap());
+        let repo = Repository::new("sqlite::memory:").await.unwrap();
+        let pool = ListenerPool::new(repo.clone());
+
+        let mut cred = credential();
+        cred.api_key = std::env::var("FCM_E2E_API_KEY").unwrap();
+        cred.app_id = std::env::var("FCM_E2E_APP_ID").unwrap();
+        cred.project_id = admin.project_id().to_string();
+        cred.webhook_enabled = false;
+        register_device(&cred).await.unwrap().apply_to(&mut cred);
+        repo.create_credential(&cred).await.unwrap();
+        pool.start_worker(&cred).await.unwrap();
+        pool.wait_for_connection(&cred.id, Duration::from_secs(60), true).await.unwrap();
+
+        let nonce = uuid::Uuid::new_v4().to_string();
+        let token = cred.fcm_token.clone().unwrap();
+        admin.send(&Message::to_token(token).with_data("nonce", &nonce)).await.unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(60);
+        loop {
+            let logs = repo.list_message_logs(Some(&cred.id), None, None, 10, 0).await.unwrap();
+            if logs.iter().any(|log| log.payload.contains(&nonce)) {
+                break;
+            }
+            assert!(Instant::now() < deadline, "push was not received within 60s");
+            tokio::time::sleep(Duration::from_millis(500)).await;
+        }
+        pool.stop_worker(&cred.id).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_connection() {
+        let repo = Repository::new("sqlite::memory:").await.unwrap();
+        let pool = ListenerPool::new(repo);
+        let timeout = Duration::from_secs(2);
+
+        // Connects after a short handshake
+        let cred = credential();
+        pool.spawn_worker(&cred, |mut shutdown_rx, status, _, _| {
+            tokio::spawn(async move {
+                tokio::time::sleep(Duration::from_millis(150)).await;
+                status.write().unwrap().connected_since = Some(Utc::now());
+                let _ = shutdown_rx.changed().await;
+            })
+        })
+        .await
+        .unwrap();
+        pool.wait_for_connection(&cred.id, timeout, true).await.unwrap();
+        pool.stop_worker(&cred.id).await.unwrap();
+
+        // Never connects: times out, and is only stopped when asked to
+        let hung = credential();
+        pool.spawn_worker(&hung, idle_worker).await.unwrap();
+        let result = pool.wait_for_connection(&hung.id, Duration::from_millis(200), false).await;
+        assert!(matches!(result, Err(AppError::Timeout(_))));
+        assert!(pool.is_running(&hung.id).await);
+        let result = pool.wait_for_connection(&hung.id, Duration::from_millis(200), true).await;
+        assert!(matches!(result, Err(AppError::Timeout(_))));
+        assert!(!pool.is_running(&hung.id).await);
+
+        // Gives up before connecting: fails without waiting out the timeout
+        let failed = credential();
+        pool.spawn_worker(&failed, |_, status, _, _| {
+            tokio::spawn(async move {
+                status.write().unwrap().last_error =
+                    Some(WorkerError::from(&AppError::FcmRegistration("rejected".to_string())));
+            })
+        })
+        .await
+        .unwrap();
+        match pool.wait_for_connection(&failed.id, timeout, true).await {
+            Err(AppError::FcmConnection(msg)) => assert!(msg.contains("rejected")),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_watchdog_respects_manual_stop() {
+        let repo = Repository::new("sqlite::memory:").await.unwrap();
+        let pool = ListenerPool::new(repo.clone());
+        let cred = credential();
+        repo.create_credential(&cred).await.unwrap();
+
+        // Stopped on purpose: not restarted
+        pool.spawn_worker(&cred, idle_worker).await.unwrap();
+        pool.stop_worker(&cred.id).await.unwrap();
+
+        // Running without a credential behind it: reported, not stopped
+        let ghost = credential();
+        pool.spawn_worker(&ghost, idle_worker).await.unwrap();
+
+        assert_eq!(pool.reconcile().await.unwrap(), 0);
+        assert!(!pool.is_running(&cred.id).await);
+        assert!(pool.is_running(&ghost.id).await);
+
+        let stats = pool.watchdog_stats();
+        assert_eq!(stats.runs, 1);
+        assert_eq!((stats.missing, stats.unexpected, stats.healed), (0, 1, 0));
+        assert!(stats.last_run_at.is_some());
+
+        // Suspending drops the marker, so unsuspending (even in the database) lets it heal
+        repo.suspend_credential(&cred.id).await.unwrap();
+        pool.reconcile().await.unwrap();
+        assert!(pool.manually_stopped.lock().unwrap().is_empty());
+
+        pool.stop_worker(&ghost.id).await.unwrap();
     }
 }
@@ -0,0 +1,136 @@
+use crate::db::Repository;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tracing::{error, info, warn};
+
+/// Get the database maintenance interval from environment (VACUUM_INTERVAL_SECS),
+/// default daily; 0 disables periodic maintenance
+pub fn get_vacuum_interval() -> Option<Duration> {
+    let secs = std::env::var("VACUUM_INTERVAL_SECS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(86400);
+    (secs > 0).then(|| Duration::from_secs(secs))
+}
+
+/// Get how many days messages are kept from environment (MESSAGE_RETENTION_DAYS);
+/// unset or 0 keeps them until a credential's max_messages cap pushes them out.
+/// Credentials can override it with their own retention_days.
+pub fn get_message_retention_days() -> Option<i64> {
+    std::env::var("MESSAGE_RETENTION_DAYS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .filter(|d: &i64| *d > 0)
+}
+
+/// Spawn the background task applying message retention, then running incremental vacuum
+/// and ANALYZE, every VACUUM_INTERVAL_SECS
+pub fn start_maintenance(repo: Repository) -> Option<JoinHandle<()>> {
+    let Some(period) = get_vacuum_interval() else {
+        info!("Periodic database maintenance disabled (VACUUM_INTERVAL_SECS=0)");
+        return None;
+    };
+
+    Some(tokio::spawn(async move {
+        // The first tick completes immediately; maintenance starts one interval after boot
+        let mut interval = tokio::time::interval(period);
+        interval.tick().await;
+
+        loop {
+            interval.tick().await;
+            run_maintenance(&repo).await;
+        }
+    }))
+}
+
+async fn run_maintenance(repo: &Repository) {
+    match apply_retention(repo).await {
+        Ok(0) => {}
+        Ok(deleted) => info!("Retention cleanup deleted {} expired messages", deleted),
+        Err(e) => error!("Retention cleanup failed: {}", e),
+    }
+
+    match repo.incremental_vacuum_enabled().await {
+        Ok(false) => warn!(
+            "Database auto_vacuum is not incremental, free pages can't be reclaimed; \
+             run POST /api/admin/vacuum?full=true once to convert it"
+        ),
+        Ok(true) => {}
+        Err(e) => error!("Failed to read auto_vacuum mode: {}", e),
+    }
+
+    match repo.vacuum(false).await {
+        Ok(report) => info!(
+            "Database maintenance done: reclaimed {} bytes ({} -> {})",
+            report.reclaimed(),
+            report.size_before,
+            report.size_after
+        ),
+        Err(e) => error!("Database maintenance failed: {}", e),
+    }
+}
+
+/// Delete each credential's messages older than its retention (retention_days, falling back
+/// to MESSAGE_RETENTION_DAYS), returning how many were deleted
+async fn apply_retention(repo: &Repository) -> anyhow::Result<u64> {
+    let mut deleted = 0;
+    for credential in repo.list_credentials(false).await? {
+        let Some(days) = credential.effective_retention_days() else {
+            continue;
+        };
+        match repo.delete_old_message_logs(&credential.id, days).await {
+            Ok(n) => deleted += n,
+            Err(e) => error!(
+                "Retention cleanup failed for credential {}: {}",
+                credential.id, e
+            ),
+        }
+    }
+    Ok(deleted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{CreateCredentialRequest, Credential, MessageLog};
+    use chrono::{Duration as ChronoDuration, Utc};
+
+    fn credential(name: &str, retention_days: i64) -> Credential {
+        let mut req: CreateCredentialRequest = serde_json::from_value(serde_json::json!({
+            "name": name,
+            "api_key": "key",
+            "app_id": "1:123:android:abc",
+            "project_id": "project",
+        }))
+        .unwrap();
+        req.retention_days = Some(retention_days);
+        Credential::new(req)
+    }
+
+    #[tokio::test]
+    async fn test_retention_per_credential() {
+        let repo = Repository::new("sqlite::memory:").await.unwrap();
+        let strict = credential("compliance", 7);
+        let archive = credential("archive", 365);
+        repo.create_credential(&strict).await.unwrap();
+        repo.create_credential(&archive).await.unwrap();
+
+        for cred in [&strict, &archive] {
+            for age_days in [1, 30, 400] {
+                let mut log = MessageLog::new(cred.id.clone(), None, "{}".to_string());
+                log.received_at = Utc::now() - ChronoDuration::days(age_days);
+                repo.create_message_log(&log).await.unwrap();
+            }
+        }
+
+        // 30 and 400 days old for the 7-day credential, 400 days old for the archive
+        assert_eq!(apply_retention(&repo).await.unwrap(), 3);
+        let strict_left = repo.count_message_logs(Some(&strict.id), None, None).await.unwrap();
+        let archive_left = repo.count_message_logs(Some(&archive.id), None, None).await.unwrap();
+        assert_eq!(strict_left, 1);
+        assert_eq!(archive_left, 2);
+
+        // Nothing left to expire
+        assert_eq!(apply_retention(&repo).await.unwrap(), 0);
+    }
+}
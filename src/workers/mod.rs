@@ -1,9 +1,37 @@
+pub mod alerts;
 pub mod dedup;
+pub mod delivery_queue;
+pub mod endpoint_health;
+pub mod events;
 pub mod fcm_worker;
+pub mod header_template;
+pub mod in_flight;
+pub mod lifecycle;
 pub mod listener_pool;
+pub mod maintenance;
+pub mod pacing;
+pub mod registration_limit;
+pub mod scheduler;
+pub mod signing;
+pub mod sink;
 pub mod webhook;
+pub mod webhook_debug;
 
+pub use alerts::*;
 pub use dedup::*;
+pub use delivery_queue::*;
+pub use endpoint_health::*;
+pub use events::*;
 pub use fcm_worker::*;
+pub use header_template::*;
+pub use in_flight::*;
+pub use lifecycle::*;
 pub use listener_pool::*;
+pub use maintenance::*;
+pub use pacing::*;
+pub use registration_limit::*;
+pub use scheduler::*;
+pub use signing::*;
+pub use sink::*;
 pub use webhook::*;
+pub use webhook_debug::*;
@@ -0,0 +1,65 @@
+use rand::Rng;
+use std::time::Duration;
+
+/// Spaces out deliveries of a batch (bulk retries) so a recovering receiver
+/// isn't hit by every message at once
+#[derive(Debug, Clone, Copy)]
+pub struct DeliveryPacer {
+    interval: Duration,
+    jitter: f64,
+}
+
+impl DeliveryPacer {
+    /// `rate` is in messages per second; `jitter` is the fraction (0..=1) by which
+    /// each gap may randomly deviate from the nominal interval
+    pub fn new(rate: f64, jitter: f64) -> Self {
+        Self {
+            interval: Duration::from_secs_f64(1.0 / rate.max(0.001)),
+            jitter: jitter.clamp(0.0, 1.0),
+        }
+    }
+
+    /// Delay to wait before dispatching the next message
+    pub fn next_delay(&self) -> Duration {
+        if self.jitter == 0.0 {
+            return self.interval;
+        }
+
+        let factor = rand::thread_rng().gen_range(1.0 - self.jitter..=1.0 + self.jitter);
+        self.interval.mul_f64(factor)
+    }
+}
+
+/// Get bulk retry rate (messages per second) from environment, default 10
+pub fn get_bulk_retry_rate() -> f64 {
+    std::env::var("BULK_RETRY_RATE")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .filter(|r: &f64| *r > 0.0)
+        .unwrap_or(10.0)
+}
+
+/// Get bulk retry jitter (fraction of the interval) from environment, default 0.2
+pub fn get_bulk_retry_jitter() -> f64 {
+    std::env::var("BULK_RETRY_JITTER")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(0.2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pacer_delay_bounds() {
+        let pacer = DeliveryPacer::new(10.0, 0.0);
+        assert_eq!(pacer.next_delay(), Duration::from_millis(100));
+
+        let pacer = DeliveryPacer::new(10.0, 0.5);
+        for _ in 0..100 {
+            let delay = pacer.next_delay();
+            assert!(delay >= Duration::from_millis(50) && delay <= Duration::from_millis(150));
+        }
+    }
+}
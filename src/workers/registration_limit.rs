@@ -0,0 +1,177 @@
+use crate::error::{AppError, LimitInfo};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use utoipa::ToSchema;
+
+const WINDOW: Duration = Duration::from_secs(3600);
+
+/// Get the minimum time between FCM registrations of one credential from environment
+/// (REGISTRATION_COOLDOWN_SECS), default 300s
+pub fn get_registration_cooldown() -> Duration {
+    let secs = std::env::var("REGISTRATION_COOLDOWN_SECS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(300);
+    Duration::from_secs(secs)
+}
+
+/// Get the maximum failed FCM registrations per credential per hour from environment
+/// (REGISTRATION_MAX_PER_HOUR), default 3
+pub fn get_registration_max_per_hour() -> usize {
+    std::env::var("REGISTRATION_MAX_PER_HOUR")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .filter(|n| *n > 0)
+        .unwrap_or(3)
+}
+
+/// Why a credential's worker stopped registering until an operator steps in
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct NeedsAttention {
+    pub reason: String,
+    pub since: DateTime<Utc>,
+}
+
+#[derive(Debug, Default)]
+struct History {
+    /// Attempts in the last hour that haven't been followed by a success
+    attempts: Vec<Instant>,
+    needs_attention: Option<NeedsAttention>,
+}
+
+/// Limits how often workers register credentials with FCM, so a credential whose
+/// registration keeps failing can't flood Google's registration endpoint. Shared by
+/// all workers of a pool, so restarts (including the watchdog's) don't reset it.
+#[derive(Clone)]
+pub struct RegistrationLimiter {
+    cooldown: Duration,
+    max_per_hour: usize,
+    history: Arc<Mutex<HashMap<String, History>>>,
+}
+
+impl RegistrationLimiter {
+    pub fn new(cooldown: Duration, max_per_hour: usize) -> Self {
+        Self {
+            cooldown,
+            max_per_hour: max_per_hour.max(1),
+            history: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Limits from REGISTRATION_COOLDOWN_SECS and REGISTRATION_MAX_PER_HOUR
+    pub fn from_env() -> Self {
+        Self::new(get_registration_cooldown(), get_registration_max_per_hour())
+    }
+
+    /// Claim a registration attempt for a credential. Fails while the cooldown since the
+    /// previous attempt runs, and once the hourly limit of failed attempts is used up,
+    /// which marks the credential as needing attention until `reset`.
+    pub fn try_acquire(&self, credential_id: &str) -> Result<(), AppError> {
+        self.try_acquire_at(credential_id, Instant::now())
+    }
+
+    fn try_acquire_at(&self, credential_id: &str, now: Instant) -> Result<(), AppError> {
+        let mut history = self.history.lock().unwrap();
+        let entry = history.entry(credential_id.to_string()).or_default();
+        entry.attempts.retain(|at| now.duration_since(*at) < WINDOW);
+
+        let limit_info = |retry_after: Duration| LimitInfo {
+            limit: self.max_per_hour as u64,
+            current: entry.attempts.len() as u64,
+            retry_after_secs: Some(retry_after.as_secs().max(1)),
+        };
+
+        if let Some(attention) = &entry.needs_attention {
+            let info = LimitInfo {
+                limit: self.max_per_hour as u64,
+                current: entry.attempts.len() as u64,
+                retry_after_secs: None,
+            };
+            return Err(AppError::TooManyRequests(attention.reason.clone(), info));
+        }
+
+        if entry.attempts.len() >= self.max_per_hour {
+            let info = limit_info(WINDOW.saturating_sub(now.duration_since(entry.attempts[0])));
+            let reason = format!(
+                "FCM registration failed {} times within an hour; start the worker again once fixed",
+                entry.attempts.len()
+            );
+            entry.needs_attention = Some(NeedsAttention {
+                reason: reason.clone(),
+                since: Utc::now(),
+            });
+            return Err(AppError::TooManyRequests(reason, info));
+        }
+
+        if let Some(last) = entry.attempts.last() {
+            let elapsed = now.duration_since(*last);
+            if elapsed < self.cooldown {
+                let info = limit_info(self.cooldown - elapsed);
+                return Err(AppError::TooManyRequests(
+                    format!(
+                        "FCM registration attempted {}s ago, next attempt allowed in {}s",
+                        elapsed.as_secs(),
+                        info.retry_after_secs.unwrap_or_default()
+                    ),
+                    info,
+                ));
+            }
+        }
+
+        entry.attempts.push(now);
+        Ok(())
+    }
+
+    /// Forget a credential's attempts and needs-attention mark, after a successful
+    /// registration or an operator's intervention
+    pub fn reset(&self, credential_id: &str) {
+        self.history.lock().unwrap().remove(credential_id);
+    }
+
+    pub fn needs_attention(&self, credential_id: &str) -> Option<NeedsAttention> {
+        self.history
+            .lock()
+            .unwrap()
+            .get(credential_id)
+            .and_then(|h| h.needs_attention.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registration_cooldown_and_hourly_limit() {
+        let limiter = RegistrationLimiter::new(Duration::from_secs(300), 2);
+        let start = Instant::now();
+        let minutes = |m: u64| start + Duration::from_secs(m * 60);
+
+        limiter.try_acquire_at("cred", start).unwrap();
+        // Within the cooldown
+        match limiter.try_acquire_at("cred", minutes(1)) {
+            Err(AppError::TooManyRequests(_, info)) => assert_eq!(info.retry_after_secs, Some(240)),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        // Other credentials have their own budget
+        limiter.try_acquire_at("other", minutes(1)).unwrap();
+
+        limiter.try_acquire_at("cred", minutes(5)).unwrap();
+        assert!(limiter.needs_attention("cred").is_none());
+
+        // Hourly limit used up: the credential needs attention, even after the window passes
+        assert!(limiter.try_acquire_at("cred", minutes(30)).is_err());
+        assert!(limiter.needs_attention("cred").is_some());
+        assert!(limiter.try_acquire_at("cred", minutes(120)).is_err());
+        limiter.reset("cred");
+        assert!(limiter.needs_attention("cred").is_none());
+        limiter.try_acquire_at("cred", minutes(121)).unwrap();
+
+        // A success forgets earlier failures
+        limiter.reset("other");
+        limiter.try_acquire_at("other", minutes(2)).unwrap();
+    }
+}
@@ -0,0 +1,250 @@
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use cron::Schedule;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use utoipa::ToSchema;
+
+/// Listening window for a credential, defined by start/stop cron expressions
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct ScheduleConfig {
+    /// Cron expression for starting the listener (5 fields: min hour day month weekday, with
+    /// weekdays numbered as in crontab, 0 or 7 = Sunday, or named)
+    #[schema(example = "0 9 * * Mon-Fri")]
+    pub start: String,
+    /// Cron expression for stopping the listener
+    #[schema(example = "0 17 * * Mon-Fri")]
+    pub stop: String,
+    /// IANA timezone the expressions are evaluated in (default UTC)
+    #[serde(default)]
+    #[schema(example = "Asia/Jakarta")]
+    pub timezone: Option<String>,
+}
+
+/// What the scheduler does at a transition
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ScheduleAction {
+    Start,
+    Stop,
+}
+
+/// Next scheduled start or stop of a credential's listener
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ScheduledTransition {
+    pub action: ScheduleAction,
+    pub at: DateTime<Utc>,
+}
+
+/// Parsed, ready-to-evaluate schedule
+pub struct CredentialSchedule {
+    start: Schedule,
+    stop: Schedule,
+    timezone: Tz,
+}
+
+impl CredentialSchedule {
+    pub fn parse(config: &ScheduleConfig) -> Result<Self, String> {
+        let timezone = match config.timezone.as_deref() {
+            Some(tz) if !tz.is_empty() => tz
+                .parse::<Tz>()
+                .map_err(|_| format!("Unknown timezone: {}", tz))?,
+            _ => Tz::UTC,
+        };
+
+        Ok(Self {
+            start: Self::parse_cron(&config.start)?,
+            stop: Self::parse_cron(&config.stop)?,
+            timezone,
+        })
+    }
+
+    /// Parse a cron expression, accepting the common 5-field crontab form (no seconds, weekday
+    /// 0 or 7 = Sunday). Longer expressions use the cron crate's syntax (weekday 1 = Sunday).
+    fn parse_cron(expr: &str) -> Result<Schedule, String> {
+        let expr = expr.trim();
+        let fields: Vec<&str> = expr.split_whitespace().collect();
+        let full = if let [minute, hour, day, month, weekday] = fields[..] {
+            let weekday = crontab_weekdays(weekday)
+                .map_err(|e| format!("Invalid cron expression '{}': {}", expr, e))?;
+            format!("0 {} {} {} {} {}", minute, hour, day, month, weekday)
+        } else {
+            expr.to_string()
+        };
+
+        Schedule::from_str(&full).map_err(|e| format!("Invalid cron expression '{}': {}", expr, e))
+    }
+
+    /// Latest transition that fired in (from, to], if any
+    pub fn transition_between(
+        &self,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Option<ScheduleAction> {
+        let from = from.with_timezone(&self.timezone);
+        let last = |schedule: &Schedule| {
+            schedule
+                .after(&from)
+                .take_while(|t| t.with_timezone(&Utc) <= to)
+                .last()
+        };
+
+        match (last(&self.start), last(&self.stop)) {
+            (Some(start), Some(stop)) if start > stop => Some(ScheduleAction::Start),
+            (Some(_), Some(_)) => Some(ScheduleAction::Stop),
+            (Some(_), None) => Some(ScheduleAction::Start),
+            (None, Some(_)) => Some(ScheduleAction::Stop),
+            (None, None) => None,
+        }
+    }
+
+    /// Next transition strictly after `after`
+    pub fn next_transition(&self, after: DateTime<Utc>) -> Option<ScheduledTransition> {
+        let after = after.with_timezone(&self.timezone);
+        let next_start = self.start.after(&after).next();
+        let next_stop = self.stop.after(&after).next();
+
+        let (action, at) = match (next_start, next_stop) {
+            (Some(start), Some(stop)) if start <= stop => (ScheduleAction::Start, start),
+            (_, Some(stop)) => (ScheduleAction::Stop, stop),
+            (Some(start), None) => (ScheduleAction::Start, start),
+            (None, None) => return None,
+        };
+
+        Some(ScheduledTransition {
+            action,
+            at: at.with_timezone(&Utc),
+        })
+    }
+}
+
+/// Translate a crontab weekday field (0 or 7 = Sunday, 1 = Monday) to the cron crate's
+/// numbering (1 = Sunday .. 7 = Saturday). Numeric parts are expanded to a list of days,
+/// names and `*` are the same in both and pass through.
+fn crontab_weekdays(field: &str) -> Result<String, String> {
+    let parse = |n: &str| match n.parse::<usize>() {
+        Ok(day) if day <= 7 => Ok(day),
+        _ => Err(format!("'{}' is not a valid day of the week", n)),
+    };
+
+    let mut parts = Vec::new();
+    for part in field.split(',') {
+        if part.chars().any(|c| c.is_ascii_alphabetic()) || !part.chars().any(|c| c.is_ascii_digit()) {
+            parts.push(part.to_string());
+            continue;
+        }
+
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => match step.parse::<usize>() {
+                Ok(step) if step > 0 => (range, Some(step)),
+                _ => return Err(format!("'{}' is not a valid step", step)),
+            },
+            None => (part, None),
+        };
+        let (first, last) = match (range, range.split_once('-')) {
+            ("*", _) => (0, 6),
+            (_, Some((first, last))) => (parse(first)?, parse(last)?),
+            // "5/2" runs from 5 to the end of the week
+            (_, None) if step.is_some() => (parse(range)?, 6),
+            (_, None) => (parse(range)?, parse(range)?),
+        };
+        if first > last {
+            return Err(format!("'{}' is not a valid range of days", range));
+        }
+
+        let mut days: Vec<usize> = (first..=last)
+            .step_by(step.unwrap_or(1))
+            .map(|day| day % 7 + 1)
+            .collect();
+        days.sort_unstable();
+        days.dedup();
+        parts.extend(days.iter().map(|day| day.to_string()));
+    }
+
+    Ok(parts.join(","))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn business_hours(timezone: Option<&str>) -> CredentialSchedule {
+        CredentialSchedule::parse(&ScheduleConfig {
+            start: "0 9 * * *".to_string(),
+            stop: "0 17 * * *".to_string(),
+            timezone: timezone.map(|s| s.to_string()),
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_transition_between() {
+        let schedule = business_hours(None);
+        let at = |h, m| Utc.with_ymd_and_hms(2024, 1, 15, h, m, 0).unwrap();
+
+        assert_eq!(schedule.transition_between(at(8, 59), at(9, 0)), Some(ScheduleAction::Start));
+        assert_eq!(schedule.transition_between(at(16, 59), at(17, 0)), Some(ScheduleAction::Stop));
+        assert_eq!(schedule.transition_between(at(10, 0), at(10, 1)), None);
+        // Both fired in the window: the later one wins
+        assert_eq!(schedule.transition_between(at(8, 0), at(18, 0)), Some(ScheduleAction::Stop));
+    }
+
+    #[test]
+    fn test_next_transition_in_timezone() {
+        let schedule = business_hours(Some("Asia/Jakarta"));
+        // 09:00 in Jakarta (UTC+7) is 02:00 UTC
+        let next = schedule
+            .next_transition(Utc.with_ymd_and_hms(2024, 1, 15, 0, 0, 0).unwrap())
+            .unwrap();
+        assert_eq!(next.action, ScheduleAction::Start);
+        assert_eq!(next.at, Utc.with_ymd_and_hms(2024, 1, 15, 2, 0, 0).unwrap());
+    }
+
+    #[test]
+    fn test_crontab_weekday_numbers() {
+        let weekdays = CredentialSchedule::parse(&ScheduleConfig {
+            start: "0 9 * * 1-5".to_string(),
+            stop: "0 17 * * 1-5".to_string(),
+            timezone: None,
+        })
+        .unwrap();
+        // 2024-01-14 is a Sunday: nothing fires until Monday 09:00
+        let sunday = |h| Utc.with_ymd_and_hms(2024, 1, 14, h, 0, 0).unwrap();
+        assert_eq!(weekdays.transition_between(sunday(0), sunday(23)), None);
+        let next = weekdays.next_transition(sunday(0)).unwrap();
+        assert_eq!(next.action, ScheduleAction::Start);
+        assert_eq!(next.at, Utc.with_ymd_and_hms(2024, 1, 15, 9, 0, 0).unwrap());
+
+        // Friday's stop is followed by Monday's start, skipping Saturday
+        let friday_evening = Utc.with_ymd_and_hms(2024, 1, 19, 18, 0, 0).unwrap();
+        let next = weekdays.next_transition(friday_evening).unwrap();
+        assert_eq!(next.at, Utc.with_ymd_and_hms(2024, 1, 22, 9, 0, 0).unwrap());
+
+        assert_eq!(crontab_weekdays("0").unwrap(), "1");
+        assert_eq!(crontab_weekdays("7").unwrap(), "1");
+        assert_eq!(crontab_weekdays("5-7").unwrap(), "1,6,7");
+        assert_eq!(crontab_weekdays("*/2").unwrap(), "1,3,5,7");
+        assert_eq!(crontab_weekdays("Mon-Fri,0").unwrap(), "Mon-Fri,1");
+        assert_eq!(crontab_weekdays("*").unwrap(), "*");
+        assert!(crontab_weekdays("8").is_err());
+        assert!(crontab_weekdays("5-1").is_err());
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid() {
+        let config = ScheduleConfig {
+            start: "not a cron".to_string(),
+            stop: "0 17 * * *".to_string(),
+            timezone: None,
+        };
+        assert!(CredentialSchedule::parse(&config).is_err());
+
+        let config = ScheduleConfig {
+            start: "0 9 * * *".to_string(),
+            stop: "0 17 * * *".to_string(),
+            timezone: Some("Mars/Olympus".to_string()),
+        };
+        assert!(CredentialSchedule::parse(&config).is_err());
+    }
+}
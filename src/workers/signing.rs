@@ -0,0 +1,125 @@
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use ed25519_dalek::{Signer, SigningKey};
+use rand::RngCore;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use tracing::error;
+use utoipa::ToSchema;
+
+/// Header carrying the base64 Ed25519 signature of the webhook body
+pub const ED25519_SIGNATURE_HEADER: &str = "X-Webhook-Signature-Ed25519";
+
+/// Header naming the key that produced the Ed25519 signature
+pub const KEY_ID_HEADER: &str = "X-Webhook-Key-Id";
+
+/// Ed25519 private key used to sign webhook bodies. Its key id is derived from the
+/// public key, so rotating to a new key changes the id receivers see.
+#[derive(Clone)]
+pub struct Ed25519Key {
+    key: SigningKey,
+    key_id: String,
+}
+
+/// Public half of a webhook signing key, for receivers to verify signatures
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct WebhookPublicKey {
+    /// Matches the X-Webhook-Key-Id header of signed requests
+    pub key_id: String,
+    #[schema(example = "ed25519")]
+    pub algorithm: String,
+    /// Raw 32-byte public key, base64-encoded
+    pub public_key: String,
+}
+
+impl Ed25519Key {
+    fn from_signing_key(key: SigningKey) -> Self {
+        let digest = Sha256::digest(key.verifying_key().as_bytes());
+        Self {
+            key_id: hex::encode(&digest[..8]),
+            key,
+        }
+    }
+
+    /// Key from a base64-encoded 32-byte seed
+    pub fn from_base64(seed: &str) -> Result<Self, String> {
+        let bytes = BASE64
+            .decode(seed.trim())
+            .map_err(|e| format!("Invalid Ed25519 key encoding: {}", e))?;
+        let seed: [u8; 32] = bytes
+            .try_into()
+            .map_err(|_| "Ed25519 key must be a 32-byte seed".to_string())?;
+        Ok(Self::from_signing_key(SigningKey::from_bytes(&seed)))
+    }
+
+    pub fn generate() -> Self {
+        let mut seed = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut seed);
+        Self::from_signing_key(SigningKey::from_bytes(&seed))
+    }
+
+    /// Base64 seed, as stored and accepted by `from_base64`
+    pub fn to_base64(&self) -> String {
+        BASE64.encode(self.key.to_bytes())
+    }
+
+    pub fn key_id(&self) -> &str {
+        &self.key_id
+    }
+
+    pub fn public_key(&self) -> WebhookPublicKey {
+        WebhookPublicKey {
+            key_id: self.key_id.clone(),
+            algorithm: "ed25519".to_string(),
+            public_key: BASE64.encode(self.key.verifying_key().as_bytes()),
+        }
+    }
+
+    /// Base64 signature of `payload`
+    pub fn sign(&self, payload: impl AsRef<[u8]>) -> String {
+        BASE64.encode(self.key.sign(payload.as_ref()).to_bytes())
+    }
+}
+
+/// Get the server-wide Ed25519 webhook signing key from environment
+/// (WEBHOOK_SIGNING_KEY or WEBHOOK_SIGNING_KEY_FILE, base64 32-byte seed), if set
+pub fn get_webhook_signing_key() -> Option<Ed25519Key> {
+    let seed = crate::secrets::var_or_log("WEBHOOK_SIGNING_KEY").filter(|s| !s.trim().is_empty())?;
+    Ed25519Key::from_base64(&seed)
+        .map_err(|e| error!("Ignoring WEBHOOK_SIGNING_KEY: {}", e))
+        .ok()
+}
+
+/// How one credential's webhook bodies are signed
+#[derive(Clone, Default)]
+pub struct WebhookSigning {
+    /// HMAC secrets (current first)
+    pub secrets: Vec<String>,
+    /// Ed25519 key: the credential's own, else the server-wide one
+    pub key: Option<Ed25519Key>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+
+    #[test]
+    fn test_ed25519_sign_and_verify() {
+        let key = Ed25519Key::generate();
+        let restored = Ed25519Key::from_base64(&key.to_base64()).unwrap();
+        assert_eq!(restored.key_id(), key.key_id());
+        assert_ne!(Ed25519Key::generate().key_id(), key.key_id());
+
+        let public = key.public_key();
+        let public_bytes: [u8; 32] = BASE64.decode(&public.public_key).unwrap().try_into().unwrap();
+        let verifying = VerifyingKey::from_bytes(&public_bytes).unwrap();
+
+        let signature_bytes: [u8; 64] = BASE64.decode(key.sign("{\"a\":1}")).unwrap().try_into().unwrap();
+        let signature = Signature::from_bytes(&signature_bytes);
+        assert!(verifying.verify(b"{\"a\":1}", &signature).is_ok());
+        assert!(verifying.verify(b"{\"a\":2}", &signature).is_err());
+
+        assert!(Ed25519Key::from_base64("not base64!").is_err());
+        assert!(Ed25519Key::from_base64(&BASE64.encode([0u8; 16])).is_err());
+    }
+}
@@ -0,0 +1,217 @@
+use crate::db::Repository;
+use crate::error::AppResult;
+use crate::models::{DeliveryState, MessageLog};
+use crate::workers::WebhookClient;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use google_cloud_auth::project::Config;
+use google_cloud_auth::token::DefaultTokenSourceProvider;
+use google_cloud_token::{TokenSource, TokenSourceProvider};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::OnceCell;
+use tracing::{error, info};
+use utoipa::ToSchema;
+
+const PUBSUB_SCOPES: [&str; 1] = ["https://www.googleapis.com/auth/pubsub"];
+
+/// Where a credential's messages are delivered
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum SinkConfig {
+    /// POST to the credential's webhook_url (or a per-topic override)
+    #[default]
+    Webhook,
+    /// Publish to a Google Cloud Pub/Sub topic using application default credentials
+    #[serde(rename = "pubsub")]
+    PubSub {
+        /// GCP project that owns the topic
+        project_id: String,
+        /// Topic name (without the projects/.../topics/ prefix)
+        topic: String,
+        /// Use the credential ID as ordering key (requires ordered delivery on the subscription)
+        #[serde(default)]
+        ordering_key: bool,
+    },
+}
+
+impl SinkConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        match self {
+            SinkConfig::Webhook => Ok(()),
+            SinkConfig::PubSub { project_id, topic, .. } => {
+                if project_id.trim().is_empty() || topic.trim().is_empty() {
+                    Err("Pub/Sub sink requires project_id and topic".to_string())
+                } else {
+                    Ok(())
+                }
+            }
+        }
+    }
+}
+
+/// Publishes messages to Pub/Sub through its REST API, reusing the webhook client's
+/// retry and status logging. The publish response (message IDs) is stored as the webhook response.
+#[derive(Clone)]
+pub struct PubSubSink {
+    project_id: String,
+    topic: String,
+    ordering_key: bool,
+    tokens: Arc<OnceCell<Arc<dyn TokenSource>>>,
+}
+
+impl PubSubSink {
+    pub fn new(project_id: String, topic: String, ordering_key: bool) -> Self {
+        Self {
+            project_id,
+            topic,
+            ordering_key,
+            tokens: Arc::new(OnceCell::new()),
+        }
+    }
+
+    /// Pub/Sub sink for a sink config, or None for HTTP webhook delivery
+    pub fn from_config(config: &SinkConfig) -> Option<Self> {
+        match config {
+            SinkConfig::Webhook => None,
+            SinkConfig::PubSub { project_id, topic, ordering_key } => {
+                Some(Self::new(project_id.clone(), topic.clone(), *ordering_key))
+            }
+        }
+    }
+
+    fn publish_url(&self) -> String {
+        format!(
+            "https://pubsub.googleapis.com/v1/projects/{}/topics/{}:publish",
+            self.project_id, self.topic
+        )
+    }
+
+    /// Publish request body for a single FCM payload
+    fn envelope(&self, credential_id: &str, message_id: &str, payload: &[u8]) -> String {
+        let mut message = json!({
+            "data": BASE64.encode(payload),
+            "attributes": {
+                "credential_id": credential_id,
+                "message_id": message_id,
+            },
+        });
+        if self.ordering_key {
+            message["orderingKey"] = json!(credential_id);
+        }
+
+        json!({ "messages": [message] }).to_string()
+    }
+
+    /// Access token from application default credentials ("Bearer ..."), initialized on first use
+    async fn token(&self) -> Result<String, String> {
+        let source = self
+            .tokens
+            .get_or_try_init(|| async {
+                let config = Config::default().with_scopes(&PUBSUB_SCOPES);
+                DefaultTokenSourceProvider::new(config)
+                    .await
+                    .map(|provider| provider.token_source())
+                    .map_err(|e| e.to_string())
+            })
+            .await?;
+
+        source.token().await.map_err(|e| e.to_string())
+    }
+
+    pub async fn publish(
+        &self,
+        client: &WebhookClient,
+        payload: &[u8],
+        capture_response: bool,
+        log: &mut MessageLog,
+        repo: &Repository,
+    ) -> AppResult<()> {
+        let token = match self.token().await {
+            Ok(token) => token,
+            Err(e) => {
+                let message = format!("Pub/Sub authentication failed: {}", e);
+                error!("{} (message {})", message, log.id);
+                log.webhook_status = Some(0);
+                log.delivery_state = DeliveryState::Failed.as_str().to_string();
+                log.webhook_response = Some(message.clone());
+                log.webhook_response_json = None;
+                if let Err(e) = repo
+                    .update_message_webhook_status(&log.id, 0, Some(&message), None)
+                    .await
+                {
+                    error!("Failed to update webhook status after failure: {}", e);
+                }
+                return Ok(());
+            }
+        };
+
+        let headers = HashMap::from([("Authorization".to_string(), token)]);
+        let body = self.envelope(&log.credential_id, &log.id, payload);
+
+        info!("Publishing message {} to Pub/Sub topic {}", log.id, self.topic);
+        // The OAuth token is the only Authorization Pub/Sub accepts
+        client
+            .clone()
+            .with_auth(None)
+            .send(&self.publish_url(), &body, Some(&headers), capture_response, log, repo)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sink_config_serde() {
+        let config: SinkConfig = serde_json::from_str(
+            r#"{"type":"pubsub","project_id":"my-project","topic":"fcm-messages"}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config,
+            SinkConfig::PubSub {
+                project_id: "my-project".to_string(),
+                topic: "fcm-messages".to_string(),
+                ordering_key: false,
+            }
+        );
+
+        let config: SinkConfig = serde_json::from_str(r#"{"type":"webhook"}"#).unwrap();
+        assert_eq!(config, SinkConfig::Webhook);
+        assert!(PubSubSink::from_config(&config).is_none());
+
+        let config = SinkConfig::PubSub {
+            project_id: String::new(),
+            topic: "t".to_string(),
+            ordering_key: false,
+        };
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_envelope() {
+        let sink = PubSubSink::new("p".to_string(), "t".to_string(), true);
+        assert_eq!(
+            sink.publish_url(),
+            "https://pubsub.googleapis.com/v1/projects/p/topics/t:publish"
+        );
+
+        let body: serde_json::Value =
+            serde_json::from_str(&sink.envelope("cred", "msg", br#"{"a":1}"#)).unwrap();
+        let message = &body["messages"][0];
+        assert_eq!(
+            BASE64.decode(message["data"].as_str().unwrap()).unwrap(),
+            br#"{"a":1}"#
+        );
+        assert_eq!(message["attributes"]["message_id"], "msg");
+        assert_eq!(message["orderingKey"], "cred");
+
+        let sink = PubSubSink::new("p".to_string(), "t".to_string(), false);
+        let body: serde_json::Value =
+            serde_json::from_str(&sink.envelope("cred", "msg", b"{}")).unwrap();
+        assert!(body["messages"][0].get("orderingKey").is_none());
+    }
+}
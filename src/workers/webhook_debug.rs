@@ -0,0 +1,157 @@
+use chrono::{DateTime, Utc};
+use reqwest::header::HeaderMap;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::info;
+
+/// Request and response bodies longer than this are cut in debug logs
+const DEBUG_BODY_LIMIT: usize = 8 * 1024;
+
+/// Longest debug window, whatever ttl is asked for
+pub const MAX_DEBUG_TTL: Duration = Duration::from_secs(24 * 3600);
+
+/// Get how long webhook debug logging stays on for a credential when no ttl_secs is given,
+/// from environment (WEBHOOK_DEBUG_TTL_SECS), default 900
+pub fn get_webhook_debug_ttl() -> Duration {
+    let secs = std::env::var("WEBHOOK_DEBUG_TTL_SECS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(900);
+    Duration::from_secs(secs)
+}
+
+/// Get the headers whose values are masked in webhook debug logs, from environment
+/// (WEBHOOK_DEBUG_REDACT_HEADERS, comma-separated), default authorization,
+/// proxy-authorization, cookie, set-cookie, x-api-key
+pub fn get_webhook_debug_redact_headers() -> Vec<String> {
+    std::env::var("WEBHOOK_DEBUG_REDACT_HEADERS")
+        .unwrap_or_else(|_| "authorization,proxy-authorization,cookie,set-cookie,x-api-key".to_string())
+        .split(',')
+        .map(|h| h.trim().to_lowercase())
+        .filter(|h| !h.is_empty())
+        .collect()
+}
+
+/// Get whether message payloads and webhook bodies may appear in logs, from environment
+/// (LOG_PAYLOADS), default false. When off, only their size is logged.
+pub fn get_log_payloads() -> bool {
+    std::env::var("LOG_PAYLOADS")
+        .map(|v| matches!(v.trim().to_lowercase().as_str(), "true" | "1" | "yes"))
+        .unwrap_or(false)
+}
+
+/// Credentials whose webhook deliveries are logged in full (headers, bodies, timing)
+/// until their debug window ends. Shared by every clone of the webhook client.
+#[derive(Clone, Default)]
+pub struct WebhookDebug {
+    until: Arc<Mutex<HashMap<String, DateTime<Utc>>>>,
+}
+
+impl WebhookDebug {
+    /// Turn debug logging on for `ttl` (at most MAX_DEBUG_TTL), returning when it switches off again
+    pub fn enable(&self, credential_id: &str, ttl: Duration) -> DateTime<Utc> {
+        let ttl = chrono::Duration::from_std(ttl.min(MAX_DEBUG_TTL)).unwrap_or_else(|_| chrono::Duration::zero());
+        let until = Utc::now() + ttl;
+        self.until.lock().unwrap().insert(credential_id.to_string(), until);
+        until
+    }
+
+    pub fn disable(&self, credential_id: &str) {
+        self.until.lock().unwrap().remove(credential_id);
+    }
+
+    /// End of the credential's debug window; an expired window is dropped here
+    pub fn expires_at(&self, credential_id: &str) -> Option<DateTime<Utc>> {
+        let mut until = self.until.lock().unwrap();
+        match until.get(credential_id) {
+            Some(at) if *at > Utc::now() => Some(*at),
+            Some(_) => {
+                until.remove(credential_id);
+                info!("Webhook debug logging expired for credential {}", credential_id);
+                None
+            }
+            None => None,
+        }
+    }
+
+    pub fn is_enabled(&self, credential_id: &str) -> bool {
+        self.expires_at(credential_id).is_some()
+    }
+}
+
+/// Headers as `name: value` lines, with the values of `redact` (lowercase names) masked
+pub fn redact_headers(headers: &HeaderMap, redact: &[String]) -> String {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            let value = if redact.iter().any(|r| r == name.as_str()) {
+                "[redacted]".to_string()
+            } else {
+                String::from_utf8_lossy(value.as_bytes()).to_string()
+            };
+            format!("{}: {}", name, value)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Body as text for a debug log, cut at DEBUG_BODY_LIMIT; just its size unless LOG_PAYLOADS is on
+pub fn debug_body(body: &[u8]) -> String {
+    body_for_log(body, get_log_payloads())
+}
+
+fn body_for_log(body: &[u8], log_payloads: bool) -> String {
+    if !log_payloads {
+        return format!("({} bytes, set LOG_PAYLOADS=true to log)", body.len());
+    }
+    let text = String::from_utf8_lossy(&body[..body.len().min(DEBUG_BODY_LIMIT)]).to_string();
+    if body.len() > DEBUG_BODY_LIMIT {
+        format!("{}... ({} bytes total)", text, body.len())
+    } else {
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    #[test]
+    fn test_debug_window_and_redaction() {
+        let debug = WebhookDebug::default();
+        assert!(!debug.is_enabled("cred"));
+
+        let until = debug.enable("cred", Duration::from_secs(60));
+        assert!(debug.is_enabled("cred"));
+        assert_eq!(debug.expires_at("cred"), Some(until));
+        assert!(!debug.is_enabled("other"));
+
+        // An elapsed window switches itself off
+        debug.enable("cred", Duration::ZERO);
+        assert!(!debug.is_enabled("cred"));
+        assert!(debug.until.lock().unwrap().is_empty());
+
+        debug.enable("cred", Duration::from_secs(60));
+        debug.disable("cred");
+        assert!(!debug.is_enabled("cred"));
+
+        let mut headers = HeaderMap::new();
+        headers.insert("authorization", HeaderValue::from_static("Bearer secret"));
+        headers.insert("x-custom", HeaderValue::from_static("visible"));
+        let logged = redact_headers(&headers, &["authorization".to_string()]);
+        assert!(logged.contains("authorization: [redacted]"));
+        assert!(logged.contains("x-custom: visible"));
+        assert!(!logged.contains("secret"));
+
+        let long = vec![b'a'; DEBUG_BODY_LIMIT + 10];
+        assert!(body_for_log(&long, true).ends_with(&format!("({} bytes total)", long.len())));
+        assert_eq!(body_for_log(b"{}", true), "{}");
+
+        // Without LOG_PAYLOADS only the size is logged
+        let hidden = body_for_log(b"{\"secret\":1}", false);
+        assert!(!hidden.contains("secret"));
+        assert!(hidden.starts_with("(12 bytes"));
+    }
+}